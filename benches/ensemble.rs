@@ -0,0 +1,57 @@
+// benches/ensemble.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BENCHES]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use metasyntraxl::config::{Config, EnsembleConfig};
+use metasyntraxl::ensemble::Ensemble;
+use tch::{nn, Device, Tensor};
+use tokio::runtime::Runtime;
+
+/// Compares `bagging_predict` with member forward passes run one at a time
+/// against running them concurrently on the blocking pool.
+fn bench_bagging_parallelism(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("failed to build tokio runtime");
+    let input = Tensor::of_slice(&[1i64, 2, 3, 4, 5, 6, 7, 8]).unsqueeze(0);
+    let num_models = 4;
+
+    let mut group = c.benchmark_group("ensemble_bagging_predict");
+    group.sample_size(10);
+    for parallelism in [1, num_models] {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let config = Config {
+            vocab_size: 2000,
+            embed_dim: 256,
+            hidden_dim: 1024,
+            num_layers: 4,
+            max_len: 64,
+            num_models,
+            ensemble: EnsembleConfig {
+                parallelism: Some(parallelism),
+                ..EnsembleConfig::default()
+            },
+            ..Config::default()
+        };
+        let ensemble = Ensemble::new(
+            &vs.root(),
+            config.num_models,
+            config.input_size,
+            config.output_size,
+            &config,
+        )
+        .expect("failed to build ensemble");
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(parallelism),
+            &ensemble,
+            |b, ensemble| {
+                b.iter(|| {
+                    runtime
+                        .block_on(ensemble.bagging_predict(&input))
+                        .expect("prediction failed")
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_bagging_parallelism);
+criterion_main!(benches);
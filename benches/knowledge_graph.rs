@@ -0,0 +1,93 @@
+// benches/knowledge_graph.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BENCHES]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use metasyntraxl::errors::MetaSyntraXLError;
+use metasyntraxl::knowledge_graph::{
+    Entity, GraphOperation, GraphState, GraphStorage, KnowledgeGraph, Relationship,
+};
+use std::collections::HashMap;
+use tokio::runtime::Runtime;
+
+const EDGE_COUNTS: [usize; 3] = [10_000, 100_000, 1_000_000];
+const EDGES_PER_ENTITY: usize = 10;
+const TYPES: [&str; 4] = ["friend", "colleague", "cites", "part_of"];
+
+/// Hands a prebuilt graph to [`KnowledgeGraph::with_storage`], so building the
+/// million-edge graph does not go through a million appends.
+struct Preloaded(Option<GraphState>);
+
+impl GraphStorage for Preloaded {
+    fn load(&mut self) -> Result<GraphState, MetaSyntraXLError> {
+        Ok(self.0.take().unwrap_or_default())
+    }
+
+    fn append(&mut self, _operation: &GraphOperation) -> Result<(), MetaSyntraXLError> {
+        Ok(())
+    }
+
+    fn snapshot(&mut self, _state: &GraphState) -> Result<(), MetaSyntraXLError> {
+        Ok(())
+    }
+}
+
+/// `edges / EDGES_PER_ENTITY` entities, each with a unique `name` and
+/// `EDGES_PER_ENTITY` outgoing relationships spread over `TYPES`.
+fn build_graph(edges: usize) -> KnowledgeGraph {
+    let entity_count = edges / EDGES_PER_ENTITY;
+    let entities: HashMap<String, Entity> = (0..entity_count)
+        .map(|i| {
+            let entity = Entity {
+                id: i.to_string(),
+                properties: HashMap::from([("name".to_string(), format!("entity-{}", i).into())]),
+            };
+            (entity.id.clone(), entity)
+        })
+        .collect();
+    let relationships = (0..edges)
+        .map(|e| {
+            let from = e / EDGES_PER_ENTITY;
+            Relationship {
+                from: from.to_string(),
+                to: ((from * 7919 + e) % entity_count).to_string(),
+                type_: TYPES[e % TYPES.len()].to_string(),
+                properties: HashMap::new(),
+            }
+        })
+        .collect();
+    let state = GraphState {
+        entities,
+        relationships,
+    };
+    KnowledgeGraph::with_storage(Box::new(Preloaded(Some(state))))
+        .expect("failed to build knowledge graph")
+}
+
+/// Lookup latency should stay flat as the graph grows a hundredfold.
+fn bench_indexed_lookups(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("failed to build tokio runtime");
+    let mut group = c.benchmark_group("knowledge_graph_lookups");
+    for edges in EDGE_COUNTS {
+        let kg = build_graph(edges);
+        let middle = (edges / EDGES_PER_ENTITY / 2).to_string();
+        let name = format!("entity-{}", middle);
+
+        group.bench_with_input(
+            BenchmarkId::new("get_connected_entities", edges),
+            &kg,
+            |b, kg| b.iter(|| runtime.block_on(kg.get_connected_entities(&middle, "friend"))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("get_relationships_to", edges),
+            &kg,
+            |b, kg| b.iter(|| runtime.block_on(kg.get_relationships_to(&middle, "cites"))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("query_entities_by_property", edges),
+            &kg,
+            |b, kg| b.iter(|| runtime.block_on(kg.query_entities_by_property("name", &name))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_indexed_lookups);
+criterion_main!(benches);
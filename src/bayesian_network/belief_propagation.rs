@@ -0,0 +1,130 @@
+// src/bayesian_network/belief_propagation.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BAYESIAN-NETWORK]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::factor::Factor;
+use super::inference::{BeliefPropagationOptions, Diagnostics, Marginals};
+use super::BayesianNetwork;
+use std::collections::HashMap;
+
+/// Estimates posteriors by sum-product message passing on the factor graph
+/// formed by the evidence-reduced CPTs.
+///
+/// Returns `None` if some node ends up with an all-zero belief, which happens
+/// when the messages cannot reconcile the evidence.
+pub(crate) fn loopy_belief_propagation(
+    network: &BayesianNetwork,
+    evidence: &HashMap<String, usize>,
+    options: &BeliefPropagationOptions,
+) -> Option<(Marginals, Diagnostics)> {
+    let mut factors: Vec<Factor> = network
+        .nodes
+        .values()
+        .map(|node| node.factor().reduce(evidence))
+        .filter(|factor| !factor.variables.is_empty())
+        .collect();
+    factors.sort_by(|a, b| a.variables.cmp(&b.variables));
+
+    // incidence[variable] lists (factor, position of the variable in that factor).
+    let mut incidence: HashMap<&str, Vec<(usize, usize)>> = HashMap::new();
+    for (f, factor) in factors.iter().enumerate() {
+        for (i, variable) in factor.variables.iter().enumerate() {
+            incidence.entry(variable.as_str()).or_default().push((f, i));
+        }
+    }
+
+    let uniform = |factor: &Factor| -> Vec<Vec<f64>> {
+        factor
+            .cardinalities
+            .iter()
+            .map(|&c| vec![1.0 / c as f64; c])
+            .collect()
+    };
+    let mut to_factor: Vec<Vec<Vec<f64>>> = factors.iter().map(uniform).collect();
+    let mut to_variable: Vec<Vec<Vec<f64>>> = factors.iter().map(uniform).collect();
+
+    let mut iterations = 0;
+    let mut max_residual = f64::INFINITY;
+    while iterations < options.max_iterations && max_residual > options.tolerance {
+        iterations += 1;
+        max_residual = 0.0;
+
+        for (f, factor) in factors.iter().enumerate() {
+            for i in 0..factor.variables.len() {
+                let mut message = vec![0.0; factor.cardinalities[i]];
+                for (index, &value) in factor.values.iter().enumerate() {
+                    let assignment = factor.assignment(index);
+                    let incoming: f64 = assignment
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, _)| j != i)
+                        .map(|(j, &state)| to_factor[f][j][state])
+                        .product();
+                    message[assignment[i]] += value * incoming;
+                }
+                normalize(&mut message);
+
+                let previous = &mut to_variable[f][i];
+                for (new, old) in message.iter_mut().zip(previous.iter()) {
+                    *new = (1.0 - options.damping) * *new + options.damping * old;
+                    max_residual = max_residual.max((*new - old).abs());
+                }
+                *previous = message;
+            }
+        }
+
+        for edges in incidence.values() {
+            for &(f, i) in edges {
+                let mut message = vec![1.0; factors[f].cardinalities[i]];
+                for &(g, j) in edges {
+                    if g != f {
+                        message
+                            .iter_mut()
+                            .zip(&to_variable[g][j])
+                            .for_each(|(m, v)| *m *= v);
+                    }
+                }
+                normalize(&mut message);
+                to_factor[f][i] = message;
+            }
+        }
+    }
+
+    let mut marginals = super::one_hot(&network.nodes, evidence);
+    for (name, node) in &network.nodes {
+        if evidence.contains_key(name) {
+            continue;
+        }
+        let edges = match incidence.get(name.as_str()) {
+            Some(edges) => edges,
+            None => continue,
+        };
+        let mut belief = vec![1.0; node.states.len()];
+        for &(f, i) in edges {
+            belief
+                .iter_mut()
+                .zip(&to_variable[f][i])
+                .for_each(|(b, v)| *b *= v);
+        }
+        let total: f64 = belief.iter().sum();
+        if total <= 0.0 || !total.is_finite() {
+            return None;
+        }
+        belief.iter_mut().for_each(|b| *b /= total);
+        marginals.insert(name.clone(), belief);
+    }
+
+    Some((
+        marginals,
+        Diagnostics::BeliefPropagation {
+            iterations,
+            max_residual,
+            converged: max_residual <= options.tolerance,
+        },
+    ))
+}
+
+/// Scales a message to sum to one, leaving an all-zero message untouched.
+fn normalize(message: &mut [f64]) {
+    let total: f64 = message.iter().sum();
+    if total > 0.0 && total.is_finite() {
+        message.iter_mut().for_each(|m| *m /= total);
+    }
+}
\ No newline at end of file
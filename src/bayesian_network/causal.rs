@@ -0,0 +1,198 @@
+// src/bayesian_network/causal.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BAYESIAN-NETWORK]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::elimination::{eliminate, EliminationOrder};
+use super::factor::Factor;
+use super::structure::combinations;
+use super::{one_hot_state, BayesianNetwork};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Graph surgery: every intervened node loses its parents and is fixed to its
+/// state, the rest of the network is unchanged.
+pub(crate) fn intervene(
+    network: &BayesianNetwork,
+    interventions: &HashMap<String, usize>,
+) -> BayesianNetwork {
+    let mut nodes = network.nodes.clone();
+    for (name, &state) in interventions {
+        let node = nodes.get_mut(name).expect("interventions are resolved");
+        let cardinality = node.states.len();
+        node.parents.clear();
+        node.cpt = Factor::new(
+            vec![name.clone()],
+            vec![cardinality],
+            one_hot_state(cardinality, state),
+        );
+        node.counts = vec![0.0; cardinality];
+    }
+    BayesianNetwork { nodes }
+}
+
+/// Whether `x` and `y` are d-separated by `given`, ignoring the edges out of
+/// `cut`. Uses the moral graph of the ancestors of `x`, `y` and `given`.
+pub(crate) fn d_separated(
+    network: &BayesianNetwork,
+    x: &BTreeSet<&str>,
+    y: &BTreeSet<&str>,
+    given: &BTreeSet<&str>,
+    cut: Option<&str>,
+) -> bool {
+    let parents = |node: &str| -> Vec<&str> {
+        network.nodes[node]
+            .parents
+            .iter()
+            .map(String::as_str)
+            .filter(|&p| Some(p) != cut)
+            .collect()
+    };
+
+    let mut relevant: HashSet<&str> = HashSet::new();
+    let mut stack: Vec<&str> = x.iter().chain(y).chain(given).copied().collect();
+    while let Some(node) = stack.pop() {
+        if relevant.insert(node) {
+            stack.extend(parents(node));
+        }
+    }
+
+    let mut neighbours: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for &node in &relevant {
+        let node_parents = parents(node);
+        for (i, &parent) in node_parents.iter().enumerate() {
+            neighbours.entry(node).or_default().insert(parent);
+            neighbours.entry(parent).or_default().insert(node);
+            for &other in &node_parents[i + 1..] {
+                neighbours.entry(parent).or_default().insert(other);
+                neighbours.entry(other).or_default().insert(parent);
+            }
+        }
+    }
+
+    let mut visited: HashSet<&str> = x.iter().copied().collect();
+    let mut stack: Vec<&str> = x.iter().copied().collect();
+    while let Some(node) = stack.pop() {
+        if y.contains(node) {
+            return false;
+        }
+        for &next in neighbours.get(node).into_iter().flatten() {
+            if !given.contains(next) && visited.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    true
+}
+
+/// Every node reachable from `node` along directed edges, `node` excluded.
+fn descendants<'a>(network: &'a BayesianNetwork, node: &str) -> HashSet<&'a str> {
+    let mut found = HashSet::new();
+    let mut stack = vec![node.to_string()];
+    while let Some(current) = stack.pop() {
+        for child in network.nodes.values() {
+            if child.parents.contains(&current) && found.insert(child.name.as_str()) {
+                stack.push(child.name.clone());
+            }
+        }
+    }
+    found
+}
+
+/// Pearl's backdoor criterion: `set` holds no descendant of `treatment` and blocks
+/// every path between `treatment` and `outcome` that starts with an edge into
+/// `treatment`.
+pub(crate) fn satisfies_backdoor(
+    network: &BayesianNetwork,
+    treatment: &str,
+    outcome: &str,
+    set: &BTreeSet<&str>,
+) -> bool {
+    if set.contains(treatment) || set.contains(outcome) {
+        return false;
+    }
+    let downstream = descendants(network, treatment);
+    if set.iter().any(|node| downstream.contains(node)) {
+        return false;
+    }
+    d_separated(
+        network,
+        &BTreeSet::from([treatment]),
+        &BTreeSet::from([outcome]),
+        set,
+        Some(treatment),
+    )
+}
+
+/// A smallest set satisfying the backdoor criterion, drawn from the ancestors of
+/// `treatment` and `outcome`; among sets of the same size, the first by node name.
+pub(crate) fn backdoor_adjustment_set(
+    network: &BayesianNetwork,
+    treatment: &str,
+    outcome: &str,
+) -> Option<Vec<String>> {
+    let downstream = descendants(network, treatment);
+    let mut ancestors: BTreeSet<&str> = BTreeSet::new();
+    let mut stack = vec![treatment, outcome];
+    while let Some(node) = stack.pop() {
+        for parent in &network.nodes[node].parents {
+            if ancestors.insert(parent.as_str()) {
+                stack.push(parent.as_str());
+            }
+        }
+    }
+    let candidates: Vec<&str> = ancestors
+        .into_iter()
+        .filter(|&node| node != treatment && node != outcome && !downstream.contains(node))
+        .collect();
+
+    // If any adjustment set exists, the whole candidate set is one.
+    let all: BTreeSet<&str> = candidates.iter().copied().collect();
+    if !satisfies_backdoor(network, treatment, outcome, &all) {
+        return None;
+    }
+    let indices: Vec<usize> = (0..candidates.len()).collect();
+    for size in 0..=candidates.len() {
+        for subset in combinations(&indices, size) {
+            let set: BTreeSet<&str> = subset.iter().map(|&i| candidates[i]).collect();
+            if satisfies_backdoor(network, treatment, outcome, &set) {
+                return Some(set.into_iter().map(str::to_string).collect());
+            }
+        }
+    }
+    None
+}
+
+/// `P(outcome | do(treatment = state))` by the adjustment formula
+/// `sum_z P(outcome | treatment, z) P(z)`, or `None` if some `z` with `P(z) > 0`
+/// has `P(treatment = state, z) = 0`.
+pub(crate) fn adjust(
+    network: &BayesianNetwork,
+    treatment: &str,
+    state: usize,
+    outcome: &str,
+    set: &[String],
+) -> Option<Vec<f64>> {
+    let factors: Vec<Factor> = network.nodes.values().map(|node| node.factor()).collect();
+    let hidden: Vec<String> = network
+        .nodes
+        .keys()
+        .filter(|name| !set.contains(name))
+        .cloned()
+        .collect();
+    let joint = eliminate(factors, &hidden, &EliminationOrder::default());
+
+    let mut result = vec![0.0; network.nodes[outcome].states.len()];
+    for (index, &weight) in joint.values.iter().enumerate() {
+        if weight <= 0.0 {
+            continue;
+        }
+        let mut evidence: HashMap<String, usize> = joint
+            .variables
+            .iter()
+            .cloned()
+            .zip(joint.assignment(index))
+            .collect();
+        evidence.insert(treatment.to_string(), state);
+        let conditional = network.posterior(outcome, &evidence, &EliminationOrder::default())?;
+        for (total, p) in result.iter_mut().zip(conditional) {
+            *total += weight * p;
+        }
+    }
+    Some(result)
+}
\ No newline at end of file
@@ -0,0 +1,427 @@
+// src/bayesian_network/dynamic.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BAYESIAN-NETWORK]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::elimination::{eliminate, product_of, EliminationOrder};
+use super::factor::Factor;
+use super::inference::Distribution;
+use super::{boolean_table, one_hot_state, validate_cpt, BayesianNetwork, BOOLEAN_STATES};
+use crate::errors::MetaSyntraXLError;
+use std::collections::HashMap;
+
+/// Separates a variable's name from its time step in unrolled networks, e.g. `Rain@3`.
+const SLICE_SEPARATOR: char = '@';
+
+/// A dynamic Bayesian network in two-slice (2-TBN) form: an initial distribution
+/// over the variables at step 0 and a transition model giving each variable at
+/// step `t` in terms of the variables at step `t` and `t - 1`.
+///
+/// Filtering and smoothing keep the joint distribution of one step as the belief
+/// state, so they are exponential in the number of variables per step but linear
+/// in the length of the sequence.
+pub struct DynamicBayesianNetwork {
+    /// The network unrolled to two steps: slice 0 holds the initial CPTs, slice 1
+    /// the transition CPTs.
+    two_slice: BayesianNetwork,
+}
+
+impl Default for DynamicBayesianNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DynamicBayesianNetwork {
+    /// Initializes a new dynamic Bayesian network without variables.
+    pub fn new() -> Self {
+        Self {
+            two_slice: BayesianNetwork::new(),
+        }
+    }
+
+    /// Adds a discrete variable to every step, replacing any variable with the same
+    /// name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable; it may not contain `@`.
+    /// * `states` - The names of the variable's states.
+    /// * `parents` - Parents within the same step, each already added.
+    /// * `initial_cpt` - The dense CPT at step 0 over `parents`, laid out as described
+    ///   on [`BayesianNetwork::add_variable`].
+    /// * `previous` - Parents at the previous step, each already added or `name`
+    ///   itself. Re-adding a variable lets it depend on one added after it.
+    /// * `transition_cpt` - The dense CPT at later steps over `previous` followed by
+    ///   `parents`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if the name contains `@`, a parent is unknown,
+    /// or either CPT is rejected by [`BayesianNetwork::add_variable`]. The network is
+    /// left unchanged.
+    pub fn add_variable(
+        &mut self,
+        name: String,
+        states: Vec<String>,
+        parents: Vec<String>,
+        initial_cpt: Vec<f64>,
+        previous: Vec<String>,
+        transition_cpt: Vec<f64>,
+    ) -> Result<(), MetaSyntraXLError> {
+        if name.contains(SLICE_SEPARATOR) {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "Variable name `{}` may not contain `{}`",
+                name, SLICE_SEPARATOR
+            )));
+        }
+        if let Some(parent) = parents.iter().find(|p| !self.contains(p)) {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "Variable `{}` has unknown parent `{}`",
+                name, parent
+            )));
+        }
+        if let Some(parent) = previous.iter().find(|p| **p != name && !self.contains(p)) {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "Variable `{}` has unknown previous-step parent `{}`",
+                name, parent
+            )));
+        }
+
+        let saved = self.two_slice.nodes.clone();
+        let initial_parents = parents.iter().map(|p| slice_name(p, 0)).collect();
+        let mut transition_parents: Vec<String> =
+            previous.iter().map(|p| slice_name(p, 0)).collect();
+        transition_parents.extend(parents.iter().map(|p| slice_name(p, 1)));
+        let result = self
+            .two_slice
+            .add_variable(
+                slice_name(&name, 0),
+                states.clone(),
+                initial_parents,
+                initial_cpt,
+            )
+            .and_then(|()| {
+                self.two_slice.add_variable(
+                    slice_name(&name, 1),
+                    states,
+                    transition_parents,
+                    transition_cpt,
+                )
+            });
+        if result.is_err() {
+            self.two_slice.nodes = saved;
+        }
+        result
+    }
+
+    /// Adds a boolean variable to every step, replacing any variable with the same
+    /// name. The variable gets the states `false` and `true`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable; it may not contain `@`.
+    /// * `parents` - Boolean parents within the same step, each already added.
+    /// * `initial` - The CPT at step 0, mapping parent values (in `parents` order)
+    ///   to the probability that the variable is `true`.
+    /// * `previous` - Boolean parents at the previous step, see
+    ///   [`DynamicBayesianNetwork::add_variable`].
+    /// * `transition` - The CPT at later steps, keyed by the values of `previous`
+    ///   followed by `parents`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if a parent is not boolean, either CPT is not a
+    /// complete table of probabilities, or for any reason listed on
+    /// [`DynamicBayesianNetwork::add_variable`].
+    pub fn add_node(
+        &mut self,
+        name: String,
+        parents: Vec<String>,
+        initial: HashMap<Vec<bool>, f64>,
+        previous: Vec<String>,
+        transition: HashMap<Vec<bool>, f64>,
+    ) -> Result<(), MetaSyntraXLError> {
+        let mut transition_parents = previous.clone();
+        transition_parents.extend(parents.iter().cloned());
+        for parent in &transition_parents {
+            if matches!(self.states(parent), Some(states) if states != BOOLEAN_STATES) {
+                return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                    "Boolean CPT of `{}` cannot condition on non-boolean parent `{}`",
+                    name, parent
+                )));
+            }
+        }
+        validate_cpt(&name, &parents, &initial)?;
+        validate_cpt(&name, &transition_parents, &transition)?;
+        let initial = boolean_table(parents.len(), &initial);
+        let transition = boolean_table(transition_parents.len(), &transition);
+        let states = BOOLEAN_STATES.iter().map(|s| s.to_string()).collect();
+        self.add_variable(name, states, parents, initial, previous, transition)
+    }
+
+    /// The names of the variables of one step, sorted.
+    pub fn variables(&self) -> Vec<String> {
+        let mut variables: Vec<String> = self
+            .two_slice
+            .nodes
+            .keys()
+            .map(|name| split_slice(name))
+            .filter(|&(_, slice)| slice == 0)
+            .map(|(name, _)| name.to_string())
+            .collect();
+        variables.sort();
+        variables
+    }
+
+    /// The names of a variable's states, or `None` if the variable is unknown.
+    pub fn states(&self, variable: &str) -> Option<&[String]> {
+        self.two_slice.states(&slice_name(variable, 0))
+    }
+
+    fn contains(&self, variable: &str) -> bool {
+        self.two_slice.nodes.contains_key(&slice_name(variable, 0))
+    }
+
+    /// Unrolls the network into a static one over `horizon` steps, where variable
+    /// `X` at step `t` is the node `X@t`.
+    pub fn unroll(&self, horizon: usize) -> BayesianNetwork {
+        let mut nodes = HashMap::new();
+        for step in 0..horizon {
+            let slice = step.min(1);
+            // Slice 0 of the transition model is the step before `step`.
+            let rename = |variable: &mut String| {
+                let (name, from) = split_slice(variable);
+                *variable = slice_name(name, step + from - slice);
+            };
+            for node in self.two_slice.nodes.values() {
+                if split_slice(&node.name).1 != slice {
+                    continue;
+                }
+                let mut node = node.clone();
+                rename(&mut node.name);
+                node.parents.iter_mut().for_each(rename);
+                node.cpt.variables.iter_mut().for_each(rename);
+                nodes.insert(node.name.clone(), node);
+            }
+        }
+        BayesianNetwork { nodes }
+    }
+
+    /// Forward filtering: the distribution of every variable at each step given
+    /// the evidence up to and including that step.
+    ///
+    /// # Arguments
+    ///
+    /// * `evidence` - One map per step from variable names to observed state names;
+    ///   the result has one entry per step.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if the evidence names an unknown variable or
+    /// state, or has probability zero.
+    pub fn filter(
+        &self,
+        evidence: &[HashMap<String, String>],
+    ) -> Result<Vec<HashMap<String, Distribution>>, MetaSyntraXLError> {
+        let evidence = self.step_evidence(evidence)?;
+        let beliefs = self.forward(&evidence)?;
+        Ok(beliefs
+            .iter()
+            .zip(&evidence)
+            .map(|(belief, observed)| self.distributions(belief, observed))
+            .collect())
+    }
+
+    /// Smoothing by forward-backward: the distribution of every variable at each
+    /// step given the evidence of the whole sequence.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` for the same reasons as
+    /// [`DynamicBayesianNetwork::filter`].
+    pub fn smooth(
+        &self,
+        evidence: &[HashMap<String, String>],
+    ) -> Result<Vec<HashMap<String, Distribution>>, MetaSyntraXLError> {
+        let evidence = self.step_evidence(evidence)?;
+        let beliefs = self.forward(&evidence)?;
+
+        let mut smoothed = Vec::with_capacity(beliefs.len());
+        // P(evidence after `step` | state at `step`), up to scale, over slice 0.
+        let mut backward = Factor::unit();
+        for step in (0..beliefs.len()).rev() {
+            let posterior = beliefs[step]
+                .product(&backward)
+                .normalized()
+                .ok_or_else(|| zero_probability(beliefs.len() - 1))?;
+            smoothed.push(self.distributions(&posterior, &evidence[step]));
+            if step == 0 {
+                break;
+            }
+
+            let mut factors = self.transition_factors(&evidence[step - 1], &evidence[step]);
+            factors.push(in_slice(backward, 1));
+            let hidden: Vec<String> = beliefs[step]
+                .variables
+                .iter()
+                .map(|variable| slice_name(split_slice(variable).0, 1))
+                .collect();
+            backward = eliminate(factors, &hidden, &EliminationOrder::default())
+                .normalized()
+                .ok_or_else(|| zero_probability(beliefs.len() - 1))?;
+        }
+        smoothed.reverse();
+        Ok(smoothed)
+    }
+
+    /// The filtered belief state of every step: the joint distribution of that
+    /// step's unobserved variables, named as in slice 0.
+    fn forward(
+        &self,
+        evidence: &[HashMap<String, usize>],
+    ) -> Result<Vec<Factor>, MetaSyntraXLError> {
+        let mut beliefs: Vec<Factor> = Vec::with_capacity(evidence.len());
+        for (step, observed) in evidence.iter().enumerate() {
+            let belief = match beliefs.last() {
+                None => {
+                    let observed = in_slice_evidence(observed, 0);
+                    let factors: Vec<Factor> = self
+                        .slice_factors(0)
+                        .map(|factor| factor.reduce(&observed))
+                        .collect();
+                    product_of(&factors)
+                }
+                Some(previous) => {
+                    let mut factors = self.transition_factors(&evidence[step - 1], observed);
+                    factors.push(previous.clone());
+                    let joint =
+                        eliminate(factors, &previous.variables, &EliminationOrder::default());
+                    in_slice(joint, 0)
+                }
+            };
+            let belief = belief.normalized().ok_or_else(|| zero_probability(step))?;
+            beliefs.push(belief);
+        }
+        Ok(beliefs)
+    }
+
+    /// The CPT factors of one slice of the two-slice network.
+    fn slice_factors(&self, slice: usize) -> impl Iterator<Item = Factor> + '_ {
+        self.two_slice
+            .nodes
+            .values()
+            .filter(move |node| split_slice(&node.name).1 == slice)
+            .map(|node| node.factor())
+    }
+
+    /// The transition CPTs reduced by the evidence of two consecutive steps.
+    fn transition_factors(
+        &self,
+        previous: &HashMap<String, usize>,
+        current: &HashMap<String, usize>,
+    ) -> Vec<Factor> {
+        let mut observed = in_slice_evidence(previous, 0);
+        observed.extend(in_slice_evidence(current, 1));
+        self.slice_factors(1)
+            .map(|factor| factor.reduce(&observed))
+            .collect()
+    }
+
+    /// Resolves the evidence of every step into state indices.
+    fn step_evidence(
+        &self,
+        evidence: &[HashMap<String, String>],
+    ) -> Result<Vec<HashMap<String, usize>>, MetaSyntraXLError> {
+        evidence
+            .iter()
+            .enumerate()
+            .map(|(step, observed)| {
+                observed
+                    .iter()
+                    .map(|(name, state)| {
+                        let states = self.states(name).ok_or_else(|| {
+                            MetaSyntraXLError::BayesianNetworkError(format!(
+                                "Evidence at step {} on unknown variable `{}`",
+                                step, name
+                            ))
+                        })?;
+                        let index = states.iter().position(|s| s == state).ok_or_else(|| {
+                            MetaSyntraXLError::BayesianNetworkError(format!(
+                                "Variable `{}` has no state `{}`",
+                                name, state
+                            ))
+                        })?;
+                        Ok((name.clone(), index))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The marginal of every variable in a belief state; observed variables put all
+    /// their mass on the observed state.
+    fn distributions(
+        &self,
+        belief: &Factor,
+        observed: &HashMap<String, usize>,
+    ) -> HashMap<String, Distribution> {
+        self.variables()
+            .into_iter()
+            .map(|name| {
+                let node = &self.two_slice.nodes[&slice_name(&name, 0)];
+                let probabilities = match observed.get(&name) {
+                    Some(&state) => one_hot_state(node.states.len(), state),
+                    None => {
+                        belief
+                            .variables
+                            .iter()
+                            .filter(|variable| **variable != node.name)
+                            .fold(belief.clone(), |marginal, variable| {
+                                marginal.sum_out(variable)
+                            })
+                            .values
+                    }
+                };
+                let states = node.states.clone();
+                (
+                    name,
+                    Distribution {
+                        states,
+                        probabilities,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+fn slice_name(name: &str, slice: usize) -> String {
+    format!("{}{}{}", name, SLICE_SEPARATOR, slice)
+}
+
+/// Splits `X@t` into `X` and `t`.
+fn split_slice(variable: &str) -> (&str, usize) {
+    let (name, slice) = variable
+        .rsplit_once(SLICE_SEPARATOR)
+        .expect("two-slice variables carry their slice");
+    (name, slice.parse().expect("slices are numbers"))
+}
+
+/// Moves every variable of `factor` into `slice`.
+fn in_slice(mut factor: Factor, slice: usize) -> Factor {
+    for variable in &mut factor.variables {
+        *variable = slice_name(split_slice(variable).0, slice);
+    }
+    factor
+}
+
+fn in_slice_evidence(evidence: &HashMap<String, usize>, slice: usize) -> HashMap<String, usize> {
+    evidence
+        .iter()
+        .map(|(name, &state)| (slice_name(name, slice), state))
+        .collect()
+}
+
+fn zero_probability(step: usize) -> MetaSyntraXLError {
+    MetaSyntraXLError::BayesianNetworkError(format!(
+        "Evidence up to step {} has probability zero",
+        step
+    ))
+}
\ No newline at end of file
@@ -0,0 +1,163 @@
+// src/bayesian_network/elimination.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BAYESIAN-NETWORK]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::factor::Factor;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Strategy for choosing the order in which hidden variables are summed out.
+///
+/// The order never changes the result, only the size of the intermediate factors.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum EliminationOrder {
+    /// Greedily eliminate the variable whose removal adds the fewest fill-in edges.
+    #[default]
+    MinFill,
+    /// Greedily eliminate the variable with the fewest neighbours.
+    MinDegree,
+    /// Eliminate in the given order; hidden variables not listed follow by name.
+    Fixed(Vec<String>),
+}
+
+/// Sums every variable in `hidden` out of the product of `factors`.
+pub(crate) fn eliminate(
+    factors: Vec<Factor>,
+    hidden: &[String],
+    order: &EliminationOrder,
+) -> Factor {
+    product_of(&sum_out_all(factors, hidden, order))
+}
+
+/// Sums `summed` out of the product of `factors`, then maximizes over `maxed`
+/// (max-product elimination). The two lists must cover every variable of the
+/// factors.
+///
+/// Returns the maximum and a state for every variable in `maxed` attaining it.
+pub(crate) fn maximize(
+    factors: Vec<Factor>,
+    summed: &[String],
+    maxed: &[String],
+    order: &EliminationOrder,
+) -> (f64, HashMap<String, usize>) {
+    let mut factors = sum_out_all(factors, summed, order);
+    // The product each variable was maximized out of, for the traceback.
+    let mut traces = Vec::with_capacity(maxed.len());
+    for variable in elimination_sequence(&factors, maxed, order) {
+        let (touching, rest): (Vec<Factor>, Vec<Factor>) =
+            factors.into_iter().partition(|f| f.contains(&variable));
+        factors = rest;
+        let product = product_of(&touching);
+        factors.push(product.max_out(&variable));
+        traces.push((variable, product));
+    }
+
+    let maximum = product_of(&factors).values[0];
+    let mut assignment = HashMap::with_capacity(traces.len());
+    for (variable, product) in traces.into_iter().rev() {
+        let state = product.argmax(&variable, &assignment);
+        assignment.insert(variable, state);
+    }
+    (maximum, assignment)
+}
+
+fn sum_out_all(factors: Vec<Factor>, hidden: &[String], order: &EliminationOrder) -> Vec<Factor> {
+    let mut factors = factors;
+    for variable in elimination_sequence(&factors, hidden, order) {
+        let (touching, rest): (Vec<Factor>, Vec<Factor>) =
+            factors.into_iter().partition(|f| f.contains(&variable));
+        factors = rest;
+        if !touching.is_empty() {
+            factors.push(product_of(&touching).sum_out(&variable));
+        }
+    }
+    factors
+}
+
+pub(crate) fn product_of(factors: &[Factor]) -> Factor {
+    factors
+        .iter()
+        .fold(Factor::unit(), |product, factor| product.product(factor))
+}
+
+fn elimination_sequence(
+    factors: &[Factor],
+    hidden: &[String],
+    order: &EliminationOrder,
+) -> Vec<String> {
+    match order {
+        EliminationOrder::Fixed(listed) => {
+            let mut sequence: Vec<String> = listed
+                .iter()
+                .filter(|v| hidden.contains(v))
+                .cloned()
+                .collect();
+            let unlisted: BTreeSet<&String> =
+                hidden.iter().filter(|v| !listed.contains(v)).collect();
+            sequence.extend(unlisted.into_iter().cloned());
+            sequence
+        }
+        EliminationOrder::MinFill | EliminationOrder::MinDegree => {
+            greedy_sequence(factors, hidden, order)
+        }
+    }
+}
+
+fn greedy_sequence(factors: &[Factor], hidden: &[String], order: &EliminationOrder) -> Vec<String> {
+    let mut neighbours: HashMap<String, HashSet<String>> = HashMap::new();
+    for factor in factors {
+        for a in &factor.variables {
+            let entry = neighbours.entry(a.clone()).or_default();
+            entry.extend(factor.variables.iter().filter(|b| *b != a).cloned());
+        }
+    }
+
+    // A BTreeSet keeps tie-breaking by name, so the order is deterministic.
+    let mut remaining: BTreeSet<String> = hidden.iter().cloned().collect();
+    let mut sequence = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let next = remaining
+            .iter()
+            .min_by_key(|v| score(&neighbours, v, order))
+            .cloned()
+            .expect("remaining is not empty");
+
+        let adjacent: Vec<String> = neighbours
+            .remove(&next)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        for a in &adjacent {
+            if let Some(links) = neighbours.get_mut(a) {
+                links.remove(&next);
+                links.extend(adjacent.iter().filter(|b| *b != a).cloned());
+            }
+        }
+
+        remaining.remove(&next);
+        sequence.push(next);
+    }
+    sequence
+}
+
+fn score(
+    neighbours: &HashMap<String, HashSet<String>>,
+    variable: &str,
+    order: &EliminationOrder,
+) -> usize {
+    let adjacent = match neighbours.get(variable) {
+        Some(adjacent) => adjacent,
+        None => return 0,
+    };
+    match order {
+        EliminationOrder::MinDegree => adjacent.len(),
+        _ => {
+            let adjacent: Vec<&String> = adjacent.iter().collect();
+            let mut fill = 0;
+            for (i, a) in adjacent.iter().enumerate() {
+                for b in &adjacent[i + 1..] {
+                    if !matches!(neighbours.get(*a), Some(links) if links.contains(*b)) {
+                        fill += 1;
+                    }
+                }
+            }
+            fill
+        }
+    }
+}
@@ -0,0 +1,136 @@
+// src/bayesian_network/explanation.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BAYESIAN-NETWORK]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::elimination::{eliminate, maximize, EliminationOrder};
+use super::factor::Factor;
+use super::BayesianNetwork;
+use std::collections::HashMap;
+
+/// A joint state of some nodes, see [`super::BayesianNetwork::most_probable_explanation`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    /// The state of every assigned node; observed nodes are not included.
+    pub states: HashMap<String, String>,
+    /// The probability of the joint state given the evidence.
+    pub probability: f64,
+}
+
+/// Why the network believes what it does about one node, see
+/// [`super::BayesianNetwork::explain`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub query: String,
+    pub state: String,
+    /// `P(query = state | evidence)`.
+    pub posterior: f64,
+    /// `P(query = state)` before any evidence.
+    pub prior: f64,
+    /// One entry per observed node, largest absolute impact first.
+    pub influences: Vec<Influence>,
+    /// The most probable joint state of the unobserved nodes given the evidence.
+    pub most_probable_explanation: Assignment,
+}
+
+/// How one piece of evidence moved the posterior of an [`Explanation`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Influence {
+    pub node: String,
+    pub state: String,
+    /// The posterior with every observation except this one.
+    pub posterior_without: f64,
+    /// `posterior - posterior_without`; positive when the observation supports the state.
+    pub impact: f64,
+}
+
+/// The most probable joint state of `query` given `evidence`, summing out every
+/// other unobserved node, or `None` if the evidence has probability zero.
+pub(crate) fn maximum_a_posteriori(
+    network: &BayesianNetwork,
+    query: &[String],
+    evidence: &HashMap<String, usize>,
+) -> Option<Assignment> {
+    let factors: Vec<Factor> = network
+        .nodes
+        .values()
+        .map(|node| node.factor().reduce(evidence))
+        .collect();
+    let hidden: Vec<String> = network
+        .nodes
+        .keys()
+        .filter(|name| !evidence.contains_key(*name))
+        .cloned()
+        .collect();
+    let order = EliminationOrder::default();
+    let evidence_probability: f64 = eliminate(factors.clone(), &hidden, &order)
+        .values
+        .iter()
+        .sum();
+    if evidence_probability <= 0.0 || !evidence_probability.is_finite() {
+        return None;
+    }
+
+    let (maxed, summed): (Vec<String>, Vec<String>) =
+        hidden.into_iter().partition(|name| query.contains(name));
+    let (maximum, states) = maximize(factors, &summed, &maxed, &order);
+    let states = states
+        .into_iter()
+        .map(|(name, state)| {
+            let state = network.nodes[&name].states[state].clone();
+            (name, state)
+        })
+        .collect();
+    Some(Assignment {
+        states,
+        probability: maximum / evidence_probability,
+    })
+}
+
+/// Explains `P(query = state | evidence)` by leaving each observation out in
+/// turn, or `None` if the evidence has probability zero.
+pub(crate) fn explain(
+    network: &BayesianNetwork,
+    query: &str,
+    state: usize,
+    evidence: &HashMap<String, usize>,
+) -> Option<Explanation> {
+    let order = EliminationOrder::default();
+    let probability = |evidence: &HashMap<String, usize>| -> Option<f64> {
+        match evidence.get(query) {
+            Some(&observed) => Some(if observed == state { 1.0 } else { 0.0 }),
+            None => Some(network.posterior(query, evidence, &order)?[state]),
+        }
+    };
+
+    let posterior = probability(evidence)?;
+    let prior = probability(&HashMap::new())?;
+    let mut influences: Vec<Influence> = evidence
+        .iter()
+        .map(|(node, &observed)| {
+            let mut rest = evidence.clone();
+            rest.remove(node);
+            // Dropping an observation cannot make the evidence impossible.
+            let posterior_without = probability(&rest).unwrap_or(prior);
+            Influence {
+                node: node.clone(),
+                state: network.nodes[node].states[observed].clone(),
+                posterior_without,
+                impact: posterior - posterior_without,
+            }
+        })
+        .collect();
+    influences.sort_by(|a, b| {
+        b.impact
+            .abs()
+            .partial_cmp(&a.impact.abs())
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.node.cmp(&b.node))
+    });
+
+    let all: Vec<String> = network.nodes.keys().cloned().collect();
+    Some(Explanation {
+        query: query.to_string(),
+        state: network.nodes[query].states[state].clone(),
+        posterior,
+        prior,
+        influences,
+        most_probable_explanation: maximum_a_posteriori(network, &all, evidence)?,
+    })
+}
\ No newline at end of file
@@ -0,0 +1,191 @@
+// src/bayesian_network/factor.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BAYESIAN-NETWORK]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use std::collections::HashMap;
+
+/// A non-negative table over discrete variables.
+///
+/// Values are stored row-major with the last variable varying fastest, so a
+/// factor over `[a, b]` holds `f(a=0,b=0), f(a=0,b=1), f(a=1,b=0), ...`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Factor {
+    pub(crate) variables: Vec<String>,
+    pub(crate) cardinalities: Vec<usize>,
+    pub(crate) values: Vec<f64>,
+}
+
+impl Factor {
+    pub(crate) fn new(variables: Vec<String>, cardinalities: Vec<usize>, values: Vec<f64>) -> Self {
+        debug_assert_eq!(values.len(), cardinalities.iter().product::<usize>());
+        Self {
+            variables,
+            cardinalities,
+            values,
+        }
+    }
+
+    /// The factor over no variables with value one, the identity of [`Factor::product`].
+    pub(crate) fn unit() -> Self {
+        Self::new(Vec::new(), Vec::new(), vec![1.0])
+    }
+
+    pub(crate) fn contains(&self, variable: &str) -> bool {
+        self.variables.iter().any(|v| v == variable)
+    }
+
+    /// Decodes a flat index into one state per variable.
+    pub(crate) fn assignment(&self, index: usize) -> Vec<usize> {
+        decode(&self.cardinalities, index)
+    }
+
+    /// Encodes one state per variable into a flat index.
+    pub(crate) fn index(&self, assignment: &[usize]) -> usize {
+        assignment
+            .iter()
+            .zip(&self.cardinalities)
+            .fold(0, |index, (&state, &cardinality)| {
+                index * cardinality + state
+            })
+    }
+
+    pub(crate) fn product(&self, other: &Factor) -> Factor {
+        let mut variables = self.variables.clone();
+        let mut cardinalities = self.cardinalities.clone();
+        for (variable, &cardinality) in other.variables.iter().zip(&other.cardinalities) {
+            if !variables.contains(variable) {
+                variables.push(variable.clone());
+                cardinalities.push(cardinality);
+            }
+        }
+
+        let left_positions = positions(&self.variables, &variables);
+        let right_positions = positions(&other.variables, &variables);
+        let size: usize = cardinalities.iter().product();
+        let values = (0..size)
+            .map(|index| {
+                let assignment = decode(&cardinalities, index);
+                let left = self.index(&project(&assignment, &left_positions));
+                let right = other.index(&project(&assignment, &right_positions));
+                self.values[left] * other.values[right]
+            })
+            .collect();
+        Factor::new(variables, cardinalities, values)
+    }
+
+    /// Sums `variable` out of the factor.
+    pub(crate) fn sum_out(&self, variable: &str) -> Factor {
+        self.eliminate(variable, |acc, value| acc + value)
+    }
+
+    /// Maximizes `variable` out of the factor.
+    pub(crate) fn max_out(&self, variable: &str) -> Factor {
+        self.eliminate(variable, f64::max)
+    }
+
+    /// The state of `variable` with the largest value once every other variable of
+    /// the factor is fixed by `assignment`; ties go to the lowest state.
+    pub(crate) fn argmax(&self, variable: &str, assignment: &HashMap<String, usize>) -> usize {
+        let row = self.reduce(assignment);
+        if row.variables != [variable] {
+            return 0;
+        }
+        let mut best = 0;
+        for (state, &value) in row.values.iter().enumerate() {
+            if value > row.values[best] {
+                best = state;
+            }
+        }
+        best
+    }
+
+    /// Keeps only the rows consistent with `evidence` and drops the observed variables.
+    pub(crate) fn reduce(&self, evidence: &HashMap<String, usize>) -> Factor {
+        let kept: Vec<usize> = (0..self.variables.len())
+            .filter(|&i| !evidence.contains_key(&self.variables[i]))
+            .collect();
+        if kept.len() == self.variables.len() {
+            return self.clone();
+        }
+
+        let mut values = Vec::new();
+        for (index, &value) in self.values.iter().enumerate() {
+            let assignment = self.assignment(index);
+            let consistent =
+                self.variables.iter().zip(&assignment).all(
+                    |(variable, state)| !matches!(evidence.get(variable), Some(s) if s != state),
+                );
+            if consistent {
+                values.push(value);
+            }
+        }
+
+        Factor::new(
+            kept.iter().map(|&i| self.variables[i].clone()).collect(),
+            kept.iter().map(|&i| self.cardinalities[i]).collect(),
+            values,
+        )
+    }
+
+    /// Scales the values to sum to one; returns `None` if they sum to zero.
+    pub(crate) fn normalized(&self) -> Option<Factor> {
+        let total: f64 = self.values.iter().sum();
+        if total <= 0.0 || !total.is_finite() {
+            return None;
+        }
+        let mut normalized = self.clone();
+        normalized.values.iter_mut().for_each(|v| *v /= total);
+        Some(normalized)
+    }
+
+    fn eliminate(&self, variable: &str, combine: impl Fn(f64, f64) -> f64) -> Factor {
+        let position = match self.variables.iter().position(|v| v == variable) {
+            Some(position) => position,
+            None => return self.clone(),
+        };
+
+        let mut variables = self.variables.clone();
+        let mut cardinalities = self.cardinalities.clone();
+        variables.remove(position);
+        cardinalities.remove(position);
+
+        let size = cardinalities.iter().product();
+        let mut result = Factor::new(variables, cardinalities, vec![0.0; size]);
+        let mut seen = vec![false; size];
+        for (index, &value) in self.values.iter().enumerate() {
+            let mut assignment = self.assignment(index);
+            assignment.remove(position);
+            let target = result.index(&assignment);
+            result.values[target] = if seen[target] {
+                combine(result.values[target], value)
+            } else {
+                value
+            };
+            seen[target] = true;
+        }
+        result
+    }
+}
+
+fn decode(cardinalities: &[usize], mut index: usize) -> Vec<usize> {
+    let mut assignment = vec![0; cardinalities.len()];
+    for (slot, &cardinality) in assignment.iter_mut().zip(cardinalities).rev() {
+        *slot = index % cardinality;
+        index /= cardinality;
+    }
+    assignment
+}
+
+/// For each variable of `subset`, its position within `superset`.
+fn positions(subset: &[String], superset: &[String]) -> Vec<usize> {
+    subset
+        .iter()
+        .map(|v| {
+            superset
+                .iter()
+                .position(|s| s == v)
+                .expect("subset variable")
+        })
+        .collect()
+}
+
+fn project(assignment: &[usize], positions: &[usize]) -> Vec<usize> {
+    positions.iter().map(|&p| assignment[p]).collect()
+}
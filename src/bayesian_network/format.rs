@@ -0,0 +1,879 @@
+// src/bayesian_network/format.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BAYESIAN-NETWORK]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::BayesianNetwork;
+use crate::errors::MetaSyntraXLError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+/// Serialized form of a network: its variables in topological order.
+#[derive(Serialize, Deserialize)]
+struct NetworkSpec {
+    variables: Vec<VariableSpec>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct VariableSpec {
+    name: String,
+    states: Vec<String>,
+    #[serde(default)]
+    parents: Vec<String>,
+    /// Laid out as in [`BayesianNetwork::add_variable`].
+    cpt: Vec<f64>,
+    /// The counts behind the CPT, omitted when nothing has been observed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    counts: Vec<f64>,
+}
+
+impl Serialize for BayesianNetwork {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        specs(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BayesianNetwork {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let spec = NetworkSpec::deserialize(deserializer)?;
+        assemble(spec.variables).map_err(serde::de::Error::custom)
+    }
+}
+
+fn specs(network: &BayesianNetwork) -> NetworkSpec {
+    let variables = network
+        .ordered_nodes()
+        .into_iter()
+        .map(|node| VariableSpec {
+            name: node.name.clone(),
+            states: node.states.clone(),
+            parents: node.parents.clone(),
+            cpt: node.cpt.values.clone(),
+            counts: if node.counts.iter().all(|&c| c == 0.0) {
+                Vec::new()
+            } else {
+                node.counts.clone()
+            },
+        })
+        .collect();
+    NetworkSpec { variables }
+}
+
+/// Builds a network from variables listed in any order, adding each one after its
+/// parents.
+fn assemble(variables: Vec<VariableSpec>) -> Result<BayesianNetwork, MetaSyntraXLError> {
+    let mut declared = HashSet::new();
+    for variable in &variables {
+        if !declared.insert(variable.name.as_str()) {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "Variable `{}` is declared twice",
+                variable.name
+            )));
+        }
+    }
+    for variable in &variables {
+        if let Some(parent) = variable
+            .parents
+            .iter()
+            .find(|p| !declared.contains(p.as_str()))
+        {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "Parent `{}` of `{}` is not declared",
+                parent, variable.name
+            )));
+        }
+    }
+
+    let mut network = BayesianNetwork::new();
+    let mut pending = variables;
+    while !pending.is_empty() {
+        let (ready, blocked): (Vec<VariableSpec>, Vec<VariableSpec>) = pending
+            .into_iter()
+            .partition(|v| v.parents.iter().all(|p| network.nodes.contains_key(p)));
+        if ready.is_empty() {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "Variables {} form a cycle",
+                blocked
+                    .iter()
+                    .map(|v| format!("`{}`", v.name))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )));
+        }
+        for variable in ready {
+            if !variable.counts.is_empty()
+                && (variable.counts.len() != variable.cpt.len()
+                    || variable
+                        .counts
+                        .iter()
+                        .any(|c| !(*c >= 0.0 && c.is_finite())))
+            {
+                return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                    "Counts of `{}` must be {} finite, non-negative values",
+                    variable.name,
+                    variable.cpt.len()
+                )));
+            }
+            let name = variable.name.clone();
+            network.add_variable(
+                variable.name,
+                variable.states,
+                variable.parents,
+                variable.cpt,
+            )?;
+            if !variable.counts.is_empty() {
+                network.nodes.get_mut(&name).expect("just added").counts = variable.counts;
+            }
+        }
+        pending = blocked;
+    }
+    Ok(network)
+}
+
+/// Writes the network in the Bayesian Interchange Format, one `variable` and one
+/// `probability` block per node in topological order.
+pub(crate) fn to_bif(network: &BayesianNetwork) -> String {
+    let mut out = String::from("network unknown {\n}\n");
+    let spec = specs(network);
+    for variable in &spec.variables {
+        let states: Vec<String> = variable.states.iter().map(|s| bif_word(s)).collect();
+        let _ = writeln!(out, "variable {} {{", bif_word(&variable.name));
+        let _ = writeln!(
+            out,
+            "  type discrete [ {} ] {{ {} }};",
+            states.len(),
+            states.join(", ")
+        );
+        out.push_str("}\n");
+    }
+    for variable in &spec.variables {
+        let node = &network.nodes[&variable.name];
+        let mut head = bif_word(&variable.name);
+        if !variable.parents.is_empty() {
+            let parents: Vec<String> = variable.parents.iter().map(|p| bif_word(p)).collect();
+            let _ = write!(head, " | {}", parents.join(", "));
+        }
+        let _ = writeln!(out, "probability ( {} ) {{", head);
+        let rows = variable.cpt.chunks(variable.states.len());
+        if variable.parents.is_empty() {
+            let _ = writeln!(out, "  table {};", numbers(&variable.cpt, ", "));
+        } else {
+            for (index, row) in rows.enumerate() {
+                let assignment = node.cpt.assignment(index * variable.states.len());
+                let states: Vec<String> = variable
+                    .parents
+                    .iter()
+                    .zip(&assignment)
+                    .map(|(parent, &state)| bif_word(&network.nodes[parent].states[state]))
+                    .collect();
+                let _ = writeln!(out, "  ({}) {};", states.join(", "), numbers(row, ", "));
+            }
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+/// Reads a network in the Bayesian Interchange Format.
+///
+/// Supports discrete variables, `property` entries (ignored), CPTs given as rows
+/// per parent configuration with an optional `default` row, and `table` entries
+/// laid out as in [`BayesianNetwork::add_variable`]. Names may be double-quoted, with
+/// `\"` and `\\` escaping a quote and a backslash.
+pub(crate) fn from_bif(text: &str) -> Result<BayesianNetwork, MetaSyntraXLError> {
+    let mut parser = BifParser {
+        tokens: tokenize(text)?,
+        position: 0,
+    };
+    let mut states: HashMap<String, Vec<String>> = HashMap::new();
+    let mut order = Vec::new();
+    let mut tables: HashMap<String, (Vec<String>, Vec<BifEntry>)> = HashMap::new();
+
+    while let Some(keyword) = parser.next_word_or_end()? {
+        match keyword.as_str() {
+            "network" => {
+                parser.word()?;
+                parser.skip_block()?;
+            }
+            "variable" => {
+                let name = parser.word()?;
+                let variable_states = parser.variable_body(&name)?;
+                if states.insert(name.clone(), variable_states).is_some() {
+                    return Err(parser.error(&format!("Variable `{}` is declared twice", name)));
+                }
+                order.push(name);
+            }
+            "probability" => {
+                let (child, parents, entries) = parser.probability()?;
+                if tables.insert(child.clone(), (parents, entries)).is_some() {
+                    return Err(parser.error(&format!("`{}` has two probability blocks", child)));
+                }
+            }
+            other => return Err(parser.error(&format!("Unexpected `{}`", other))),
+        }
+    }
+
+    if let Some(child) = tables.keys().find(|child| !states.contains_key(*child)) {
+        return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+            "Probability block for undeclared variable `{}`",
+            child
+        )));
+    }
+    let variables = order
+        .into_iter()
+        .map(|name| {
+            let (parents, entries) = tables.remove(&name).ok_or_else(|| {
+                MetaSyntraXLError::BayesianNetworkError(format!(
+                    "Variable `{}` has no probability block",
+                    name
+                ))
+            })?;
+            let cpt = bif_table(&name, &parents, &entries, &states)?;
+            Ok(VariableSpec {
+                states: states[&name].clone(),
+                name,
+                parents,
+                cpt,
+                counts: Vec::new(),
+            })
+        })
+        .collect::<Result<Vec<_>, MetaSyntraXLError>>()?;
+    assemble(variables)
+}
+
+enum BifEntry {
+    Table(Vec<f64>),
+    Default(Vec<f64>),
+    Row(Vec<String>, Vec<f64>),
+}
+
+/// Lays the entries of a `probability` block out as a dense CPT.
+fn bif_table(
+    name: &str,
+    parents: &[String],
+    entries: &[BifEntry],
+    states: &HashMap<String, Vec<String>>,
+) -> Result<Vec<f64>, MetaSyntraXLError> {
+    let error = |message: String| MetaSyntraXLError::BayesianNetworkError(message);
+    for parent in parents {
+        if !states.contains_key(parent) {
+            return Err(error(format!(
+                "Parent `{}` of `{}` is not declared",
+                parent, name
+            )));
+        }
+    }
+    let cardinality = states[name].len();
+    let rows: usize = parents.iter().map(|p| states[p].len()).product();
+    let mut table: Vec<Option<f64>> = vec![None; rows * cardinality];
+    let mut default = None;
+
+    for entry in entries {
+        match entry {
+            BifEntry::Table(values) => {
+                if values.len() != table.len() {
+                    return Err(error(format!(
+                        "Table of `{}` has {} values, expected {}",
+                        name,
+                        values.len(),
+                        table.len()
+                    )));
+                }
+                table = values.iter().copied().map(Some).collect();
+            }
+            BifEntry::Default(values) | BifEntry::Row(_, values) if values.len() != cardinality => {
+                return Err(error(format!(
+                    "A row of `{}` has {} values, expected {}",
+                    name,
+                    values.len(),
+                    cardinality
+                )));
+            }
+            BifEntry::Default(values) => default = Some(values),
+            BifEntry::Row(row_states, values) => {
+                if row_states.len() != parents.len() {
+                    return Err(error(format!(
+                        "A row of `{}` names {} parent states, expected {}",
+                        name,
+                        row_states.len(),
+                        parents.len()
+                    )));
+                }
+                let mut row = 0;
+                for (parent, state) in parents.iter().zip(row_states) {
+                    let parent_states = &states[parent];
+                    let index = parent_states
+                        .iter()
+                        .position(|s| s == state)
+                        .ok_or_else(|| {
+                            error(format!("Variable `{}` has no state `{}`", parent, state))
+                        })?;
+                    row = row * parent_states.len() + index;
+                }
+                for (cell, &value) in table[row * cardinality..].iter_mut().zip(values) {
+                    *cell = Some(value);
+                }
+            }
+        }
+    }
+
+    table
+        .chunks(cardinality)
+        .flat_map(|row| {
+            row.iter()
+                .enumerate()
+                .map(move |(state, value)| match (value, default) {
+                    (Some(value), _) => Ok(*value),
+                    (None, Some(default)) => Ok(default[state]),
+                    (None, None) => Err(()),
+                })
+        })
+        .collect::<Result<Vec<f64>, ()>>()
+        .map_err(|_| error(format!("Probability block of `{}` misses rows", name)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Symbol(char),
+}
+
+const BIF_SYMBOLS: &str = "{}()[]|,;";
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, MetaSyntraXLError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            previous = c;
+                        }
+                        None => {
+                            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                                "Unterminated comment at line {}",
+                                line
+                            )))
+                        }
+                    }
+                }
+            }
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // Other backslashes are kept, as older files use them unescaped.
+                        Some('\\') if matches!(chars.peek(), Some('"' | '\\')) => {
+                            word.extend(chars.next())
+                        }
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            word.push(c);
+                        }
+                        None => {
+                            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                                "Unterminated string at line {}",
+                                line
+                            )))
+                        }
+                    }
+                }
+                tokens.push((Token::Word(word), line));
+            }
+            c if BIF_SYMBOLS.contains(c) => tokens.push((Token::Symbol(c), line)),
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '"' || BIF_SYMBOLS.contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push((Token::Word(word), line));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct BifParser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl BifParser {
+    fn error(&self, message: &str) -> MetaSyntraXLError {
+        let line = self
+            .tokens
+            .get(self.position.saturating_sub(1))
+            .map_or(0, |(_, line)| *line);
+        MetaSyntraXLError::BayesianNetworkError(format!("BIF line {}: {}", line, message))
+    }
+
+    fn next(&mut self) -> Result<Token, MetaSyntraXLError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|(token, _)| token.clone());
+        self.position += 1;
+        token.ok_or_else(|| self.error("Unexpected end of input"))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next_word_or_end(&mut self) -> Result<Option<String>, MetaSyntraXLError> {
+        if self.peek().is_none() {
+            return Ok(None);
+        }
+        self.word().map(Some)
+    }
+
+    fn word(&mut self) -> Result<String, MetaSyntraXLError> {
+        match self.next()? {
+            Token::Word(word) => Ok(word),
+            Token::Symbol(c) => Err(self.error(&format!("Expected a name, found `{}`", c))),
+        }
+    }
+
+    fn symbol(&mut self, expected: char) -> Result<(), MetaSyntraXLError> {
+        match self.next()? {
+            Token::Symbol(c) if c == expected => Ok(()),
+            Token::Symbol(c) => Err(self.error(&format!("Expected `{}`, found `{}`", expected, c))),
+            Token::Word(word) => {
+                Err(self.error(&format!("Expected `{}`, found `{}`", expected, word)))
+            }
+        }
+    }
+
+    fn skip_statement(&mut self) -> Result<(), MetaSyntraXLError> {
+        while self.next()? != Token::Symbol(';') {}
+        Ok(())
+    }
+
+    fn skip_block(&mut self) -> Result<(), MetaSyntraXLError> {
+        self.symbol('{')?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Names separated by commas, up to and including `close`.
+    fn list(&mut self, close: char) -> Result<Vec<String>, MetaSyntraXLError> {
+        let mut words = Vec::new();
+        loop {
+            match self.next()? {
+                Token::Symbol(c) if c == close => return Ok(words),
+                Token::Symbol(',') => {}
+                Token::Word(word) => words.push(word),
+                Token::Symbol(c) => return Err(self.error(&format!("Unexpected `{}`", c))),
+            }
+        }
+    }
+
+    /// Numbers separated by commas or whitespace, up to and including `;`.
+    fn numbers(&mut self) -> Result<Vec<f64>, MetaSyntraXLError> {
+        self.list(';')?
+            .iter()
+            .map(|word| {
+                word.parse()
+                    .map_err(|_| self.error(&format!("`{}` is not a number", word)))
+            })
+            .collect()
+    }
+
+    fn variable_body(&mut self, name: &str) -> Result<Vec<String>, MetaSyntraXLError> {
+        self.symbol('{')?;
+        let mut states = None;
+        loop {
+            match self.next()? {
+                Token::Symbol('}') => break,
+                Token::Word(word) if word == "property" => self.skip_statement()?,
+                Token::Word(word) if word == "type" => {
+                    let kind = self.word()?;
+                    if kind != "discrete" {
+                        return Err(self.error(&format!(
+                            "Variable `{}` has unsupported type `{}`",
+                            name, kind
+                        )));
+                    }
+                    self.symbol('[')?;
+                    let count = self.word()?;
+                    self.symbol(']')?;
+                    self.symbol('{')?;
+                    let names = self.list('}')?;
+                    self.symbol(';')?;
+                    if count.parse() != Ok(names.len()) {
+                        return Err(self.error(&format!(
+                            "Variable `{}` declares {} states but lists {}",
+                            name,
+                            count,
+                            names.len()
+                        )));
+                    }
+                    states = Some(names);
+                }
+                other => return Err(self.error(&format!("Unexpected {:?}", other))),
+            }
+        }
+        states.ok_or_else(|| self.error(&format!("Variable `{}` has no type", name)))
+    }
+
+    fn probability(&mut self) -> Result<(String, Vec<String>, Vec<BifEntry>), MetaSyntraXLError> {
+        self.symbol('(')?;
+        let child = self.word()?;
+        let parents = match self.next()? {
+            Token::Symbol(')') => Vec::new(),
+            Token::Symbol('|') => self.list(')')?,
+            other => return Err(self.error(&format!("Unexpected {:?}", other))),
+        };
+        self.symbol('{')?;
+        let mut entries = Vec::new();
+        loop {
+            match self.next()? {
+                Token::Symbol('}') => break,
+                Token::Symbol('(') => {
+                    let states = self.list(')')?;
+                    entries.push(BifEntry::Row(states, self.numbers()?));
+                }
+                Token::Word(word) if word == "table" => {
+                    entries.push(BifEntry::Table(self.numbers()?))
+                }
+                Token::Word(word) if word == "default" => {
+                    entries.push(BifEntry::Default(self.numbers()?))
+                }
+                Token::Word(word) if word == "property" => self.skip_statement()?,
+                other => return Err(self.error(&format!("Unexpected {:?}", other))),
+            }
+        }
+        Ok((child, parents, entries))
+    }
+}
+
+/// Quotes names that would not read back as a single BIF word, escaping `"` and
+/// `\` inside the quotes.
+fn bif_word(name: &str) -> String {
+    let plain = !name.is_empty()
+        && !name.chars().any(|c| {
+            c.is_whitespace() || c == '"' || c == '\\' || c == '/' || BIF_SYMBOLS.contains(c)
+        });
+    if plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+fn numbers(values: &[f64], separator: &str) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+/// Writes the network as XMLBIF 0.3.
+pub(crate) fn to_xmlbif(network: &BayesianNetwork) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\"?>\n<BIF VERSION=\"0.3\">\n<NETWORK>\n<NAME>unknown</NAME>\n",
+    );
+    let spec = specs(network);
+    for variable in &spec.variables {
+        out.push_str("<VARIABLE TYPE=\"nature\">\n");
+        let _ = writeln!(out, "  <NAME>{}</NAME>", escape(&variable.name));
+        for state in &variable.states {
+            let _ = writeln!(out, "  <OUTCOME>{}</OUTCOME>", escape(state));
+        }
+        out.push_str("</VARIABLE>\n");
+    }
+    for variable in &spec.variables {
+        out.push_str("<DEFINITION>\n");
+        let _ = writeln!(out, "  <FOR>{}</FOR>", escape(&variable.name));
+        for parent in &variable.parents {
+            let _ = writeln!(out, "  <GIVEN>{}</GIVEN>", escape(parent));
+        }
+        let _ = writeln!(out, "  <TABLE>{}</TABLE>", numbers(&variable.cpt, " "));
+        out.push_str("</DEFINITION>\n");
+    }
+    out.push_str("</NETWORK>\n</BIF>\n");
+    out
+}
+
+/// Reads a network in XMLBIF (0.3, or the older `PROBABILITY` element in place of
+/// `DEFINITION`). The `TABLE` of each definition is laid out as in
+/// [`BayesianNetwork::add_variable`]: given variables in order, the defined
+/// variable varying fastest.
+pub(crate) fn from_xmlbif(text: &str) -> Result<BayesianNetwork, MetaSyntraXLError> {
+    let root = XmlParser { text, position: 0 }.document()?;
+    let network = if root.is("NETWORK") {
+        &root
+    } else {
+        root.child("NETWORK")
+            .ok_or_else(|| xml_error("Missing NETWORK element".to_string()))?
+    };
+
+    let mut states: Vec<(String, Vec<String>)> = Vec::new();
+    for variable in network.children.iter().filter(|e| e.is("VARIABLE")) {
+        let name = variable
+            .child("NAME")
+            .ok_or_else(|| xml_error("VARIABLE without NAME".to_string()))?
+            .text();
+        let outcomes = variable
+            .children
+            .iter()
+            .filter(|e| e.is("OUTCOME") || e.is("VALUE"))
+            .map(Element::text)
+            .collect();
+        states.push((name, outcomes));
+    }
+
+    let mut definitions: HashMap<String, (Vec<String>, Vec<f64>)> = HashMap::new();
+    for definition in network
+        .children
+        .iter()
+        .filter(|e| e.is("DEFINITION") || e.is("PROBABILITY"))
+    {
+        let child = definition
+            .child("FOR")
+            .ok_or_else(|| xml_error("DEFINITION without FOR".to_string()))?
+            .text();
+        let parents = definition
+            .children
+            .iter()
+            .filter(|e| e.is("GIVEN"))
+            .map(Element::text)
+            .collect();
+        let table = definition
+            .child("TABLE")
+            .ok_or_else(|| xml_error(format!("Definition of `{}` has no TABLE", child)))?
+            .text();
+        let values = table
+            .split_whitespace()
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| xml_error(format!("`{}` is not a number", v)))
+            })
+            .collect::<Result<Vec<f64>, _>>()?;
+        if definitions
+            .insert(child.clone(), (parents, values))
+            .is_some()
+        {
+            return Err(xml_error(format!("`{}` is defined twice", child)));
+        }
+    }
+
+    if let Some(child) = definitions
+        .keys()
+        .find(|child| !states.iter().any(|(name, _)| name == *child))
+    {
+        return Err(xml_error(format!(
+            "Definition for undeclared variable `{}`",
+            child
+        )));
+    }
+    let variables = states
+        .into_iter()
+        .map(|(name, states)| {
+            let (parents, cpt) = definitions
+                .remove(&name)
+                .ok_or_else(|| xml_error(format!("Variable `{}` has no definition", name)))?;
+            Ok(VariableSpec {
+                name,
+                states,
+                parents,
+                cpt,
+                counts: Vec::new(),
+            })
+        })
+        .collect::<Result<Vec<_>, MetaSyntraXLError>>()?;
+    assemble(variables)
+}
+
+fn xml_error(message: String) -> MetaSyntraXLError {
+    MetaSyntraXLError::BayesianNetworkError(format!("XMLBIF: {}", message))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// An XML element; attributes are not kept.
+struct Element {
+    name: String,
+    children: Vec<Element>,
+    content: String,
+}
+
+impl Element {
+    fn is(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|e| e.is(name))
+    }
+
+    fn text(&self) -> String {
+        self.content.trim().to_string()
+    }
+}
+
+/// Just enough of an XML reader for XMLBIF: elements, text, comments, CDATA,
+/// processing instructions and a DOCTYPE with an internal subset.
+struct XmlParser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl XmlParser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.position..]
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<&str, MetaSyntraXLError> {
+        let rest = &self.text[self.position..];
+        let offset = rest
+            .find(end)
+            .ok_or_else(|| xml_error(format!("Expected `{}`", end)))?;
+        self.position += offset + end.len();
+        Ok(&rest[..offset])
+    }
+
+    /// Skips comments, processing instructions and declarations; returns whether
+    /// anything was skipped.
+    fn skip_markup(&mut self) -> Result<bool, MetaSyntraXLError> {
+        if self.rest().starts_with("<!--") {
+            self.skip_past("-->")?;
+        } else if self.rest().starts_with("<?") {
+            self.skip_past("?>")?;
+        } else if self.rest().starts_with("<!") && !self.rest().starts_with("<![CDATA[") {
+            let rest = self.rest();
+            let close = rest.find('>').unwrap_or(rest.len());
+            if matches!(rest.find('['), Some(open) if open < close) {
+                self.skip_past("]")?;
+            }
+            self.skip_past(">")?;
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn document(mut self) -> Result<Element, MetaSyntraXLError> {
+        loop {
+            let trimmed = self.rest().trim_start();
+            self.position = self.text.len() - trimmed.len();
+            if !self.skip_markup()? {
+                break;
+            }
+        }
+        if !self.rest().starts_with('<') {
+            return Err(xml_error("Missing root element".to_string()));
+        }
+        self.element()
+    }
+
+    fn element(&mut self) -> Result<Element, MetaSyntraXLError> {
+        self.position += 1;
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..end].to_string();
+        self.position += end;
+
+        let mut quote = None;
+        let mut previous = ' ';
+        let mut chars = self.rest().char_indices();
+        let self_closing = loop {
+            let (offset, c) = chars
+                .next()
+                .ok_or_else(|| xml_error(format!("Unterminated tag `{}`", name)))?;
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"') | (None, '\'') => quote = Some(c),
+                (None, '>') => {
+                    self.position += offset + 1;
+                    break previous == '/';
+                }
+                _ => {}
+            }
+            previous = c;
+        };
+
+        let mut element = Element {
+            name,
+            children: Vec::new(),
+            content: String::new(),
+        };
+        if self_closing {
+            return Ok(element);
+        }
+        loop {
+            let rest = self.rest();
+            let offset = rest
+                .find('<')
+                .ok_or_else(|| xml_error(format!("Unclosed element `{}`", element.name)))?;
+            element.content.push_str(&unescape(&rest[..offset]));
+            self.position += offset;
+
+            if self.rest().starts_with("</") {
+                self.position += 2;
+                let closing = self.skip_past(">")?.trim().to_string();
+                if closing != element.name {
+                    return Err(xml_error(format!(
+                        "Element `{}` closed by `{}`",
+                        element.name, closing
+                    )));
+                }
+                return Ok(element);
+            } else if self.rest().starts_with("<![CDATA[") {
+                self.position += "<![CDATA[".len();
+                let data = self.skip_past("]]>")?.to_string();
+                element.content.push_str(&data);
+            } else if !self.skip_markup()? {
+                let child = self.element()?;
+                element.children.push(child);
+            }
+        }
+    }
+}
\ No newline at end of file
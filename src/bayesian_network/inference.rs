@@ -0,0 +1,193 @@
+// src/bayesian_network/inference.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BAYESIAN-NETWORK]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::elimination::EliminationOrder;
+use crate::errors::MetaSyntraXLError;
+use std::collections::HashMap;
+
+/// Algorithm used to answer a query, see [`super::BayesianNetwork::reason_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum InferenceMethod {
+    /// Variable elimination; exact but exponential in the network's treewidth.
+    Exact(EliminationOrder),
+    /// Forward sampling with evidence clamped and samples weighted by its likelihood.
+    LikelihoodWeighting(SamplingOptions),
+    /// Markov chain Monte Carlo resampling each hidden node from its Markov blanket.
+    Gibbs(GibbsOptions),
+    /// Sum-product message passing on the factor graph; exact on polytrees.
+    LoopyBeliefPropagation(BeliefPropagationOptions),
+}
+
+impl InferenceMethod {
+    /// Rejects sampling options that cannot produce an estimate, so they are not
+    /// mistaken for evidence with probability zero.
+    pub(crate) fn check(&self) -> Result<(), MetaSyntraXLError> {
+        match self {
+            InferenceMethod::LikelihoodWeighting(options) if options.samples == 0 => Err(
+                MetaSyntraXLError::BayesianNetworkError(
+                    "Likelihood weighting needs at least one sample".to_string(),
+                ),
+            ),
+            InferenceMethod::Gibbs(options) if options.samples < options.chains.max(1) => {
+                Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                    "Gibbs sampling needs at least one sample per chain, got {} samples for {} chains",
+                    options.samples,
+                    options.chains.max(1)
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for InferenceMethod {
+    fn default() -> Self {
+        InferenceMethod::Exact(EliminationOrder::default())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SamplingOptions {
+    pub samples: usize,
+    pub seed: u64,
+    /// Effective sample size below which the estimate is reported as not converged.
+    pub min_effective_sample_size: f64,
+}
+
+impl Default for SamplingOptions {
+    fn default() -> Self {
+        Self {
+            samples: 10_000,
+            seed: 0,
+            min_effective_sample_size: 100.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GibbsOptions {
+    /// Samples kept across all chains, after burn-in.
+    pub samples: usize,
+    /// Sweeps discarded at the start of every chain.
+    pub burn_in: usize,
+    /// Independent chains, used for the R-hat convergence diagnostic.
+    pub chains: usize,
+    pub seed: u64,
+}
+
+impl Default for GibbsOptions {
+    fn default() -> Self {
+        Self {
+            samples: 10_000,
+            burn_in: 500,
+            chains: 4,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeliefPropagationOptions {
+    pub max_iterations: usize,
+    /// Stop once no message changes by more than this between iterations.
+    pub tolerance: f64,
+    /// Weight of the previous message when updating, in `[0, 1)`; helps loopy graphs settle.
+    pub damping: f64,
+}
+
+impl Default for BeliefPropagationOptions {
+    fn default() -> Self {
+        Self {
+            max_iterations: 100,
+            tolerance: 1e-6,
+            damping: 0.0,
+        }
+    }
+}
+
+/// How an estimate was obtained and how far it can be trusted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostics {
+    Exact,
+    LikelihoodWeighting {
+        samples: usize,
+        /// `(sum w)^2 / sum w^2`; far below `samples` means the evidence is unlikely
+        /// under the prior and the estimate is noisy.
+        effective_sample_size: f64,
+        /// [`SamplingOptions::min_effective_sample_size`] of the run.
+        min_effective_sample_size: f64,
+    },
+    Gibbs {
+        samples: usize,
+        chains: usize,
+        /// Largest Gelman-Rubin statistic over the hidden nodes.
+        r_hat: f64,
+    },
+    BeliefPropagation {
+        iterations: usize,
+        /// Largest message change in the final iteration.
+        max_residual: f64,
+        converged: bool,
+    },
+}
+
+impl Diagnostics {
+    /// Whether the estimate can be used as is: exact, sampled with at least the
+    /// minimum effective sample size, mixed chains (`r_hat < 1.1`) or settled
+    /// messages.
+    pub fn converged(&self) -> bool {
+        match self {
+            Diagnostics::Exact => true,
+            Diagnostics::LikelihoodWeighting {
+                effective_sample_size,
+                min_effective_sample_size,
+                ..
+            } => *effective_sample_size >= *min_effective_sample_size,
+            Diagnostics::Gibbs { r_hat, .. } => *r_hat < 1.1,
+            Diagnostics::BeliefPropagation { converged, .. } => *converged,
+        }
+    }
+}
+
+/// Posterior distribution of one node over its named states.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution {
+    pub states: Vec<String>,
+    pub probabilities: Vec<f64>,
+}
+
+impl Distribution {
+    /// The probability of `state`, or `None` if the node has no such state.
+    pub fn probability(&self, state: &str) -> Option<f64> {
+        let index = self.states.iter().position(|s| s == state)?;
+        Some(self.probabilities[index])
+    }
+
+    /// The most probable state; ties go to the state listed first.
+    pub fn most_likely(&self) -> &str {
+        let mut best = 0;
+        for (i, &p) in self.probabilities.iter().enumerate() {
+            if p > self.probabilities[best] {
+                best = i;
+            }
+        }
+        &self.states[best]
+    }
+}
+
+/// Posteriors for every node, see [`super::BayesianNetwork::infer_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inference {
+    /// Observed nodes put all their mass on the observed state.
+    pub beliefs: HashMap<String, Distribution>,
+    pub diagnostics: Diagnostics,
+}
+
+/// Posterior probabilities indexed by state, per node, as computed by the algorithms.
+pub(crate) type Marginals = HashMap<String, Vec<f64>>;
+
+/// Posterior for a single boolean node, see [`super::BayesianNetwork::reason_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+    /// The probability that the node is `true`.
+    pub probability: f64,
+    pub diagnostics: Diagnostics,
+}
\ No newline at end of file
@@ -0,0 +1,298 @@
+// src/bayesian_network/learning.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BAYESIAN-NETWORK]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::elimination::{eliminate, EliminationOrder};
+use super::factor::Factor;
+use super::BayesianNetwork;
+use crate::errors::MetaSyntraXLError;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Read};
+
+/// How CPT rows are estimated from (expected) counts.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Estimator {
+    /// Relative frequencies. Rows whose parent configuration was never observed
+    /// keep their current values.
+    #[default]
+    MaximumLikelihood,
+    /// Posterior mean under a symmetric Dirichlet prior that adds `pseudo_count`
+    /// to every CPT cell, so unseen configurations get a uniform row.
+    Dirichlet { pseudo_count: f64 },
+}
+
+impl Estimator {
+    fn check(&self) -> Result<(), MetaSyntraXLError> {
+        match self {
+            Estimator::Dirichlet { pseudo_count }
+                if !(*pseudo_count >= 0.0 && pseudo_count.is_finite()) =>
+            {
+                Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                    "Dirichlet pseudo count must be finite and non-negative, got {}",
+                    pseudo_count
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Re-estimates `table` row by row from `counts`, both laid out like the CPT.
+    fn estimate(&self, counts: &[f64], table: &mut [f64], states: usize) {
+        for (row, counts) in table.chunks_mut(states).zip(counts.chunks(states)) {
+            let total: f64 = counts.iter().sum();
+            let pseudo_count = match self {
+                Estimator::MaximumLikelihood => 0.0,
+                Estimator::Dirichlet { pseudo_count } => *pseudo_count,
+            };
+            let denominator = total + pseudo_count * states as f64;
+            if denominator > 0.0 {
+                for (p, c) in row.iter_mut().zip(counts) {
+                    *p = (c + pseudo_count) / denominator;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LearningOptions {
+    pub estimator: Estimator,
+    /// Upper bound on EM iterations; complete data always takes a single pass.
+    pub max_iterations: usize,
+    /// EM stops once the log-likelihood improves by less than this.
+    pub tolerance: f64,
+}
+
+impl Default for LearningOptions {
+    fn default() -> Self {
+        Self {
+            estimator: Estimator::default(),
+            max_iterations: 100,
+            tolerance: 1e-6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LearningReport {
+    pub iterations: usize,
+    /// Log-likelihood of the records under the learned parameters, skipped records excluded.
+    pub log_likelihood: f64,
+    pub converged: bool,
+    /// Records with probability zero under the parameters at the time they were
+    /// used. They are left out of the log-likelihood, and contributed no counts
+    /// unless every node was observed.
+    pub skipped_records: usize,
+}
+
+/// Records by state index, deduplicated with their multiplicities.
+type Patterns = Vec<(HashMap<String, usize>, f64)>;
+
+/// Replaces every node's counts and CPT with estimates from `records`, running
+/// EM from the current parameters when some records are partially observed.
+pub(crate) fn fit(
+    network: &mut BayesianNetwork,
+    records: &[HashMap<String, usize>],
+    options: &LearningOptions,
+) -> Result<LearningReport, MetaSyntraXLError> {
+    options.estimator.check()?;
+    let patterns = group(records);
+    let complete = patterns
+        .iter()
+        .all(|(evidence, _)| evidence.len() == network.nodes.len());
+
+    let mut iterations = 0;
+    let mut converged = false;
+    let mut previous = f64::NEG_INFINITY;
+    while iterations < options.max_iterations.max(1) {
+        iterations += 1;
+        let (counts, log_likelihood, _) = expected_counts(network, &patterns);
+        for (name, counts) in counts {
+            let node = network.nodes.get_mut(&name).expect("counts are per node");
+            options
+                .estimator
+                .estimate(&counts, &mut node.cpt.values, node.states.len());
+            node.counts = counts;
+        }
+        if complete || (log_likelihood - previous).abs() <= options.tolerance {
+            converged = true;
+            break;
+        }
+        previous = log_likelihood;
+    }
+
+    let (_, log_likelihood, skipped) = expected_counts(network, &patterns);
+    Ok(LearningReport {
+        iterations,
+        log_likelihood,
+        converged,
+        skipped_records: skipped.round() as usize,
+    })
+}
+
+/// Adds the (expected) counts of one record to the network's counts and
+/// re-estimates every CPT.
+pub(crate) fn observe(
+    network: &mut BayesianNetwork,
+    record: HashMap<String, usize>,
+    estimator: &Estimator,
+) -> Result<(), MetaSyntraXLError> {
+    estimator.check()?;
+    let complete = record.len() == network.nodes.len();
+    let (counts, _, skipped) = expected_counts(network, &[(record, 1.0)]);
+    if skipped > 0.0 && !complete {
+        return Err(MetaSyntraXLError::BayesianNetworkError(
+            "Record has probability zero under the current parameters".to_string(),
+        ));
+    }
+    for (name, counts) in counts {
+        let node = network.nodes.get_mut(&name).expect("counts are per node");
+        node.counts
+            .iter_mut()
+            .zip(&counts)
+            .for_each(|(c, n)| *c += n);
+        estimator.estimate(&node.counts, &mut node.cpt.values, node.states.len());
+    }
+    Ok(())
+}
+
+/// Reads records from CSV: a header row of node names, then one row of state
+/// names per record. Empty cells and `?` mark unobserved values. Fields are
+/// split on commas and trimmed; quoting is not supported.
+pub(crate) fn read_csv<R: Read>(
+    reader: R,
+) -> Result<Vec<HashMap<String, String>>, MetaSyntraXLError> {
+    let mut lines = BufReader::new(reader).lines();
+    let header: Vec<String> = match lines.next() {
+        Some(line) => line?.split(',').map(|h| h.trim().to_string()).collect(),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut records = Vec::new();
+    for (number, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let cells: Vec<&str> = line.split(',').map(str::trim).collect();
+        if cells.len() != header.len() {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "CSV line {} has {} fields, expected {}",
+                number + 2,
+                cells.len(),
+                header.len()
+            )));
+        }
+        records.push(
+            header
+                .iter()
+                .zip(cells)
+                .filter(|(_, cell)| !cell.is_empty() && *cell != "?")
+                .map(|(name, cell)| (name.clone(), cell.to_string()))
+                .collect(),
+        );
+    }
+    Ok(records)
+}
+
+fn group(records: &[HashMap<String, usize>]) -> Patterns {
+    let mut grouped: BTreeMap<Vec<(&String, usize)>, f64> = BTreeMap::new();
+    for record in records {
+        let mut key: Vec<(&String, usize)> = record.iter().map(|(k, &v)| (k, v)).collect();
+        key.sort();
+        *grouped.entry(key).or_insert(0.0) += 1.0;
+    }
+    grouped
+        .into_iter()
+        .map(|(key, weight)| {
+            let evidence = key.into_iter().map(|(k, v)| (k.clone(), v)).collect();
+            (evidence, weight)
+        })
+        .collect()
+}
+
+/// E-step: per-node expected counts laid out like the CPTs, the log-likelihood of
+/// the patterns, and the total weight of the patterns with probability zero.
+/// Families whose variables are all observed are counted directly, so complete
+/// patterns count even when the current parameters rule them out.
+fn expected_counts(
+    network: &BayesianNetwork,
+    patterns: &[(HashMap<String, usize>, f64)],
+) -> (HashMap<String, Vec<f64>>, f64, f64) {
+    let mut counts: HashMap<String, Vec<f64>> = network
+        .nodes
+        .values()
+        .map(|node| (node.name.clone(), vec![0.0; node.cpt.values.len()]))
+        .collect();
+    let mut log_likelihood = 0.0;
+    let mut skipped = 0.0;
+
+    for (evidence, weight) in patterns {
+        let factors: Vec<Factor> = network
+            .nodes
+            .values()
+            .map(|node| node.factor().reduce(evidence))
+            .collect();
+        let probability: f64 = marginalize(factors.clone(), &[]).values.iter().sum();
+        if probability > 0.0 && probability.is_finite() {
+            log_likelihood += weight * probability.ln();
+        } else {
+            skipped += weight;
+            // Without a posterior to fill them in from, only records whose
+            // values are all observed can still be counted.
+            if evidence.len() != network.nodes.len() {
+                continue;
+            }
+        }
+
+        for node in network.nodes.values() {
+            let family = &node.cpt;
+            let unobserved: Vec<String> = family
+                .variables
+                .iter()
+                .filter(|v| !evidence.contains_key(*v))
+                .cloned()
+                .collect();
+            let posterior = if unobserved.is_empty() {
+                Factor::unit()
+            } else {
+                match marginalize(factors.clone(), &unobserved).normalized() {
+                    Some(posterior) => posterior,
+                    None => continue,
+                }
+            };
+
+            let node_counts = counts.get_mut(&node.name).expect("counts are per node");
+            for (index, count) in node_counts.iter_mut().enumerate() {
+                let assignment = family.assignment(index);
+                let consistent = family
+                    .variables
+                    .iter()
+                    .zip(&assignment)
+                    .all(|(v, s)| !matches!(evidence.get(v), Some(observed) if observed != s));
+                if !consistent {
+                    continue;
+                }
+                let projected: Vec<usize> = posterior
+                    .variables
+                    .iter()
+                    .map(|v| {
+                        let position = family.variables.iter().position(|f| f == v);
+                        assignment[position.expect("posterior is over the family")]
+                    })
+                    .collect();
+                *count += weight * posterior.values[posterior.index(&projected)];
+            }
+        }
+    }
+    (counts, log_likelihood, skipped)
+}
+
+/// Sums every variable except `keep` out of the product of `factors`.
+fn marginalize(factors: Vec<Factor>, keep: &[String]) -> Factor {
+    let mut hidden: Vec<String> = factors
+        .iter()
+        .flat_map(|f| f.variables.iter().cloned())
+        .filter(|v| !keep.contains(v))
+        .collect();
+    hidden.sort();
+    hidden.dedup();
+    eliminate(factors, &hidden, &EliminationOrder::default())
+}
\ No newline at end of file
@@ -0,0 +1,2703 @@
+// src/bayesian_network/mod.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BAYESIAN-NETWORK]Xyn>=====S===t===u====d===i===o===s====[R|$>
+mod belief_propagation;
+mod causal;
+mod dynamic;
+mod elimination;
+mod explanation;
+mod factor;
+mod format;
+mod inference;
+mod learning;
+mod sampling;
+mod structure;
+
+pub use dynamic::DynamicBayesianNetwork;
+pub use elimination::EliminationOrder;
+pub use explanation::{Assignment, Explanation, Influence};
+pub use inference::{
+    BeliefPropagationOptions, Diagnostics, Distribution, Estimate, GibbsOptions, Inference,
+    InferenceMethod, SamplingOptions,
+};
+pub use learning::{Estimator, LearningOptions, LearningReport};
+pub use structure::{StructureOptions, StructureScore, StructureSearch};
+
+use crate::errors::MetaSyntraXLError;
+use elimination::eliminate;
+use factor::Factor;
+use inference::Marginals;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// State names of the nodes created through the boolean API, `false` being state 0.
+const BOOLEAN_STATES: [&str; 2] = ["false", "true"];
+
+/// How far a CPT row may sum away from one before it is rejected.
+const ROW_TOLERANCE: f64 = 1e-6;
+
+pub struct BayesianNetwork {
+    nodes: HashMap<String, Node>,
+}
+
+#[derive(Clone)]
+struct Node {
+    name: String,
+    states: Vec<String>,
+    parents: Vec<String>,
+    /// Conditional Probability Table over `parents + [name]`; every row over the
+    /// node's own states sums to one.
+    cpt: Factor,
+    /// Observed (or, for partial records, expected) counts behind the CPT, in the
+    /// same layout; see [`BayesianNetwork::observe`].
+    counts: Vec<f64>,
+}
+
+impl Node {
+    fn factor(&self) -> Factor {
+        self.cpt.clone()
+    }
+
+    fn is_boolean(&self) -> bool {
+        self.states == BOOLEAN_STATES
+    }
+
+    /// The distribution over the node's states given the parents' states in
+    /// `states`, which must hold every parent.
+    fn distribution(&self, states: &HashMap<String, usize>) -> &[f64] {
+        let mut assignment: Vec<usize> = self.parents.iter().map(|p| states[p]).collect();
+        assignment.push(0);
+        let start = self.cpt.index(&assignment);
+        &self.cpt.values[start..start + self.states.len()]
+    }
+}
+
+impl Default for BayesianNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BayesianNetwork {
+    /// Initializes a new, empty Bayesian Network.
+    pub fn new() -> Self {
+        Self { nodes: HashMap::new() }
+    }
+
+    /// Adds a discrete variable to the Bayesian Network, replacing any node with the
+    /// same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the node.
+    /// * `states` - The names of the node's states.
+    /// * `parents` - A list of parent node names, each already in the network.
+    /// * `cpt` - The dense Conditional Probability Table, row-major over the parents'
+    ///   states (in `parents` order, the last parent varying fastest) followed by the
+    ///   node's own states, so each consecutive run of `states.len()` values is the
+    ///   distribution for one combination of parent states.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if the states are empty or repeated, a parent is
+    /// unknown or listed twice, the node would close a cycle, the node has children
+    /// and its states would change, or the CPT has the wrong size, a negative value,
+    /// or a row that does not sum to one. The network is left unchanged.
+    pub fn add_variable(
+        &mut self,
+        name: String,
+        states: Vec<String>,
+        parents: Vec<String>,
+        cpt: Vec<f64>,
+    ) -> Result<(), MetaSyntraXLError> {
+        self.check_parents(&name, &parents)?;
+        if states.is_empty() {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "Node `{}` has no states",
+                name
+            )));
+        }
+        for (i, state) in states.iter().enumerate() {
+            if states[..i].contains(state) {
+                return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                    "Node `{}` lists state `{}` twice",
+                    name, state
+                )));
+            }
+        }
+        if let Some(existing) = self.nodes.get(&name) {
+            let has_children = self
+                .nodes
+                .values()
+                .any(|n| n.parents.iter().any(|p| p == &name));
+            if has_children && existing.states != states {
+                return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                    "Cannot change the states of `{}` while other nodes depend on it",
+                    name
+                )));
+            }
+        }
+
+        let mut variables = parents.clone();
+        variables.push(name.clone());
+        let mut cardinalities: Vec<usize> =
+            parents.iter().map(|p| self.nodes[p].states.len()).collect();
+        cardinalities.push(states.len());
+        validate_table(&name, &cardinalities, &cpt)?;
+
+        let counts = vec![0.0; cpt.len()];
+        let cpt = Factor::new(variables, cardinalities, cpt);
+        self.nodes.insert(
+            name.clone(),
+            Node {
+                name,
+                states,
+                parents,
+                cpt,
+                counts,
+            },
+        );
+        Ok(())
+    }
+
+    /// Adds a boolean node to the Bayesian Network, replacing any node with the same
+    /// name. The node gets the states `false` and `true`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the node.
+    /// * `parents` - A list of boolean parent node names, each already in the network.
+    /// * `cpt` - The Conditional Probability Table for the node, mapping parent
+    ///   values (in `parents` order) to the probability that the node is `true`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if a parent is not boolean, the CPT is not a
+    /// complete table of probabilities (see [`BayesianNetwork::update_belief`]), or
+    /// for any reason listed on [`BayesianNetwork::add_variable`].
+    pub fn add_node(
+        &mut self,
+        name: String,
+        parents: Vec<String>,
+        cpt: HashMap<Vec<bool>, f64>,
+    ) -> Result<(), MetaSyntraXLError> {
+        self.check_parents(&name, &parents)?;
+        self.check_boolean_parents(&name, &parents)?;
+        validate_cpt(&name, &parents, &cpt)?;
+        let table = boolean_table(parents.len(), &cpt);
+        let states = BOOLEAN_STATES.iter().map(|s| s.to_string()).collect();
+        self.add_variable(name, states, parents, table)
+    }
+
+    /// The names of a node's states, or `None` if the node is unknown.
+    pub fn states(&self, node: &str) -> Option<&[String]> {
+        self.nodes.get(node).map(|n| n.states.as_slice())
+    }
+
+    /// The names of a node's parents, or `None` if the node is unknown.
+    pub fn parents(&self, node: &str) -> Option<&[String]> {
+        self.nodes.get(node).map(|n| n.parents.as_slice())
+    }
+
+    /// Learns a network, structure and CPTs, from complete records.
+    ///
+    /// # Arguments
+    ///
+    /// * `variables` - Every variable with its state names, in the order used to
+    ///   break ties during the search.
+    /// * `records` - Observations naming a state for every variable.
+    /// * `options` - The search algorithm, edge constraints and CPT estimator.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if a variable is listed twice, a record misses a
+    /// variable or names an unknown variable or state, the edge constraints name
+    /// unknown variables, contradict each other or form a cycle, a search option
+    /// is invalid, or the PC search finds an edge it cannot orient either way.
+    pub fn learn_structure(
+        variables: &[(String, Vec<String>)],
+        records: &[HashMap<String, String>],
+        options: &StructureOptions,
+    ) -> Result<BayesianNetwork, MetaSyntraXLError> {
+        for (i, (name, _)) in variables.iter().enumerate() {
+            if variables[..i].iter().any(|(other, _)| other == name) {
+                return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                    "Variable `{}` is listed twice",
+                    name
+                )));
+            }
+        }
+        let table = records
+            .iter()
+            .map(|record| {
+                if let Some(name) = record
+                    .keys()
+                    .find(|name| !variables.iter().any(|(v, _)| v == *name))
+                {
+                    return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                        "Record names unknown variable `{}`",
+                        name
+                    )));
+                }
+                variables
+                    .iter()
+                    .map(|(name, states)| {
+                        let state = record.get(name).ok_or_else(|| {
+                            MetaSyntraXLError::BayesianNetworkError(format!(
+                                "Structure learning needs complete records; `{}` is missing",
+                                name
+                            ))
+                        })?;
+                        states.iter().position(|s| s == state).ok_or_else(|| {
+                            MetaSyntraXLError::BayesianNetworkError(format!(
+                                "Variable `{}` has no state `{}`",
+                                name, state
+                            ))
+                        })
+                    })
+                    .collect::<Result<Vec<usize>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let parents = structure::learn(variables, &table, options)?;
+        structure::build(variables, &parents, records, &options.estimator)
+    }
+
+    /// Boolean convenience over [`BayesianNetwork::learn_structure`]: the variables
+    /// are the nodes named in the records, ordered by name.
+    pub fn learn_structure_boolean(
+        records: &[HashMap<String, bool>],
+        options: &StructureOptions,
+    ) -> Result<BayesianNetwork, MetaSyntraXLError> {
+        let names: BTreeSet<&String> = records.iter().flat_map(|r| r.keys()).collect();
+        let variables: Vec<(String, Vec<String>)> = names
+            .into_iter()
+            .map(|name| {
+                let states = BOOLEAN_STATES.iter().map(|s| s.to_string()).collect();
+                (name.clone(), states)
+            })
+            .collect();
+        let records: Vec<HashMap<String, String>> = records.iter().map(boolean_record).collect();
+        Self::learn_structure(&variables, &records, options)
+    }
+
+    /// Performs inference on the Bayesian Network given some evidence.
+    ///
+    /// Posteriors are computed exactly by variable elimination with a min-fill order.
+    ///
+    /// # Arguments
+    ///
+    /// * `evidence` - A map of node names to their observed state names.
+    ///
+    /// # Returns
+    ///
+    /// * `HashMap<String, Distribution>` - The posterior distribution of every node;
+    ///   observed nodes put all their mass on the observed state.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if the evidence names an unknown node or state,
+    /// or has probability zero.
+    pub fn infer(
+        &self,
+        evidence: &HashMap<String, String>,
+    ) -> Result<HashMap<String, Distribution>, MetaSyntraXLError> {
+        self.infer_with_order(evidence, &EliminationOrder::default())
+    }
+
+    /// Like [`BayesianNetwork::infer`], with an explicit elimination order.
+    pub fn infer_with_order(
+        &self,
+        evidence: &HashMap<String, String>,
+        order: &EliminationOrder,
+    ) -> Result<HashMap<String, Distribution>, MetaSyntraXLError> {
+        self.infer_with(evidence, &InferenceMethod::Exact(order.clone()))
+            .map(|inference| inference.beliefs)
+    }
+
+    /// Like [`BayesianNetwork::infer`], with the inference algorithm chosen per call.
+    ///
+    /// For the sampling methods, evidence that no sample was consistent with is
+    /// reported like evidence with probability zero; options that request no
+    /// samples (or fewer samples than Gibbs chains) are rejected up front.
+    pub fn infer_with(
+        &self,
+        evidence: &HashMap<String, String>,
+        method: &InferenceMethod,
+    ) -> Result<Inference, MetaSyntraXLError> {
+        method.check()?;
+        let evidence = self.state_evidence(evidence)?;
+        let (marginals, diagnostics) = self.marginals(&evidence, method).ok_or_else(|| {
+            MetaSyntraXLError::BayesianNetworkError("Evidence has probability zero".to_string())
+        })?;
+        let beliefs = marginals
+            .into_iter()
+            .map(|(name, probabilities)| {
+                let states = self.nodes[&name].states.clone();
+                (
+                    name,
+                    Distribution {
+                        states,
+                        probabilities,
+                    },
+                )
+            })
+            .collect();
+        Ok(Inference {
+            beliefs,
+            diagnostics,
+        })
+    }
+
+    /// Boolean convenience over [`BayesianNetwork::infer`].
+    ///
+    /// # Arguments
+    ///
+    /// * `evidence` - A map of node names to their observed boolean values; entries
+    ///   for unknown or non-boolean nodes are ignored.
+    ///
+    /// # Returns
+    ///
+    /// * `HashMap<String, f64>` - The posterior probability that each boolean node is
+    ///   `true`. Observed nodes map to 1.0 or 0.0; if the evidence itself has
+    ///   probability zero, unobserved nodes are omitted.
+    pub fn infer_boolean(&self, evidence: &HashMap<String, bool>) -> HashMap<String, f64> {
+        let evidence = self.boolean_evidence(evidence);
+        let marginals = self
+            .marginals(&evidence, &InferenceMethod::default())
+            .map(|(marginals, _)| marginals)
+            .unwrap_or_else(|| one_hot(&self.nodes, &evidence));
+        marginals
+            .into_iter()
+            .filter(|(name, _)| self.nodes[name].is_boolean())
+            .map(|(name, probabilities)| (name, probabilities[1]))
+            .collect()
+    }
+
+    /// Reasons about a specific query node given the evidence.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The name of the boolean node to query.
+    /// * `evidence` - A map of node names to their observed boolean values; entries
+    ///   for unknown or non-boolean nodes are ignored.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<f64>` - The probability of the query node being true, or `None` if
+    ///   the node is unknown or not boolean, or the evidence has probability zero.
+    pub fn reason(&self, query: &str, evidence: &HashMap<String, bool>) -> Option<f64> {
+        self.reason_with(query, evidence, &InferenceMethod::default())
+            .ok()
+            .flatten()
+            .map(|estimate| estimate.probability)
+    }
+
+    /// Like [`BayesianNetwork::reason`], with the inference algorithm chosen per
+    /// query and diagnostics describing how far the estimate can be trusted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if the sampling options request no samples,
+    /// or fewer samples than Gibbs chains.
+    pub fn reason_with(
+        &self,
+        query: &str,
+        evidence: &HashMap<String, bool>,
+        method: &InferenceMethod,
+    ) -> Result<Option<Estimate>, MetaSyntraXLError> {
+        method.check()?;
+        if !matches!(self.nodes.get(query), Some(node) if node.is_boolean()) {
+            return Ok(None);
+        }
+        let evidence = self.boolean_evidence(evidence);
+        let (probabilities, diagnostics) = match method {
+            InferenceMethod::Exact(order) => match evidence.get(query) {
+                Some(&state) => (one_hot_state(2, state), Diagnostics::Exact),
+                None => match self.posterior(query, &evidence, order) {
+                    Some(probabilities) => (probabilities, Diagnostics::Exact),
+                    None => return Ok(None),
+                },
+            },
+            _ => match self.marginals(&evidence, method) {
+                Some((mut marginals, diagnostics)) => match marginals.remove(query) {
+                    Some(probabilities) => (probabilities, diagnostics),
+                    None => return Ok(None),
+                },
+                None => return Ok(None),
+            },
+        };
+        Ok(Some(Estimate {
+            probability: probabilities[1],
+            diagnostics,
+        }))
+    }
+
+    /// The posterior distribution of every node, or `None` if the evidence has
+    /// probability zero (or, when sampling, no sample was consistent with it).
+    fn marginals(
+        &self,
+        evidence: &HashMap<String, usize>,
+        method: &InferenceMethod,
+    ) -> Option<(Marginals, Diagnostics)> {
+        match method {
+            InferenceMethod::Exact(order) => {
+                let mut marginals = one_hot(&self.nodes, evidence);
+                for name in self.nodes.keys() {
+                    if !evidence.contains_key(name) {
+                        marginals.insert(name.clone(), self.posterior(name, evidence, order)?);
+                    }
+                }
+                Some((marginals, Diagnostics::Exact))
+            }
+            InferenceMethod::LikelihoodWeighting(options) => {
+                sampling::likelihood_weighting(self, evidence, options)
+            }
+            InferenceMethod::Gibbs(options) => sampling::gibbs(self, evidence, options),
+            InferenceMethod::LoopyBeliefPropagation(options) => {
+                belief_propagation::loopy_belief_propagation(self, evidence, options)
+            }
+        }
+    }
+
+    /// `P(query | evidence)` by variable elimination.
+    fn posterior(
+        &self,
+        query: &str,
+        evidence: &HashMap<String, usize>,
+        order: &EliminationOrder,
+    ) -> Option<Vec<f64>> {
+        if !self.nodes.contains_key(query) {
+            return None;
+        }
+
+        let factors: Vec<Factor> = self
+            .nodes
+            .values()
+            .map(|node| node.factor().reduce(evidence))
+            .collect();
+
+        let mut hidden: Vec<String> = factors
+            .iter()
+            .flat_map(|f| f.variables.iter().cloned())
+            .filter(|v| v != query)
+            .collect();
+        hidden.sort();
+        hidden.dedup();
+
+        let marginal = eliminate(factors, &hidden, order).normalized()?;
+        Some(marginal.values)
+    }
+
+    /// Resolves evidence given as state names into state indices.
+    fn state_evidence(
+        &self,
+        evidence: &HashMap<String, String>,
+    ) -> Result<HashMap<String, usize>, MetaSyntraXLError> {
+        evidence
+            .iter()
+            .map(|(name, state)| {
+                let node = self.nodes.get(name).ok_or_else(|| {
+                    MetaSyntraXLError::BayesianNetworkError(format!(
+                        "Evidence on unknown node `{}`",
+                        name
+                    ))
+                })?;
+                let index = node.states.iter().position(|s| s == state).ok_or_else(|| {
+                    MetaSyntraXLError::BayesianNetworkError(format!(
+                        "Node `{}` has no state `{}`",
+                        name, state
+                    ))
+                })?;
+                Ok((name.clone(), index))
+            })
+            .collect()
+    }
+
+    /// Resolves boolean evidence into state indices, dropping unknown and non-boolean nodes.
+    fn boolean_evidence(&self, evidence: &HashMap<String, bool>) -> HashMap<String, usize> {
+        evidence
+            .iter()
+            .filter(|(name, _)| matches!(self.nodes.get(*name), Some(node) if node.is_boolean()))
+            .map(|(name, &value)| (name.clone(), value as usize))
+            .collect()
+    }
+
+    /// Node names ordered so that every parent precedes its children.
+    ///
+    /// Nodes that become ready at the same time are ordered by name, so the order
+    /// is deterministic.
+    pub fn topological_order(&self) -> Vec<String> {
+        self.ordered_nodes()
+            .into_iter()
+            .map(|node| node.name.clone())
+            .collect()
+    }
+
+    /// The nodes in [`BayesianNetwork::topological_order`].
+    fn ordered_nodes(&self) -> Vec<&Node> {
+        let mut pending: HashMap<&str, usize> = self
+            .nodes
+            .values()
+            .map(|node| (node.name.as_str(), node.parents.len()))
+            .collect();
+        let mut ready: BTreeSet<&str> = pending
+            .iter()
+            .filter(|(_, &count)| count == 0)
+            .map(|(&name, _)| name)
+            .collect();
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(name) = ready.iter().next().copied() {
+            ready.remove(name);
+            order.push(&self.nodes[name]);
+            for child in self.nodes.values() {
+                if child.parents.iter().any(|p| p == name) {
+                    let count = pending
+                        .get_mut(child.name.as_str())
+                        .expect("every node is pending");
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.insert(child.name.as_str());
+                    }
+                }
+            }
+        }
+        order
+    }
+
+    /// Checks that `parents` are distinct, known nodes and that making them the
+    /// parents of `name` keeps the network acyclic.
+    fn check_parents(&self, name: &str, parents: &[String]) -> Result<(), MetaSyntraXLError> {
+        for (i, parent) in parents.iter().enumerate() {
+            if parents[..i].contains(parent) {
+                return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                    "Node `{}` lists parent `{}` twice",
+                    name, parent
+                )));
+            }
+            if parent != name && !self.nodes.contains_key(parent) {
+                return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                    "Node `{}` has unknown parent `{}`",
+                    name, parent
+                )));
+            }
+            if self.is_ancestor(name, parent) {
+                return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                    "Making `{}` a parent of `{}` would create a cycle",
+                    parent, name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_boolean_parents(
+        &self,
+        name: &str,
+        parents: &[String],
+    ) -> Result<(), MetaSyntraXLError> {
+        match parents.iter().find(|p| !self.nodes[*p].is_boolean()) {
+            Some(parent) => Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "Boolean CPT of `{}` cannot condition on non-boolean parent `{}`",
+                name, parent
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether `ancestor` is `node` itself or can be reached from it through parents.
+    fn is_ancestor(&self, ancestor: &str, node: &str) -> bool {
+        let mut stack = vec![node];
+        let mut visited = HashSet::new();
+        while let Some(current) = stack.pop() {
+            if current == ancestor {
+                return true;
+            }
+            if visited.insert(current) {
+                if let Some(n) = self.nodes.get(current) {
+                    stack.extend(n.parents.iter().map(String::as_str));
+                }
+            }
+        }
+        false
+    }
+
+    /// Validates a prediction based on the Bayesian Network's inference.
+    ///
+    /// # Arguments
+    ///
+    /// * `prediction` - The name of the prediction node.
+    /// * `evidence` - A map of node names to their observed boolean values.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` if the prediction is valid, otherwise `false`.
+    pub fn validate_prediction(&self, prediction: &str, evidence: &HashMap<String, bool>) -> bool {
+        self.validate_prediction_with_threshold(prediction, evidence, 0.5)
+    }
+
+    /// Like [`BayesianNetwork::validate_prediction`], accepting the prediction when
+    /// its posterior probability is above `threshold` instead of 0.5.
+    pub fn validate_prediction_with_threshold(
+        &self,
+        prediction: &str,
+        evidence: &HashMap<String, bool>,
+        threshold: f64,
+    ) -> bool {
+        if let Some(prob) = self.reason(prediction, evidence) {
+            prob > threshold
+        } else {
+            false
+        }
+    }
+
+    /// Like [`BayesianNetwork::validate_prediction`], but returns the reasoning behind
+    /// the verdict: the prediction is valid when the explanation's `posterior` is
+    /// above 0.5.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Explanation>` - The explanation of `prediction` being `true`, or
+    ///   `None` if the node is unknown or not boolean, or the evidence has
+    ///   probability zero.
+    pub fn explain_prediction(
+        &self,
+        prediction: &str,
+        evidence: &HashMap<String, bool>,
+    ) -> Option<Explanation> {
+        if !matches!(self.nodes.get(prediction), Some(node) if node.is_boolean()) {
+            return None;
+        }
+        let evidence = self.boolean_evidence(evidence);
+        explanation::explain(self, prediction, 1, &evidence)
+    }
+
+    /// Explains `P(query = state | evidence)`: the posterior next to the prior, the
+    /// most probable explanation of the evidence, and the influence of every
+    /// observation, measured by how far the posterior moves when that observation
+    /// alone is left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if a node or state is unknown, or the evidence
+    /// has probability zero.
+    pub fn explain(
+        &self,
+        query: &str,
+        state: &str,
+        evidence: &HashMap<String, String>,
+    ) -> Result<Explanation, MetaSyntraXLError> {
+        let state = self.state_index(query, state)?;
+        let evidence = self.state_evidence(evidence)?;
+        explanation::explain(self, query, state, &evidence).ok_or_else(|| {
+            MetaSyntraXLError::BayesianNetworkError("Evidence has probability zero".to_string())
+        })
+    }
+
+    /// The most probable explanation (MPE): the jointly most likely state of every
+    /// unobserved node given the evidence, found by max-product elimination.
+    /// Ties go to the lowest state.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if the evidence names an unknown node or state,
+    /// or has probability zero.
+    pub fn most_probable_explanation(
+        &self,
+        evidence: &HashMap<String, String>,
+    ) -> Result<Assignment, MetaSyntraXLError> {
+        let query: Vec<String> = self.nodes.keys().cloned().collect();
+        self.maximum_a_posteriori(&query, evidence)
+    }
+
+    /// The maximum a posteriori (MAP) assignment: the jointly most likely state of
+    /// the `query` nodes given the evidence, with every other unobserved node summed
+    /// out. Observed query nodes are left out of the assignment.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if a query node is unknown, the evidence names
+    /// an unknown node or state, or the evidence has probability zero.
+    pub fn maximum_a_posteriori(
+        &self,
+        query: &[String],
+        evidence: &HashMap<String, String>,
+    ) -> Result<Assignment, MetaSyntraXLError> {
+        self.node_set(query)?;
+        let evidence = self.state_evidence(evidence)?;
+        explanation::maximum_a_posteriori(self, query, &evidence).ok_or_else(|| {
+            MetaSyntraXLError::BayesianNetworkError("Evidence has probability zero".to_string())
+        })
+    }
+
+    /// Learns every CPT from a table of records, replacing the current parameters.
+    ///
+    /// Records name the observed state of any subset of the nodes. When every
+    /// record is complete the CPTs are estimated in a single pass; otherwise
+    /// expectation-maximization runs from the current parameters, filling in the
+    /// unobserved values with their posterior under the parameters so far.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if a record names an unknown node or state,
+    /// or the estimator is invalid.
+    pub fn fit_parameters(
+        &mut self,
+        records: &[HashMap<String, String>],
+        options: &LearningOptions,
+    ) -> Result<LearningReport, MetaSyntraXLError> {
+        let records = records
+            .iter()
+            .map(|record| self.state_evidence(record))
+            .collect::<Result<Vec<_>, _>>()?;
+        learning::fit(self, &records, options)
+    }
+
+    /// Boolean convenience over [`BayesianNetwork::fit_parameters`]; every node a
+    /// record mentions must be boolean.
+    pub fn fit_parameters_boolean(
+        &mut self,
+        records: &[HashMap<String, bool>],
+        options: &LearningOptions,
+    ) -> Result<LearningReport, MetaSyntraXLError> {
+        let records: Vec<HashMap<String, String>> = records.iter().map(boolean_record).collect();
+        self.fit_parameters(&records, options)
+    }
+
+    /// Like [`BayesianNetwork::fit_parameters`], reading the records from CSV: a
+    /// header row of node names, then one row of state names per record, with empty
+    /// cells or `?` for unobserved values. Quoted fields are not supported.
+    pub fn fit_parameters_csv<R: std::io::Read>(
+        &mut self,
+        reader: R,
+        options: &LearningOptions,
+    ) -> Result<LearningReport, MetaSyntraXLError> {
+        let records = learning::read_csv(reader)?;
+        self.fit_parameters(&records, options)
+    }
+
+    /// Updates the CPTs online with one new record.
+    ///
+    /// The record's counts are added to those accumulated by earlier calls and by
+    /// [`BayesianNetwork::fit_parameters`], and every CPT is re-estimated from the
+    /// total. Unobserved values contribute their expected counts under the current
+    /// parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if the record names an unknown node or state,
+    /// leaves a node unobserved while having probability zero under the current
+    /// parameters, or the estimator is invalid.
+    pub fn observe(
+        &mut self,
+        record: &HashMap<String, String>,
+        estimator: &Estimator,
+    ) -> Result<(), MetaSyntraXLError> {
+        let record = self.state_evidence(record)?;
+        learning::observe(self, record, estimator)
+    }
+
+    /// Boolean convenience over [`BayesianNetwork::observe`].
+    pub fn observe_boolean(
+        &mut self,
+        record: &HashMap<String, bool>,
+        estimator: &Estimator,
+    ) -> Result<(), MetaSyntraXLError> {
+        self.observe(&boolean_record(record), estimator)
+    }
+
+    /// Replaces the dense Conditional Probability Table of a node, laid out as
+    /// described on [`BayesianNetwork::add_variable`]. The counts accumulated by
+    /// [`BayesianNetwork::observe`] are reset.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if the node is unknown, or the CPT has the
+    /// wrong size, a negative value, or a row that does not sum to one.
+    pub fn set_cpt(&mut self, node: &str, cpt: Vec<f64>) -> Result<(), MetaSyntraXLError> {
+        let n = self.nodes.get_mut(node).ok_or_else(|| {
+            MetaSyntraXLError::BayesianNetworkError(format!("Unknown node `{}`", node))
+        })?;
+        validate_table(&n.name, &n.cpt.cardinalities, &cpt)?;
+        n.counts = vec![0.0; cpt.len()];
+        n.cpt.values = cpt;
+        Ok(())
+    }
+
+    /// Updates the Conditional Probability Table (CPT) of a specific boolean node.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The name of the node to update.
+    /// * `new_cpt` - The new Conditional Probability Table.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if the node is unknown or not boolean, or if
+    /// `new_cpt` has a row whose arity differs from the node's parent count, misses
+    /// a combination of parent values, or holds a value outside `[0, 1]`.
+    pub fn update_belief(
+        &mut self,
+        node: &str,
+        new_cpt: HashMap<Vec<bool>, f64>,
+    ) -> Result<(), MetaSyntraXLError> {
+        let n = self.nodes.get(node).ok_or_else(|| {
+            MetaSyntraXLError::BayesianNetworkError(format!("Unknown node `{}`", node))
+        })?;
+        if !n.is_boolean() {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "Node `{}` is not boolean",
+                node
+            )));
+        }
+        self.check_boolean_parents(node, &n.parents)?;
+        validate_cpt(node, &n.parents, &new_cpt)?;
+        let table = boolean_table(n.parents.len(), &new_cpt);
+        self.set_cpt(node, table)
+    }
+
+    /// Performs the intervention `do(interventions)` by graph surgery: every
+    /// intervened node loses its incoming edges and is fixed to the given state.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if an intervention names an unknown node or state.
+    pub fn intervene(
+        &self,
+        interventions: &HashMap<String, String>,
+    ) -> Result<BayesianNetwork, MetaSyntraXLError> {
+        let interventions = self.state_evidence(interventions)?;
+        Ok(causal::intervene(self, &interventions))
+    }
+
+    /// Interventional posteriors `P(node | do(interventions), evidence)` of every
+    /// node, computed exactly on the network after [`BayesianNetwork::intervene`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if an intervention or the evidence names an
+    /// unknown node or state, or the evidence has probability zero under the
+    /// intervention.
+    pub fn infer_do(
+        &self,
+        interventions: &HashMap<String, String>,
+        evidence: &HashMap<String, String>,
+    ) -> Result<HashMap<String, Distribution>, MetaSyntraXLError> {
+        self.intervene(interventions)?.infer(evidence)
+    }
+
+    /// Boolean convenience over [`BayesianNetwork::infer_do`]: the probability that
+    /// `query` is `true` under `do(interventions)` given `evidence`, or `None` as for
+    /// [`BayesianNetwork::reason`]. Interventions on unknown or non-boolean nodes
+    /// are ignored.
+    pub fn reason_do(
+        &self,
+        query: &str,
+        interventions: &HashMap<String, bool>,
+        evidence: &HashMap<String, bool>,
+    ) -> Option<f64> {
+        let interventions = self.boolean_evidence(interventions);
+        causal::intervene(self, &interventions).reason(query, evidence)
+    }
+
+    /// Whether every node in `x` is d-separated from every node in `y` by `given`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if a node is unknown or the three sets overlap.
+    pub fn d_separated(
+        &self,
+        x: &[String],
+        y: &[String],
+        given: &[String],
+    ) -> Result<bool, MetaSyntraXLError> {
+        let x = self.node_set(x)?;
+        let y = self.node_set(y)?;
+        let given = self.node_set(given)?;
+        if !x.is_disjoint(&y) || !x.is_disjoint(&given) || !y.is_disjoint(&given) {
+            return Err(MetaSyntraXLError::BayesianNetworkError(
+                "d-separation needs disjoint sets of nodes".to_string(),
+            ));
+        }
+        Ok(causal::d_separated(self, &x, &y, &given, None))
+    }
+
+    /// Finds a smallest set of nodes satisfying the backdoor criterion for the
+    /// effect of `treatment` on `outcome`: it contains no descendant of `treatment`
+    /// and blocks every path that enters `treatment` through its parents. Among
+    /// sets of the same size, the first in node name order is returned.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Vec<String>>` - The adjustment set, sorted by name, or `None` if no
+    ///   set of observed nodes satisfies the criterion, as when `outcome` is a cause
+    ///   of `treatment`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if either node is unknown or they are the same node.
+    pub fn backdoor_adjustment_set(
+        &self,
+        treatment: &str,
+        outcome: &str,
+    ) -> Result<Option<Vec<String>>, MetaSyntraXLError> {
+        self.check_effect(treatment, outcome)?;
+        Ok(causal::backdoor_adjustment_set(self, treatment, outcome))
+    }
+
+    /// Estimates `P(outcome | do(treatment = state))` from the observational
+    /// distribution with the backdoor adjustment formula
+    /// `sum_z P(outcome | treatment = state, z) P(z)` over the states `z` of
+    /// `adjustment_set`.
+    ///
+    /// With a valid adjustment set this agrees with [`BayesianNetwork::infer_do`],
+    /// which needs the full causal network rather than the observational joint.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if a node or state is unknown, the set does not
+    /// satisfy the backdoor criterion, or `treatment = state` has probability zero
+    /// in some stratum of the set.
+    pub fn backdoor_adjustment(
+        &self,
+        treatment: &str,
+        state: &str,
+        outcome: &str,
+        adjustment_set: &[String],
+    ) -> Result<Distribution, MetaSyntraXLError> {
+        self.check_effect(treatment, outcome)?;
+        let set = self.node_set(adjustment_set)?;
+        if !causal::satisfies_backdoor(self, treatment, outcome, &set) {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "{:?} does not satisfy the backdoor criterion for `{}` -> `{}`",
+                set, treatment, outcome
+            )));
+        }
+        let state = self.state_index(treatment, state)?;
+        let probabilities = causal::adjust(self, treatment, state, outcome, adjustment_set)
+            .ok_or_else(|| {
+                MetaSyntraXLError::BayesianNetworkError(format!(
+                    "`{}` has probability zero in some stratum of the adjustment set",
+                    treatment
+                ))
+            })?;
+        Ok(Distribution {
+            states: self.nodes[outcome].states.clone(),
+            probabilities,
+        })
+    }
+
+    /// The average treatment effect
+    /// `P(outcome = outcome_state | do(treatment = treated)) -
+    /// P(outcome = outcome_state | do(treatment = control))`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if a node or state is unknown, or `treatment`
+    /// and `outcome` are the same node.
+    pub fn average_treatment_effect(
+        &self,
+        treatment: &str,
+        treated: &str,
+        control: &str,
+        outcome: &str,
+        outcome_state: &str,
+    ) -> Result<f64, MetaSyntraXLError> {
+        self.check_effect(treatment, outcome)?;
+        let outcome_index = self.state_index(outcome, outcome_state)?;
+        let mut effect = 0.0;
+        for (state, sign) in [(treated, 1.0), (control, -1.0)] {
+            let interventions = HashMap::from([(treatment.to_string(), state.to_string())]);
+            let beliefs = self.infer_do(&interventions, &HashMap::new())?;
+            effect += sign * beliefs[outcome].probabilities[outcome_index];
+        }
+        Ok(effect)
+    }
+
+    /// Boolean convenience over [`BayesianNetwork::average_treatment_effect`]:
+    /// `P(outcome | do(treatment)) - P(outcome | do(not treatment))`.
+    pub fn average_treatment_effect_boolean(
+        &self,
+        treatment: &str,
+        outcome: &str,
+    ) -> Result<f64, MetaSyntraXLError> {
+        let [control, treated] = BOOLEAN_STATES;
+        self.average_treatment_effect(treatment, treated, control, outcome, treated)
+    }
+
+    /// Checks that `treatment` and `outcome` are two distinct known nodes.
+    fn check_effect(&self, treatment: &str, outcome: &str) -> Result<(), MetaSyntraXLError> {
+        self.node_set(&[treatment.to_string(), outcome.to_string()])?;
+        if treatment == outcome {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "`{}` cannot be both treatment and outcome",
+                treatment
+            )));
+        }
+        Ok(())
+    }
+
+    fn node_set<'a>(&self, nodes: &'a [String]) -> Result<BTreeSet<&'a str>, MetaSyntraXLError> {
+        nodes
+            .iter()
+            .map(|name| {
+                if self.nodes.contains_key(name) {
+                    Ok(name.as_str())
+                } else {
+                    Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                        "Unknown node `{}`",
+                        name
+                    )))
+                }
+            })
+            .collect()
+    }
+
+    fn state_index(&self, node: &str, state: &str) -> Result<usize, MetaSyntraXLError> {
+        let evidence = HashMap::from([(node.to_string(), state.to_string())]);
+        Ok(self.state_evidence(&evidence)?[node])
+    }
+
+    /// Reads a network in the Bayesian Interchange Format (BIF).
+    ///
+    /// Only discrete variables are supported. CPTs may be given per parent
+    /// configuration, with an optional `default` row, or as a `table` laid out as
+    /// described on [`BayesianNetwork::add_variable`]. `property` entries are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if the text is not valid BIF, or the network
+    /// it describes is rejected by [`BayesianNetwork::add_variable`].
+    pub fn from_bif(text: &str) -> Result<Self, MetaSyntraXLError> {
+        format::from_bif(text)
+    }
+
+    /// Writes the network in the Bayesian Interchange Format (BIF). Names that are
+    /// not plain BIF words are double-quoted.
+    pub fn to_bif(&self) -> String {
+        format::to_bif(self)
+    }
+
+    /// Reads a network in XMLBIF.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if the text is not valid XMLBIF, or the
+    /// network it describes is rejected by [`BayesianNetwork::add_variable`].
+    pub fn from_xmlbif(text: &str) -> Result<Self, MetaSyntraXLError> {
+        format::from_xmlbif(text)
+    }
+
+    /// Writes the network as XMLBIF 0.3.
+    pub fn to_xmlbif(&self) -> String {
+        format::to_xmlbif(self)
+    }
+
+    /// Reads a network from its JSON representation, see [`BayesianNetwork::to_json`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `BayesianNetworkError` if the JSON is malformed, or the network it
+    /// describes is rejected by [`BayesianNetwork::add_variable`].
+    pub fn from_json(text: &str) -> Result<Self, MetaSyntraXLError> {
+        serde_json::from_str(text).map_err(|e| {
+            MetaSyntraXLError::BayesianNetworkError(format!("Invalid network JSON: {}", e))
+        })
+    }
+
+    /// Writes the network as JSON: a `variables` array in topological order, each
+    /// with its `name`, `states`, `parents`, dense `cpt` and, once data has been
+    /// observed, the `counts` behind it.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("networks serialize to JSON")
+    }
+}
+
+/// Checks that `cpt` holds a probability for every combination of parent values.
+fn validate_cpt(
+    name: &str,
+    parents: &[String],
+    cpt: &HashMap<Vec<bool>, f64>,
+) -> Result<(), MetaSyntraXLError> {
+    for (row, &p) in cpt {
+        if row.len() != parents.len() {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "CPT of `{}` has a row for {} parent values, expected {}",
+                name,
+                row.len(),
+                parents.len()
+            )));
+        }
+        if !(0.0..=1.0).contains(&p) {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "CPT of `{}` has probability {} for {:?}, outside [0, 1]",
+                name, p, row
+            )));
+        }
+    }
+
+    if parents.len() >= usize::BITS as usize {
+        return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+            "Node `{}` has too many parents for a CPT",
+            name
+        )));
+    }
+    for combination in 0..(1usize << parents.len()) {
+        let row = boolean_row(parents.len(), combination);
+        if !cpt.contains_key(&row) {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "CPT of `{}` is missing the row for {:?} = {:?}",
+                name, parents, row
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `values` is a dense CPT over `cardinalities`, the node's own last.
+fn validate_table(
+    name: &str,
+    cardinalities: &[usize],
+    values: &[f64],
+) -> Result<(), MetaSyntraXLError> {
+    let size = cardinalities
+        .iter()
+        .try_fold(1usize, |size, &c| size.checked_mul(c));
+    if size != Some(values.len()) {
+        return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+            "CPT of `{}` has {} values, expected {:?} for cardinalities {:?}",
+            name,
+            values.len(),
+            size,
+            cardinalities
+        )));
+    }
+    if let Some(p) = values.iter().find(|p| !(0.0..=1.0).contains(*p)) {
+        return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+            "CPT of `{}` has probability {}, outside [0, 1]",
+            name, p
+        )));
+    }
+    let states = cardinalities.last().copied().unwrap_or(1);
+    for (row, chunk) in values.chunks(states).enumerate() {
+        let total: f64 = chunk.iter().sum();
+        if (total - 1.0).abs() > ROW_TOLERANCE {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "CPT row {} of `{}` sums to {}, expected 1",
+                row, name, total
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The parent values of row `combination` of a boolean CPT, the last parent varying fastest.
+fn boolean_row(num_parents: usize, combination: usize) -> Vec<bool> {
+    (0..num_parents)
+        .map(|i| (combination >> (num_parents - 1 - i)) & 1 == 1)
+        .collect()
+}
+
+/// Converts a validated boolean CPT into the dense layout, `false` first in each row.
+fn boolean_table(num_parents: usize, cpt: &HashMap<Vec<bool>, f64>) -> Vec<f64> {
+    let mut values = Vec::with_capacity(2 << num_parents);
+    for combination in 0..(1usize << num_parents) {
+        let p_true = cpt[&boolean_row(num_parents, combination)];
+        values.push(1.0 - p_true);
+        values.push(p_true);
+    }
+    values
+}
+
+/// Boolean values as the state names of the nodes created by [`BayesianNetwork::add_node`].
+fn boolean_record(record: &HashMap<String, bool>) -> HashMap<String, String> {
+    record
+        .iter()
+        .map(|(name, value)| (name.clone(), value.to_string()))
+        .collect()
+}
+
+/// Point-mass marginals for the observed nodes.
+fn one_hot(nodes: &HashMap<String, Node>, evidence: &HashMap<String, usize>) -> Marginals {
+    evidence
+        .iter()
+        .map(|(name, &state)| (name.clone(), one_hot_state(nodes[name].states.len(), state)))
+        .collect()
+}
+
+fn one_hot_state(cardinality: usize, state: usize) -> Vec<f64> {
+    let mut probabilities = vec![0.0; cardinality];
+    probabilities[state] = 1.0;
+    probabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpt(entries: &[(&[bool], f64)]) -> HashMap<Vec<bool>, f64> {
+        entries.iter().map(|(k, v)| (k.to_vec(), *v)).collect()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn sprinkler() -> BayesianNetwork {
+        let mut bn = BayesianNetwork::new();
+        bn.add_node("Cloudy".to_string(), vec![], cpt(&[(&[], 0.5)]))
+            .unwrap();
+        bn.add_node(
+            "Sprinkler".to_string(),
+            names(&["Cloudy"]),
+            cpt(&[(&[true], 0.1), (&[false], 0.5)]),
+        )
+        .unwrap();
+        bn.add_node(
+            "Rain".to_string(),
+            names(&["Cloudy"]),
+            cpt(&[(&[true], 0.8), (&[false], 0.2)]),
+        )
+        .unwrap();
+        bn.add_node(
+            "WetGrass".to_string(),
+            names(&["Sprinkler", "Rain"]),
+            cpt(&[
+                (&[true, true], 0.99),
+                (&[true, false], 0.9),
+                (&[false, true], 0.9),
+                (&[false, false], 0.0),
+            ]),
+        )
+        .unwrap();
+        bn
+    }
+
+    fn alarm() -> BayesianNetwork {
+        let mut bn = BayesianNetwork::new();
+        bn.add_node("Burglary".to_string(), vec![], cpt(&[(&[], 0.001)]))
+            .unwrap();
+        bn.add_node("Earthquake".to_string(), vec![], cpt(&[(&[], 0.002)]))
+            .unwrap();
+        bn.add_node(
+            "Alarm".to_string(),
+            names(&["Burglary", "Earthquake"]),
+            cpt(&[
+                (&[true, true], 0.95),
+                (&[true, false], 0.94),
+                (&[false, true], 0.29),
+                (&[false, false], 0.001),
+            ]),
+        )
+        .unwrap();
+        bn.add_node(
+            "JohnCalls".to_string(),
+            names(&["Alarm"]),
+            cpt(&[(&[true], 0.9), (&[false], 0.05)]),
+        )
+        .unwrap();
+        bn.add_node(
+            "MaryCalls".to_string(),
+            names(&["Alarm"]),
+            cpt(&[(&[true], 0.7), (&[false], 0.01)]),
+        )
+        .unwrap();
+        bn
+    }
+
+    fn evidence(entries: &[(&str, bool)]) -> HashMap<String, bool> {
+        entries.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+    }
+
+    fn observed(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    /// Weather (sunny, cloudy, rainy) -> Umbrella (boolean) -> Late (boolean).
+    fn weather() -> BayesianNetwork {
+        let mut bn = BayesianNetwork::new();
+        bn.add_variable(
+            "Weather".to_string(),
+            names(&["sunny", "cloudy", "rainy"]),
+            vec![],
+            vec![0.5, 0.3, 0.2],
+        )
+        .unwrap();
+        bn.add_variable(
+            "Umbrella".to_string(),
+            names(&["false", "true"]),
+            names(&["Weather"]),
+            vec![0.9, 0.1, 0.6, 0.4, 0.1, 0.9],
+        )
+        .unwrap();
+        bn.add_node(
+            "Late".to_string(),
+            names(&["Umbrella"]),
+            cpt(&[(&[true], 0.3), (&[false], 0.1)]),
+        )
+        .unwrap();
+        bn
+    }
+
+    #[test]
+    fn test_sprinkler_posteriors() {
+        let bn = sprinkler();
+
+        let prior = bn.infer_boolean(&HashMap::new());
+        assert!((prior["WetGrass"] - 0.6471).abs() < 1e-4);
+        assert!((prior["Rain"] - 0.5).abs() < 1e-9);
+
+        let posterior = bn.infer_boolean(&evidence(&[("WetGrass", true)]));
+        assert!((posterior["Rain"] - 0.7079).abs() < 1e-4);
+        assert!((posterior["Sprinkler"] - 0.4298).abs() < 1e-4);
+        assert_eq!(posterior["WetGrass"], 1.0);
+    }
+
+    #[test]
+    fn test_alarm_posteriors() {
+        let bn = alarm();
+        let calls = evidence(&[("JohnCalls", true), ("MaryCalls", true)]);
+
+        let burglary = bn.reason("Burglary", &calls).unwrap();
+        assert!((burglary - 0.2842).abs() < 1e-4);
+        let earthquake = bn.reason("Earthquake", &calls).unwrap();
+        assert!((earthquake - 0.1761).abs() < 1e-4);
+        assert!(!bn.validate_prediction("Burglary", &calls));
+    }
+
+    #[test]
+    fn test_elimination_order_does_not_change_posteriors() {
+        let bn = alarm();
+        let calls = observed(&[("JohnCalls", "true")]);
+        let min_fill = bn.infer(&calls).unwrap();
+        let min_degree = bn
+            .infer_with_order(&calls, &EliminationOrder::MinDegree)
+            .unwrap();
+        let fixed = bn
+            .infer_with_order(
+                &calls,
+                &EliminationOrder::Fixed(names(&["MaryCalls", "Alarm", "Earthquake", "Burglary"])),
+            )
+            .unwrap();
+        for (name, distribution) in &min_fill {
+            for (i, p) in distribution.probabilities.iter().enumerate() {
+                assert!((p - min_degree[name].probabilities[i]).abs() < 1e-12);
+                assert!((p - fixed[name].probabilities[i]).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_impossible_evidence_has_no_posterior() {
+        let bn = sprinkler();
+        let impossible = evidence(&[("Sprinkler", false), ("Rain", false), ("WetGrass", true)]);
+        assert_eq!(bn.reason("Cloudy", &impossible), None);
+        assert_eq!(bn.infer_boolean(&impossible).len(), 3);
+
+        let impossible = observed(&[
+            ("Sprinkler", "false"),
+            ("Rain", "false"),
+            ("WetGrass", "true"),
+        ]);
+        assert!(bn.infer(&impossible).is_err());
+    }
+
+    #[test]
+    fn test_sampling_methods_approximate_exact_posteriors() {
+        let bn = sprinkler();
+        let wet = evidence(&[("WetGrass", true)]);
+        let methods = [
+            InferenceMethod::LikelihoodWeighting(SamplingOptions {
+                samples: 20_000,
+                seed: 7,
+                ..SamplingOptions::default()
+            }),
+            InferenceMethod::Gibbs(GibbsOptions {
+                samples: 50_000,
+                seed: 7,
+                ..GibbsOptions::default()
+            }),
+        ];
+        for method in &methods {
+            let rain = bn.reason_with("Rain", &wet, method).unwrap().unwrap();
+            assert!((rain.probability - 0.7079).abs() < 0.02, "{:?}", rain);
+            assert!(rain.diagnostics.converged(), "{:?}", rain.diagnostics);
+
+            let again = bn.reason_with("Rain", &wet, method).unwrap().unwrap();
+            assert_eq!(rain, again);
+        }
+    }
+
+    #[test]
+    fn test_gibbs_starts_from_states_consistent_with_the_evidence() {
+        // A -> B -> C, each copying its parent. Observing C pins A and B to true,
+        // and a chain started from A = B = false could never leave that state.
+        let mut bn = BayesianNetwork::new();
+        bn.add_node("A".to_string(), vec![], cpt(&[(&[], 0.5)]))
+            .unwrap();
+        for (node, parent) in [("B", "A"), ("C", "B")] {
+            bn.add_node(
+                node.to_string(),
+                names(&[parent]),
+                cpt(&[(&[true], 1.0), (&[false], 0.0)]),
+            )
+            .unwrap();
+        }
+        let method = InferenceMethod::Gibbs(GibbsOptions {
+            samples: 2_000,
+            burn_in: 10,
+            chains: 8,
+            seed: 5,
+        });
+        let a = bn
+            .reason_with("A", &evidence(&[("C", true)]), &method)
+            .unwrap()
+            .unwrap();
+        assert_eq!(a.probability, 1.0);
+        assert!(a.diagnostics.converged(), "{:?}", a.diagnostics);
+    }
+
+    #[test]
+    fn test_sampling_methods_reject_options_without_samples() {
+        let bn = sprinkler();
+        let wet = observed(&[("WetGrass", "true")]);
+        let methods = [
+            InferenceMethod::LikelihoodWeighting(SamplingOptions {
+                samples: 0,
+                ..SamplingOptions::default()
+            }),
+            InferenceMethod::Gibbs(GibbsOptions {
+                samples: 0,
+                ..GibbsOptions::default()
+            }),
+            InferenceMethod::Gibbs(GibbsOptions {
+                samples: 3,
+                chains: 4,
+                ..GibbsOptions::default()
+            }),
+        ];
+        for method in &methods {
+            match bn.infer_with(&wet, method) {
+                Err(MetaSyntraXLError::BayesianNetworkError(message)) => {
+                    assert!(message.contains("sample"), "{}", message)
+                }
+                other => panic!("{:?}: unexpected {:?}", method, other),
+            }
+            assert!(bn
+                .reason_with("Rain", &evidence(&[("WetGrass", true)]), method)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_likelihood_weighting_flags_unlikely_evidence() {
+        // Both calls happen in about 0.2% of worlds, so few samples carry weight.
+        let bn = alarm();
+        let calls = observed(&[("JohnCalls", "true"), ("MaryCalls", "true")]);
+        let options = SamplingOptions {
+            samples: 5_000,
+            seed: 1,
+            ..SamplingOptions::default()
+        };
+        let method = InferenceMethod::LikelihoodWeighting(options.clone());
+        let inference = bn.infer_with(&calls, &method).unwrap();
+        match inference.diagnostics {
+            Diagnostics::LikelihoodWeighting {
+                effective_sample_size,
+                ..
+            } => assert!(effective_sample_size < 100.0, "{}", effective_sample_size),
+            ref other => panic!("unexpected diagnostics {:?}", other),
+        }
+        assert!(!inference.diagnostics.converged());
+
+        let lenient = InferenceMethod::LikelihoodWeighting(SamplingOptions {
+            min_effective_sample_size: 1.0,
+            ..options
+        });
+        assert!(bn
+            .infer_with(&calls, &lenient)
+            .unwrap()
+            .diagnostics
+            .converged());
+    }
+
+    #[test]
+    fn test_belief_propagation_is_exact_on_polytrees() {
+        let bn = alarm();
+        let calls = observed(&[("JohnCalls", "true"), ("MaryCalls", "true")]);
+        let method = InferenceMethod::LoopyBeliefPropagation(BeliefPropagationOptions::default());
+
+        let inference = bn.infer_with(&calls, &method).unwrap();
+        assert!(inference.diagnostics.converged());
+        let exact = bn.infer(&calls).unwrap();
+        for (name, distribution) in &exact {
+            let approximate = &inference.beliefs[name];
+            for (p, q) in distribution
+                .probabilities
+                .iter()
+                .zip(&approximate.probabilities)
+            {
+                assert!((p - q).abs() < 1e-6, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_loopy_belief_propagation_reports_diagnostics() {
+        let bn = sprinkler();
+        let wet = evidence(&[("WetGrass", true)]);
+        let method = InferenceMethod::LoopyBeliefPropagation(BeliefPropagationOptions {
+            damping: 0.5,
+            ..BeliefPropagationOptions::default()
+        });
+
+        let rain = bn.reason_with("Rain", &wet, &method).unwrap().unwrap();
+        match rain.diagnostics {
+            Diagnostics::BeliefPropagation {
+                iterations,
+                converged,
+                ..
+            } => {
+                assert!(converged);
+                assert!(iterations > 1);
+            }
+            other => panic!("unexpected diagnostics {:?}", other),
+        }
+        assert!((rain.probability - 0.7079).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_add_node_rejects_invalid_structure() {
+        let mut bn = sprinkler();
+
+        let unknown = bn.add_node(
+            "Puddle".to_string(),
+            names(&["Hose"]),
+            cpt(&[(&[true], 0.9), (&[false], 0.1)]),
+        );
+        assert!(matches!(
+            unknown,
+            Err(MetaSyntraXLError::BayesianNetworkError(_))
+        ));
+
+        let own_parent = bn.add_node(
+            "Cloudy".to_string(),
+            names(&["Cloudy"]),
+            cpt(&[(&[true], 0.9), (&[false], 0.1)]),
+        );
+        assert!(own_parent.is_err());
+
+        let cycle = bn.add_node(
+            "Cloudy".to_string(),
+            names(&["WetGrass"]),
+            cpt(&[(&[true], 0.9), (&[false], 0.1)]),
+        );
+        assert!(cycle.is_err());
+        assert!(bn.nodes["Cloudy"].parents.is_empty());
+    }
+
+    #[test]
+    fn test_add_node_rejects_invalid_cpts() {
+        let mut bn = sprinkler();
+        let parents = names(&["Sprinkler", "Rain"]);
+
+        let wrong_arity = cpt(&[(&[true], 0.9), (&[false], 0.1)]);
+        assert!(bn
+            .add_node("Puddle".to_string(), parents.clone(), wrong_arity)
+            .is_err());
+
+        let incomplete = cpt(&[
+            (&[true, true], 0.9),
+            (&[true, false], 0.5),
+            (&[false, true], 0.5),
+        ]);
+        assert!(bn
+            .add_node("Puddle".to_string(), parents.clone(), incomplete)
+            .is_err());
+
+        let out_of_range = cpt(&[
+            (&[true, true], 1.2),
+            (&[true, false], 0.5),
+            (&[false, true], 0.5),
+            (&[false, false], 0.0),
+        ]);
+        assert!(bn
+            .add_node("Puddle".to_string(), parents.clone(), out_of_range)
+            .is_err());
+
+        let nan = cpt(&[
+            (&[true, true], f64::NAN),
+            (&[true, false], 0.5),
+            (&[false, true], 0.5),
+            (&[false, false], 0.0),
+        ]);
+        assert!(bn.add_node("Puddle".to_string(), parents, nan).is_err());
+        assert!(!bn.nodes.contains_key("Puddle"));
+    }
+
+    #[test]
+    fn test_update_belief_validates_cpt() {
+        let mut bn = sprinkler();
+        assert!(bn.update_belief("Hose", cpt(&[(&[], 0.5)])).is_err());
+        assert!(bn.update_belief("Rain", cpt(&[(&[], 0.5)])).is_err());
+        assert!(bn
+            .update_belief("Rain", cpt(&[(&[true], 0.7), (&[false], 0.3)]))
+            .is_ok());
+        let rain = bn.reason("Rain", &evidence(&[("Cloudy", true)])).unwrap();
+        assert!((rain - 0.7).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_topological_order() {
+        let order = alarm().topological_order();
+        assert_eq!(
+            order,
+            names(&["Burglary", "Earthquake", "Alarm", "JohnCalls", "MaryCalls"])
+        );
+
+        let order = sprinkler().topological_order();
+        assert_eq!(order, names(&["Cloudy", "Rain", "Sprinkler", "WetGrass"]));
+    }
+
+    #[test]
+    fn test_categorical_posteriors() {
+        let bn = weather();
+        let umbrella = observed(&[("Umbrella", "true")]);
+
+        let posterior = bn.infer(&umbrella).unwrap();
+        let weather = &posterior["Weather"];
+        assert_eq!(weather.states, names(&["sunny", "cloudy", "rainy"]));
+        assert!((weather.probability("sunny").unwrap() - 0.05 / 0.35).abs() < 1e-9);
+        assert!((weather.probability("cloudy").unwrap() - 0.12 / 0.35).abs() < 1e-9);
+        assert!((weather.probability("rainy").unwrap() - 0.18 / 0.35).abs() < 1e-9);
+        assert_eq!(weather.most_likely(), "rainy");
+        assert_eq!(posterior["Umbrella"].probability("true"), Some(1.0));
+
+        let late = observed(&[("Weather", "rainy")]);
+        let p_late = bn.infer(&late).unwrap()["Late"]
+            .probability("true")
+            .unwrap();
+        assert!((p_late - (0.9 * 0.3 + 0.1 * 0.1)).abs() < 1e-9);
+        assert!((bn.reason("Late", &HashMap::new()).unwrap() - 0.1 - 0.2 * 0.35).abs() < 1e-9);
+        assert_eq!(bn.reason("Weather", &HashMap::new()), None);
+    }
+
+    #[test]
+    fn test_approximate_methods_handle_categorical_variables() {
+        let bn = weather();
+        let late = observed(&[("Late", "true")]);
+        let exact = bn.infer(&late).unwrap();
+        let methods = [
+            InferenceMethod::LikelihoodWeighting(SamplingOptions {
+                samples: 20_000,
+                seed: 3,
+                ..SamplingOptions::default()
+            }),
+            InferenceMethod::Gibbs(GibbsOptions {
+                samples: 20_000,
+                seed: 3,
+                ..GibbsOptions::default()
+            }),
+            InferenceMethod::LoopyBeliefPropagation(BeliefPropagationOptions::default()),
+        ];
+        for method in &methods {
+            let inference = bn.infer_with(&late, method).unwrap();
+            let approximate = &inference.beliefs["Weather"];
+            for (p, q) in exact["Weather"]
+                .probabilities
+                .iter()
+                .zip(&approximate.probabilities)
+            {
+                assert!((p - q).abs() < 0.02, "{:?}: {} vs {}", method, p, q);
+            }
+        }
+    }
+
+    #[test]
+    fn test_evidence_must_name_known_states() {
+        let bn = weather();
+        assert!(bn.infer(&observed(&[("Weather", "foggy")])).is_err());
+        assert!(bn.infer(&observed(&[("Season", "winter")])).is_err());
+        assert_eq!(bn.states("Weather").unwrap().len(), 3);
+        assert_eq!(bn.states("Season"), None);
+    }
+
+    #[test]
+    fn test_add_variable_rejects_invalid_tables() {
+        let mut bn = weather();
+        let states = names(&["low", "high"]);
+        let parents = names(&["Weather"]);
+
+        let short = vec![0.5, 0.5, 0.5, 0.5];
+        assert!(bn
+            .add_variable(
+                "Traffic".to_string(),
+                states.clone(),
+                parents.clone(),
+                short
+            )
+            .is_err());
+        let unnormalized = vec![0.5, 0.5, 0.5, 0.4, 0.5, 0.5];
+        assert!(bn
+            .add_variable(
+                "Traffic".to_string(),
+                states.clone(),
+                parents.clone(),
+                unnormalized
+            )
+            .is_err());
+        let negative = vec![0.5, 0.5, 1.5, -0.5, 0.5, 0.5];
+        assert!(bn
+            .add_variable("Traffic".to_string(), states, parents.clone(), negative)
+            .is_err());
+        assert!(bn
+            .add_variable(
+                "Traffic".to_string(),
+                names(&["low", "low"]),
+                parents,
+                vec![0.5; 6]
+            )
+            .is_err());
+        assert!(bn
+            .add_variable(
+                "Weather".to_string(),
+                names(&["dry", "wet"]),
+                vec![],
+                vec![0.5, 0.5]
+            )
+            .is_err());
+        assert!(bn.set_cpt("Weather", vec![0.2, 0.3, 0.5]).is_ok());
+        assert!(bn.set_cpt("Weather", vec![0.2, 0.3]).is_err());
+    }
+
+    #[test]
+    fn test_boolean_api_requires_boolean_nodes() {
+        let mut bn = weather();
+        let on_weather = bn.add_node(
+            "Picnic".to_string(),
+            names(&["Weather"]),
+            cpt(&[(&[true], 0.3), (&[false], 0.1)]),
+        );
+        assert!(on_weather.is_err());
+        assert!(bn.update_belief("Weather", cpt(&[(&[], 0.5)])).is_err());
+    }
+
+    /// Two boolean nodes `A -> B` with uninformative CPTs, to be learned.
+    fn two_nodes() -> BayesianNetwork {
+        let mut bn = BayesianNetwork::new();
+        bn.add_node("A".to_string(), vec![], cpt(&[(&[], 0.5)]))
+            .unwrap();
+        bn.add_node(
+            "B".to_string(),
+            names(&["A"]),
+            cpt(&[(&[true], 0.5), (&[false], 0.5)]),
+        )
+        .unwrap();
+        bn
+    }
+
+    fn two_node_records() -> Vec<HashMap<String, bool>> {
+        let mut records = Vec::new();
+        for (a, b, n) in [
+            (true, true, 4),
+            (true, false, 2),
+            (false, true, 1),
+            (false, false, 3),
+        ] {
+            for _ in 0..n {
+                records.push(evidence(&[("A", a), ("B", b)]));
+            }
+        }
+        records
+    }
+
+    /// Forward samples of the sprinkler network.
+    fn sprinkler_records(n: usize, seed: u64) -> Vec<HashMap<String, bool>> {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        (0..n)
+            .map(|_| {
+                let cloudy = rng.gen_bool(0.5);
+                let sprinkler = rng.gen_bool(if cloudy { 0.1 } else { 0.5 });
+                let rain = rng.gen_bool(if cloudy { 0.8 } else { 0.2 });
+                let wet = match (sprinkler, rain) {
+                    (true, true) => rng.gen_bool(0.99),
+                    (false, false) => false,
+                    _ => rng.gen_bool(0.9),
+                };
+                evidence(&[
+                    ("Cloudy", cloudy),
+                    ("Sprinkler", sprinkler),
+                    ("Rain", rain),
+                    ("WetGrass", wet),
+                ])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_maximum_likelihood_and_dirichlet_estimates() {
+        let mut bn = two_nodes();
+        let report = bn
+            .fit_parameters_boolean(&two_node_records(), &LearningOptions::default())
+            .unwrap();
+        assert_eq!(report.iterations, 1);
+        assert!(report.converged);
+        assert!((bn.reason("A", &HashMap::new()).unwrap() - 0.6).abs() < 1e-12);
+        let b_given_a = bn.reason("B", &evidence(&[("A", true)])).unwrap();
+        assert!((b_given_a - 4.0 / 6.0).abs() < 1e-12);
+        let b_given_not_a = bn.reason("B", &evidence(&[("A", false)])).unwrap();
+        assert!((b_given_not_a - 0.25).abs() < 1e-12);
+        let expected = 6.0 * 0.6f64.ln()
+            + 4.0 * 0.4f64.ln()
+            + 4.0 * (4.0f64 / 6.0).ln()
+            + 2.0 * (2.0f64 / 6.0).ln()
+            + 0.25f64.ln()
+            + 3.0 * 0.75f64.ln();
+        assert!((report.log_likelihood - expected).abs() < 1e-9);
+
+        let dirichlet = LearningOptions {
+            estimator: Estimator::Dirichlet { pseudo_count: 1.0 },
+            ..LearningOptions::default()
+        };
+        bn.fit_parameters_boolean(&two_node_records(), &dirichlet)
+            .unwrap();
+        assert!((bn.reason("A", &HashMap::new()).unwrap() - 7.0 / 12.0).abs() < 1e-12);
+        let b_given_a = bn.reason("B", &evidence(&[("A", true)])).unwrap();
+        assert!((b_given_a - 5.0 / 8.0).abs() < 1e-12);
+
+        let invalid = LearningOptions {
+            estimator: Estimator::Dirichlet { pseudo_count: -1.0 },
+            ..LearningOptions::default()
+        };
+        assert!(bn
+            .fit_parameters_boolean(&two_node_records(), &invalid)
+            .is_err());
+    }
+
+    #[test]
+    fn test_complete_records_override_deterministic_zeros() {
+        let certain = || {
+            let mut bn = BayesianNetwork::new();
+            bn.add_variable("A".to_string(), names(&["x", "y"]), vec![], vec![1.0, 0.0])
+                .unwrap();
+            bn
+        };
+        let records: Vec<HashMap<String, String>> = (0..10)
+            .map(|i| observed(&[("A", if i < 7 { "x" } else { "y" })]))
+            .collect();
+        let mut bn = certain();
+        let report = bn
+            .fit_parameters(&records, &LearningOptions::default())
+            .unwrap();
+        assert!(report.converged);
+        assert_eq!(report.skipped_records, 0);
+        assert_eq!(bn.nodes["A"].cpt.values, vec![0.7, 0.3]);
+
+        let mut online = certain();
+        online
+            .observe(&observed(&[("A", "y")]), &Estimator::MaximumLikelihood)
+            .unwrap();
+        assert_eq!(online.nodes["A"].cpt.values, vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_em_learns_from_partially_observed_records() {
+        let mut records = sprinkler_records(2000, 11);
+        for record in records.iter_mut().step_by(2) {
+            record.remove("Cloudy");
+        }
+
+        let mut bn = sprinkler();
+        for (name, parents) in [("Sprinkler", 1), ("Rain", 1), ("WetGrass", 2)] {
+            let rows = (0..1usize << parents)
+                .map(|r| (boolean_row(parents, r), 0.5))
+                .collect();
+            bn.update_belief(name, rows).unwrap();
+        }
+        let report = bn
+            .fit_parameters_boolean(&records, &LearningOptions::default())
+            .unwrap();
+
+        assert!(report.converged, "{:?}", report);
+        assert!(report.iterations > 1);
+        assert_eq!(report.skipped_records, 0);
+        let rain = bn.reason("Rain", &evidence(&[("Cloudy", true)])).unwrap();
+        assert!((rain - 0.8).abs() < 0.05, "{}", rain);
+        let sprinkler = bn
+            .reason("Sprinkler", &evidence(&[("Cloudy", false)]))
+            .unwrap();
+        assert!((sprinkler - 0.5).abs() < 0.05, "{}", sprinkler);
+        let cloudy = bn.reason("Cloudy", &HashMap::new()).unwrap();
+        assert!((cloudy - 0.5).abs() < 0.05, "{}", cloudy);
+    }
+
+    #[test]
+    fn test_fit_parameters_from_csv() {
+        let mut bn = weather();
+        let csv = "Weather, Umbrella, Late\n\
+                   rainy, true, false\n\
+                   rainy, true, ?\n\
+                   sunny, false, false\n\
+                   cloudy, , true\n\
+                   \n\
+                   sunny, false, true\n";
+        let report = bn
+            .fit_parameters_csv(
+                csv.as_bytes(),
+                &LearningOptions {
+                    estimator: Estimator::Dirichlet { pseudo_count: 0.5 },
+                    ..LearningOptions::default()
+                },
+            )
+            .unwrap();
+        assert!(report.converged);
+
+        let prior = bn.infer(&HashMap::new()).unwrap();
+        let weather = &prior["Weather"];
+        assert!((weather.probability("rainy").unwrap() - 2.5 / 6.5).abs() < 1e-9);
+        assert!((weather.probability("sunny").unwrap() - 2.5 / 6.5).abs() < 1e-9);
+
+        let malformed = "Weather, Umbrella\nrainy\n";
+        assert!(bn
+            .fit_parameters_csv(malformed.as_bytes(), &LearningOptions::default())
+            .is_err());
+        let unknown_state = "Weather\nfoggy\n";
+        assert!(bn
+            .fit_parameters_csv(unknown_state.as_bytes(), &LearningOptions::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_online_updates_match_batch_estimates() {
+        let records = sprinkler_records(500, 5);
+        let estimator = Estimator::Dirichlet { pseudo_count: 1.0 };
+        let options = LearningOptions {
+            estimator: estimator.clone(),
+            ..LearningOptions::default()
+        };
+
+        let mut batch = sprinkler();
+        batch.fit_parameters_boolean(&records, &options).unwrap();
+
+        let mut online = sprinkler();
+        online
+            .fit_parameters_boolean(&records[..100], &options)
+            .unwrap();
+        for record in &records[100..] {
+            online.observe_boolean(record, &estimator).unwrap();
+        }
+
+        for (name, node) in &batch.nodes {
+            for (p, q) in node.cpt.values.iter().zip(&online.nodes[name].cpt.values) {
+                assert!((p - q).abs() < 1e-12, "{}", name);
+            }
+        }
+
+        let impossible = evidence(&[("Sprinkler", false), ("Rain", false), ("WetGrass", true)]);
+        let mut strict = sprinkler();
+        assert!(strict
+            .observe_boolean(&impossible, &Estimator::MaximumLikelihood)
+            .is_err());
+    }
+
+    fn parent_set(bn: &BayesianNetwork, node: &str) -> BTreeSet<String> {
+        bn.parents(node).unwrap().iter().cloned().collect()
+    }
+
+    fn adjacent(bn: &BayesianNetwork, a: &str, b: &str) -> bool {
+        parent_set(bn, a).contains(b) || parent_set(bn, b).contains(a)
+    }
+
+    fn assert_sprinkler_structure(bn: &BayesianNetwork) {
+        assert!(adjacent(bn, "Cloudy", "Sprinkler"));
+        assert!(adjacent(bn, "Cloudy", "Rain"));
+        assert!(!adjacent(bn, "Cloudy", "WetGrass"));
+        assert!(!adjacent(bn, "Sprinkler", "Rain"));
+        // The v-structure at WetGrass is identifiable from data.
+        assert_eq!(
+            parent_set(bn, "WetGrass"),
+            names(&["Rain", "Sprinkler"]).into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn test_hill_climbing_recovers_sprinkler_structure() {
+        let records = sprinkler_records(5000, 21);
+        for score in [
+            StructureScore::Bic,
+            StructureScore::BDeu {
+                equivalent_sample_size: 10.0,
+            },
+        ] {
+            let options = StructureOptions {
+                search: StructureSearch::HillClimbing {
+                    score,
+                    max_parents: 2,
+                    max_iterations: 100,
+                    tabu_length: 20,
+                },
+                ..StructureOptions::default()
+            };
+            let bn = BayesianNetwork::learn_structure_boolean(&records, &options).unwrap();
+            assert_sprinkler_structure(&bn);
+
+            let rain = bn.reason("Rain", &evidence(&[("Cloudy", true)]));
+            let cloudy = bn.reason("Cloudy", &evidence(&[("Rain", true)]));
+            assert!(rain.is_some() && cloudy.is_some());
+            let wet = bn
+                .reason(
+                    "WetGrass",
+                    &evidence(&[("Sprinkler", false), ("Rain", true)]),
+                )
+                .unwrap();
+            assert!((wet - 0.9).abs() < 0.05, "{}", wet);
+        }
+    }
+
+    #[test]
+    fn test_pc_recovers_sprinkler_structure() {
+        let records = sprinkler_records(5000, 21);
+        let options = StructureOptions {
+            search: StructureSearch::Pc {
+                significance: 0.01,
+                max_condition_size: 2,
+            },
+            ..StructureOptions::default()
+        };
+        let bn = BayesianNetwork::learn_structure_boolean(&records, &options).unwrap();
+        assert_sprinkler_structure(&bn);
+    }
+
+    #[test]
+    fn test_structure_learning_respects_edge_constraints() {
+        let records = sprinkler_records(2000, 8);
+        let edge = |a: &str, b: &str| (a.to_string(), b.to_string());
+        for search in [
+            StructureSearch::default(),
+            StructureSearch::Pc {
+                significance: 0.01,
+                max_condition_size: 2,
+            },
+        ] {
+            let options = StructureOptions {
+                search,
+                whitelist: vec![edge("Cloudy", "WetGrass")],
+                blacklist: vec![edge("Rain", "WetGrass"), edge("WetGrass", "Rain")],
+                ..StructureOptions::default()
+            };
+            let bn = BayesianNetwork::learn_structure_boolean(&records, &options).unwrap();
+            assert!(parent_set(&bn, "WetGrass").contains("Cloudy"));
+            assert!(!adjacent(&bn, "Rain", "WetGrass"));
+        }
+
+        let contradictory = StructureOptions {
+            whitelist: vec![edge("Rain", "WetGrass")],
+            blacklist: vec![edge("Rain", "WetGrass")],
+            ..StructureOptions::default()
+        };
+        assert!(BayesianNetwork::learn_structure_boolean(&records, &contradictory).is_err());
+        let cyclic = StructureOptions {
+            whitelist: vec![edge("Rain", "WetGrass"), edge("WetGrass", "Rain")],
+            ..StructureOptions::default()
+        };
+        assert!(BayesianNetwork::learn_structure_boolean(&records, &cyclic).is_err());
+        let unknown = StructureOptions {
+            blacklist: vec![edge("Rain", "Hose")],
+            ..StructureOptions::default()
+        };
+        assert!(BayesianNetwork::learn_structure_boolean(&records, &unknown).is_err());
+
+        let mut incomplete = records;
+        incomplete[0].remove("Rain");
+        assert!(BayesianNetwork::learn_structure_boolean(
+            &incomplete,
+            &StructureOptions::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_pc_reports_edges_it_cannot_orient() {
+        // X and Y agree 90% of the time while Z is independent noise. The
+        // whitelist pins Y -> Z -> X, so X -> Y closes a cycle and Y -> X is
+        // blacklisted.
+        let records: Vec<HashMap<String, bool>> = (0..400)
+            .map(|i| {
+                let x = i % 2 == 0;
+                let y = if i % 10 == 0 { !x } else { x };
+                evidence(&[("X", x), ("Y", y), ("Z", (i / 2) % 2 == 0)])
+            })
+            .collect();
+        let edge = |a: &str, b: &str| (a.to_string(), b.to_string());
+        let options = StructureOptions {
+            search: StructureSearch::Pc {
+                significance: 0.01,
+                max_condition_size: 1,
+            },
+            whitelist: vec![edge("Y", "Z"), edge("Z", "X")],
+            blacklist: vec![edge("Y", "X")],
+            ..StructureOptions::default()
+        };
+        match BayesianNetwork::learn_structure_boolean(&records, &options) {
+            Err(MetaSyntraXLError::BayesianNetworkError(message)) => {
+                assert!(
+                    message.contains("`X`") && message.contains("`Y`"),
+                    "{}",
+                    message
+                )
+            }
+            other => panic!("unexpected {:?}", other.map(|bn| bn.topological_order())),
+        }
+    }
+
+    fn assert_same_network(a: &BayesianNetwork, b: &BayesianNetwork) {
+        assert_eq!(a.topological_order(), b.topological_order());
+        for (name, node) in &a.nodes {
+            let other = &b.nodes[name];
+            assert_eq!(node.states, other.states, "{}", name);
+            assert_eq!(node.parents, other.parents, "{}", name);
+            assert_eq!(node.counts.len(), other.counts.len(), "{}", name);
+            let values = node.cpt.values.iter().chain(&node.counts);
+            for (p, q) in values.zip(other.cpt.values.iter().chain(&other.counts)) {
+                assert!((p - q).abs() < 1e-12, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bif_fixture_round_trips() {
+        let asia = BayesianNetwork::from_bif(include_str!("fixtures/asia.bif")).unwrap();
+        assert_eq!(asia.nodes.len(), 8);
+        assert_eq!(asia.parents("either").unwrap(), names(&["lung", "tub"]));
+        assert_eq!(
+            asia.nodes["either"].cpt.values,
+            vec![1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0]
+        );
+        let beliefs = asia.infer(&observed(&[("smoke", "yes")])).unwrap();
+        assert!((beliefs["lung"].probability("yes").unwrap() - 0.1).abs() < 1e-12);
+
+        assert_same_network(&asia, &BayesianNetwork::from_bif(&asia.to_bif()).unwrap());
+        assert_same_network(
+            &asia,
+            &BayesianNetwork::from_xmlbif(&asia.to_xmlbif()).unwrap(),
+        );
+        assert_same_network(&asia, &BayesianNetwork::from_json(&asia.to_json()).unwrap());
+    }
+
+    #[test]
+    fn test_xmlbif_fixture_round_trips() {
+        let bn = BayesianNetwork::from_xmlbif(include_str!("fixtures/sprinkler.xml")).unwrap();
+        assert_same_network(&sprinkler(), &bn);
+
+        assert_same_network(&bn, &BayesianNetwork::from_xmlbif(&bn.to_xmlbif()).unwrap());
+        assert_same_network(&bn, &BayesianNetwork::from_bif(&bn.to_bif()).unwrap());
+    }
+
+    #[test]
+    fn test_json_keeps_counts_and_odd_names_round_trip() {
+        let mut bn = weather();
+        bn.add_variable(
+            "Mood & <later>".to_string(),
+            names(&["fine day", "so-so"]),
+            names(&["Late"]),
+            vec![0.7, 0.3, 0.2, 0.8],
+        )
+        .unwrap();
+        bn.add_variable(
+            "Say \"hi\" \\ bye".to_string(),
+            names(&["\"", "C:\\"]),
+            vec![],
+            vec![0.4, 0.6],
+        )
+        .unwrap();
+        bn.observe(
+            &observed(&[("Weather", "rainy")]),
+            &Estimator::MaximumLikelihood,
+        )
+        .unwrap();
+
+        let json = serde_json::to_string(&bn).unwrap();
+        let restored: BayesianNetwork = serde_json::from_str(&json).unwrap();
+        assert_same_network(&bn, &restored);
+        assert!(restored.nodes["Weather"].counts.iter().any(|&c| c > 0.0));
+
+        // BIF and XMLBIF carry no counts.
+        let mut uncounted = restored;
+        for node in uncounted.nodes.values_mut() {
+            node.counts.iter_mut().for_each(|c| *c = 0.0);
+        }
+        assert!(uncounted.to_bif().contains("\"Mood & <later>\" | Late"));
+        assert!(uncounted
+            .to_bif()
+            .contains(r#"variable "Say \"hi\" \\ bye""#));
+        assert_same_network(
+            &uncounted,
+            &BayesianNetwork::from_bif(&bn.to_bif()).unwrap(),
+        );
+        assert!(uncounted.to_xmlbif().contains("Mood &amp; &lt;later&gt;"));
+        assert_same_network(
+            &uncounted,
+            &BayesianNetwork::from_xmlbif(&bn.to_xmlbif()).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_malformed_networks_are_rejected() {
+        let unknown_parent = "variable a { type discrete [ 2 ] { x, y }; }\n\
+                              probability ( a | b ) { (x) 0.5, 0.5; (y) 0.5, 0.5; }";
+        assert!(BayesianNetwork::from_bif(unknown_parent).is_err());
+        let missing_row = "variable a { type discrete [ 2 ] { x, y }; }\n\
+                           variable b { type discrete [ 2 ] { x, y }; }\n\
+                           probability ( a ) { table 0.5, 0.5; }\n\
+                           probability ( b | a ) { (x) 0.5, 0.5; }";
+        assert!(BayesianNetwork::from_bif(missing_row).is_err());
+        let bad_count = "variable a { type discrete [ 3 ] { x, y }; }";
+        assert!(BayesianNetwork::from_bif(bad_count).is_err());
+
+        let mismatched = "<BIF><NETWORK><VARIABLE><NAME>a</VARIABLE></NETWORK></BIF>";
+        assert!(BayesianNetwork::from_xmlbif(mismatched).is_err());
+        let cyclic = r#"{"variables": [
+            {"name": "a", "states": ["x"], "parents": ["b"], "cpt": [1.0]},
+            {"name": "b", "states": ["x"], "parents": ["a"], "cpt": [1.0]}
+        ]}"#;
+        assert!(BayesianNetwork::from_json(cyclic).is_err());
+        let bad_row = r#"{"variables": [{"name": "a", "states": ["x", "y"], "cpt": [0.5, 0.6]}]}"#;
+        assert!(BayesianNetwork::from_json(bad_row).is_err());
+    }
+
+    #[test]
+    fn test_interventions_differ_from_observations() {
+        let bn = sprinkler();
+        let sprinkler_on = evidence(&[("Sprinkler", true)]);
+        let observed_wet = bn.reason("WetGrass", &sprinkler_on).unwrap();
+        assert!((observed_wet - 0.927).abs() < 1e-9);
+        let forced_wet = bn
+            .reason_do("WetGrass", &sprinkler_on, &HashMap::new())
+            .unwrap();
+        assert!((forced_wet - 0.945).abs() < 1e-9);
+
+        // Wetting the grass by hand says nothing about the rain.
+        let wet = evidence(&[("WetGrass", true)]);
+        assert!(bn.reason("Rain", &wet).unwrap() > 0.7);
+        assert!((bn.reason_do("Rain", &wet, &HashMap::new()).unwrap() - 0.5).abs() < 1e-9);
+
+        let surgery = bn.intervene(&observed(&[("Sprinkler", "true")])).unwrap();
+        assert!(surgery.parents("Sprinkler").unwrap().is_empty());
+        assert_eq!(
+            surgery.parents("WetGrass").unwrap(),
+            names(&["Sprinkler", "Rain"])
+        );
+        let beliefs = bn
+            .infer_do(
+                &observed(&[("Sprinkler", "true")]),
+                &observed(&[("Cloudy", "true")]),
+            )
+            .unwrap();
+        assert_eq!(beliefs["Sprinkler"].probability("true"), Some(1.0));
+        assert!((beliefs["Rain"].probability("true").unwrap() - 0.8).abs() < 1e-9);
+        assert!(bn.intervene(&observed(&[("Hose", "true")])).is_err());
+        assert!(bn.intervene(&observed(&[("Sprinkler", "maybe")])).is_err());
+    }
+
+    #[test]
+    fn test_backdoor_adjustment_matches_graph_surgery() {
+        let bn = sprinkler();
+        assert_eq!(
+            bn.backdoor_adjustment_set("Sprinkler", "WetGrass").unwrap(),
+            Some(names(&["Cloudy"]))
+        );
+        assert_eq!(
+            bn.backdoor_adjustment_set("Cloudy", "WetGrass").unwrap(),
+            Some(vec![])
+        );
+        // Rain is caused by Cloudy, so no adjustment recovers an effect on it.
+        assert_eq!(bn.backdoor_adjustment_set("Rain", "Cloudy").unwrap(), None);
+        assert!(bn.backdoor_adjustment_set("WetGrass", "WetGrass").is_err());
+
+        for set in [
+            names(&["Cloudy"]),
+            names(&["Rain"]),
+            names(&["Cloudy", "Rain"]),
+        ] {
+            let adjusted = bn
+                .backdoor_adjustment("Sprinkler", "true", "WetGrass", &set)
+                .unwrap();
+            assert!((adjusted.probability("true").unwrap() - 0.945).abs() < 1e-9);
+        }
+        assert!(bn
+            .backdoor_adjustment("Sprinkler", "true", "WetGrass", &[])
+            .is_err());
+        assert!(bn
+            .backdoor_adjustment("Cloudy", "true", "Rain", &names(&["Sprinkler"]))
+            .is_err());
+
+        assert!(bn
+            .d_separated(
+                &names(&["Sprinkler"]),
+                &names(&["Rain"]),
+                &names(&["Cloudy"])
+            )
+            .unwrap());
+        assert!(!bn
+            .d_separated(
+                &names(&["Sprinkler"]),
+                &names(&["Rain"]),
+                &names(&["Cloudy", "WetGrass"])
+            )
+            .unwrap());
+        assert!(bn
+            .d_separated(&names(&["Rain"]), &names(&["Rain"]), &[])
+            .is_err());
+    }
+
+    #[test]
+    fn test_average_treatment_effect() {
+        let ate = sprinkler()
+            .average_treatment_effect_boolean("Sprinkler", "WetGrass")
+            .unwrap();
+        assert!((ate - 0.495).abs() < 1e-9);
+        assert!(
+            (sprinkler()
+                .average_treatment_effect_boolean("WetGrass", "Rain")
+                .unwrap())
+            .abs()
+                < 1e-12
+        );
+
+        let ate = weather()
+            .average_treatment_effect("Weather", "rainy", "sunny", "Late", "true")
+            .unwrap();
+        assert!((ate - 0.16).abs() < 1e-9);
+        assert!(weather()
+            .average_treatment_effect("Weather", "snowy", "sunny", "Late", "true")
+            .is_err());
+    }
+
+    #[test]
+    fn test_most_probable_explanation_and_map() {
+        let bn = sprinkler();
+        let mpe = bn.most_probable_explanation(&HashMap::new()).unwrap();
+        let expected = observed(&[
+            ("Cloudy", "true"),
+            ("Sprinkler", "false"),
+            ("Rain", "true"),
+            ("WetGrass", "true"),
+        ]);
+        assert_eq!(mpe.states, expected);
+        assert!((mpe.probability - 0.324).abs() < 1e-12);
+
+        let wet = observed(&[("WetGrass", "true")]);
+        let mpe = bn.most_probable_explanation(&wet).unwrap();
+        assert_eq!(mpe.states.len(), 3);
+        assert!(!mpe.states.contains_key("WetGrass"));
+        assert!((mpe.probability - 0.324 / 0.6471).abs() < 1e-12);
+
+        let map = bn
+            .maximum_a_posteriori(&names(&["Sprinkler", "Rain"]), &wet)
+            .unwrap();
+        assert_eq!(
+            map.states,
+            observed(&[("Sprinkler", "false"), ("Rain", "true")])
+        );
+        assert!((map.probability - 0.369 / 0.6471).abs() < 1e-12);
+
+        let impossible = observed(&[
+            ("Sprinkler", "false"),
+            ("Rain", "false"),
+            ("WetGrass", "true"),
+        ]);
+        assert!(bn.most_probable_explanation(&impossible).is_err());
+        assert!(bn.maximum_a_posteriori(&names(&["Hose"]), &wet).is_err());
+    }
+
+    #[test]
+    fn test_map_differs_from_projected_mpe() {
+        // X is usually true, but then Y is spread over three states, so the single
+        // most likely joint state has X false.
+        let mut bn = BayesianNetwork::new();
+        bn.add_node("X".to_string(), vec![], cpt(&[(&[], 0.6)]))
+            .unwrap();
+        bn.add_variable(
+            "Y".to_string(),
+            names(&["a", "b", "c"]),
+            names(&["X"]),
+            vec![1.0, 0.0, 0.0, 1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0],
+        )
+        .unwrap();
+
+        let mpe = bn.most_probable_explanation(&HashMap::new()).unwrap();
+        assert_eq!(mpe.states, observed(&[("X", "false"), ("Y", "a")]));
+        assert!((mpe.probability - 0.4).abs() < 1e-12);
+        let map = bn
+            .maximum_a_posteriori(&names(&["X"]), &HashMap::new())
+            .unwrap();
+        assert_eq!(map.states, observed(&[("X", "true")]));
+        assert!((map.probability - 0.6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_explanation_ranks_evidence_by_influence() {
+        let bn = sprinkler();
+        let explanation = bn
+            .explain(
+                "Rain",
+                "true",
+                &observed(&[("WetGrass", "true"), ("Sprinkler", "true")]),
+            )
+            .unwrap();
+        assert!((explanation.posterior - 0.33 / 1.03).abs() < 1e-9);
+        assert!((explanation.prior - 0.5).abs() < 1e-12);
+
+        // The sprinkler explains the wet grass away.
+        let influences: Vec<&str> = explanation
+            .influences
+            .iter()
+            .map(|i| i.node.as_str())
+            .collect();
+        assert_eq!(influences, ["Sprinkler", "WetGrass"]);
+        let sprinkler_influence = &explanation.influences[0];
+        assert_eq!(sprinkler_influence.state, "true");
+        assert!((sprinkler_influence.posterior_without - 0.7079276773296).abs() < 1e-9);
+        assert!(sprinkler_influence.impact < -0.38);
+        assert!((explanation.influences[1].posterior_without - 0.3).abs() < 1e-9);
+        assert!(explanation.influences[1].impact > 0.0);
+        assert_eq!(
+            explanation.most_probable_explanation.states,
+            observed(&[("Cloudy", "false"), ("Rain", "false")])
+        );
+
+        let evidence = evidence(&[("WetGrass", true), ("Sprinkler", true)]);
+        let prediction = bn.explain_prediction("Rain", &evidence).unwrap();
+        assert_eq!(prediction, explanation);
+        assert_eq!(
+            prediction.posterior > 0.5,
+            bn.validate_prediction("Rain", &evidence)
+        );
+        assert!(bn.explain_prediction("Hose", &evidence).is_none());
+        assert!(bn.explain("Rain", "maybe", &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_validate_prediction_with_threshold() {
+        let bn = sprinkler();
+        let wet = evidence(&[("WetGrass", true)]);
+        let posterior = bn.reason("Rain", &wet).unwrap();
+        assert!(bn.validate_prediction_with_threshold("Rain", &wet, posterior - 1e-6));
+        assert!(!bn.validate_prediction_with_threshold("Rain", &wet, posterior));
+        assert_eq!(
+            bn.validate_prediction("Rain", &wet),
+            bn.validate_prediction_with_threshold("Rain", &wet, 0.5)
+        );
+        assert!(!bn.validate_prediction_with_threshold("Hose", &wet, 0.0));
+    }
+
+    /// The umbrella world: Rain persists with probability 0.7, the director
+    /// carries an umbrella on 90% of rainy days and 20% of dry ones.
+    fn umbrella() -> DynamicBayesianNetwork {
+        let mut dbn = DynamicBayesianNetwork::new();
+        dbn.add_node(
+            "Rain".to_string(),
+            vec![],
+            cpt(&[(&[], 0.5)]),
+            names(&["Rain"]),
+            cpt(&[(&[true], 0.7), (&[false], 0.3)]),
+        )
+        .unwrap();
+        let sensor = cpt(&[(&[true], 0.9), (&[false], 0.2)]);
+        dbn.add_node(
+            "Umbrella".to_string(),
+            names(&["Rain"]),
+            sensor.clone(),
+            vec![],
+            sensor,
+        )
+        .unwrap();
+        dbn
+    }
+
+    fn unrolled_evidence(steps: &[HashMap<String, String>]) -> HashMap<String, String> {
+        steps
+            .iter()
+            .enumerate()
+            .flat_map(|(step, observed)| {
+                observed
+                    .iter()
+                    .map(move |(name, state)| (format!("{}@{}", name, step), state.clone()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dynamic_filtering_and_smoothing() {
+        let dbn = umbrella();
+        assert_eq!(dbn.variables(), names(&["Rain", "Umbrella"]));
+        let umbrellas = vec![observed(&[("Umbrella", "true")]); 2];
+
+        let filtered = dbn.filter(&umbrellas).unwrap();
+        assert_eq!(filtered.len(), 2);
+        let rain =
+            |beliefs: &HashMap<String, Distribution>| beliefs["Rain"].probability("true").unwrap();
+        assert!((rain(&filtered[0]) - 0.45 / 0.55).abs() < 1e-9);
+        assert!((rain(&filtered[1]) - 0.883).abs() < 1e-3);
+        assert_eq!(filtered[1]["Umbrella"].probabilities, vec![0.0, 1.0]);
+
+        let smoothed = dbn.smooth(&umbrellas).unwrap();
+        assert!((rain(&smoothed[0]) - 0.883).abs() < 1e-3);
+        assert_eq!(smoothed[1], filtered[1]);
+
+        // Without evidence the chain predicts forward from the prior.
+        let predicted = dbn.filter(&[observed(&[("Umbrella", "true")]), HashMap::new()]);
+        let predicted = predicted.unwrap();
+        let expected = 0.7 * rain(&filtered[0]) + 0.3 * (1.0 - rain(&filtered[0]));
+        assert!((rain(&predicted[1]) - expected).abs() < 1e-9);
+        assert!(
+            (predicted[1]["Umbrella"].probability("true").unwrap()
+                - (0.9 * expected + 0.2 * (1.0 - expected)))
+                .abs()
+                < 1e-9
+        );
+        assert!(dbn.filter(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dynamic_matches_unrolled_network() {
+        // A three-state Weather that persists, a sensor within the step, and an
+        // Alarm that depends on the previous step's Weather and its own past.
+        let mut dbn = DynamicBayesianNetwork::new();
+        dbn.add_variable(
+            "Weather".to_string(),
+            names(&["sunny", "cloudy", "rainy"]),
+            vec![],
+            vec![0.5, 0.3, 0.2],
+            names(&["Weather"]),
+            vec![0.6, 0.3, 0.1, 0.3, 0.4, 0.3, 0.1, 0.3, 0.6],
+        )
+        .unwrap();
+        dbn.add_variable(
+            "Reading".to_string(),
+            names(&["low", "high"]),
+            names(&["Weather"]),
+            vec![0.9, 0.1, 0.5, 0.5, 0.2, 0.8],
+            vec![],
+            vec![0.9, 0.1, 0.5, 0.5, 0.2, 0.8],
+        )
+        .unwrap();
+        let mut transition = Vec::new();
+        for weather in [0.05, 0.2, 0.6] {
+            for persistence in [0.0, 0.3] {
+                let p: f64 = weather + persistence;
+                transition.extend([1.0 - p, p]);
+            }
+        }
+        dbn.add_variable(
+            "Alarm".to_string(),
+            BOOLEAN_STATES.iter().map(|s| s.to_string()).collect(),
+            vec![],
+            vec![0.9, 0.1],
+            names(&["Weather", "Alarm"]),
+            transition,
+        )
+        .unwrap();
+
+        let steps = vec![
+            observed(&[("Reading", "high")]),
+            HashMap::new(),
+            observed(&[("Reading", "low"), ("Alarm", "true")]),
+            observed(&[("Reading", "high")]),
+        ];
+        let unrolled = dbn.unroll(steps.len());
+        assert_eq!(unrolled.nodes.len(), 12);
+        assert_eq!(
+            unrolled.parents("Alarm@2").unwrap(),
+            names(&["Weather@1", "Alarm@1"]).as_slice()
+        );
+        assert_eq!(
+            unrolled.parents("Reading@3").unwrap(),
+            names(&["Weather@3"]).as_slice()
+        );
+        assert!(dbn.unroll(0).nodes.is_empty());
+
+        let assert_close = |beliefs: &HashMap<String, Distribution>,
+                            exact: &HashMap<String, Distribution>,
+                            step: usize| {
+            for (name, distribution) in beliefs {
+                let expected = &exact[&format!("{}@{}", name, step)];
+                assert_eq!(distribution.states, expected.states);
+                for (p, q) in distribution
+                    .probabilities
+                    .iter()
+                    .zip(&expected.probabilities)
+                {
+                    assert!((p - q).abs() < 1e-9, "{}@{}: {} vs {}", name, step, p, q);
+                }
+            }
+        };
+        let filtered = dbn.filter(&steps).unwrap();
+        let smoothed = dbn.smooth(&steps).unwrap();
+        let exact = unrolled.infer(&unrolled_evidence(&steps)).unwrap();
+        for step in 0..steps.len() {
+            let prefix = dbn.unroll(step + 1);
+            let exact_prefix = prefix.infer(&unrolled_evidence(&steps[..=step])).unwrap();
+            assert_close(&filtered[step], &exact_prefix, step);
+            assert_close(&smoothed[step], &exact, step);
+        }
+    }
+
+    #[test]
+    fn test_dynamic_rejects_invalid_input() {
+        let mut dbn = umbrella();
+        assert!(dbn
+            .add_node(
+                "Rain@1".to_string(),
+                vec![],
+                cpt(&[(&[], 0.5)]),
+                vec![],
+                cpt(&[(&[], 0.5)])
+            )
+            .is_err());
+        assert!(dbn
+            .add_node(
+                "Wind".to_string(),
+                vec![],
+                cpt(&[(&[], 0.5)]),
+                names(&["Storm"]),
+                cpt(&[(&[true], 0.5), (&[false], 0.5)])
+            )
+            .is_err());
+        // A bad transition CPT must not leave the initial slice behind.
+        assert!(dbn
+            .add_variable(
+                "Wind".to_string(),
+                names(&["calm", "windy"]),
+                vec![],
+                vec![0.5, 0.5],
+                names(&["Wind"]),
+                vec![0.5, 0.5],
+            )
+            .is_err());
+        assert_eq!(dbn.variables(), names(&["Rain", "Umbrella"]));
+
+        let rain_stops = vec![
+            observed(&[("Rain", "true")]),
+            observed(&[("Rain", "false")]),
+        ];
+        assert!(dbn.filter(&rain_stops).is_ok());
+        let mut certain = DynamicBayesianNetwork::new();
+        certain
+            .add_node(
+                "On".to_string(),
+                vec![],
+                cpt(&[(&[], 1.0)]),
+                names(&["On"]),
+                cpt(&[(&[true], 1.0), (&[false], 0.0)]),
+            )
+            .unwrap();
+        let switched_off = vec![observed(&[("On", "true")]), observed(&[("On", "false")])];
+        assert!(certain.filter(&switched_off).is_err());
+        assert!(certain.smooth(&switched_off).is_err());
+        assert!(dbn.filter(&[observed(&[("Snow", "true")])]).is_err());
+        assert!(dbn.smooth(&[observed(&[("Rain", "maybe")])]).is_err());
+    }
+}
\ No newline at end of file
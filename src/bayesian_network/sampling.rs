@@ -0,0 +1,225 @@
+// src/bayesian_network/sampling.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BAYESIAN-NETWORK]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::inference::{Diagnostics, GibbsOptions, Marginals, SamplingOptions};
+use super::{BayesianNetwork, Node};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
+/// Estimates posteriors by likelihood weighting.
+///
+/// Returns `None` if every sample has weight zero. The options are expected to
+/// have passed [`super::InferenceMethod::check`].
+pub(crate) fn likelihood_weighting(
+    network: &BayesianNetwork,
+    evidence: &HashMap<String, usize>,
+    options: &SamplingOptions,
+) -> Option<(Marginals, Diagnostics)> {
+    let order = network.ordered_nodes();
+    let mut rng = StdRng::seed_from_u64(options.seed);
+
+    let mut state_weight: HashMap<&str, Vec<f64>> = order
+        .iter()
+        .map(|n| (n.name.as_str(), vec![0.0; n.states.len()]))
+        .collect();
+    let (mut total, mut total_squared) = (0.0, 0.0);
+    let mut states = HashMap::with_capacity(order.len());
+
+    for _ in 0..options.samples {
+        let mut weight = 1.0;
+        for node in &order {
+            let distribution = node.distribution(&states);
+            let state = match evidence.get(&node.name) {
+                Some(&state) => {
+                    weight *= distribution[state];
+                    state
+                }
+                None => sample(&mut rng, distribution),
+            };
+            states.insert(node.name.clone(), state);
+        }
+
+        total += weight;
+        total_squared += weight * weight;
+        for node in &order {
+            state_weight
+                .get_mut(node.name.as_str())
+                .expect("every node is counted")[states[&node.name]] += weight;
+        }
+    }
+
+    if total <= 0.0 {
+        return None;
+    }
+
+    let marginals = state_weight
+        .into_iter()
+        .map(|(name, weights)| {
+            let probabilities = weights.iter().map(|w| w / total).collect();
+            (name.to_string(), probabilities)
+        })
+        .collect();
+    Some((
+        marginals,
+        Diagnostics::LikelihoodWeighting {
+            samples: options.samples,
+            effective_sample_size: total * total / total_squared,
+            min_effective_sample_size: options.min_effective_sample_size,
+        },
+    ))
+}
+
+/// Forward-sampled starting states a Gibbs chain draws before giving up on
+/// finding one consistent with the evidence.
+const START_ATTEMPTS: usize = 1_000;
+
+/// Estimates posteriors by Gibbs sampling over several independent chains.
+///
+/// Each chain starts from a forward sample with the evidence clamped, re-drawn
+/// until the evidence has nonzero likelihood; with deterministic CPTs a chain
+/// started in a zero-probability state may never leave it. Returns `None` if no
+/// such start is found, like evidence with probability zero. The options are
+/// expected to have passed [`super::InferenceMethod::check`].
+pub(crate) fn gibbs(
+    network: &BayesianNetwork,
+    evidence: &HashMap<String, usize>,
+    options: &GibbsOptions,
+) -> Option<(Marginals, Diagnostics)> {
+    let order = network.ordered_nodes();
+    let chains = options.chains.max(1);
+    let per_chain = options.samples / chains;
+
+    let mut children: HashMap<&str, Vec<&Node>> = HashMap::new();
+    for node in &order {
+        for parent in &node.parents {
+            children.entry(parent.as_str()).or_default().push(node);
+        }
+    }
+    let hidden: Vec<&Node> = order
+        .iter()
+        .copied()
+        .filter(|n| !evidence.contains_key(&n.name))
+        .collect();
+
+    // chain_means[chain][i][s] is the fraction of kept sweeps with hidden[i] in state s.
+    let mut chain_means = Vec::with_capacity(chains);
+    for chain in 0..chains {
+        let mut rng = StdRng::seed_from_u64(options.seed.wrapping_add(chain as u64));
+
+        let mut states: HashMap<String, usize> = HashMap::with_capacity(order.len());
+        let mut started = false;
+        for _ in 0..START_ATTEMPTS {
+            let mut weight = 1.0;
+            for node in &order {
+                let distribution = node.distribution(&states);
+                let state = match evidence.get(&node.name) {
+                    Some(&state) => {
+                        weight *= distribution[state];
+                        state
+                    }
+                    None => sample(&mut rng, distribution),
+                };
+                states.insert(node.name.clone(), state);
+            }
+            if weight > 0.0 {
+                started = true;
+                break;
+            }
+        }
+        if !started {
+            return None;
+        }
+
+        let mut counts: Vec<Vec<usize>> = hidden.iter().map(|n| vec![0; n.states.len()]).collect();
+        for sweep in 0..(options.burn_in + per_chain) {
+            for (i, node) in hidden.iter().enumerate() {
+                let current = states[&node.name];
+                let weights: Vec<f64> = (0..node.states.len())
+                    .map(|state| {
+                        states.insert(node.name.clone(), state);
+                        let mut weight = node.distribution(&states)[state];
+                        for child in children.get(node.name.as_str()).into_iter().flatten() {
+                            weight *= child.distribution(&states)[states[&child.name]];
+                        }
+                        weight
+                    })
+                    .collect();
+                let state = if weights.iter().sum::<f64>() > 0.0 {
+                    sample(&mut rng, &weights)
+                } else {
+                    current
+                };
+                states.insert(node.name.clone(), state);
+                if sweep >= options.burn_in {
+                    counts[i][state] += 1;
+                }
+            }
+        }
+        chain_means.push(
+            counts
+                .iter()
+                .map(|c| {
+                    c.iter()
+                        .map(|&c| c as f64 / per_chain as f64)
+                        .collect::<Vec<f64>>()
+                })
+                .collect::<Vec<Vec<f64>>>(),
+        );
+    }
+
+    let mut marginals = super::one_hot(&network.nodes, evidence);
+    let mut r_hat: f64 = 1.0;
+    for (i, node) in hidden.iter().enumerate() {
+        let mut probabilities = Vec::with_capacity(node.states.len());
+        for state in 0..node.states.len() {
+            let means: Vec<f64> = chain_means.iter().map(|m| m[i][state]).collect();
+            probabilities.push(means.iter().sum::<f64>() / chains as f64);
+            r_hat = r_hat.max(gelman_rubin(&means, per_chain));
+        }
+        marginals.insert(node.name.clone(), probabilities);
+    }
+
+    Some((
+        marginals,
+        Diagnostics::Gibbs {
+            samples: per_chain * chains,
+            chains,
+            r_hat,
+        },
+    ))
+}
+
+/// Draws a state with probability proportional to `weights`.
+fn sample(rng: &mut StdRng, weights: &[f64]) -> usize {
+    let mut remaining = rng.gen::<f64>() * weights.iter().sum::<f64>();
+    for (state, &weight) in weights.iter().enumerate() {
+        if remaining < weight {
+            return state;
+        }
+        remaining -= weight;
+    }
+    // Rounding can leave a sliver of mass past the last state; give it to the
+    // last state that can actually occur.
+    weights.iter().rposition(|&w| w > 0.0).unwrap_or(0)
+}
+
+/// Potential scale reduction factor for an indicator variable from per-chain means.
+fn gelman_rubin(means: &[f64], n: usize) -> f64 {
+    let m = means.len();
+    if m < 2 || n < 2 {
+        return 1.0;
+    }
+    let n = n as f64;
+    let overall = means.iter().sum::<f64>() / m as f64;
+    let between = n / (m as f64 - 1.0) * means.iter().map(|x| (x - overall).powi(2)).sum::<f64>();
+    // The sample variance of a 0/1 indicator with mean p is n/(n-1) * p(1-p).
+    let within = means
+        .iter()
+        .map(|p| n / (n - 1.0) * p * (1.0 - p))
+        .sum::<f64>()
+        / m as f64;
+    if within <= 0.0 {
+        return if between <= 0.0 { 1.0 } else { f64::INFINITY };
+    }
+    let pooled = (n - 1.0) / n * within + between / n;
+    (pooled / within).sqrt()
+}
\ No newline at end of file
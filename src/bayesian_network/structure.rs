@@ -0,0 +1,756 @@
+// src/bayesian_network/structure.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[BAYESIAN-NETWORK]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::learning::{Estimator, LearningOptions};
+use super::BayesianNetwork;
+use crate::errors::MetaSyntraXLError;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+/// Decomposable score maximized by [`StructureSearch::HillClimbing`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum StructureScore {
+    /// Log-likelihood penalized by half the free parameters times `ln N`.
+    #[default]
+    Bic,
+    /// Bayesian Dirichlet score with a uniform prior of the given equivalent sample size.
+    BDeu { equivalent_sample_size: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StructureSearch {
+    /// Greedy search over single edge additions, removals and reversals, starting
+    /// from the whitelisted edges.
+    HillClimbing {
+        score: StructureScore,
+        /// Upper bound on parents per node; whitelisted edges may exceed it.
+        max_parents: usize,
+        max_iterations: usize,
+        /// When positive, turns the search into tabu search: it keeps taking the best
+        /// move that does not revisit one of the last `tabu_length` structures, even
+        /// if the score drops, and stops after `tabu_length` moves without finding
+        /// a better structure. Escapes the local optima plain hill climbing gets stuck in.
+        tabu_length: usize,
+    },
+    /// Constraint-based search: removes edges between variables found conditionally
+    /// independent by a G-test, orients v-structures and applies Meek's rules.
+    /// Edges left undirected are oriented along the variable order; one that the
+    /// blacklist and a cycle rule out both ways is an error.
+    Pc {
+        /// p-value above which two variables are taken as independent.
+        significance: f64,
+        /// Largest conditioning set tried.
+        max_condition_size: usize,
+    },
+}
+
+impl Default for StructureSearch {
+    fn default() -> Self {
+        StructureSearch::HillClimbing {
+            score: StructureScore::default(),
+            max_parents: 3,
+            max_iterations: 1000,
+            tabu_length: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StructureOptions {
+    pub search: StructureSearch,
+    /// Edges `(parent, child)` the learned network must contain.
+    pub whitelist: Vec<(String, String)>,
+    /// Edges `(parent, child)` the learned network must not contain.
+    pub blacklist: Vec<(String, String)>,
+    /// How the CPTs of the learned structure are estimated.
+    pub estimator: Estimator,
+}
+
+/// Learns parents for `variables` from complete `records` (state indices, in
+/// `variables` order) and returns them by variable index.
+pub(crate) fn learn(
+    variables: &[(String, Vec<String>)],
+    records: &[Vec<usize>],
+    options: &StructureOptions,
+) -> Result<Vec<BTreeSet<usize>>, MetaSyntraXLError> {
+    let data = Data {
+        cardinalities: variables.iter().map(|(_, states)| states.len()).collect(),
+        records,
+    };
+    let constraints = Constraints::new(variables, options)?;
+    match &options.search {
+        StructureSearch::HillClimbing {
+            score,
+            max_parents,
+            max_iterations,
+            tabu_length,
+        } => {
+            if let StructureScore::BDeu {
+                equivalent_sample_size,
+            } = score
+            {
+                if !(*equivalent_sample_size > 0.0 && equivalent_sample_size.is_finite()) {
+                    return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                        "BDeu equivalent sample size must be positive, got {}",
+                        equivalent_sample_size
+                    )));
+                }
+            }
+            Ok(hill_climb(
+                &data,
+                &constraints,
+                score,
+                *max_parents,
+                *max_iterations,
+                *tabu_length,
+            ))
+        }
+        StructureSearch::Pc {
+            significance,
+            max_condition_size,
+        } => pc(
+            variables,
+            &data,
+            &constraints,
+            *significance,
+            *max_condition_size,
+        ),
+    }
+}
+
+/// Builds the learned network: the structure from [`learn`], CPTs fitted to `records`.
+pub(crate) fn build(
+    variables: &[(String, Vec<String>)],
+    parents: &[BTreeSet<usize>],
+    records: &[HashMap<String, String>],
+    estimator: &Estimator,
+) -> Result<BayesianNetwork, MetaSyntraXLError> {
+    let mut network = BayesianNetwork::new();
+    for index in topological(parents) {
+        let (name, states) = &variables[index];
+        let parent_names: Vec<String> = parents[index]
+            .iter()
+            .map(|&p| variables[p].0.clone())
+            .collect();
+        let rows: usize = parents[index]
+            .iter()
+            .map(|&p| variables[p].1.len())
+            .product();
+        let uniform = vec![1.0 / states.len() as f64; rows * states.len()];
+        network.add_variable(name.clone(), states.clone(), parent_names, uniform)?;
+    }
+    network.fit_parameters(
+        records,
+        &LearningOptions {
+            estimator: estimator.clone(),
+            ..LearningOptions::default()
+        },
+    )?;
+    Ok(network)
+}
+
+struct Data<'a> {
+    cardinalities: Vec<usize>,
+    records: &'a [Vec<usize>],
+}
+
+impl Data<'_> {
+    /// Joint counts over `variables`, row-major with the last variable varying fastest.
+    fn counts(&self, variables: &[usize]) -> Vec<f64> {
+        let size: usize = variables.iter().map(|&v| self.cardinalities[v]).product();
+        let mut counts = vec![0.0; size];
+        for record in self.records {
+            let index = variables
+                .iter()
+                .fold(0, |index, &v| index * self.cardinalities[v] + record[v]);
+            counts[index] += 1.0;
+        }
+        counts
+    }
+}
+
+struct Constraints {
+    whitelist: HashSet<(usize, usize)>,
+    blacklist: HashSet<(usize, usize)>,
+}
+
+impl Constraints {
+    fn new(
+        variables: &[(String, Vec<String>)],
+        options: &StructureOptions,
+    ) -> Result<Self, MetaSyntraXLError> {
+        let index = |name: &str| {
+            variables
+                .iter()
+                .position(|(v, _)| v == name)
+                .ok_or_else(|| {
+                    MetaSyntraXLError::BayesianNetworkError(format!(
+                        "Edge constraint on unknown variable `{}`",
+                        name
+                    ))
+                })
+        };
+        let edges =
+            |list: &[(String, String)]| -> Result<HashSet<(usize, usize)>, MetaSyntraXLError> {
+                list.iter()
+                    .map(|(parent, child)| Ok((index(parent)?, index(child)?)))
+                    .collect()
+            };
+        let whitelist = edges(&options.whitelist)?;
+        let blacklist = edges(&options.blacklist)?;
+
+        if let Some(&(parent, child)) = whitelist
+            .iter()
+            .find(|e| e.0 == e.1 || blacklist.contains(e))
+        {
+            return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                "Whitelisted edge `{}` -> `{}` is a self-loop or also blacklisted",
+                variables[parent].0, variables[child].0
+            )));
+        }
+        let mut parents = vec![BTreeSet::new(); variables.len()];
+        for &(parent, child) in &whitelist {
+            parents[child].insert(parent);
+        }
+        if topological(&parents).len() != variables.len() {
+            return Err(MetaSyntraXLError::BayesianNetworkError(
+                "Whitelisted edges form a cycle".to_string(),
+            ));
+        }
+        Ok(Self {
+            whitelist,
+            blacklist,
+        })
+    }
+
+    fn required(&self, parent: usize, child: usize) -> bool {
+        self.whitelist.contains(&(parent, child))
+    }
+
+    fn forbidden(&self, parent: usize, child: usize) -> bool {
+        self.blacklist.contains(&(parent, child))
+    }
+}
+
+enum Move {
+    Add(usize, usize),
+    Remove(usize, usize),
+    Reverse(usize, usize),
+}
+
+fn hill_climb(
+    data: &Data,
+    constraints: &Constraints,
+    score: &StructureScore,
+    max_parents: usize,
+    max_iterations: usize,
+    tabu_length: usize,
+) -> Vec<BTreeSet<usize>> {
+    let n = data.cardinalities.len();
+    let mut parents = vec![BTreeSet::new(); n];
+    for &(parent, child) in &constraints.whitelist {
+        parents[child].insert(parent);
+    }
+
+    let mut cache: HashMap<(usize, Vec<usize>), f64> = HashMap::new();
+    let mut local = |child: usize, parents: &BTreeSet<usize>| -> f64 {
+        let key = (child, parents.iter().copied().collect::<Vec<usize>>());
+        *cache
+            .entry(key)
+            .or_insert_with(|| local_score(data, child, parents, score))
+    };
+
+    let mut current_score: f64 = (0..n).map(|v| local(v, &parents[v])).sum();
+    let mut best_score = current_score;
+    let mut best_parents = parents.clone();
+    let mut tabu: VecDeque<Vec<BTreeSet<usize>>> = VecDeque::with_capacity(tabu_length + 1);
+    let mut since_best = 0;
+
+    for _ in 0..max_iterations {
+        // Without a tabu list only improving moves are taken; with one, the best
+        // move that does not revisit a recent structure is taken regardless.
+        let threshold = if tabu_length == 0 {
+            1e-9
+        } else {
+            f64::NEG_INFINITY
+        };
+        let mut best: Option<(f64, Move)> = None;
+        let mut consider = |delta: f64, candidate: Move, parents: &[BTreeSet<usize>]| {
+            if delta <= threshold || matches!(best, Some((current, _)) if current >= delta) {
+                return;
+            }
+            if !tabu.is_empty() {
+                let mut result = parents.to_vec();
+                candidate.apply(&mut result);
+                if tabu.contains(&result) {
+                    return;
+                }
+            }
+            best = Some((delta, candidate));
+        };
+
+        for x in 0..n {
+            for y in 0..n {
+                if x == y {
+                    continue;
+                }
+                let current_y = local(y, &parents[y]);
+                if parents[y].contains(&x) {
+                    if constraints.required(x, y) {
+                        continue;
+                    }
+                    let mut without = parents[y].clone();
+                    without.remove(&x);
+                    let removal = local(y, &without) - current_y;
+                    consider(removal, Move::Remove(x, y), &parents);
+
+                    if !constraints.forbidden(y, x)
+                        && parents[x].len() < max_parents
+                        && !has_path(&parents, x, y, Some((x, y)))
+                    {
+                        let mut with = parents[x].clone();
+                        with.insert(y);
+                        let reversal = removal + local(x, &with) - local(x, &parents[x]);
+                        consider(reversal, Move::Reverse(x, y), &parents);
+                    }
+                } else if !parents[x].contains(&y)
+                    && !constraints.forbidden(x, y)
+                    && parents[y].len() < max_parents
+                    && !has_path(&parents, y, x, None)
+                {
+                    let mut with = parents[y].clone();
+                    with.insert(x);
+                    consider(local(y, &with) - current_y, Move::Add(x, y), &parents);
+                }
+            }
+        }
+
+        let (delta, candidate) = match best {
+            Some(best) => best,
+            None => break,
+        };
+        candidate.apply(&mut parents);
+        current_score += delta;
+        if current_score > best_score + 1e-9 {
+            best_score = current_score;
+            best_parents = parents.clone();
+            since_best = 0;
+        } else {
+            since_best += 1;
+            if since_best > tabu_length {
+                break;
+            }
+        }
+        if tabu_length > 0 {
+            tabu.push_back(parents.clone());
+            if tabu.len() > tabu_length {
+                tabu.pop_front();
+            }
+        }
+    }
+    best_parents
+}
+
+impl Move {
+    fn apply(&self, parents: &mut [BTreeSet<usize>]) {
+        match *self {
+            Move::Add(x, y) => {
+                parents[y].insert(x);
+            }
+            Move::Remove(x, y) => {
+                parents[y].remove(&x);
+            }
+            Move::Reverse(x, y) => {
+                parents[y].remove(&x);
+                parents[x].insert(y);
+            }
+        }
+    }
+}
+
+fn local_score(
+    data: &Data,
+    child: usize,
+    parents: &BTreeSet<usize>,
+    score: &StructureScore,
+) -> f64 {
+    let mut family: Vec<usize> = parents.iter().copied().collect();
+    family.push(child);
+    let counts = data.counts(&family);
+    let r = data.cardinalities[child];
+    let q = counts.len() / r;
+
+    match score {
+        StructureScore::Bic => {
+            let mut log_likelihood = 0.0;
+            for row in counts.chunks(r) {
+                let total: f64 = row.iter().sum();
+                for &c in row.iter().filter(|&&c| c > 0.0) {
+                    log_likelihood += c * (c / total).ln();
+                }
+            }
+            let n = data.records.len().max(1) as f64;
+            log_likelihood - 0.5 * n.ln() * (q * (r - 1)) as f64
+        }
+        StructureScore::BDeu {
+            equivalent_sample_size,
+        } => {
+            let alpha_j = equivalent_sample_size / q as f64;
+            let alpha_jk = alpha_j / r as f64;
+            let mut total_score = 0.0;
+            for row in counts.chunks(r) {
+                let total: f64 = row.iter().sum();
+                total_score += ln_gamma(alpha_j) - ln_gamma(alpha_j + total);
+                for &c in row {
+                    total_score += ln_gamma(alpha_jk + c) - ln_gamma(alpha_jk);
+                }
+            }
+            total_score
+        }
+    }
+}
+
+fn pc(
+    variables: &[(String, Vec<String>)],
+    data: &Data,
+    constraints: &Constraints,
+    significance: f64,
+    max_condition_size: usize,
+) -> Result<Vec<BTreeSet<usize>>, MetaSyntraXLError> {
+    let n = data.cardinalities.len();
+    let mut adjacent: Vec<BTreeSet<usize>> = (0..n)
+        .map(|x| (0..n).filter(|&y| y != x).collect())
+        .collect();
+    for (x, neighbours) in adjacent.iter_mut().enumerate() {
+        neighbours.retain(|&y| {
+            let pinned = constraints.required(x, y) || constraints.required(y, x);
+            pinned || !(constraints.forbidden(x, y) && constraints.forbidden(y, x))
+        });
+    }
+
+    let mut separating: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for size in 0..=max_condition_size {
+        let mut tested = false;
+        for x in 0..n {
+            for y in adjacent[x].clone() {
+                if !adjacent[x].contains(&y)
+                    || constraints.required(x, y)
+                    || constraints.required(y, x)
+                {
+                    continue;
+                }
+                let others: Vec<usize> = adjacent[x].iter().copied().filter(|&v| v != y).collect();
+                if others.len() < size {
+                    continue;
+                }
+                tested = true;
+                for condition in combinations(&others, size) {
+                    if g_test(data, x, y, &condition) > significance {
+                        adjacent[x].remove(&y);
+                        adjacent[y].remove(&x);
+                        separating.insert((x.min(y), x.max(y)), condition);
+                        break;
+                    }
+                }
+            }
+        }
+        if !tested {
+            break;
+        }
+    }
+
+    let mut graph = PartialDag {
+        adjacent,
+        parents: vec![BTreeSet::new(); n],
+        constraints,
+    };
+    for &(parent, child) in &constraints.whitelist {
+        graph.orient(parent, child);
+    }
+
+    // V-structures: x - z - y with x, y non-adjacent and z outside their separating set.
+    for z in 0..n {
+        let neighbours: Vec<usize> = graph.adjacent[z].iter().copied().collect();
+        for (i, &x) in neighbours.iter().enumerate() {
+            for &y in &neighbours[i + 1..] {
+                if graph.adjacent[x].contains(&y) {
+                    continue;
+                }
+                let separated =
+                    matches!(separating.get(&(x.min(y), x.max(y))), Some(s) if s.contains(&z));
+                if !separated {
+                    graph.orient(x, z);
+                    graph.orient(y, z);
+                }
+            }
+        }
+    }
+
+    graph.apply_meek_rules();
+
+    // Orient what is left along a topological order of the directed part. An edge
+    // the blacklist forbids one way and a cycle the other cannot be kept.
+    let order = topological(&graph.parents);
+    let rank: Vec<usize> = {
+        let mut rank = vec![0; n];
+        for (position, &v) in order.iter().enumerate() {
+            rank[v] = position;
+        }
+        rank
+    };
+    for x in 0..n {
+        for y in graph.adjacent[x].clone() {
+            if graph.undirected(x, y)
+                && rank[x] < rank[y]
+                && !graph.orient(x, y)
+                && !graph.orient(y, x)
+            {
+                return Err(MetaSyntraXLError::BayesianNetworkError(format!(
+                    "Edge between `{}` and `{}` cannot be oriented without a blacklisted \
+                     edge or a cycle",
+                    variables[x].0, variables[y].0
+                )));
+            }
+        }
+    }
+    Ok(graph.parents)
+}
+
+struct PartialDag<'a> {
+    adjacent: Vec<BTreeSet<usize>>,
+    parents: Vec<BTreeSet<usize>>,
+    constraints: &'a Constraints,
+}
+
+impl PartialDag<'_> {
+    fn undirected(&self, x: usize, y: usize) -> bool {
+        self.adjacent[x].contains(&y)
+            && !self.parents[y].contains(&x)
+            && !self.parents[x].contains(&y)
+    }
+
+    fn directed(&self, x: usize, y: usize) -> bool {
+        self.parents[y].contains(&x)
+    }
+
+    /// Orients the adjacent pair as `parent -> child` unless it is already oriented
+    /// the other way, blacklisted, or would close a cycle.
+    fn orient(&mut self, parent: usize, child: usize) -> bool {
+        if self.directed(parent, child) {
+            return true;
+        }
+        if self.directed(child, parent)
+            || self.constraints.forbidden(parent, child)
+            || has_path(&self.parents, child, parent, None)
+        {
+            return false;
+        }
+        self.adjacent[parent].insert(child);
+        self.adjacent[child].insert(parent);
+        self.parents[child].insert(parent);
+        true
+    }
+
+    fn apply_meek_rules(&mut self) {
+        let n = self.adjacent.len();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for a in 0..n {
+                for b in self.adjacent[a].clone() {
+                    if !self.undirected(a, b) {
+                        continue;
+                    }
+                    // R1: c -> a - b with c, b non-adjacent.
+                    let r1 = self.parents[a]
+                        .iter()
+                        .any(|&c| c != b && !self.adjacent[c].contains(&b));
+                    // R2: a -> c -> b.
+                    let r2 = self.adjacent[a]
+                        .iter()
+                        .any(|&c| self.directed(a, c) && self.directed(c, b));
+                    // R3: a - c -> b and a - d -> b with c, d non-adjacent.
+                    let feeders: Vec<usize> = self.adjacent[a]
+                        .iter()
+                        .copied()
+                        .filter(|&c| self.undirected(a, c) && self.directed(c, b))
+                        .collect();
+                    let r3 = feeders.iter().enumerate().any(|(i, &c)| {
+                        feeders[i + 1..]
+                            .iter()
+                            .any(|&d| !self.adjacent[c].contains(&d))
+                    });
+                    if (r1 || r2 || r3) && self.orient(a, b) {
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// p-value of the G-test of independence between `x` and `y` given `condition`.
+fn g_test(data: &Data, x: usize, y: usize, condition: &[usize]) -> f64 {
+    let (rx, ry) = (data.cardinalities[x], data.cardinalities[y]);
+    let mut variables = condition.to_vec();
+    variables.push(x);
+    variables.push(y);
+    let counts = data.counts(&variables);
+
+    let mut statistic = 0.0;
+    for block in counts.chunks(rx * ry) {
+        let total: f64 = block.iter().sum();
+        if total == 0.0 {
+            continue;
+        }
+        let row_totals: Vec<f64> = block.chunks(ry).map(|r| r.iter().sum()).collect();
+        let column_totals: Vec<f64> = (0..ry)
+            .map(|j| (0..rx).map(|i| block[i * ry + j]).sum())
+            .collect();
+        for i in 0..rx {
+            for j in 0..ry {
+                let observed = block[i * ry + j];
+                if observed > 0.0 {
+                    let expected = row_totals[i] * column_totals[j] / total;
+                    statistic += 2.0 * observed * (observed / expected).ln();
+                }
+            }
+        }
+    }
+
+    let condition_states: usize = condition.iter().map(|&v| data.cardinalities[v]).product();
+    let degrees = ((rx - 1) * (ry - 1) * condition_states) as f64;
+    if degrees == 0.0 {
+        return 1.0;
+    }
+    1.0 - regularized_gamma(degrees / 2.0, statistic / 2.0)
+}
+
+/// Whether a directed path leads from `from` to `to`, ignoring the edge `skip`.
+fn has_path(
+    parents: &[BTreeSet<usize>],
+    from: usize,
+    to: usize,
+    skip: Option<(usize, usize)>,
+) -> bool {
+    let mut stack = vec![to];
+    let mut visited = HashSet::new();
+    while let Some(current) = stack.pop() {
+        if current == from {
+            return true;
+        }
+        if visited.insert(current) {
+            for &p in &parents[current] {
+                if skip != Some((p, current)) {
+                    stack.push(p);
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Variable indices with parents first; ties go to the lower index. Variables on a
+/// cycle are left out.
+fn topological(parents: &[BTreeSet<usize>]) -> Vec<usize> {
+    let mut pending: Vec<usize> = parents.iter().map(BTreeSet::len).collect();
+    let mut ready: BTreeSet<usize> = (0..parents.len()).filter(|&v| pending[v] == 0).collect();
+    let mut order = Vec::with_capacity(parents.len());
+    while let Some(v) = ready.iter().next().copied() {
+        ready.remove(&v);
+        order.push(v);
+        for (child, child_parents) in parents.iter().enumerate() {
+            if child_parents.contains(&v) {
+                pending[child] -= 1;
+                if pending[child] == 0 {
+                    ready.insert(child);
+                }
+            }
+        }
+    }
+    order
+}
+
+pub(crate) fn combinations(items: &[usize], size: usize) -> Vec<Vec<usize>> {
+    if size == 0 {
+        return vec![Vec::new()];
+    }
+    let mut result = Vec::new();
+    for (i, &first) in items.iter().enumerate() {
+        for mut rest in combinations(&items[i + 1..], size - 1) {
+            rest.insert(0, first);
+            result.push(rest);
+        }
+    }
+    result
+}
+
+/// `ln Γ(x)` for `x > 0` by the Lanczos approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, &c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// The regularized lower incomplete gamma function `P(a, x)`.
+fn regularized_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let prefactor = (a * x.ln() - x - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        // Series expansion.
+        let (mut term, mut sum, mut n) = (1.0 / a, 1.0 / a, a);
+        for _ in 0..500 {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * 1e-14 {
+                break;
+            }
+        }
+        (sum * prefactor).min(1.0)
+    } else {
+        // Continued fraction for Q(a, x), by the modified Lentz method.
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..500 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-14 {
+                break;
+            }
+        }
+        (1.0 - prefactor * h).max(0.0)
+    }
+}
\ No newline at end of file
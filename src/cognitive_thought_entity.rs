@@ -4,6 +4,7 @@ use crate::environment::Environment;
 use std::sync::Arc;
 use tch::{nn, Device, Tensor};
 use tokio::sync::Mutex;
+use tracing::error;
 
 pub struct CognitiveThoughtEntity<'a> {
     pub state: Tensor,
@@ -56,7 +57,7 @@ impl<'a> CognitiveThoughtEntity<'a> {
                 let mut ppo = self.ppo.lock().await;
                 ppo.learn(&self.state, &action, &log_prob, &returns, &advantages)
             } {
-                eprintln!("Error during PPO update: {:?}", e);
+                error!(error = ?e, "Error during PPO update");
             }
         }
 
@@ -1,4 +1,7 @@
 // src/config.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[CONFIG]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use crate::errors::MetaSyntraXLError;
+use serde::Deserialize;
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub vocab_size: i64,
@@ -15,6 +18,9 @@ pub struct Config {
     pub use_cuda: bool,
     pub elasticsearch: ElasticsearchConfig,
     pub prometheus: PrometheusConfig,
+    pub logging: LoggingConfig,
+    pub ensemble: EnsembleConfig,
+    pub validation: ValidationConfig,
 }
 
 #[derive(Debug, Clone)]
@@ -28,6 +34,111 @@ pub struct PrometheusConfig {
     pub port: u16,
 }
 
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EnsembleConfig {
+    /// Configured vote weight per member for weighted aggregation; empty means
+    /// uniform. `Ensemble::fit_boosting` replaces them with the weights it learns.
+    pub member_weights: Vec<f64>,
+    /// Minimum number of members that must answer for a prediction to succeed,
+    /// at most the number of members; `None` requires every member.
+    pub quorum: Option<usize>,
+    /// Per-member deadline for a forward pass; `None` waits indefinitely.
+    pub member_timeout_ms: Option<u64>,
+    /// Maximum member forward passes running at once on the blocking pool;
+    /// `None` uses the number of available CPUs.
+    pub parallelism: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ValidationConfig {
+    /// Posterior probability above which the Bayesian network accepts a prediction.
+    pub threshold: f64,
+    /// What the controller does with outputs whose prediction is not accepted.
+    pub action: ValidationAction,
+}
+
+/// Handling of outputs that fail Bayesian validation, see [`ValidationConfig`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationAction {
+    /// Return the output unchanged, with the validation result attached.
+    #[default]
+    Annotate,
+    /// Scale the output by the posterior probability of the prediction.
+    DownWeight,
+    /// Fail the request with a `ValidationError`.
+    Reject,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 0.5,
+            action: ValidationAction::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoggingConfig {
+    /// Level or `EnvFilter` directive, e.g. `"info"` or `"metasyntraxl=debug"`.
+    pub level: String,
+    #[serde(default)]
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl LoggingConfig {
+    /// Reads the `[logging]` section of a TOML configuration file.
+    pub fn from_file(path: &str) -> Result<Self, MetaSyntraXLError> {
+        ::config::Config::builder()
+            .add_source(::config::File::with_name(path))
+            .build()
+            .and_then(|settings| settings.get::<LoggingConfig>("logging"))
+            .map_err(|e| MetaSyntraXLError::ConfigError(e.to_string()))
+    }
+}
+
+impl EnsembleConfig {
+    /// Reads the `[ensemble]` section of a TOML configuration file.
+    pub fn from_file(path: &str) -> Result<Self, MetaSyntraXLError> {
+        ::config::Config::builder()
+            .add_source(::config::File::with_name(path))
+            .build()
+            .and_then(|settings| settings.get::<EnsembleConfig>("ensemble"))
+            .map_err(|e| MetaSyntraXLError::ConfigError(e.to_string()))
+    }
+}
+
+impl ValidationConfig {
+    /// Reads the `[validation]` section of a TOML configuration file.
+    pub fn from_file(path: &str) -> Result<Self, MetaSyntraXLError> {
+        ::config::Config::builder()
+            .add_source(::config::File::with_name(path))
+            .build()
+            .and_then(|settings| settings.get::<ValidationConfig>("validation"))
+            .map_err(|e| MetaSyntraXLError::ConfigError(e.to_string()))
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Text,
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -50,6 +161,9 @@ impl Default for Config {
             prometheus: PrometheusConfig {
                 port: 9090,
             },
+            logging: LoggingConfig::default(),
+            ensemble: EnsembleConfig::default(),
+            validation: ValidationConfig::default(),
         }
     }
 }
\ No newline at end of file
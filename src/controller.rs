@@ -1,21 +1,358 @@
 // src/controller.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[CONTROLLER]Xyn>=====S===t===u====d===i===o===s====[R|$>
 
-use crate::config::Config;
+use crate::bayesian_network::BayesianNetwork;
+use crate::config::{Config, ValidationAction, ValidationConfig};
 use crate::errors::MetaSyntraXLError;
 use crate::transformer_rag::TransformerRAG;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tch::{nn, Tensor};
+use tracing::{info, info_span, warn, Instrument};
+
+/// Maps a request's model output and retrieved documents to boolean evidence for
+/// the validating Bayesian network, keyed by node name.
+pub type EvidenceMapper = Box<dyn Fn(&Tensor, &[String]) -> HashMap<String, bool> + Send + Sync>;
 
 pub struct Controller {
     transformer_rag: TransformerRAG,
+    validator: Option<Validator>,
+    next_request_id: AtomicU64,
+}
+
+/// The result of [`Controller::process_validated`].
+#[derive(Debug)]
+pub struct ControllerOutput {
+    pub output: Tensor,
+    /// Present when the controller has a [`Validator`].
+    pub validation: Option<Validation>,
+}
+
+/// Checks model outputs against a Bayesian network: the output is mapped to
+/// evidence and the network decides whether the prediction node is likely enough.
+pub struct Validator {
+    network: BayesianNetwork,
+    prediction: String,
+    evidence: EvidenceMapper,
+    config: ValidationConfig,
+}
+
+/// The verdict of a [`Validator`] on one output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Validation {
+    pub prediction: String,
+    /// The evidence the output was mapped to.
+    pub evidence: HashMap<String, bool>,
+    /// Posterior probability that the prediction holds, or `None` if the prediction
+    /// node is unknown or not boolean, or the evidence has probability zero.
+    pub posterior: Option<f64>,
+    /// Whether the posterior is above the configured threshold.
+    pub valid: bool,
+    /// The action taken; `None` when the output passed.
+    pub action: Option<ValidationAction>,
 }
 
 impl Controller {
     pub fn new(vs: &nn::Path, config: &Config) -> Result<Self, MetaSyntraXLError> {
         let transformer_rag = TransformerRAG::new(vs, config)?;
-        Ok(Self { transformer_rag })
+        Ok(Self {
+            transformer_rag,
+            validator: None,
+            next_request_id: AtomicU64::new(0),
+        })
+    }
+
+    /// Like [`Controller::new`], with every output checked by a [`Validator`] over
+    /// `network` and `prediction`, configured by `config.validation`.
+    pub fn with_validator(
+        vs: &nn::Path,
+        config: &Config,
+        network: BayesianNetwork,
+        prediction: String,
+        evidence: EvidenceMapper,
+    ) -> Result<Self, MetaSyntraXLError> {
+        let mut controller = Self::new(vs, config)?;
+        controller.validator = Some(Validator::new(
+            network,
+            prediction,
+            evidence,
+            &config.validation,
+        ));
+        Ok(controller)
     }
 
+    /// Runs `input` through the Transformer-RAG and, when a validator is set, the
+    /// Bayesian validation stage; see [`Controller::process_validated`] for the
+    /// validation result.
+    ///
+    /// # Errors
+    ///
+    /// Besides model and retrieval errors, returns `ValidationError` when the
+    /// validator rejects the output.
     pub async fn process(&self, input: &Tensor) -> Result<Tensor, MetaSyntraXLError> {
-        self.transformer_rag.forward(input).await
+        self.process_validated(input)
+            .await
+            .map(|result| result.output)
+    }
+
+    /// Like [`Controller::process`], returning the [`Validation`] along with the output.
+    pub async fn process_validated(
+        &self,
+        input: &Tensor,
+    ) -> Result<ControllerOutput, MetaSyntraXLError> {
+        let request_id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        async {
+            let (output, documents) = self.transformer_rag.forward_with_documents(input).await?;
+            match &self.validator {
+                Some(validator) => {
+                    let validation = validator.validate(&output, &documents);
+                    let output = validator.apply(output, &validation)?;
+                    Ok(ControllerOutput {
+                        output,
+                        validation: Some(validation),
+                    })
+                }
+                None => Ok(ControllerOutput {
+                    output,
+                    validation: None,
+                }),
+            }
+        }
+        .instrument(info_span!("request", request_id))
+        .await
+    }
+}
+
+impl Validator {
+    /// Validates outputs by the posterior of the boolean node `prediction` given
+    /// the evidence that `evidence` extracts from each output and its documents.
+    pub fn new(
+        network: BayesianNetwork,
+        prediction: String,
+        evidence: EvidenceMapper,
+        config: &ValidationConfig,
+    ) -> Self {
+        Self {
+            network,
+            prediction,
+            evidence,
+            config: config.clone(),
+        }
+    }
+
+    /// Computes the verdict on `output`; the output itself is not changed.
+    pub fn validate(&self, output: &Tensor, documents: &[String]) -> Validation {
+        let evidence = (self.evidence)(output, documents);
+        let posterior = self.network.reason(&self.prediction, &evidence);
+        let valid = matches!(posterior, Some(p) if p > self.config.threshold);
+        info!(
+            prediction = %self.prediction,
+            posterior = posterior.unwrap_or(f64::NAN),
+            valid,
+            "Validated prediction"
+        );
+        Validation {
+            prediction: self.prediction.clone(),
+            evidence,
+            posterior,
+            valid,
+            action: if valid { None } else { Some(self.config.action) },
+        }
+    }
+
+    /// Applies the configured action to an output that failed `validation`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ValidationError` if the output failed and the action is `Reject`.
+    pub fn apply(&self, output: Tensor, validation: &Validation) -> Result<Tensor, MetaSyntraXLError> {
+        match validation.action {
+            None | Some(ValidationAction::Annotate) => Ok(output),
+            Some(ValidationAction::DownWeight) => Ok(output * validation.posterior.unwrap_or(0.0)),
+            Some(ValidationAction::Reject) => {
+                warn!(prediction = %validation.prediction, "Rejected output");
+                Err(MetaSyntraXLError::ValidationError(format!(
+                    "Prediction `{}` has posterior {:?}, threshold is {}",
+                    validation.prediction, validation.posterior, self.config.threshold
+                )))
+            }
+        }
+    }
+}
+
+/// An [`EvidenceMapper`] that observes a node as `true` when any retrieved document
+/// mentions one of its keywords, ignoring case. Nodes without a match stay
+/// unobserved.
+pub fn keyword_evidence(keywords: HashMap<String, Vec<String>>) -> EvidenceMapper {
+    let keywords: Vec<(String, Vec<String>)> = keywords
+        .into_iter()
+        .map(|(node, words)| (node, words.iter().map(|w| w.to_lowercase()).collect()))
+        .collect();
+    Box::new(move |_output: &Tensor, documents: &[String]| {
+        let documents: Vec<String> = documents.iter().map(|d| d.to_lowercase()).collect();
+        keywords
+            .iter()
+            .filter(|(_, words)| {
+                words
+                    .iter()
+                    .any(|word| documents.iter().any(|d| d.contains(word.as_str())))
+            })
+            .map(|(node, _)| (node.clone(), true))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rain (prior 0.2) -> WetGrass.
+    fn rain_network() -> BayesianNetwork {
+        let mut network = BayesianNetwork::new();
+        network
+            .add_node("Rain".to_string(), vec![], HashMap::from([(vec![], 0.2)]))
+            .unwrap();
+        network
+            .add_node(
+                "WetGrass".to_string(),
+                vec!["Rain".to_string()],
+                HashMap::from([(vec![true], 0.9), (vec![false], 0.1)]),
+            )
+            .unwrap();
+        network
+    }
+
+    /// Observes WetGrass when a retrieved document mentions it.
+    fn wet_evidence() -> EvidenceMapper {
+        keyword_evidence(HashMap::from([(
+            "WetGrass".to_string(),
+            vec!["Wet".to_string()],
+        )]))
+    }
+
+    fn validation_config(action: ValidationAction) -> ValidationConfig {
+        ValidationConfig {
+            threshold: 0.5,
+            action,
+        }
+    }
+
+    fn rain_validator(action: ValidationAction) -> Validator {
+        Validator::new(
+            rain_network(),
+            "Rain".to_string(),
+            wet_evidence(),
+            &validation_config(action),
+        )
+    }
+
+    #[test]
+    fn test_bayesian_validation_actions() -> Result<(), MetaSyntraXLError> {
+        let output = Tensor::of_slice(&[1.0f64, 2.0]);
+        let wet = vec!["The grass is wet this morning".to_string()];
+
+        let validator = rain_validator(ValidationAction::Reject);
+        let validation = validator.validate(&output, &wet);
+        assert!(validation.valid);
+        assert_eq!(validation.evidence, HashMap::from([("WetGrass".to_string(), true)]));
+        assert!((validation.posterior.unwrap() - 0.18 / 0.26).abs() < 1e-9);
+        assert_eq!(validation.action, None);
+        assert!(validator.apply(output.shallow_clone(), &validation).is_ok());
+
+        let validation = validator.validate(&output, &[]);
+        assert!(!validation.valid);
+        assert!((validation.posterior.unwrap() - 0.2).abs() < 1e-9);
+        assert!(matches!(
+            validator.apply(output.shallow_clone(), &validation),
+            Err(MetaSyntraXLError::ValidationError(_))
+        ));
+
+        let validator = rain_validator(ValidationAction::DownWeight);
+        let validation = validator.validate(&output, &[]);
+        assert_eq!(validation.action, Some(ValidationAction::DownWeight));
+        let weighted = validator.apply(output.shallow_clone(), &validation)?;
+        assert!((weighted.double_value(&[0]) - 0.2).abs() < 1e-9);
+        assert!((weighted.double_value(&[1]) - 0.4).abs() < 1e-9);
+
+        // Without a posterior the output carries no weight at all.
+        let unknown = Validator::new(
+            rain_network(),
+            "Snow".to_string(),
+            wet_evidence(),
+            &validation_config(ValidationAction::DownWeight),
+        );
+        let validation = unknown.validate(&output, &wet);
+        assert_eq!(validation.posterior, None);
+        assert!(!validation.valid);
+        let weighted = unknown.apply(output.shallow_clone(), &validation)?;
+        assert_eq!(weighted.abs().max().double_value(&[]), 0.0);
+
+        let validator = rain_validator(ValidationAction::Annotate);
+        let validation = validator.validate(&output, &[]);
+        assert_eq!(validation.action, Some(ValidationAction::Annotate));
+        let annotated = validator.apply(output.shallow_clone(), &validation)?;
+        assert_eq!(annotated.double_value(&[0]), 1.0);
+        assert_eq!(annotated.double_value(&[1]), 2.0);
+        Ok(())
+    }
+
+    fn small_config(action: ValidationAction) -> Config {
+        Config {
+            vocab_size: 8,
+            embed_dim: 8,
+            num_heads: 2,
+            hidden_dim: 16,
+            num_layers: 1,
+            max_len: 8,
+            dropout: 0.0,
+            validation: validation_config(action),
+            ..Config::default()
+        }
+    }
+
+    /// Processes `input` with a validating controller whose parameters are copied
+    /// from `reference`, so outputs of different controllers can be compared.
+    async fn process_with(
+        action: ValidationAction,
+        reference: &nn::VarStore,
+        input: &Tensor,
+    ) -> Result<ControllerOutput, MetaSyntraXLError> {
+        let mut vs = nn::VarStore::new(tch::Device::Cpu);
+        let controller = Controller::with_validator(
+            &vs.root(),
+            &small_config(action),
+            rain_network(),
+            "Rain".to_string(),
+            wet_evidence(),
+        )?;
+        vs.copy(reference)
+            .map_err(|e| MetaSyntraXLError::TchError(e.to_string()))?;
+        controller.process_validated(input).await
+    }
+
+    #[tokio::test]
+    async fn test_controller_validates_outputs() -> Result<(), MetaSyntraXLError> {
+        let reference = nn::VarStore::new(tch::Device::Cpu);
+        Controller::new(&reference.root(), &small_config(ValidationAction::Annotate))?;
+        let input = Tensor::of_slice(&[1i64, 2, 3]).unsqueeze(0);
+        // The retrieved documents never mention wet grass, so Rain keeps its prior
+        // of 0.2 and fails the threshold.
+        let process = |action| process_with(action, &reference, &input);
+
+        let annotated = process(ValidationAction::Annotate).await?;
+        let validation = annotated.validation.unwrap();
+        assert!(!validation.valid);
+        assert!(validation.evidence.is_empty());
+        assert!((validation.posterior.unwrap() - 0.2).abs() < 1e-9);
+        assert_eq!(validation.action, Some(ValidationAction::Annotate));
+
+        let weighted = process(ValidationAction::DownWeight).await?;
+        let expected = &annotated.output * 0.2;
+        assert!((&weighted.output - expected).abs().max().double_value(&[]) < 1e-6);
+
+        assert!(matches!(
+            process(ValidationAction::Reject).await,
+            Err(MetaSyntraXLError::ValidationError(_))
+        ));
+        Ok(())
     }
 }
\ No newline at end of file
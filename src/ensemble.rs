@@ -1,53 +1,1412 @@
 // src/ensemble.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[ENSEMBLE]Xyn>=====S===t===u====d===i===o===s====[R|$>
 use crate::config::Config;
 use crate::errors::MetaSyntraXLError;
-use crate::transformer_rag::TransformerRAG;
+use crate::transformer_model::{self, linear_parameters};
+use crate::transformer_rag::{pool_sequence, TransformerRAG};
 use futures::future::join_all;
-use tch::{nn, Kind, Tensor};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tch::{nn, nn::Module, Device, Kind, Tensor};
+use tokio::sync::Semaphore;
+use tracing::{debug, info_span, warn, Instrument, Span};
 
 pub struct Ensemble {
-    models: Vec<TransformerRAG>,
-    #[allow(dead_code)]
+    models: Vec<Arc<TransformerRAG>>,
     meta_model: nn::Sequential,
+    meta_parameters: Vec<Tensor>,
+    model_weights: Vec<f64>,
+    output_size: i64,
+    quorum: usize,
+    member_timeout: Option<Duration>,
+    /// Bounds how many member forward passes run on the blocking pool at once.
+    compute_slots: Arc<Semaphore>,
+}
+
+/// How member outputs are combined by [`Ensemble::predict`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregation {
+    /// Plain mean of member logits, as in [`Ensemble::bagging_predict`].
+    Mean,
+    /// Mean of member logits weighted by the ensemble's member weights.
+    WeightedMean,
+    /// Share of members whose argmax picked each token.
+    MajorityVote,
+    /// Mean of member probability distributions.
+    SoftVote,
+    /// Mean of member logits after dropping the given fraction of the lowest and
+    /// highest values per element.
+    TrimmedMean(f64),
+}
+
+/// Aggregated ensemble output together with an uncertainty signal.
+#[derive(Debug)]
+pub struct EnsemblePrediction {
+    /// Logits for the mean variants, vote shares or probabilities for the voting ones.
+    pub output: Tensor,
+    /// Across-member variance of the softmax probabilities, averaged over the
+    /// vocabulary, per position (`[batch, seq]`).
+    pub variance: Tensor,
+    /// Entropy of the members' mean probability distribution per position (`[batch, seq]`).
+    pub entropy: Tensor,
+    /// Members left out of the aggregate because they failed or timed out.
+    pub dropped: Vec<DroppedMember>,
+}
+
+/// Output of [`Ensemble::bagging_predict`].
+#[derive(Debug)]
+pub struct BaggingPrediction {
+    /// Mean of the logits of the members that answered.
+    pub output: Tensor,
+    /// Members left out of the mean because they failed or timed out.
+    pub dropped: Vec<DroppedMember>,
+}
+
+/// A member excluded from a prediction, and why.
+#[derive(Debug, Clone)]
+pub struct DroppedMember {
+    pub model_index: usize,
+    pub reason: DropReason,
+}
+
+#[derive(Debug, Clone)]
+pub enum DropReason {
+    TimedOut(Duration),
+    Failed(String),
+}
+
+impl fmt::Display for DropReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DropReason::TimedOut(limit) => write!(f, "timed out after {:?}", limit),
+            DropReason::Failed(error) => write!(f, "failed: {}", error),
+        }
+    }
+}
+
+/// Outputs of the members that answered, keyed by member index.
+struct MemberOutputs {
+    predictions: Vec<(usize, Tensor)>,
+    dropped: Vec<DroppedMember>,
+}
+
+impl MemberOutputs {
+    fn stacked(&self) -> Tensor {
+        let predictions: Vec<&Tensor> = self.predictions.iter().map(|(_, p)| p).collect();
+        Tensor::stack(&predictions, 0)
+    }
+}
+
+impl EnsemblePrediction {
+    /// Marks the positions whose predictive entropy exceeds `max_entropy`.
+    pub fn uncertain_positions(&self, max_entropy: f64) -> Tensor {
+        self.entropy.gt(max_entropy)
+    }
+}
+
+/// Per-member hyperparameter variation for [`Ensemble::with_diversity`].
+///
+/// Empty lists leave the corresponding setting from the shared `Config` untouched;
+/// otherwise values are assigned to members round-robin.
+#[derive(Debug, Clone, Default)]
+pub struct MemberDiversity {
+    pub num_layers: Vec<usize>,
+    pub dropout: Vec<f64>,
+    /// Member `i` is initialised after seeding torch with `seed + i`.
+    ///
+    /// Torch has one global generator, so once the members are built it is
+    /// reseeded from a value drawn from it beforehand: later draws still follow
+    /// the caller's own seeding instead of continuing from `seed`.
+    pub seed: Option<u64>,
+}
+
+impl MemberDiversity {
+    fn member_config(&self, config: &Config, member: usize) -> Config {
+        let mut member_config = config.clone();
+        if !self.num_layers.is_empty() {
+            member_config.num_layers = self.num_layers[member % self.num_layers.len()];
+        }
+        if !self.dropout.is_empty() {
+            member_config.dropout = self.dropout[member % self.dropout.len()];
+        }
+        member_config
+    }
+}
+
+/// Options for [`Ensemble::fit_bagging`].
+#[derive(Debug, Clone)]
+pub struct BaggingOptions {
+    /// Passes over each member's bootstrap sample.
+    pub epochs: usize,
+    pub learning_rate: f64,
+    /// Bootstrap sample size as a fraction of the training set.
+    pub sample_fraction: f64,
+    pub seed: u64,
+}
+
+impl Default for BaggingOptions {
+    fn default() -> Self {
+        Self {
+            epochs: 5,
+            learning_rate: 1e-2,
+            sample_fraction: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Bootstrap draws and out-of-bag errors from [`Ensemble::fit_bagging`].
+#[derive(Debug, Clone)]
+pub struct BaggingReport {
+    /// Sample indices drawn with replacement for each member.
+    pub bootstrap_indices: Vec<Vec<usize>>,
+    /// Error rate of each member on the samples it did not draw, `NaN` if it drew them all.
+    pub out_of_bag_errors: Vec<f64>,
+}
+
+/// Pairwise disagreement between members from [`Ensemble::disagreement`].
+#[derive(Debug, Clone)]
+pub struct DiversityReport {
+    /// `pairwise[i][j]` is the fraction of rows on which members `i` and `j` predict
+    /// different classes.
+    pub pairwise: Vec<Vec<f64>>,
+    /// Mean of the off-diagonal entries of `pairwise`.
+    pub mean: f64,
+}
+
+/// Options for [`Ensemble::fit_meta_model`].
+#[derive(Debug, Clone)]
+pub struct StackingOptions {
+    /// Number of folds used to generate out-of-fold base predictions.
+    pub k_folds: usize,
+    /// Passes over the training folds when fitting each base member.
+    pub base_epochs: usize,
+    /// Full-batch passes over the out-of-fold predictions when fitting the meta-model.
+    pub meta_epochs: usize,
+    pub learning_rate: f64,
+}
+
+impl Default for StackingOptions {
+    fn default() -> Self {
+        Self {
+            k_folds: 5,
+            base_epochs: 1,
+            meta_epochs: 100,
+            learning_rate: 1e-2,
+        }
+    }
+}
+
+/// Options for [`Ensemble::fit_boosting`].
+#[derive(Debug, Clone)]
+pub struct BoostingOptions {
+    /// Passes over the reweighted samples when fitting each member.
+    pub epochs: usize,
+    pub learning_rate: f64,
+}
+
+impl Default for BoostingOptions {
+    fn default() -> Self {
+        Self {
+            epochs: 5,
+            learning_rate: 1e-2,
+        }
+    }
+}
+
+/// Per-member statistics from [`Ensemble::fit_boosting`].
+#[derive(Debug, Clone)]
+pub struct BoostingReport {
+    /// Weighted training error of each member at the time it was fit.
+    pub weighted_errors: Vec<f64>,
+    /// Learned vote weight (`alpha`) of each member.
+    pub model_weights: Vec<f64>,
+}
+
+/// Losses observed while fitting the stacking meta-model.
+#[derive(Debug, Clone)]
+pub struct StackingReport {
+    /// Cross-entropy of the averaged base members on each held-out fold.
+    pub fold_losses: Vec<f64>,
+    /// Cross-entropy of the meta-model on the out-of-fold predictions before training.
+    pub initial_meta_loss: f64,
+    /// Cross-entropy of the meta-model on the out-of-fold predictions after training.
+    pub meta_loss: f64,
 }
 
 impl Ensemble {
     pub fn new(
+        vs: &nn::Path,
+        num_models: usize,
+        input_size: i64,
+        output_size: i64,
+        config: &Config,
+    ) -> Result<Self, MetaSyntraXLError> {
+        Self::with_diversity(
+            vs,
+            num_models,
+            input_size,
+            output_size,
+            config,
+            &MemberDiversity::default(),
+        )
+    }
+
+    /// Builds an ensemble whose members differ in depth, dropout or initial weights.
+    pub fn with_diversity(
         vs: &nn::Path,
         num_models: usize,
         _input_size: i64,
         output_size: i64,
         config: &Config,
+        diversity: &MemberDiversity,
     ) -> Result<Self, MetaSyntraXLError> {
-        let mut models = Vec::new();
-        for i in 0..num_models {
-            let model_vs = vs.sub(&format!("ensemble_model{}", i));
-            let model = TransformerRAG::new(&model_vs, config)?;
-            models.push(model);
-        }
-
-        let meta_model = nn::seq()
-            .add(nn::linear(
-                vs / "meta1",
-                num_models as i64 * output_size,
-                64,
-                Default::default(),
-            ))
-            .add_fn(|x| x.relu())
-            .add(nn::linear(vs / "meta2", 64, output_size, Default::default()));
-
-        Ok(Self { models, meta_model })
-    }
-
-    pub async fn bagging_predict(&self, input: &Tensor) -> Result<Tensor, MetaSyntraXLError> {
-        let predictions = join_all(self.models.iter().map(|model| model.forward(input))).await;
-        let valid_predictions: Result<Vec<Tensor>, MetaSyntraXLError> =
-            predictions.into_iter().collect();
-        let stacked_predictions = Tensor::stack(&valid_predictions?, 0);
-        Ok(stacked_predictions.mean_dim(
-            Some(&[0i64][..]),
-            false,
-            Kind::Float,
-        ))
+        let quorum = config.ensemble.quorum.unwrap_or(num_models).max(1);
+        if quorum > num_models {
+            return Err(MetaSyntraXLError::EnsembleError(format!(
+                "Quorum {} exceeds the number of members ({})",
+                quorum, num_models
+            )));
+        }
+
+        let resume_seed = diversity.seed.map(|_| {
+            Tensor::randint(i64::MAX, &[1i64][..], (Kind::Int64, Device::Cpu)).int64_value(&[0])
+        });
+        let models: Result<Vec<Arc<TransformerRAG>>, MetaSyntraXLError> = (0..num_models)
+            .map(|i| {
+                if let Some(seed) = diversity.seed {
+                    tch::manual_seed(seed.wrapping_add(i as u64) as i64);
+                }
+                let model_vs = vs.sub(&format!("ensemble_model{}", i));
+                TransformerRAG::new(&model_vs, &diversity.member_config(config, i)).map(Arc::new)
+            })
+            .collect();
+        if let Some(resume_seed) = resume_seed {
+            tch::manual_seed(resume_seed);
+        }
+        let models = models?;
+
+        // The meta-model sees every member's sequence-pooled vocabulary logits side by side.
+        let meta1 = nn::linear(
+            vs / "meta1",
+            num_models as i64 * config.vocab_size,
+            64,
+            Default::default(),
+        );
+        let meta2 = nn::linear(vs / "meta2", 64, output_size, Default::default());
+
+        let mut meta_parameters = linear_parameters(&meta1);
+        meta_parameters.extend(linear_parameters(&meta2));
+
+        let meta_model = nn::seq().add(meta1).add_fn(|x| x.relu()).add(meta2);
+
+        let parallelism = config
+            .ensemble
+            .parallelism
+            .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+            .unwrap_or(1)
+            .max(1);
+
+        let model_weights = if config.ensemble.member_weights.is_empty() {
+            vec![1.0; models.len()]
+        } else {
+            validate_weights(&config.ensemble.member_weights, models.len())?;
+            config.ensemble.member_weights.clone()
+        };
+
+        Ok(Self {
+            model_weights,
+            models,
+            meta_model,
+            meta_parameters,
+            output_size,
+            quorum,
+            member_timeout: config.ensemble.member_timeout_ms.map(Duration::from_millis),
+            compute_slots: Arc::new(Semaphore::new(parallelism)),
+        })
+    }
+
+    /// Averages the outputs of the members that answer in time, and lists the
+    /// members that did not.
+    ///
+    /// Fails with `EnsembleError` only when fewer members than the configured quorum answer.
+    pub async fn bagging_predict(
+        &self,
+        input: &Tensor,
+    ) -> Result<BaggingPrediction, MetaSyntraXLError> {
+        let span = info_span!("ensemble.bagging_predict", num_models = self.models.len());
+        let outputs = self.member_outputs(input, self.quorum).instrument(span).await?;
+        let stacked_predictions = outputs.stacked();
+        Ok(BaggingPrediction {
+            output: stacked_predictions.mean_dim(Some(&[0i64][..]), false, Kind::Float),
+            dropped: outputs.dropped,
+        })
+    }
+
+    /// Combines member outputs with the chosen aggregation strategy and reports
+    /// per-position variance and entropy across members.
+    pub async fn predict(
+        &self,
+        input: &Tensor,
+        aggregation: Aggregation,
+    ) -> Result<EnsemblePrediction, MetaSyntraXLError> {
+        let span = info_span!("ensemble.predict", num_models = self.models.len(), ?aggregation);
+        let outputs = self.member_outputs(input, self.quorum).instrument(span).await?;
+        let stacked = outputs.stacked();
+        // Only the weights of members that answered take part.
+        let member_weights: Vec<f64> = outputs
+            .predictions
+            .iter()
+            .map(|(model_index, _)| self.model_weights[*model_index])
+            .collect();
+        let output = aggregate(&stacked, &member_weights, aggregation)?;
+
+        let probabilities = stacked.softmax(-1, Kind::Float);
+        let mean_probabilities = probabilities.mean_dim(Some(&[0i64][..]), false, Kind::Float);
+        let deviation = &probabilities - &mean_probabilities;
+        let variance = (&deviation * &deviation)
+            .mean_dim(Some(&[0i64][..]), false, Kind::Float)
+            .mean_dim(Some(&[-1i64][..]), false, Kind::Float);
+        let entropy = -(&mean_probabilities * mean_probabilities.clamp_min(1e-12).log())
+            .sum_dim_intlist(Some(&[-1i64][..]), false, Kind::Float);
+
+        Ok(EnsemblePrediction {
+            output,
+            variance,
+            entropy,
+            dropped: outputs.dropped,
+        })
+    }
+
+    /// Replaces the member weights used by [`Aggregation::WeightedMean`] and
+    /// [`Ensemble::boosting_predict`].
+    pub fn set_model_weights(&mut self, weights: Vec<f64>) -> Result<(), MetaSyntraXLError> {
+        validate_weights(&weights, self.models.len())?;
+        self.model_weights = weights;
+        Ok(())
+    }
+
+    pub fn model_weights(&self) -> &[f64] {
+        &self.model_weights
+    }
+
+    /// Predicts by feeding the concatenated base-model outputs through the meta-model.
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` - Meta-model logits of shape `[batch, output_size]`.
+    pub async fn stacking_predict(&self, input: &Tensor) -> Result<Tensor, MetaSyntraXLError> {
+        let span = info_span!("ensemble.stacking_predict", num_models = self.models.len());
+        let features = self.meta_features(input).instrument(span).await?;
+        Ok(self.meta_model.forward(&features))
+    }
+
+    /// Trains the base members and the stacking meta-model on `samples`.
+    ///
+    /// Samples are split round-robin into `k_folds` folds. For each fold the base
+    /// members are reset, trained on the remaining folds and asked to predict the
+    /// held-out fold, so every sample gets a prediction from members that never saw
+    /// it. The meta-model is then trained on those out-of-fold predictions, and the
+    /// base members are finally refit on all samples for inference.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - `(input, target)` pairs; targets hold one class index per batch
+    ///   row, below both `output_size` and the vocabulary size.
+    /// * `options` - Fold count, epochs and learning rate.
+    pub async fn fit_meta_model(
+        &mut self,
+        samples: &[(Tensor, Tensor)],
+        options: &StackingOptions,
+    ) -> Result<StackingReport, MetaSyntraXLError> {
+        if options.k_folds < 2 || samples.len() < options.k_folds {
+            return Err(MetaSyntraXLError::EnsembleError(format!(
+                "Stacking needs at least 2 folds and one sample per fold, got {} folds for {} samples",
+                options.k_folds,
+                samples.len()
+            )));
+        }
+
+        let initial_members = self.snapshot_members();
+        let mut out_of_fold: Vec<Option<Tensor>> = samples.iter().map(|_| None).collect();
+        let mut fold_losses = Vec::with_capacity(options.k_folds);
+
+        for fold in 0..options.k_folds {
+            let span = info_span!("ensemble.stacking_fold", fold);
+            self.restore_members(&initial_members);
+
+            let training: Vec<&(Tensor, Tensor)> = samples
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| i % options.k_folds != fold)
+                .map(|(_, sample)| sample)
+                .collect();
+            self.train_members(&training, options.base_epochs, options.learning_rate)?;
+
+            let mut fold_loss = 0.0;
+            let mut fold_size = 0;
+            for (i, (input, target)) in samples.iter().enumerate() {
+                if i % options.k_folds != fold {
+                    continue;
+                }
+                let features = self.meta_features(input).instrument(span.clone()).await?;
+                fold_loss += self.averaged_member_loss(&features, target);
+                fold_size += 1;
+                out_of_fold[i] = Some(features);
+            }
+            fold_losses.push(fold_loss / fold_size as f64);
+            debug!(fold, loss = fold_losses[fold], "Generated out-of-fold predictions");
+        }
+
+        self.restore_members(&initial_members);
+        let all_samples: Vec<&(Tensor, Tensor)> = samples.iter().collect();
+        self.train_members(&all_samples, options.base_epochs, options.learning_rate)?;
+
+        let features = Tensor::cat(&out_of_fold.into_iter().flatten().collect::<Vec<_>>(), 0);
+        let targets = Tensor::cat(
+            &samples.iter().map(|(_, target)| target.to_kind(Kind::Int64)).collect::<Vec<_>>(),
+            0,
+        )
+        .to_device(features.device());
+
+        let initial_meta_loss = self.meta_loss(&features, &targets);
+        for _ in 0..options.meta_epochs {
+            let loss = self.meta_model.forward(&features).cross_entropy_for_logits(&targets);
+            loss.backward();
+            transformer_model::sgd_step(&self.meta_parameters, options.learning_rate);
+        }
+
+        Ok(StackingReport {
+            fold_losses,
+            initial_meta_loss,
+            meta_loss: self.meta_loss(&features, &targets),
+        })
+    }
+
+    /// Trains every member on its own bootstrap sample of `samples`.
+    ///
+    /// Draws are made with replacement from a generator seeded with
+    /// `options.seed`, so runs are reproducible. Members are fit with
+    /// [`TransformerRAG::train_step`] and scored on the samples they did not draw.
+    pub fn fit_bagging(
+        &mut self,
+        samples: &[(Tensor, Tensor)],
+        options: &BaggingOptions,
+    ) -> Result<BaggingReport, MetaSyntraXLError> {
+        let draws = ((samples.len() as f64) * options.sample_fraction).round() as usize;
+        if samples.is_empty() || draws == 0 {
+            return Err(MetaSyntraXLError::EnsembleError(
+                "Bagging needs at least one bootstrap draw per member".to_string(),
+            ));
+        }
+
+        let mut rng = StdRng::seed_from_u64(options.seed);
+        let mut bootstrap_indices = Vec::with_capacity(self.models.len());
+        let mut out_of_bag_errors = Vec::with_capacity(self.models.len());
+
+        for (model_index, model) in self.models.iter().enumerate() {
+            let indices: Vec<usize> = (0..draws)
+                .map(|_| rng.gen_range(0..samples.len()))
+                .collect();
+            for _ in 0..options.epochs {
+                for &i in &indices {
+                    let (input, target) = &samples[i];
+                    model.train_step(input, target, None, options.learning_rate)?;
+                }
+            }
+
+            let drawn: HashSet<usize> = indices.iter().copied().collect();
+            let (mut missed, mut rows) = (0i64, 0i64);
+            let held_out = samples
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !drawn.contains(i));
+            for (_, (input, target)) in held_out {
+                let predicted = model
+                    .pooled_logits(input)
+                    .argmax(-1, false)
+                    .to_device(Device::Cpu);
+                let target = target.to_kind(Kind::Int64).to_device(Device::Cpu);
+                missed += predicted.ne_tensor(&target).sum(Kind::Int64).int64_value(&[]);
+                rows += target.size()[0];
+            }
+            let out_of_bag_error = if rows == 0 {
+                f64::NAN
+            } else {
+                missed as f64 / rows as f64
+            };
+            debug!(model_index, out_of_bag_error, "Fit bagging member");
+
+            bootstrap_indices.push(indices);
+            out_of_bag_errors.push(out_of_bag_error);
+        }
+
+        Ok(BaggingReport {
+            bootstrap_indices,
+            out_of_bag_errors,
+        })
+    }
+
+    /// Measures how often pairs of members predict different classes on `inputs`.
+    pub fn disagreement(&self, inputs: &[Tensor]) -> DiversityReport {
+        let predictions: Vec<Tensor> = self
+            .models
+            .iter()
+            .map(|model| {
+                let per_input: Vec<Tensor> = inputs
+                    .iter()
+                    .map(|input| {
+                        model
+                            .pooled_logits(input)
+                            .argmax(-1, false)
+                            .to_device(Device::Cpu)
+                    })
+                    .collect();
+                Tensor::cat(&per_input, 0)
+            })
+            .collect();
+
+        let num_models = self.models.len();
+        let mut pairwise = vec![vec![0.0; num_models]; num_models];
+        let mut total = 0.0;
+        for (i, left) in predictions.iter().enumerate() {
+            for (j, right) in predictions.iter().enumerate().skip(i + 1) {
+                let rate = left
+                    .ne_tensor(right)
+                    .to_kind(Kind::Float)
+                    .mean(Kind::Float)
+                    .double_value(&[]);
+                pairwise[i][j] = rate;
+                pairwise[j][i] = rate;
+                total += rate;
+            }
+        }
+        let pairs = num_models * num_models.saturating_sub(1) / 2;
+        let mean = if pairs == 0 { 0.0 } else { total / pairs as f64 };
+
+        DiversityReport { pairwise, mean }
+    }
+
+    /// Trains the members sequentially with multi-class AdaBoost (SAMME).
+    ///
+    /// Every member is fit on the samples reweighted towards the rows its
+    /// predecessors misclassified, then given a vote weight from its weighted
+    /// error. Members are scored with [`TransformerRAG::pooled_logits`], the same
+    /// prediction [`TransformerRAG::train_step`] fits. A member no better than
+    /// chance gets a weight of zero and stops the reweighting. The learned vote
+    /// weights replace any configured `member_weights`, so
+    /// [`Aggregation::WeightedMean`] uses them too afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - `(input, target)` pairs; targets hold one class index below
+    ///   `output_size` per batch row.
+    /// * `options` - Epochs and learning rate for each member.
+    pub fn fit_boosting(
+        &mut self,
+        samples: &[(Tensor, Tensor)],
+        options: &BoostingOptions,
+    ) -> Result<BoostingReport, MetaSyntraXLError> {
+        if samples.is_empty() {
+            return Err(MetaSyntraXLError::EnsembleError(
+                "Boosting needs at least one sample".to_string(),
+            ));
+        }
+
+        let num_classes = self.output_size as f64;
+        let targets: Vec<Tensor> = samples
+            .iter()
+            .map(|(_, target)| target.to_kind(Kind::Int64).to_device(Device::Cpu))
+            .collect();
+        let num_rows: i64 = targets.iter().map(|t| t.size()[0]).sum();
+        // Weights are kept with a mean of one so the learning rate keeps its scale.
+        let mut weights: Vec<Tensor> = targets
+            .iter()
+            .map(|t| t.ones_like().to_kind(Kind::Float))
+            .collect();
+
+        let mut weighted_errors = Vec::with_capacity(self.models.len());
+        let mut model_weights = Vec::with_capacity(self.models.len());
+        let mut stopped = false;
+
+        for (model_index, model) in self.models.iter().enumerate() {
+            if stopped {
+                weighted_errors.push(f64::NAN);
+                model_weights.push(0.0);
+                continue;
+            }
+
+            for _ in 0..options.epochs {
+                for ((input, _), (target, weight)) in samples.iter().zip(targets.iter().zip(&weights))
+                {
+                    model.train_step(input, target, Some(weight), options.learning_rate)?;
+                }
+            }
+
+            let misses: Vec<Tensor> = samples
+                .iter()
+                .zip(&targets)
+                .map(|((input, _), target)| {
+                    let predicted = model
+                        .pooled_logits(input)
+                        .argmax(-1, false)
+                        .to_device(Device::Cpu);
+                    predicted.ne_tensor(target).to_kind(Kind::Float)
+                })
+                .collect();
+
+            let total_weight = total(&weights);
+            let missed_weight: f64 = weights
+                .iter()
+                .zip(&misses)
+                .map(|(w, miss)| (w * miss).sum(Kind::Float).double_value(&[]))
+                .sum();
+            let error = (missed_weight / total_weight).clamp(1e-10, 1.0);
+
+            let alpha = ((1.0 - error) / error).ln() + (num_classes - 1.0).ln();
+            weighted_errors.push(error);
+            debug!(model_index, error, alpha, "Fit boosting member");
+
+            if alpha <= 0.0 {
+                model_weights.push(0.0);
+                stopped = true;
+                continue;
+            }
+            model_weights.push(alpha);
+
+            for (weight, miss) in weights.iter_mut().zip(&misses) {
+                *weight = &*weight * (miss * alpha).exp();
+            }
+            let scale = num_rows as f64 / total(&weights);
+            for weight in weights.iter_mut() {
+                *weight = &*weight * scale;
+            }
+        }
+
+        self.model_weights = model_weights.clone();
+        Ok(BoostingReport {
+            weighted_errors,
+            model_weights,
+        })
+    }
+
+    /// Combines member votes using the weights learned by [`Ensemble::fit_boosting`].
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` - Vote shares of shape `[batch, output_size]`; each row sums to one
+    ///   unless every member voted outside the class range.
+    pub fn boosting_predict(&self, input: &Tensor) -> Result<Tensor, MetaSyntraXLError> {
+        let total_weight: f64 = self.model_weights.iter().sum();
+        if total_weight <= 0.0 {
+            return Err(MetaSyntraXLError::EnsembleError(
+                "No ensemble member has a positive boosting weight".to_string(),
+            ));
+        }
+
+        let mut scores: Option<Tensor> = None;
+        for (model, &alpha) in self.models.iter().zip(&self.model_weights) {
+            if alpha <= 0.0 {
+                continue;
+            }
+            let logits = model.pooled_logits(input);
+            let vocab_size = logits.size()[1];
+            let votes = logits
+                .argmax(-1, false)
+                .one_hot(vocab_size)
+                .narrow(1, 0, self.output_size.min(vocab_size))
+                .to_kind(Kind::Float)
+                * alpha;
+            scores = Some(match scores {
+                Some(scores) => scores + votes,
+                None => votes,
+            });
+        }
+
+        Ok(scores.expect("at least one member has a positive weight") / total_weight)
+    }
+
+    /// Runs every member, dropping those that fail or miss the member timeout.
+    ///
+    /// Retrieval runs on the async executor; the transformer pass of each member is
+    /// dispatched to tokio's blocking pool, at most `parallelism` at a time. A member
+    /// that times out is dropped from the result, but its transformer pass finishes
+    /// on the pool and holds its slot until then.
+    async fn member_outputs(
+        &self,
+        input: &Tensor,
+        quorum: usize,
+    ) -> Result<MemberOutputs, MetaSyntraXLError> {
+        let member_timeout = self.member_timeout;
+        let results = join_all(self.models.iter().enumerate().map(|(model_index, model)| {
+            let span = info_span!("ensemble.member", model_index);
+            let forward = self.member_forward(model, input).instrument(span);
+            async move {
+                match member_timeout {
+                    Some(limit) => match tokio::time::timeout(limit, forward).await {
+                        Ok(result) => result.map_err(|e| DropReason::Failed(e.to_string())),
+                        Err(_) => Err(DropReason::TimedOut(limit)),
+                    },
+                    None => forward.await.map_err(|e| DropReason::Failed(e.to_string())),
+                }
+            }
+        }))
+        .await;
+
+        let mut predictions = Vec::with_capacity(results.len());
+        let mut dropped = Vec::new();
+        for (model_index, result) in results.into_iter().enumerate() {
+            match result {
+                Ok(prediction) => predictions.push((model_index, prediction)),
+                Err(reason) => {
+                    warn!(model_index, %reason, "Dropping ensemble member");
+                    dropped.push(DroppedMember {
+                        model_index,
+                        reason,
+                    });
+                }
+            }
+        }
+
+        if predictions.len() < quorum {
+            let reasons: Vec<String> = dropped
+                .iter()
+                .map(|d| format!("member {} {}", d.model_index, d.reason))
+                .collect();
+            return Err(MetaSyntraXLError::EnsembleError(format!(
+                "Only {} of {} members answered, quorum is {}: {}",
+                predictions.len(),
+                self.models.len(),
+                quorum,
+                reasons.join("; ")
+            )));
+        }
+
+        Ok(MemberOutputs {
+            predictions,
+            dropped,
+        })
+    }
+
+    async fn member_forward(
+        &self,
+        model: &Arc<TransformerRAG>,
+        input: &Tensor,
+    ) -> Result<Tensor, MetaSyntraXLError> {
+        let augmented = model.augment(input).await?;
+
+        let slot = Arc::clone(&self.compute_slots)
+            .acquire_owned()
+            .await
+            .map_err(|e| MetaSyntraXLError::EnsembleError(e.to_string()))?;
+        let model = Arc::clone(model);
+        // The blocking pool does not inherit the member's span.
+        let span = Span::current();
+        let output = tokio::task::spawn_blocking(move || {
+            let _slot = slot;
+            let _enter = span.enter();
+            model.transform(&augmented)
+        })
+        .await?;
+        Ok(output)
+    }
+
+    /// Concatenates each member's sequence-pooled logits into `[batch, num_models * vocab]`.
+    ///
+    /// The meta-model's input width is fixed, so every member must answer.
+    async fn meta_features(&self, input: &Tensor) -> Result<Tensor, MetaSyntraXLError> {
+        let pooled: Vec<Tensor> = self
+            .member_outputs(input, self.models.len())
+            .await?
+            .predictions
+            .iter()
+            .map(|(_, prediction)| pool_sequence(prediction).detach())
+            .collect();
+        Ok(Tensor::cat(&pooled, -1))
+    }
+
+    fn meta_loss(&self, features: &Tensor, targets: &Tensor) -> f64 {
+        tch::no_grad(|| {
+            self.meta_model
+                .forward(features)
+                .cross_entropy_for_logits(targets)
+                .double_value(&[])
+        })
+    }
+
+    fn averaged_member_loss(&self, features: &Tensor, target: &Tensor) -> f64 {
+        let batch_size = features.size()[0];
+        features
+            .view([batch_size, self.models.len() as i64, -1])
+            .mean_dim(Some(&[1i64][..]), false, Kind::Float)
+            .cross_entropy_for_logits(&target.to_kind(Kind::Int64).to_device(features.device()))
+            .double_value(&[])
+    }
+
+    fn train_members(
+        &self,
+        samples: &[&(Tensor, Tensor)],
+        epochs: usize,
+        learning_rate: f64,
+    ) -> Result<(), MetaSyntraXLError> {
+        for model in &self.models {
+            for _ in 0..epochs {
+                for (input, target) in samples {
+                    model.train_step(input, target, None, learning_rate)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn snapshot_members(&self) -> Vec<Vec<Tensor>> {
+        self.models
+            .iter()
+            .map(|model| transformer_model::snapshot(&model.parameters()))
+            .collect()
+    }
+
+    fn restore_members(&self, snapshots: &[Vec<Tensor>]) {
+        for (model, saved) in self.models.iter().zip(snapshots) {
+            transformer_model::restore(&model.parameters(), saved);
+        }
+    }
+}
+
+/// Combines member outputs stacked along the first dimension; `member_weights`
+/// holds the weight of each stacked member for [`Aggregation::WeightedMean`].
+fn aggregate(
+    stacked: &Tensor,
+    member_weights: &[f64],
+    aggregation: Aggregation,
+) -> Result<Tensor, MetaSyntraXLError> {
+    let num_members = stacked.size()[0];
+    let output = match aggregation {
+        Aggregation::Mean => stacked.mean_dim(Some(&[0i64][..]), false, Kind::Float),
+        Aggregation::WeightedMean => {
+            let total: f64 = member_weights.iter().sum();
+            if total <= 0.0 {
+                return Err(MetaSyntraXLError::EnsembleError(
+                    "Responding members have no positive weight".to_string(),
+                ));
+            }
+            let mut shape = vec![1i64; stacked.dim()];
+            shape[0] = num_members;
+            let weights = Tensor::of_slice(member_weights)
+                .to_kind(Kind::Float)
+                .to_device(stacked.device())
+                .view(shape.as_slice());
+            (stacked * weights).sum_dim_intlist(Some(&[0i64][..]), false, Kind::Float) / total
+        }
+        Aggregation::MajorityVote => {
+            let vocab_size = stacked.size()[stacked.dim() - 1];
+            stacked
+                .argmax(-1, false)
+                .one_hot(vocab_size)
+                .to_kind(Kind::Float)
+                .mean_dim(Some(&[0i64][..]), false, Kind::Float)
+        }
+        Aggregation::SoftVote => stacked
+            .softmax(-1, Kind::Float)
+            .mean_dim(Some(&[0i64][..]), false, Kind::Float),
+        Aggregation::TrimmedMean(fraction) => {
+            let trimmed = (fraction * num_members as f64).floor() as i64;
+            if !(0.0..0.5).contains(&fraction) || num_members - 2 * trimmed < 1 {
+                return Err(MetaSyntraXLError::EnsembleError(format!(
+                    "Cannot trim {} of {} members from each side",
+                    fraction, num_members
+                )));
+            }
+            let (sorted, _) = stacked.sort(0, false);
+            sorted
+                .narrow(0, trimmed, num_members - 2 * trimmed)
+                .mean_dim(Some(&[0i64][..]), false, Kind::Float)
+        }
+    };
+    Ok(output)
+}
+
+fn total(weights: &[Tensor]) -> f64 {
+    weights
+        .iter()
+        .map(|w| w.sum(Kind::Float).double_value(&[]))
+        .sum()
+}
+
+fn validate_weights(weights: &[f64], num_models: usize) -> Result<(), MetaSyntraXLError> {
+    if weights.len() != num_models {
+        return Err(MetaSyntraXLError::EnsembleError(format!(
+            "Expected {} member weights, got {}",
+            num_models,
+            weights.len()
+        )));
+    }
+    if weights.iter().any(|w| !w.is_finite() || *w < 0.0) || weights.iter().sum::<f64>() <= 0.0 {
+        return Err(MetaSyntraXLError::EnsembleError(
+            "Member weights must be non-negative with a positive sum".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EnsembleConfig;
+    use crate::retrieval_system::{DocumentSource, RetrievalSystem};
+    use futures::FutureExt;
+
+    /// A configuration small enough for quick forward passes. Dropout is off so
+    /// repeated passes over the same input agree.
+    fn small_config(num_models: usize) -> Config {
+        Config {
+            vocab_size: 16,
+            embed_dim: 8,
+            num_heads: 2,
+            hidden_dim: 16,
+            num_layers: 1,
+            max_len: 16,
+            num_models,
+            output_size: 2,
+            dropout: 0.0,
+            ..Config::default()
+        }
+    }
+
+    fn build(config: &Config) -> Result<Ensemble, MetaSyntraXLError> {
+        let vs = nn::VarStore::new(Device::Cpu);
+        Ensemble::new(
+            &vs.root(),
+            config.num_models,
+            config.input_size,
+            config.output_size,
+            config,
+        )
+    }
+
+    fn max_difference(left: &[Tensor], right: &[Tensor]) -> f64 {
+        left.iter()
+            .zip(right)
+            .map(|(l, r)| (l - r).abs().max().double_value(&[]))
+            .fold(0.0, f64::max)
+    }
+
+    #[tokio::test]
+    async fn test_ensemble_stacking() -> Result<(), MetaSyntraXLError> {
+        let config = small_config(3);
+        let mut ensemble = build(&config)?;
+        let initial = ensemble.snapshot_members();
+        let samples: Vec<(Tensor, Tensor)> = (0..6i64)
+            .map(|i| {
+                (
+                    Tensor::of_slice(&[i % 2, 1, 2, 3]).unsqueeze(0),
+                    Tensor::of_slice(&[i % 2]),
+                )
+            })
+            .collect();
+        let options = StackingOptions {
+            k_folds: 3,
+            meta_epochs: 50,
+            ..StackingOptions::default()
+        };
+        let report = ensemble.fit_meta_model(&samples, &options).await?;
+        assert!(
+            report.meta_loss < report.initial_meta_loss,
+            "{:?}",
+            report
+        );
+
+        // Every fold starts from the initial members: retraining a copy of them on
+        // the fold's training samples reproduces its held-out loss.
+        let reference = build(&config)?;
+        assert_eq!(report.fold_losses.len(), 3);
+        for (fold, &fold_loss) in report.fold_losses.iter().enumerate() {
+            reference.restore_members(&initial);
+            let training: Vec<&(Tensor, Tensor)> = samples
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| i % 3 != fold)
+                .map(|(_, sample)| sample)
+                .collect();
+            reference.train_members(&training, options.base_epochs, options.learning_rate)?;
+            let mut loss = 0.0;
+            for (input, target) in samples.iter().skip(fold).step_by(3) {
+                let features = reference.meta_features(input).await?;
+                loss += reference.averaged_member_loss(&features, target);
+            }
+            assert!((loss / 2.0 - fold_loss).abs() < 1e-5, "fold {}", fold);
+        }
+
+        // The members are finally refit on every sample from the same start.
+        reference.restore_members(&initial);
+        let all_samples: Vec<&(Tensor, Tensor)> = samples.iter().collect();
+        reference.train_members(&all_samples, options.base_epochs, options.learning_rate)?;
+        for (fitted, expected) in ensemble
+            .snapshot_members()
+            .iter()
+            .zip(reference.snapshot_members())
+        {
+            assert!(max_difference(fitted, &expected) < 1e-5);
+        }
+
+        let prediction = ensemble.stacking_predict(&samples[0].0).await?;
+        assert_eq!(prediction.size(), &[1, config.output_size]);
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_ensemble_boosting_synthetic_classification() -> Result<(), MetaSyntraXLError> {
+        let config = Config {
+            vocab_size: 8,
+            embed_dim: 16,
+            hidden_dim: 32,
+            max_len: 8,
+            ..small_config(3)
+        };
+        let mut ensemble = build(&config)?;
+
+        // Class 0 sequences use tokens 1-3, class 1 sequences use tokens 4-6.
+        let samples: Vec<(Tensor, Tensor)> = (0..12i64)
+            .map(|i| {
+                let label = i % 2;
+                let offset = 1 + 3 * label;
+                let tokens = [
+                    offset + i % 3,
+                    offset + (i + 1) % 3,
+                    offset + (i + 2) % 3,
+                    offset,
+                ];
+                (
+                    Tensor::of_slice(&tokens).unsqueeze(0),
+                    Tensor::of_slice(&[label]),
+                )
+            })
+            .collect();
+
+        let options = BoostingOptions {
+            epochs: 20,
+            learning_rate: 0.05,
+        };
+        let report = ensemble.fit_boosting(&samples, &options)?;
+        assert_eq!(report.model_weights.len(), config.num_models);
+        assert!(report.model_weights[0] > 0.0);
+        assert_eq!(ensemble.model_weights(), &report.model_weights[..]);
+
+        let correct = samples
+            .iter()
+            .filter(|(input, target)| {
+                let scores = ensemble.boosting_predict(input).unwrap();
+                assert_eq!(scores.size(), &[1, config.output_size]);
+                scores.argmax(-1, false).int64_value(&[0]) == target.int64_value(&[0])
+            })
+            .count();
+        assert!(correct as f64 / samples.len() as f64 >= 0.8);
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_ensemble_aggregation_strategies() -> Result<(), MetaSyntraXLError> {
+        let config = Config {
+            ensemble: EnsembleConfig {
+                member_weights: vec![1.0, 2.0, 1.0, 0.5, 0.5],
+                ..EnsembleConfig::default()
+            },
+            ..small_config(5)
+        };
+        let mut ensemble = build(&config)?;
+        let input = Tensor::of_slice(&[1i64, 2, 3, 4]).unsqueeze(0);
+
+        for aggregation in [
+            Aggregation::Mean,
+            Aggregation::WeightedMean,
+            Aggregation::MajorityVote,
+            Aggregation::SoftVote,
+            Aggregation::TrimmedMean(0.2),
+        ] {
+            let prediction = ensemble.predict(&input, aggregation).await?;
+            let size = prediction.output.size();
+            assert_eq!(size[size.len() - 1], config.vocab_size);
+            assert_eq!(prediction.entropy.size(), &size[..size.len() - 1]);
+            assert_eq!(prediction.variance.size(), &size[..size.len() - 1]);
+        }
+
+        // Uneven weights move the weighted mean away from the plain mean; even ones
+        // do not.
+        let mean = ensemble.predict(&input, Aggregation::Mean).await?.output;
+        let weighted = ensemble
+            .predict(&input, Aggregation::WeightedMean)
+            .await?
+            .output;
+        assert!((&weighted - &mean).abs().max().double_value(&[]) > 1e-6);
+        ensemble.set_model_weights(vec![3.0; config.num_models])?;
+        let even = ensemble
+            .predict(&input, Aggregation::WeightedMean)
+            .await?
+            .output;
+        assert!((even - mean).abs().max().double_value(&[]) < 1e-5);
+        Ok(())
+    }
+
+    fn values(tensor: &Tensor) -> Vec<f64> {
+        tensor
+            .to_kind(Kind::Double)
+            .flatten(0, -1)
+            .iter::<f64>()
+            .unwrap()
+            .collect()
+    }
+
+    fn assert_close(actual: &Tensor, expected: &[f64]) {
+        let actual = values(actual);
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    /// Five members' logits over a vocabulary of three at a single position.
+    fn member_logits() -> Tensor {
+        Tensor::of_slice(&[
+            1.0f32, 0.0, 0.0, //
+            2.0, 0.0, 0.0, //
+            3.0, 5.0, 0.0, //
+            -100.0, 1.0, 0.0, //
+            100.0, 0.0, 9.0,
+        ])
+        .view([5, 1, 3])
+    }
+
+    #[test]
+    fn test_aggregate_means() -> Result<(), MetaSyntraXLError> {
+        let stacked = member_logits();
+        let uniform = [1.0; 5];
+        assert_close(
+            &aggregate(&stacked, &uniform, Aggregation::Mean)?,
+            &[1.2, 1.2, 1.8],
+        );
+        assert_close(
+            &aggregate(&stacked, &[1.0, 1.0, 1.0, 0.0, 2.0], Aggregation::WeightedMean)?,
+            &[41.2, 1.0, 3.6],
+        );
+        assert!(aggregate(&stacked, &[0.0; 5], Aggregation::WeightedMean).is_err());
+
+        // One member is trimmed from each side of every element, so the outliers
+        // at -100 and 100 do not count.
+        assert_close(
+            &aggregate(&stacked, &uniform, Aggregation::TrimmedMean(0.2))?,
+            &[2.0, 1.0 / 3.0, 0.0],
+        );
+        assert_close(
+            &aggregate(&stacked, &uniform, Aggregation::TrimmedMean(0.0))?,
+            &[1.2, 1.2, 1.8],
+        );
+        for fraction in [0.5, -0.1] {
+            assert!(aggregate(&stacked, &uniform, Aggregation::TrimmedMean(fraction)).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate_votes() -> Result<(), MetaSyntraXLError> {
+        let stacked = member_logits();
+        let uniform = [1.0; 5];
+        assert_close(
+            &aggregate(&stacked, &uniform, Aggregation::MajorityVote)?,
+            &[0.6, 0.4, 0.0],
+        );
+
+        let softmax = |logits: [f64; 3]| {
+            let total: f64 = logits.iter().map(|l| l.exp()).sum();
+            logits.map(|l| l.exp() / total)
+        };
+        let mut expected = [0.0; 3];
+        for logits in [
+            [1.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [3.0, 5.0, 0.0],
+            [-100.0, 1.0, 0.0],
+            [100.0, 0.0, 9.0],
+        ] {
+            for (e, p) in expected.iter_mut().zip(softmax(logits)) {
+                *e += p / 5.0;
+            }
+        }
+        let soft = aggregate(&stacked, &uniform, Aggregation::SoftVote)?;
+        assert_close(&soft, &expected);
+        assert!((values(&soft).iter().sum::<f64>() - 1.0).abs() < 1e-6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_weights() {
+        assert!(validate_weights(&[1.0, 0.0, 2.5], 3).is_ok());
+        for (weights, message) in [
+            (vec![1.0, 1.0], "Expected 3 member weights, got 2"),
+            (vec![1.0, -1.0, 1.0], "non-negative"),
+            (vec![1.0, f64::NAN, 1.0], "non-negative"),
+            (vec![1.0, f64::INFINITY, 1.0], "non-negative"),
+            (vec![0.0, 0.0, 0.0], "positive sum"),
+        ] {
+            match validate_weights(&weights, 3) {
+                Err(MetaSyntraXLError::EnsembleError(error)) => {
+                    assert!(error.contains(message), "{}", error)
+                }
+                other => panic!("expected an error for {:?}, got {:?}", weights, other),
+            }
+        }
+    }
+    #[test]
+    fn test_ensemble_bootstrap_bagging_diversity() -> Result<(), MetaSyntraXLError> {
+        let config = Config {
+            vocab_size: 8,
+            max_len: 8,
+            ..small_config(4)
+        };
+        let diversity = MemberDiversity {
+            num_layers: vec![1, 2],
+            dropout: vec![0.0, 0.2],
+            seed: Some(7),
+        };
+        let vs = nn::VarStore::new(Device::Cpu);
+        let mut ensemble = Ensemble::with_diversity(
+            &vs.root(),
+            config.num_models,
+            config.input_size,
+            config.output_size,
+            &config,
+            &diversity,
+        )?;
+        let samples: Vec<(Tensor, Tensor)> = (0..10i64)
+            .map(|i| {
+                (
+                    Tensor::of_slice(&[1 + i % 7, 2, 3]).unsqueeze(0),
+                    Tensor::of_slice(&[i % 2]),
+                )
+            })
+            .collect();
+
+        let options = BaggingOptions {
+            epochs: 2,
+            seed: 42,
+            ..BaggingOptions::default()
+        };
+        let report = ensemble.fit_bagging(&samples, &options)?;
+        assert_eq!(report.bootstrap_indices.len(), config.num_models);
+        assert!(report
+            .bootstrap_indices
+            .iter()
+            .all(|draws| draws.len() == samples.len()));
+        assert_ne!(report.bootstrap_indices[0], report.bootstrap_indices[1]);
+        assert_eq!(
+            ensemble.fit_bagging(&samples, &options)?.bootstrap_indices,
+            report.bootstrap_indices
+        );
+
+        let inputs: Vec<Tensor> = samples
+            .iter()
+            .map(|(input, _)| input.shallow_clone())
+            .collect();
+        let diversity = ensemble.disagreement(&inputs);
+        assert_eq!(diversity.pairwise.len(), config.num_models);
+        assert!((0.0..=1.0).contains(&diversity.mean));
+        assert_eq!(diversity.pairwise[0][0], 0.0);
+        Ok(())
+    }
+    /// Replaces member `index` with one whose retrieval answers from `source`.
+    fn replace_member(
+        ensemble: &mut Ensemble,
+        index: usize,
+        config: &Config,
+        source: DocumentSource,
+    ) -> Result<(), MetaSyntraXLError> {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let retrieval_system = RetrievalSystem::with_source(config, source)?;
+        let model = TransformerRAG::with_retrieval_system(&vs.root(), config, retrieval_system)?;
+        ensemble.models[index] = Arc::new(model);
+        Ok(())
+    }
+
+    fn unavailable() -> DocumentSource {
+        Box::new(|_| {
+            async { Err(MetaSyntraXLError::RetrievalError("index unavailable".to_string())) }
+                .boxed()
+        })
+    }
+
+    fn stalled() -> DocumentSource {
+        Box::new(|_| {
+            async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok(Vec::new())
+            }
+            .boxed()
+        })
+    }
+
+    fn quorum_error(result: Result<BaggingPrediction, MetaSyntraXLError>) -> String {
+        match result {
+            Err(MetaSyntraXLError::EnsembleError(message)) => message,
+            other => panic!("expected a quorum error, got {:?}", other.map(|p| p.dropped)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ensemble_quorum() -> Result<(), MetaSyntraXLError> {
+        let config = |quorum: Option<usize>, member_timeout_ms: Option<u64>| Config {
+            ensemble: EnsembleConfig {
+                quorum,
+                member_timeout_ms,
+                ..EnsembleConfig::default()
+            },
+            ..small_config(3)
+        };
+        let input = Tensor::of_slice(&[1i64, 2, 3, 4]).unsqueeze(0);
+
+        match build(&config(Some(4), None)) {
+            Err(MetaSyntraXLError::EnsembleError(message)) => {
+                assert!(message.contains("Quorum 4 exceeds the number of members (3)"))
+            }
+            Err(other) => panic!("expected a quorum error, got {:?}", other),
+            Ok(_) => panic!("expected a quorum error"),
+        }
+
+        // Member 1 cannot retrieve its documents and member 2 never gets them.
+        let config = config(Some(1), Some(500));
+        let mut ensemble = build(&config)?;
+        replace_member(&mut ensemble, 1, &config, unavailable())?;
+        replace_member(&mut ensemble, 2, &config, stalled())?;
+
+        let prediction = ensemble.bagging_predict(&input).await?;
+        assert_eq!(prediction.dropped.len(), 2);
+        assert_eq!(prediction.dropped[0].model_index, 1);
+        match &prediction.dropped[0].reason {
+            DropReason::Failed(error) => assert!(error.contains("index unavailable"), "{}", error),
+            other => panic!("expected a failure, got {:?}", other),
+        }
+        assert_eq!(prediction.dropped[1].model_index, 2);
+        assert!(matches!(
+            prediction.dropped[1].reason,
+            DropReason::TimedOut(limit) if limit == Duration::from_millis(500)
+        ));
+        let prediction = ensemble.predict(&input, Aggregation::Mean).await?;
+        assert_eq!(
+            prediction
+                .dropped
+                .iter()
+                .map(|d| d.model_index)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        ensemble.quorum = 2;
+        let message = quorum_error(ensemble.bagging_predict(&input).await);
+        assert!(
+            message.starts_with("Only 1 of 3 members answered, quorum is 2: member 1 failed: "),
+            "{}",
+            message
+        );
+        assert!(message.ends_with("member 2 timed out after 500ms"), "{}", message);
+        Ok(())
     }
 }
\ No newline at end of file
@@ -35,4 +35,16 @@ pub enum MetaSyntraXLError {
 
     #[error("Tch error: {0}")]
     TchError(String),
+
+    #[error("Config error: {0}")]
+    ConfigError(String),
+
+    #[error("Bayesian network error: {0}")]
+    BayesianNetworkError(String),
+
+    #[error("Validation error: {0}")]
+    ValidationError(String),
+
+    #[error("Knowledge graph error: {0}")]
+    KnowledgeGraphError(String),
 }
\ No newline at end of file
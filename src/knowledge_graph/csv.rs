@@ -0,0 +1,333 @@
+// src/knowledge_graph/csv.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[KNOWLEDGE-GRAPH]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::{PropertyValue, Relationship};
+use crate::errors::MetaSyntraXLError;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, Write};
+
+/// How the columns of a CSV edge list map onto relationships. Every column but
+/// the endpoints and the type holds a relationship property.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub from_column: String,
+    pub to_column: String,
+    pub type_column: String,
+    /// Values of the type column and the relationship type each stands for;
+    /// other values are used as they are.
+    pub types: HashMap<String, String>,
+    /// Read unquoted property cells that parse as an integer, float, boolean,
+    /// RFC 3339 datetime or JSON list as one, rather than as strings. Quoted cells
+    /// stay strings unless they hold a JSON list.
+    pub infer_types: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            from_column: "from".to_string(),
+            to_column: "to".to_string(),
+            type_column: "type".to_string(),
+            types: HashMap::new(),
+            infer_types: true,
+        }
+    }
+}
+
+impl CsvOptions {
+    fn type_name(&self, type_: &str) -> String {
+        self.types
+            .iter()
+            .filter(|(_, t)| *t == type_)
+            .map(|(value, _)| value)
+            .min()
+            .cloned()
+            .unwrap_or_else(|| type_.to_string())
+    }
+}
+
+/// Reads the relationships of an edge list a record at a time. The first record
+/// names the columns; quoted fields may hold delimiters, doubled quotes and line
+/// breaks, and empty property cells are left out unless quoted.
+pub(crate) struct CsvReader<'a, R> {
+    reader: R,
+    options: &'a CsvOptions,
+    line: usize,
+    header: Option<Header>,
+}
+
+struct Field {
+    text: String,
+    quoted: bool,
+}
+
+struct Header {
+    names: Vec<String>,
+    from: usize,
+    to: usize,
+    type_: usize,
+}
+
+impl<'a, R: BufRead> CsvReader<'a, R> {
+    pub(crate) fn new(reader: R, options: &'a CsvOptions) -> Self {
+        Self {
+            reader,
+            options,
+            line: 0,
+            header: None,
+        }
+    }
+
+    /// The next non-empty record and the line it starts on.
+    fn record(&mut self) -> Result<Option<(usize, Vec<Field>)>, MetaSyntraXLError> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            if !line.trim_end_matches(['\r', '\n']).is_empty() {
+                break;
+            }
+        }
+        let start = self.line;
+        let mut chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        // Whether the current field opened with a quote.
+        let mut opened = false;
+        loop {
+            if i == chars.len() {
+                if !quoted {
+                    break;
+                }
+                line.clear();
+                if self.reader.read_line(&mut line)? == 0 {
+                    return Err(error(start, "unterminated quoted field"));
+                }
+                self.line += 1;
+                chars = line.chars().collect();
+                i = 0;
+                continue;
+            }
+            let c = chars[i];
+            i += 1;
+            if quoted {
+                if c != '"' {
+                    field.push(c);
+                } else if chars.get(i) == Some(&'"') {
+                    field.push('"');
+                    i += 1;
+                } else {
+                    quoted = false;
+                }
+            } else if c == '"' && field.is_empty() {
+                quoted = true;
+                opened = true;
+            } else if c == self.options.delimiter {
+                fields.push(Field {
+                    text: std::mem::take(&mut field),
+                    quoted: std::mem::take(&mut opened),
+                });
+            } else if c != '\r' && c != '\n' {
+                field.push(c);
+            }
+        }
+        fields.push(Field {
+            text: field,
+            quoted: opened,
+        });
+        Ok(Some((start, fields)))
+    }
+
+    fn header(&mut self) -> Result<Option<()>, MetaSyntraXLError> {
+        let (line, names): (usize, Vec<String>) = match self.record()? {
+            Some((line, fields)) => (line, fields.into_iter().map(|f| f.text).collect()),
+            None => return Ok(None),
+        };
+        let column = |name: &str| {
+            names
+                .iter()
+                .position(|n| n.trim() == name)
+                .ok_or_else(|| error(line, &format!("missing column `{}`", name)))
+        };
+        self.header = Some(Header {
+            from: column(&self.options.from_column)?,
+            to: column(&self.options.to_column)?,
+            type_: column(&self.options.type_column)?,
+            names: names.iter().map(|n| n.trim().to_string()).collect(),
+        });
+        Ok(Some(()))
+    }
+
+    fn relationship(&mut self) -> Result<Option<Relationship>, MetaSyntraXLError> {
+        if self.header.is_none() && self.header()?.is_none() {
+            return Ok(None);
+        }
+        let (line, fields) = match self.record()? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let header = self.header.as_ref().expect("the header is read first");
+        if fields.len() != header.names.len() {
+            return Err(error(
+                line,
+                &format!(
+                    "expected {} fields, found {}",
+                    header.names.len(),
+                    fields.len()
+                ),
+            ));
+        }
+        let required = |index: usize| {
+            let value = fields[index].text.trim();
+            if value.is_empty() {
+                Err(error(line, &format!("empty `{}`", header.names[index])))
+            } else {
+                Ok(value.to_string())
+            }
+        };
+        let type_ = required(header.type_)?;
+        let mut relationship = Relationship {
+            from: required(header.from)?,
+            to: required(header.to)?,
+            type_: self.options.types.get(&type_).cloned().unwrap_or(type_),
+            properties: HashMap::new(),
+        };
+        for (index, (name, field)) in header.names.iter().zip(&fields).enumerate() {
+            let cell = &field.text;
+            if (cell.is_empty() && !field.quoted)
+                || [header.from, header.to, header.type_].contains(&index)
+            {
+                continue;
+            }
+            let value = if self.options.infer_types && (!field.quoted || cell.starts_with('[')) {
+                infer(cell)
+            } else {
+                PropertyValue::String(cell.clone())
+            };
+            relationship.properties.insert(name.clone(), value);
+        }
+        Ok(Some(relationship))
+    }
+}
+
+impl<R: BufRead> Iterator for CsvReader<'_, R> {
+    type Item = Result<Relationship, MetaSyntraXLError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.relationship().transpose()
+    }
+}
+
+fn error(line: usize, message: &str) -> MetaSyntraXLError {
+    MetaSyntraXLError::KnowledgeGraphError(format!("CSV parse error at line {}: {}", line, message))
+}
+
+fn infer(cell: &str) -> PropertyValue {
+    match cell {
+        "true" => return PropertyValue::Bool(true),
+        "false" => return PropertyValue::Bool(false),
+        // As written for non-finite floats.
+        "NaN" => return PropertyValue::Float(f64::NAN),
+        "inf" => return PropertyValue::Float(f64::INFINITY),
+        "-inf" => return PropertyValue::Float(f64::NEG_INFINITY),
+        _ => {}
+    }
+    if let Ok(value) = cell.parse() {
+        return PropertyValue::Int(value);
+    }
+    let numeric = cell.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c))
+        && cell.contains(|c: char| c.is_ascii_digit());
+    if let (true, Ok(value)) = (numeric, cell.parse()) {
+        return PropertyValue::Float(value);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(cell) {
+        return PropertyValue::DateTime(time.with_timezone(&Utc));
+    }
+    if cell.starts_with('[') {
+        if let Ok(values) = serde_json::from_str(cell) {
+            return PropertyValue::List(values);
+        }
+    }
+    PropertyValue::String(cell.to_string())
+}
+
+/// The cell a value is written as, read back as the same value when types are
+/// inferred; floats keep their decimal point and lists are written as JSON.
+/// Strings are the exception: see [`write_csv`] for how they are kept apart.
+fn cell(value: &PropertyValue) -> String {
+    match value {
+        PropertyValue::Float(value) => format!("{:?}", value),
+        PropertyValue::DateTime(time) => time.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        PropertyValue::List(values) => {
+            serde_json::to_string(values).expect("property values serialize")
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Writes `relationships` as an edge list: the endpoint and type columns, then a
+/// column per property key in key order.
+///
+/// String properties that are empty or would be inferred as another value are
+/// quoted, so they read back as strings; a string holding a JSON list still reads
+/// back as the list.
+pub(crate) fn write_csv(
+    out: &mut impl Write,
+    relationships: &[&Relationship],
+    options: &CsvOptions,
+) -> Result<(), MetaSyntraXLError> {
+    let keys: BTreeSet<&String> = relationships
+        .iter()
+        .flat_map(|r| r.properties.keys())
+        .collect();
+    let quoted = |field: &str| format!("\"{}\"", field.replace('"', "\"\""));
+    let quote = |field: &str| {
+        let needs_quotes =
+            field.contains([options.delimiter, '"', '\n', '\r']) || field.trim() != field;
+        if needs_quotes {
+            quoted(field)
+        } else {
+            field.to_string()
+        }
+    };
+    let delimiter = options.delimiter.to_string();
+    let header: Vec<String> = [
+        &options.from_column,
+        &options.to_column,
+        &options.type_column,
+    ]
+    .into_iter()
+    .chain(keys.iter().copied())
+    .map(|name| quote(name))
+    .collect();
+    writeln!(out, "{}", header.join(&delimiter))?;
+    for relationship in relationships {
+        let mut row = vec![
+            quote(&relationship.from),
+            quote(&relationship.to),
+            quote(&options.type_name(&relationship.type_)),
+        ];
+        row.extend(keys.iter().map(|key| {
+            relationship
+                .properties
+                .get(*key)
+                .map(|value| match value {
+                    PropertyValue::String(text)
+                        if text.is_empty() || !matches!(infer(text), PropertyValue::String(_)) =>
+                    {
+                        quoted(text)
+                    }
+                    value => quote(&cell(value)),
+                })
+                .unwrap_or_default()
+        }));
+        writeln!(out, "{}", row.join(&delimiter))?;
+    }
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,344 @@
+// src/knowledge_graph/index.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[KNOWLEDGE-GRAPH]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::value::{IndexKey, Number};
+use super::{Entity, PropertyValue, Relationship};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
+
+/// Entities by id, with an inverted index from each property key and value to
+/// the ids of the entities holding it, and an ordered one over numeric values.
+#[derive(Debug, Default)]
+pub(crate) struct EntityTable {
+    entities: HashMap<String, Entity>,
+    by_property: HashMap<String, HashMap<IndexKey, HashSet<String>>>,
+    by_number: HashMap<String, BTreeMap<Number, HashSet<String>>>,
+}
+
+impl EntityTable {
+    pub(crate) fn new(entities: HashMap<String, Entity>) -> Self {
+        let mut table = Self::default();
+        for entity in entities.into_values() {
+            table.insert(entity);
+        }
+        table
+    }
+
+    pub(crate) fn entities(&self) -> &HashMap<String, Entity> {
+        &self.entities
+    }
+
+    pub(crate) fn get(&self, id: &str) -> Option<&Entity> {
+        self.entities.get(id)
+    }
+
+    /// Inserts `entity`, replacing and unindexing any entity with the same id.
+    pub(crate) fn insert(&mut self, entity: Entity) {
+        self.remove(&entity.id);
+        for (key, value) in &entity.properties {
+            self.by_property
+                .entry(key.clone())
+                .or_default()
+                .entry(IndexKey::from(value))
+                .or_default()
+                .insert(entity.id.clone());
+            if let Some(number) = indexed_number(value) {
+                self.by_number
+                    .entry(key.clone())
+                    .or_default()
+                    .entry(number)
+                    .or_default()
+                    .insert(entity.id.clone());
+            }
+        }
+        self.entities.insert(entity.id.clone(), entity);
+    }
+
+    /// Removes and unindexes the entity with id `id`.
+    pub(crate) fn remove(&mut self, id: &str) -> Option<Entity> {
+        let removed = self.entities.remove(id)?;
+        for (key, value) in &removed.properties {
+            let values = self.by_property.get_mut(key).expect("indexed key");
+            let value_key = IndexKey::from(value);
+            let ids = values.get_mut(&value_key).expect("indexed value");
+            ids.remove(&removed.id);
+            if ids.is_empty() {
+                values.remove(&value_key);
+            }
+            if values.is_empty() {
+                self.by_property.remove(key);
+            }
+
+            if let Some(number) = indexed_number(value) {
+                let numbers = self.by_number.get_mut(key).expect("indexed key");
+                let ids = numbers.get_mut(&number).expect("indexed number");
+                ids.remove(&removed.id);
+                if ids.is_empty() {
+                    numbers.remove(&number);
+                }
+                if numbers.is_empty() {
+                    self.by_number.remove(key);
+                }
+            }
+        }
+        Some(removed)
+    }
+
+    /// The entities whose property `key` is `value`.
+    pub(crate) fn with_property<'a>(
+        &'a self,
+        key: &str,
+        value: &PropertyValue,
+    ) -> impl Iterator<Item = &'a Entity> + 'a {
+        self.by_property
+            .get(key)
+            .and_then(|values| values.get(&IndexKey::from(value)))
+            .into_iter()
+            .flatten()
+            .map(move |id| &self.entities[id])
+    }
+
+    /// The entities whose property `key` is a number within `range`, by
+    /// increasing value and then by id.
+    pub(crate) fn in_range(&self, key: &str, range: (Bound<f64>, Bound<f64>)) -> Vec<&Entity> {
+        let before =
+            |start: f64, end: f64, inclusive: bool| start < end || (inclusive && start == end);
+        let empty = match range {
+            (Bound::Included(start), Bound::Included(end)) => !before(start, end, true),
+            (
+                Bound::Included(start) | Bound::Excluded(start),
+                Bound::Included(end) | Bound::Excluded(end),
+            ) => !before(start, end, false),
+            (Bound::Included(bound) | Bound::Excluded(bound), Bound::Unbounded)
+            | (Bound::Unbounded, Bound::Included(bound) | Bound::Excluded(bound)) => bound.is_nan(),
+            (Bound::Unbounded, Bound::Unbounded) => false,
+        };
+        let numbers = match self.by_number.get(key) {
+            Some(numbers) if !empty => numbers,
+            _ => return Vec::new(),
+        };
+        let mut entities = Vec::new();
+        for ids in numbers
+            .range((number_bound(range.0), number_bound(range.1)))
+            .map(|(_, ids)| ids)
+        {
+            let start = entities.len();
+            entities.extend(ids.iter().map(|id| &self.entities[id]));
+            entities[start..].sort_by(|a: &&Entity, b| a.id.cmp(&b.id));
+        }
+        entities
+    }
+}
+
+fn number_bound(bound: Bound<f64>) -> Bound<Number> {
+    match bound {
+        Bound::Included(value) => Bound::Included(Number(value)),
+        Bound::Excluded(value) => Bound::Excluded(Number(value)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// The number `value` is filed under in the range index, if any.
+fn indexed_number(value: &PropertyValue) -> Option<Number> {
+    value.as_f64().filter(|n| !n.is_nan()).map(Number)
+}
+
+/// Relationships in insertion order, with adjacency lists by source and by target,
+/// each keyed by relationship type, and positions by type.
+///
+/// Removing a relationship leaves a gap at its position, so the positions held
+/// by the indexes stay valid; the gaps are closed once they outnumber the
+/// relationships.
+#[derive(Debug, Default)]
+pub(crate) struct RelationshipTable {
+    relationships: Vec<Option<Relationship>>,
+    removed: usize,
+    outgoing: HashMap<String, HashMap<String, Vec<usize>>>,
+    incoming: HashMap<String, HashMap<String, Vec<usize>>>,
+    by_type: HashMap<String, BTreeSet<usize>>,
+}
+
+/// Gaps that are never worth closing.
+const MIN_COMPACTION: usize = 1024;
+
+impl RelationshipTable {
+    pub(crate) fn new(relationships: Vec<Relationship>) -> Self {
+        let mut table = Self::default();
+        for relationship in relationships {
+            table.push(relationship);
+        }
+        table
+    }
+
+    /// The relationships in insertion order.
+    pub(crate) fn relationships(&self) -> impl Iterator<Item = &Relationship> {
+        self.relationships.iter().flatten()
+    }
+
+    pub(crate) fn push(&mut self, relationship: Relationship) {
+        let position = self.relationships.len();
+        for (endpoint, adjacency) in [
+            (&relationship.from, &mut self.outgoing),
+            (&relationship.to, &mut self.incoming),
+        ] {
+            adjacency
+                .entry(endpoint.clone())
+                .or_default()
+                .entry(relationship.type_.clone())
+                .or_default()
+                .push(position);
+        }
+        self.by_type
+            .entry(relationship.type_.clone())
+            .or_default()
+            .insert(position);
+        self.relationships.push(Some(relationship));
+    }
+
+    /// Whether a relationship with the `from`, `to` and `type_` of `relationship`
+    /// is in the table, whatever its properties.
+    pub(crate) fn connects(&self, relationship: &Relationship) -> bool {
+        self.outgoing(&relationship.from, &relationship.type_)
+            .any(|r| r.to == relationship.to)
+    }
+
+    /// Whether a relationship equal to `relationship` is in the table.
+    pub(crate) fn contains(&self, relationship: &Relationship) -> bool {
+        self.outgoing(&relationship.from, &relationship.type_)
+            .any(|r| r == relationship)
+    }
+
+    /// Removes every relationship equal to `relationship`, returning how many.
+    pub(crate) fn remove(&mut self, relationship: &Relationship) -> usize {
+        let positions: Vec<usize> = lookup(&self.outgoing, &relationship.from, &relationship.type_)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|&p| self.relationships[p].as_ref() == Some(relationship))
+            .collect();
+        self.remove_positions(positions)
+    }
+
+    /// Removes every relationship leaving or arriving at `entity`, returning how many.
+    pub(crate) fn remove_touching(&mut self, entity: &str) -> usize {
+        let mut positions: Vec<usize> = [&self.outgoing, &self.incoming]
+            .into_iter()
+            .filter_map(|adjacency| adjacency.get(entity))
+            .flat_map(|types| types.values().flatten().copied())
+            .collect();
+        positions.sort_unstable();
+        // A relationship from `entity` to itself is in both lists.
+        positions.dedup();
+        self.remove_positions(positions)
+    }
+
+    fn remove_positions(&mut self, positions: Vec<usize>) -> usize {
+        let count = positions.len();
+        for position in positions {
+            let relationship = self.relationships[position]
+                .take()
+                .expect("indexed positions hold relationships");
+            for (endpoint, adjacency) in [
+                (&relationship.from, &mut self.outgoing),
+                (&relationship.to, &mut self.incoming),
+            ] {
+                let types = adjacency.get_mut(endpoint).expect("indexed endpoint");
+                let list = types.get_mut(&relationship.type_).expect("indexed type");
+                // Positions are pushed in increasing order, so every list is sorted.
+                let index = list.binary_search(&position).expect("indexed position");
+                list.remove(index);
+                if list.is_empty() {
+                    types.remove(&relationship.type_);
+                }
+                if types.is_empty() {
+                    adjacency.remove(endpoint);
+                }
+            }
+            let positions = self
+                .by_type
+                .get_mut(&relationship.type_)
+                .expect("indexed type");
+            positions.remove(&position);
+            if positions.is_empty() {
+                self.by_type.remove(&relationship.type_);
+            }
+        }
+        self.removed += count;
+        if self.removed > MIN_COMPACTION && self.removed * 2 > self.relationships.len() {
+            let relationships = std::mem::take(&mut self.relationships);
+            *self = Self::new(relationships.into_iter().flatten().collect());
+        }
+        count
+    }
+
+    /// The relationships of type `type_` leaving `from`, in insertion order.
+    pub(crate) fn outgoing<'a>(
+        &'a self,
+        from: &str,
+        type_: &str,
+    ) -> impl Iterator<Item = &'a Relationship> + 'a {
+        self.resolve(lookup(&self.outgoing, from, type_).into_iter().flatten())
+    }
+
+    /// The relationships of type `type_` arriving at `to`, in insertion order.
+    pub(crate) fn incoming<'a>(
+        &'a self,
+        to: &str,
+        type_: &str,
+    ) -> impl Iterator<Item = &'a Relationship> + 'a {
+        self.resolve(lookup(&self.incoming, to, type_).into_iter().flatten())
+    }
+
+    pub(crate) fn with_type<'a>(
+        &'a self,
+        type_: &str,
+    ) -> impl Iterator<Item = &'a Relationship> + 'a {
+        self.resolve(self.by_type.get(type_).into_iter().flatten())
+    }
+
+    /// The relationships leaving (`outgoing`) or arriving at `entity` whose type is
+    /// in `types`, or of any type if `types` is empty, in insertion order.
+    pub(crate) fn adjacent(
+        &self,
+        entity: &str,
+        outgoing: bool,
+        types: &[String],
+    ) -> Vec<&Relationship> {
+        let adjacency = if outgoing {
+            &self.outgoing
+        } else {
+            &self.incoming
+        };
+        let mut positions: Vec<usize> = adjacency
+            .get(entity)
+            .into_iter()
+            .flatten()
+            .filter(|(type_, _)| types.is_empty() || types.contains(type_))
+            .flat_map(|(_, positions)| positions.iter().copied())
+            .collect();
+        positions.sort_unstable();
+        positions
+            .into_iter()
+            .map(|position| self.at(position))
+            .collect()
+    }
+
+    fn at(&self, position: usize) -> &Relationship {
+        self.relationships[position]
+            .as_ref()
+            .expect("indexed positions hold relationships")
+    }
+
+    fn resolve<'a>(
+        &'a self,
+        positions: impl Iterator<Item = &'a usize> + 'a,
+    ) -> impl Iterator<Item = &'a Relationship> + 'a {
+        positions.map(move |&position| self.at(position))
+    }
+}
+
+fn lookup<'a>(
+    adjacency: &'a HashMap<String, HashMap<String, Vec<usize>>>,
+    endpoint: &str,
+    type_: &str,
+) -> Option<&'a Vec<usize>> {
+    adjacency.get(endpoint).and_then(|types| types.get(type_))
+}
\ No newline at end of file
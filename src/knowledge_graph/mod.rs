@@ -0,0 +1,2749 @@
+// src/knowledge_graph/mod.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[KNOWLEDGE-GRAPH]Xyn>=====S===t===u====d===i===o===s====[R|$>
+mod csv;
+mod index;
+mod plan;
+mod query;
+mod rdf;
+mod storage;
+mod traversal;
+mod value;
+
+pub use csv::CsvOptions;
+pub use plan::{QueryResult, QueryValue};
+pub use rdf::Vocabulary;
+pub use storage::{
+    GraphOperation, GraphState, GraphStorage, LogStorage, LogStorageOptions, MemoryStorage,
+};
+pub use traversal::{Direction, GraphPath, Subgraph, TraversalOptions, Visit};
+pub use value::PropertyValue;
+
+use crate::errors::MetaSyntraXLError;
+use csv::CsvReader;
+use index::{EntityTable, RelationshipTable};
+use plan::Plan;
+use rdf::{Statement, TurtleReader};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Read, Write};
+use std::ops::RangeBounds;
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+use traversal::Graph;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Entity {
+    pub id: String,
+    pub properties: HashMap<String, PropertyValue>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Relationship {
+    pub from: String,
+    pub to: String,
+    pub type_: String,
+    /// E.g. a weight, confidence, timestamp or provenance.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub properties: HashMap<String, PropertyValue>,
+}
+
+/// Rules every change to a [`KnowledgeGraph`] must keep; none by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphConstraints {
+    /// Reject relationships whose `from` or `to` is not an entity of the graph.
+    pub referential_integrity: bool,
+    /// Reject a relationship equal to one already in the graph, i.e. with the
+    /// same `from`, `to` and `type_`.
+    pub unique_relationships: bool,
+}
+
+// Locks are always taken in field order, relationships before entities before
+// storage, so readers and writers cannot deadlock. The storage is only locked on
+// the blocking thread pool, where its I/O cannot stall the async executor.
+pub struct KnowledgeGraph {
+    constraints: GraphConstraints,
+    relationships: RwLock<RelationshipTable>,
+    entities: RwLock<EntityTable>,
+    storage: Arc<Mutex<Box<dyn GraphStorage>>>,
+}
+
+impl Default for KnowledgeGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KnowledgeGraph {
+    /// Initializes a new, empty Knowledge Graph that lives only in memory.
+    pub fn new() -> Self {
+        Self {
+            constraints: GraphConstraints::default(),
+            relationships: RwLock::new(RelationshipTable::default()),
+            entities: RwLock::new(EntityTable::default()),
+            storage: Arc::new(Mutex::new(Box::new(MemoryStorage))),
+        }
+    }
+
+    /// Opens the Knowledge Graph kept in `storage`, e.g. a [`LogStorage`]; every
+    /// later change is recorded there before it becomes visible. Loading blocks the
+    /// calling thread.
+    ///
+    /// # Errors
+    ///
+    /// Returns the storage's error if its contents cannot be loaded.
+    pub fn with_storage(mut storage: Box<dyn GraphStorage>) -> Result<Self, MetaSyntraXLError> {
+        let state = storage.load()?;
+        Ok(Self {
+            constraints: GraphConstraints::default(),
+            relationships: RwLock::new(RelationshipTable::new(state.relationships)),
+            entities: RwLock::new(EntityTable::new(state.entities)),
+            storage: Arc::new(Mutex::new(storage)),
+        })
+    }
+
+    /// Enforces `constraints` on every later change.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KnowledgeGraphError` naming a relationship of the current contents
+    /// that breaks `constraints`.
+    pub fn with_constraints(
+        mut self,
+        constraints: GraphConstraints,
+    ) -> Result<Self, MetaSyntraXLError> {
+        let relationships = self.relationships.get_mut();
+        let entities = self.entities.get_mut();
+        let mut seen = HashSet::new();
+        for relationship in relationships.relationships() {
+            check_references(&constraints, entities, relationship)?;
+            let key = (&relationship.from, &relationship.to, &relationship.type_);
+            if constraints.unique_relationships && !seen.insert(key) {
+                return Err(duplicate(relationship));
+            }
+        }
+        self.constraints = constraints;
+        Ok(self)
+    }
+
+    /// Adds a new entity to the Knowledge Graph, replacing any entity with the
+    /// same id.
+    ///
+    /// # Errors
+    ///
+    /// Returns the storage's error if the entity cannot be recorded; the graph is
+    /// left unchanged.
+    pub async fn add_entity(&self, entity: Entity) -> Result<(), MetaSyntraXLError> {
+        let mut entities = self.entities.write().await;
+        let operation = GraphOperation::AddEntity {
+            entity: entity.clone(),
+        };
+        self.record(operation).await?;
+        entities.insert(entity);
+        drop(entities);
+        self.snapshot_if_needed().await
+    }
+
+    /// Sets the properties of an existing entity to whatever `update` leaves in
+    /// them, and returns the updated entity.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KnowledgeGraphError` if there is no entity with id `id`, or the
+    /// storage's error if the update cannot be recorded.
+    pub async fn update_entity(
+        &self,
+        id: &str,
+        update: impl FnOnce(&mut HashMap<String, PropertyValue>),
+    ) -> Result<Entity, MetaSyntraXLError> {
+        let mut entities = self.entities.write().await;
+        let mut entity = entities.get(id).cloned().ok_or_else(|| {
+            MetaSyntraXLError::KnowledgeGraphError(format!("Entity {} does not exist", id))
+        })?;
+        update(&mut entity.properties);
+        let operation = GraphOperation::AddEntity {
+            entity: entity.clone(),
+        };
+        self.record(operation).await?;
+        entities.insert(entity.clone());
+        drop(entities);
+        self.snapshot_if_needed().await?;
+        Ok(entity)
+    }
+
+    /// Adds `entity`, or, if an entity with its id exists, sets the properties of
+    /// `entity` on it and keeps the others. Returns the entity as stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns the storage's error if the entity cannot be recorded; the graph is
+    /// left unchanged.
+    pub async fn merge_entity(&self, entity: Entity) -> Result<Entity, MetaSyntraXLError> {
+        let mut entities = self.entities.write().await;
+        let merged = match entities.get(&entity.id) {
+            Some(existing) => {
+                let mut merged = existing.clone();
+                merged.properties.extend(entity.properties);
+                merged
+            }
+            None => entity,
+        };
+        let operation = GraphOperation::AddEntity {
+            entity: merged.clone(),
+        };
+        self.record(operation).await?;
+        entities.insert(merged.clone());
+        drop(entities);
+        self.snapshot_if_needed().await?;
+        Ok(merged)
+    }
+
+    /// Removes the entity with id `id` together with every relationship leaving or
+    /// arriving at it, and returns the entity if there was one.
+    ///
+    /// # Errors
+    ///
+    /// Returns the storage's error if the removal cannot be recorded; the graph is
+    /// left unchanged.
+    pub async fn remove_entity(&self, id: &str) -> Result<Option<Entity>, MetaSyntraXLError> {
+        let mut relationships = self.relationships.write().await;
+        let mut entities = self.entities.write().await;
+        if entities.get(id).is_none()
+            && relationships.adjacent(id, true, &[]).is_empty()
+            && relationships.adjacent(id, false, &[]).is_empty()
+        {
+            return Ok(None);
+        }
+        let operation = GraphOperation::RemoveEntity { id: id.to_string() };
+        self.record(operation).await?;
+        relationships.remove_touching(id);
+        let removed = entities.remove(id);
+        drop(entities);
+        drop(relationships);
+        self.snapshot_if_needed().await?;
+        Ok(removed)
+    }
+
+    /// Adds a new relationship to the Knowledge Graph.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KnowledgeGraphError` if the relationship breaks the graph's
+    /// [`GraphConstraints`], or the storage's error if it cannot be recorded; the
+    /// graph is left unchanged.
+    pub async fn add_relationship(
+        &self,
+        relationship: Relationship,
+    ) -> Result<(), MetaSyntraXLError> {
+        let mut relationships = self.relationships.write().await;
+        let entities = self.entities.read().await;
+        check_references(&self.constraints, &entities, &relationship)?;
+        if self.constraints.unique_relationships && relationships.connects(&relationship) {
+            return Err(duplicate(&relationship));
+        }
+        let operation = GraphOperation::AddRelationship {
+            relationship: relationship.clone(),
+        };
+        self.record(operation).await?;
+        relationships.push(relationship);
+        drop(entities);
+        drop(relationships);
+        self.snapshot_if_needed().await
+    }
+
+    /// Adds `relationship` unless one with the same `from`, `to` and `type_`
+    /// exists, whatever its properties; returns whether it was added.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KnowledgeGraphError` if the relationship breaks the graph's
+    /// referential integrity, or the storage's error if it cannot be recorded.
+    pub async fn merge_relationship(
+        &self,
+        relationship: Relationship,
+    ) -> Result<bool, MetaSyntraXLError> {
+        let mut relationships = self.relationships.write().await;
+        if relationships.connects(&relationship) {
+            return Ok(false);
+        }
+        let entities = self.entities.read().await;
+        check_references(&self.constraints, &entities, &relationship)?;
+        let operation = GraphOperation::AddRelationship {
+            relationship: relationship.clone(),
+        };
+        self.record(operation).await?;
+        relationships.push(relationship);
+        drop(entities);
+        drop(relationships);
+        self.snapshot_if_needed().await?;
+        Ok(true)
+    }
+
+    /// Removes every relationship equal to `relationship`, returning how many.
+    ///
+    /// # Errors
+    ///
+    /// Returns the storage's error if the removal cannot be recorded; the graph is
+    /// left unchanged.
+    pub async fn remove_relationship(
+        &self,
+        relationship: &Relationship,
+    ) -> Result<usize, MetaSyntraXLError> {
+        let mut relationships = self.relationships.write().await;
+        if !relationships.contains(relationship) {
+            return Ok(0);
+        }
+        let operation = GraphOperation::RemoveRelationship {
+            relationship: relationship.clone(),
+        };
+        self.record(operation).await?;
+        let removed = relationships.remove(relationship);
+        drop(relationships);
+        self.snapshot_if_needed().await?;
+        Ok(removed)
+    }
+
+    /// Removes every relationship whose `from` or `to` is not an entity of the
+    /// graph, returning how many.
+    ///
+    /// # Errors
+    ///
+    /// Returns the storage's error if a removal cannot be recorded; the
+    /// relationships removed before it stay removed.
+    pub async fn remove_dangling_relationships(&self) -> Result<usize, MetaSyntraXLError> {
+        let mut relationships = self.relationships.write().await;
+        let entities = self.entities.read().await;
+        let mut dangling: Vec<Relationship> = Vec::new();
+        for relationship in relationships.relationships() {
+            let missing = entities.get(&relationship.from).is_none()
+                || entities.get(&relationship.to).is_none();
+            if missing && !dangling.contains(relationship) {
+                dangling.push(relationship.clone());
+            }
+        }
+        let operations = dangling
+            .into_iter()
+            .map(|relationship| GraphOperation::RemoveRelationship { relationship })
+            .collect();
+        let (recorded, result) = self.record_all(operations).await;
+        let mut removed = 0;
+        for operation in recorded {
+            if let GraphOperation::RemoveRelationship { relationship } = operation {
+                removed += relationships.remove(&relationship);
+            }
+        }
+        drop(entities);
+        drop(relationships);
+        result?;
+        self.snapshot_if_needed().await?;
+        Ok(removed)
+    }
+
+    /// Compacts the storage into a snapshot of the current contents.
+    ///
+    /// # Errors
+    ///
+    /// Returns the storage's error if the snapshot cannot be written.
+    pub async fn snapshot(&self) -> Result<(), MetaSyntraXLError> {
+        let relationships = self.relationships.read().await;
+        let entities = self.entities.read().await;
+        let state = GraphState {
+            entities: entities.entities().clone(),
+            relationships: relationships.relationships().cloned().collect(),
+        };
+        self.on_storage(move |storage| storage.snapshot(&state))
+            .await
+    }
+
+    async fn snapshot_if_needed(&self) -> Result<(), MetaSyntraXLError> {
+        let needed = self
+            .on_storage(|storage| Ok(storage.needs_snapshot()))
+            .await?;
+        if needed {
+            self.snapshot().await?;
+        }
+        Ok(())
+    }
+
+    /// Runs `f` on the storage on the blocking thread pool.
+    async fn on_storage<T, F>(&self, f: F) -> Result<T, MetaSyntraXLError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut dyn GraphStorage) -> Result<T, MetaSyntraXLError> + Send + 'static,
+    {
+        let storage = Arc::clone(&self.storage);
+        tokio::task::spawn_blocking(move || {
+            let mut storage = storage.lock().map_err(|_| {
+                MetaSyntraXLError::KnowledgeGraphError(
+                    "Storage is unusable after a panic".to_string(),
+                )
+            })?;
+            f(storage.as_mut())
+        })
+        .await?
+    }
+
+    async fn record(&self, operation: GraphOperation) -> Result<(), MetaSyntraXLError> {
+        self.on_storage(move |storage| storage.append(&operation))
+            .await
+    }
+
+    /// Records `operations` in order, returning those recorded before the first
+    /// error along with the outcome.
+    async fn record_all(
+        &self,
+        operations: Vec<GraphOperation>,
+    ) -> (Vec<GraphOperation>, Result<(), MetaSyntraXLError>) {
+        let recorded = self
+            .on_storage(move |storage| {
+                let mut operations = operations;
+                for (i, operation) in operations.iter().enumerate() {
+                    if let Err(e) = storage.append(operation) {
+                        operations.truncate(i);
+                        return Ok((operations, Err(e)));
+                    }
+                }
+                Ok((operations, Ok(())))
+            })
+            .await;
+        recorded.unwrap_or_else(|e| (Vec::new(), Err(e)))
+    }
+
+    /// Queries entities based on a property value; values of different types
+    /// never match, so `3` finds neither `3.0` nor `"3"`.
+    pub async fn query_entities_by_property(
+        &self,
+        key: &str,
+        value: impl Into<PropertyValue>,
+    ) -> Vec<Entity> {
+        let entities = self.entities.read().await;
+        entities
+            .with_property(key, &value.into())
+            .cloned()
+            .collect()
+    }
+
+    /// Queries entities whose property `key` is an integer or float within
+    /// `range`, by increasing value and then by id. Integers are compared as
+    /// floats, so beyond 2^53 they are rounded.
+    pub async fn query_entities_by_range(
+        &self,
+        key: &str,
+        range: impl RangeBounds<f64>,
+    ) -> Vec<Entity> {
+        let entities = self.entities.read().await;
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        entities.in_range(key, range).into_iter().cloned().collect()
+    }
+
+    /// Retrieves all relationships of a specific type.
+    pub async fn get_relationships_by_type(&self, type_: &str) -> Vec<Relationship> {
+        let relationships = self.relationships.read().await;
+        relationships.with_type(type_).cloned().collect()
+    }
+
+    /// Retrieves the relationships of a specific type leaving a given entity.
+    pub async fn get_relationships_from(&self, entity_id: &str, type_: &str) -> Vec<Relationship> {
+        let relationships = self.relationships.read().await;
+        relationships.outgoing(entity_id, type_).cloned().collect()
+    }
+
+    /// Retrieves the relationships of a specific type arriving at a given entity.
+    pub async fn get_relationships_to(&self, entity_id: &str, type_: &str) -> Vec<Relationship> {
+        let relationships = self.relationships.read().await;
+        relationships.incoming(entity_id, type_).cloned().collect()
+    }
+
+    /// Retrieves all entities connected to a given entity via a specific relationship type.
+    pub async fn get_connected_entities(
+        &self,
+        entity_id: &str,
+        relationship_type: &str,
+    ) -> Vec<Entity> {
+        let relationships = self.relationships.read().await;
+        let entities = self.entities.read().await;
+
+        relationships
+            .outgoing(entity_id, relationship_type)
+            .filter_map(|r| entities.get(&r.to).cloned())
+            .collect()
+    }
+
+    /// Retrieves all entities with a relationship of a specific type to a given entity.
+    pub async fn get_incoming_entities(
+        &self,
+        entity_id: &str,
+        relationship_type: &str,
+    ) -> Vec<Entity> {
+        let relationships = self.relationships.read().await;
+        let entities = self.entities.read().await;
+
+        relationships
+            .incoming(entity_id, relationship_type)
+            .filter_map(|r| entities.get(&r.from).cloned())
+            .collect()
+    }
+
+    /// Visits the entities reachable from `start` in breadth-first order, each once
+    /// and at its smallest depth, up to `max_depth` hops (`None` for no limit).
+    /// Relationships to entities that are not in the graph are not followed.
+    pub async fn breadth_first(
+        &self,
+        start: &str,
+        max_depth: Option<usize>,
+        options: &TraversalOptions,
+    ) -> Vec<Visit> {
+        let relationships = self.relationships.read().await;
+        let entities = self.entities.read().await;
+        Graph {
+            relationships: &relationships,
+            entities: &entities,
+        }
+        .breadth_first(start, max_depth, options)
+    }
+
+    /// Like [`KnowledgeGraph::breadth_first`], in depth-first preorder. Each entity
+    /// is visited once, at the depth of the first path that reaches it.
+    pub async fn depth_first(
+        &self,
+        start: &str,
+        max_depth: Option<usize>,
+        options: &TraversalOptions,
+    ) -> Vec<Visit> {
+        let relationships = self.relationships.read().await;
+        let entities = self.entities.read().await;
+        Graph {
+            relationships: &relationships,
+            entities: &entities,
+        }
+        .depth_first(start, max_depth, options)
+    }
+
+    /// A path from `from` to `to` with the fewest relationships, or `None` if `to`
+    /// cannot be reached.
+    pub async fn shortest_path(
+        &self,
+        from: &str,
+        to: &str,
+        options: &TraversalOptions,
+    ) -> Option<GraphPath> {
+        let relationships = self.relationships.read().await;
+        let entities = self.entities.read().await;
+        Graph {
+            relationships: &relationships,
+            entities: &entities,
+        }
+        .shortest_path(from, to, options)
+    }
+
+    /// A path from `from` to `to` with the least total `weight`, or `None` if `to`
+    /// cannot be reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KnowledgeGraphError` if a relationship explored on the way has a
+    /// negative or non-finite weight.
+    pub async fn weighted_shortest_path(
+        &self,
+        from: &str,
+        to: &str,
+        options: &TraversalOptions,
+        weight: impl Fn(&Relationship) -> f64,
+    ) -> Result<Option<GraphPath>, MetaSyntraXLError> {
+        let relationships = self.relationships.read().await;
+        let entities = self.entities.read().await;
+        Graph {
+            relationships: &relationships,
+            entities: &entities,
+        }
+        .weighted_shortest_path(from, to, options, weight)
+    }
+
+    /// Every path from `from` to `to` with at most `max_length` relationships that
+    /// visits no entity twice. The number of paths can grow exponentially with
+    /// `max_length`.
+    pub async fn all_paths(
+        &self,
+        from: &str,
+        to: &str,
+        max_length: usize,
+        options: &TraversalOptions,
+    ) -> Vec<GraphPath> {
+        let relationships = self.relationships.read().await;
+        let entities = self.entities.read().await;
+        Graph {
+            relationships: &relationships,
+            entities: &entities,
+        }
+        .all_paths(from, to, max_length, options)
+    }
+
+    /// The entities within `radius` hops of `center`, in breadth-first order, and
+    /// the relationships of the followed types among them.
+    pub async fn neighborhood(
+        &self,
+        center: &str,
+        radius: usize,
+        options: &TraversalOptions,
+    ) -> Subgraph {
+        let relationships = self.relationships.read().await;
+        let entities = self.entities.read().await;
+        Graph {
+            relationships: &relationships,
+            entities: &entities,
+        }
+        .neighborhood(center, radius, options)
+    }
+
+    /// Runs a query written in a small Cypher-like language:
+    ///
+    /// ```text
+    /// [MATCH] pattern {, pattern} [WHERE condition] RETURN item {, item} [LIMIT n]
+    /// pattern      = node {relationship node}
+    /// node         = ( [variable] [{key: literal, ...}] )
+    /// relationship = -[[variable][:type{|type}]]-> | <-[...]- | -[...]-
+    /// condition    = operand (= | <> | != | < | <= | > | >= | CONTAINS
+    ///                | STARTS WITH | ENDS WITH) operand | operand IS [NOT] NULL
+    ///                | NOT condition | condition AND condition
+    ///                | condition OR condition | ( condition )
+    /// operand      = variable.key | "string" | 'string' | number | true | false | null
+    /// item         = variable [AS alias] | variable.key [AS alias]
+    /// ```
+    ///
+    /// For example, `MATCH (a {type: "person"})-[:friend]->(b) WHERE b.name = "Alice"
+    /// RETURN a.name`. Keywords are case-insensitive. `id` is an entity's id, and
+    /// relationships have `from`, `to` and `type` besides their properties. Values
+    /// of different types are never equal, except that integers and floats compare
+    /// as numbers and a string compares with a datetime as the RFC 3339 datetime it
+    /// spells; `CONTAINS` also finds an element of a list. A relationship is
+    /// matched at most once per row, and rows come in a deterministic order.
+    ///
+    /// Each pattern starts from a node that an earlier pattern bound, or has a
+    /// known `id`, or a known property value (inline or an equality joined to the
+    /// `WHERE` clause by `AND`), which is found through the property index; only
+    /// otherwise are all entities scanned. The pattern is then matched along the
+    /// adjacency lists. [`KnowledgeGraph::explain`] shows the plan.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KnowledgeGraphError` with the line and column of the problem if
+    /// the query does not parse, or naming the variable if it uses an unbound one.
+    pub async fn query(&self, text: &str) -> Result<QueryResult, MetaSyntraXLError> {
+        let query = query::parse(text)?;
+        let relationships = self.relationships.read().await;
+        let entities = self.entities.read().await;
+        Ok(Plan::new(&query).execute(&Graph {
+            relationships: &relationships,
+            entities: &entities,
+        }))
+    }
+
+    /// The steps [`KnowledgeGraph::query`] would take to run `text`, one per line.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KnowledgeGraphError` if the query is invalid.
+    pub fn explain(text: &str) -> Result<Vec<String>, MetaSyntraXLError> {
+        let query = query::parse(text)?;
+        Ok(Plan::new(&query).describe())
+    }
+
+    /// Imports an N-Triples document, read a line at a time, and returns the
+    /// number of triples in it.
+    ///
+    /// A triple whose object is a literal sets a property of its subject, typed by
+    /// the literal's XSD datatype; further values for the same property make it a
+    /// list. Any other triple adds a relationship. Subjects and objects become
+    /// entities, created without properties if they do not exist, and
+    /// `vocabulary` names them and the predicates. Triples already in the graph
+    /// are skipped, so importing a document twice changes nothing, except that
+    /// anonymous blank nodes are new nodes on every import.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KnowledgeGraphError` with the line and column of the problem if
+    /// the document does not parse, or an error from the triple being added; the
+    /// triples before it stay imported.
+    pub async fn import_ntriples(
+        &self,
+        reader: impl BufRead,
+        vocabulary: &Vocabulary,
+    ) -> Result<usize, MetaSyntraXLError> {
+        // N-Triples is a subset of Turtle.
+        self.import_turtle(reader, vocabulary).await
+    }
+
+    /// Imports a Turtle document, read a statement at a time, as
+    /// [`KnowledgeGraph::import_ntriples`] does. Collections are not supported.
+    ///
+    /// # Errors
+    ///
+    /// As for [`KnowledgeGraph::import_ntriples`].
+    pub async fn import_turtle(
+        &self,
+        reader: impl BufRead,
+        vocabulary: &Vocabulary,
+    ) -> Result<usize, MetaSyntraXLError> {
+        let mut count = 0;
+        for triple in TurtleReader::new(reader) {
+            self.import_statement(vocabulary.statement(triple?)).await?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Imports a JSON-LD document with an inline context as
+    /// [`KnowledgeGraph::import_ntriples`] does. A JSON document is one value, so
+    /// it is read whole before anything is imported.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KnowledgeGraphError` if the document is not JSON or uses JSON-LD
+    /// that is not supported, such as a remote context, or an error from the
+    /// triple being added.
+    pub async fn import_jsonld(
+        &self,
+        reader: impl Read,
+        vocabulary: &Vocabulary,
+    ) -> Result<usize, MetaSyntraXLError> {
+        let document = serde_json::from_reader(reader)
+            .map_err(|e| MetaSyntraXLError::KnowledgeGraphError(format!("JSON-LD error: {}", e)))?;
+        let triples = rdf::read_jsonld(document)?;
+        let count = triples.len();
+        for triple in triples {
+            self.import_statement(vocabulary.statement(triple)).await?;
+        }
+        Ok(count)
+    }
+
+    /// Imports a CSV edge list, read a record at a time, and returns the number of
+    /// relationships in it. Endpoints that are not entities are created without
+    /// properties, and relationships already in the graph are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KnowledgeGraphError` with the line of the problem if the edge
+    /// list does not parse, or an error from the relationship being added; the
+    /// relationships before it stay imported.
+    pub async fn import_csv(
+        &self,
+        reader: impl BufRead,
+        options: &CsvOptions,
+    ) -> Result<usize, MetaSyntraXLError> {
+        let mut count = 0;
+        for relationship in CsvReader::new(reader, options) {
+            self.import_statement(Statement::Relationship(relationship?))
+                .await?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Writes the graph as N-Triples: entities in id order, each with its
+    /// properties in key order and then the relationships leaving it. A list
+    /// property is written as one triple per element. Relationship properties
+    /// and entities with neither properties nor relationships leaving them cannot
+    /// be written as triples and are left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `out`.
+    pub async fn export_ntriples(
+        &self,
+        mut out: impl Write,
+        vocabulary: &Vocabulary,
+    ) -> Result<(), MetaSyntraXLError> {
+        let relationships = self.relationships.read().await;
+        let entities = self.entities.read().await;
+        rdf::write_ntriples(
+            &mut out,
+            &rdf::subjects(&relationships, &entities),
+            vocabulary,
+        )
+    }
+
+    /// Writes the graph as Turtle, in the order and with the omissions of
+    /// [`KnowledgeGraph::export_ntriples`], using prefixes for the vocabulary's
+    /// namespaces.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `out`.
+    pub async fn export_turtle(
+        &self,
+        mut out: impl Write,
+        vocabulary: &Vocabulary,
+    ) -> Result<(), MetaSyntraXLError> {
+        let relationships = self.relationships.read().await;
+        let entities = self.entities.read().await;
+        rdf::write_turtle(
+            &mut out,
+            &rdf::subjects(&relationships, &entities),
+            vocabulary,
+        )
+    }
+
+    /// Writes the graph as a JSON-LD document with a node object per entity,
+    /// including entities without properties. Relationship properties are left
+    /// out.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `out`.
+    pub async fn export_jsonld(
+        &self,
+        mut out: impl Write,
+        vocabulary: &Vocabulary,
+    ) -> Result<(), MetaSyntraXLError> {
+        let relationships = self.relationships.read().await;
+        let entities = self.entities.read().await;
+        rdf::write_jsonld(
+            &mut out,
+            &rdf::subjects(&relationships, &entities),
+            vocabulary,
+        )
+    }
+
+    /// Writes the relationships, in insertion order, as a CSV edge list with a
+    /// column per relationship property key. Entity properties are not written.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `out`.
+    pub async fn export_csv(
+        &self,
+        mut out: impl Write,
+        options: &CsvOptions,
+    ) -> Result<(), MetaSyntraXLError> {
+        let relationships = self.relationships.read().await;
+        let relationships: Vec<&Relationship> = relationships.relationships().collect();
+        csv::write_csv(&mut out, &relationships, options)
+    }
+
+    /// Adds an imported statement unless the graph already holds it, creating the
+    /// entities it mentions.
+    async fn import_statement(&self, statement: Statement) -> Result<(), MetaSyntraXLError> {
+        let mut relationships = self.relationships.write().await;
+        let mut entities = self.entities.write().await;
+        let mut operations = Vec::new();
+        match statement {
+            Statement::Property {
+                subject,
+                key,
+                value,
+            } => {
+                let mut entity = entities.get(&subject).cloned().unwrap_or(Entity {
+                    id: subject,
+                    properties: HashMap::new(),
+                });
+                if !add_value(&mut entity.properties, key, value) {
+                    return Ok(());
+                }
+                operations.push(GraphOperation::AddEntity { entity });
+            }
+            Statement::Relationship(relationship) => {
+                if relationships.contains(&relationship) {
+                    return Ok(());
+                }
+                if self.constraints.unique_relationships && relationships.connects(&relationship) {
+                    return Err(duplicate(&relationship));
+                }
+                let mut endpoints = vec![relationship.from.clone()];
+                if relationship.to != relationship.from {
+                    endpoints.push(relationship.to.clone());
+                }
+                for id in endpoints {
+                    if entities.get(&id).is_none() {
+                        let entity = Entity {
+                            id,
+                            properties: HashMap::new(),
+                        };
+                        operations.push(GraphOperation::AddEntity { entity });
+                    }
+                }
+                operations.push(GraphOperation::AddRelationship { relationship });
+            }
+        }
+        let (recorded, result) = self.record_all(operations).await;
+        for operation in recorded {
+            match operation {
+                GraphOperation::AddEntity { entity } => entities.insert(entity),
+                GraphOperation::AddRelationship { relationship } => {
+                    relationships.push(relationship)
+                }
+                _ => unreachable!("imports only add"),
+            }
+        }
+        drop(entities);
+        drop(relationships);
+        result?;
+        self.snapshot_if_needed().await
+    }
+}
+
+/// Adds `value` to the values of `key`, making them a list if there already is
+/// one; returns `false` if `value` is among them already.
+fn add_value(
+    properties: &mut HashMap<String, PropertyValue>,
+    key: String,
+    value: PropertyValue,
+) -> bool {
+    match properties.get_mut(&key) {
+        None => {
+            properties.insert(key, value);
+        }
+        Some(PropertyValue::List(values)) => {
+            if values.contains(&value) {
+                return false;
+            }
+            values.push(value);
+        }
+        Some(existing) => {
+            if *existing == value {
+                return false;
+            }
+            let first = std::mem::replace(existing, PropertyValue::List(Vec::new()));
+            *existing = PropertyValue::List(vec![first, value]);
+        }
+    }
+    true
+}
+
+/// Checks `relationship` against `constraints.referential_integrity`.
+fn check_references(
+    constraints: &GraphConstraints,
+    entities: &EntityTable,
+    relationship: &Relationship,
+) -> Result<(), MetaSyntraXLError> {
+    if !constraints.referential_integrity {
+        return Ok(());
+    }
+    match [&relationship.from, &relationship.to]
+        .into_iter()
+        .find(|id| entities.get(id).is_none())
+    {
+        Some(missing) => Err(MetaSyntraXLError::KnowledgeGraphError(format!(
+            "Relationship {} -[{}]-> {} references missing entity {}",
+            relationship.from, relationship.type_, relationship.to, missing
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn duplicate(relationship: &Relationship) -> MetaSyntraXLError {
+    MetaSyntraXLError::KnowledgeGraphError(format!(
+        "Relationship {} -[{}]-> {} already exists",
+        relationship.from, relationship.type_, relationship.to
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::ops::Bound;
+    use std::path::{Path, PathBuf};
+    use std::process::{Command, Stdio};
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn test_add_and_query_entity() {
+        let kg = KnowledgeGraph::new();
+
+        let mut properties = HashMap::new();
+        properties.insert("type".to_string(), "person".into());
+        properties.insert("name".to_string(), "Alice".into());
+
+        let entity = Entity {
+            id: "1".to_string(),
+            properties,
+        };
+
+        kg.add_entity(entity.clone()).await.unwrap();
+
+        let results = kg.query_entities_by_property("name", "Alice").await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "1");
+    }
+
+    #[tokio::test]
+    async fn test_add_and_query_relationship() {
+        let kg = KnowledgeGraph::new();
+
+        let entity1 = Entity {
+            id: "1".to_string(),
+            properties: HashMap::new(),
+        };
+        let entity2 = Entity {
+            id: "2".to_string(),
+            properties: HashMap::new(),
+        };
+
+        kg.add_entity(entity1.clone()).await.unwrap();
+        kg.add_entity(entity2.clone()).await.unwrap();
+
+        let relationship = Relationship {
+            from: "1".to_string(),
+            to: "2".to_string(),
+            type_: "friend".to_string(),
+            properties: HashMap::new(),
+        };
+
+        kg.add_relationship(relationship.clone()).await.unwrap();
+
+        let relationships = kg.get_relationships_by_type("friend").await;
+        assert_eq!(relationships.len(), 1);
+        assert_eq!(relationships[0].from, "1");
+        assert_eq!(relationships[0].to, "2");
+    }
+
+    #[tokio::test]
+    async fn test_get_connected_entities() {
+        let kg = KnowledgeGraph::new();
+
+        let entity1 = Entity {
+            id: "1".to_string(),
+            properties: HashMap::new(),
+        };
+        let entity2 = Entity {
+            id: "2".to_string(),
+            properties: HashMap::new(),
+        };
+        let entity3 = Entity {
+            id: "3".to_string(),
+            properties: HashMap::new(),
+        };
+
+        kg.add_entity(entity1.clone()).await.unwrap();
+        kg.add_entity(entity2.clone()).await.unwrap();
+        kg.add_entity(entity3.clone()).await.unwrap();
+
+        let relationship1 = Relationship {
+            from: "1".to_string(),
+            to: "2".to_string(),
+            type_: "colleague".to_string(),
+            properties: HashMap::new(),
+        };
+        let relationship2 = Relationship {
+            from: "1".to_string(),
+            to: "3".to_string(),
+            type_: "friend".to_string(),
+            properties: HashMap::new(),
+        };
+
+        kg.add_relationship(relationship1.clone()).await.unwrap();
+        kg.add_relationship(relationship2.clone()).await.unwrap();
+
+        let connected = kg.get_connected_entities("1", "colleague").await;
+        assert_eq!(connected.len(), 1);
+        assert_eq!(connected[0].id, "2");
+
+        let connected = kg.get_connected_entities("1", "friend").await;
+        assert_eq!(connected.len(), 1);
+        assert_eq!(connected[0].id, "3");
+    }
+
+    #[tokio::test]
+    async fn test_indexed_lookups() {
+        let kg = KnowledgeGraph::new();
+        for i in 0..4 {
+            kg.add_entity(node(i)).await.unwrap();
+        }
+        for (from, to) in [(0, 1), (0, 2), (1, 2), (3, 2)] {
+            kg.add_relationship(next(from, to)).await.unwrap();
+        }
+        kg.add_relationship(Relationship {
+            from: "0".to_string(),
+            to: "3".to_string(),
+            type_: "friend".to_string(),
+            properties: HashMap::new(),
+        })
+        .await
+        .unwrap();
+
+        let targets: Vec<String> = kg
+            .get_relationships_from("0", "next")
+            .await
+            .into_iter()
+            .map(|r| r.to)
+            .collect();
+        assert_eq!(targets, vec!["1", "2"]);
+        let sources: Vec<String> = kg
+            .get_relationships_to("2", "next")
+            .await
+            .into_iter()
+            .map(|r| r.from)
+            .collect();
+        assert_eq!(sources, vec!["0", "1", "3"]);
+        assert!(kg.get_relationships_to("2", "friend").await.is_empty());
+        assert!(kg.get_relationships_from("9", "next").await.is_empty());
+        assert_eq!(kg.get_relationships_by_type("next").await.len(), 4);
+
+        // Replacing an entity moves it in the property index.
+        kg.add_entity(Entity {
+            id: "2".to_string(),
+            properties: HashMap::from([("type".to_string(), "leaf".into())]),
+        })
+        .await
+        .unwrap();
+        let mut ids: Vec<String> = kg
+            .query_entities_by_property("type", "node")
+            .await
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["0", "1", "3"]);
+        assert_eq!(
+            kg.query_entities_by_property("type", "leaf").await[0].id,
+            "2"
+        );
+        assert!(kg
+            .query_entities_by_property("colour", "node")
+            .await
+            .is_empty());
+    }
+
+    fn relationship(from: &str, to: &str, type_: &str) -> Relationship {
+        Relationship {
+            from: from.to_string(),
+            to: to.to_string(),
+            type_: type_.to_string(),
+            properties: HashMap::new(),
+        }
+    }
+
+    /// a -> b -> c -> d -> e as friends, a -> c as colleagues, an isolated x and
+    /// a friendship of a with an entity that was never added.
+    async fn acquaintances() -> KnowledgeGraph {
+        let kg = KnowledgeGraph::new();
+        for id in ["a", "b", "c", "d", "e", "x"] {
+            kg.add_entity(Entity {
+                id: id.to_string(),
+                properties: HashMap::new(),
+            })
+            .await
+            .unwrap();
+        }
+        for (from, to, type_) in [
+            ("a", "b", "friend"),
+            ("b", "c", "friend"),
+            ("c", "d", "friend"),
+            ("a", "c", "colleague"),
+            ("d", "e", "friend"),
+            ("a", "ghost", "friend"),
+        ] {
+            kg.add_relationship(relationship(from, to, type_))
+                .await
+                .unwrap();
+        }
+        kg
+    }
+
+    fn visited(visits: &[Visit]) -> Vec<(&str, usize)> {
+        visits
+            .iter()
+            .map(|v| (v.entity.id.as_str(), v.depth))
+            .collect()
+    }
+
+    fn ids(path: &GraphPath) -> Vec<&str> {
+        path.entities.iter().map(|e| e.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_breadth_and_depth_first() {
+        let kg = acquaintances().await;
+        let all = TraversalOptions::default();
+        let friends = TraversalOptions {
+            types: vec!["friend".to_string()],
+            ..TraversalOptions::default()
+        };
+
+        let visits = kg.breadth_first("a", None, &all).await;
+        assert_eq!(
+            visited(&visits),
+            vec![("a", 0), ("b", 1), ("c", 1), ("d", 2), ("e", 3)]
+        );
+        assert_eq!(visits[0].via, None);
+        assert_eq!(visits[2].via, Some(relationship("a", "c", "colleague")));
+        assert_eq!(
+            visited(&kg.breadth_first("a", Some(1), &all).await),
+            vec![("a", 0), ("b", 1), ("c", 1)]
+        );
+        assert_eq!(
+            visited(&kg.breadth_first("a", None, &friends).await),
+            vec![("a", 0), ("b", 1), ("c", 2), ("d", 3), ("e", 4)]
+        );
+
+        let incoming = TraversalOptions {
+            direction: Direction::Incoming,
+            ..TraversalOptions::default()
+        };
+        assert_eq!(
+            visited(&kg.breadth_first("e", None, &incoming).await),
+            vec![("e", 0), ("d", 1), ("c", 2), ("b", 3), ("a", 3)]
+        );
+        let both = TraversalOptions {
+            direction: Direction::Both,
+            ..TraversalOptions::default()
+        };
+        assert_eq!(
+            visited(&kg.breadth_first("b", Some(1), &both).await),
+            vec![("b", 0), ("c", 1), ("a", 1)]
+        );
+
+        assert_eq!(
+            visited(&kg.depth_first("a", None, &all).await),
+            vec![("a", 0), ("b", 1), ("c", 2), ("d", 3), ("e", 4)]
+        );
+        // Depth-first search reaches c through b first, so d is past the limit.
+        assert_eq!(
+            visited(&kg.depth_first("a", Some(2), &all).await),
+            vec![("a", 0), ("b", 1), ("c", 2)]
+        );
+        assert!(kg.breadth_first("ghost", None, &all).await.is_empty());
+        assert!(kg.depth_first("ghost", None, &all).await.is_empty());
+
+        let incoming: Vec<String> = kg
+            .get_incoming_entities("c", "friend")
+            .await
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(incoming, vec!["b"]);
+    }
+
+    #[tokio::test]
+    async fn test_path_queries() {
+        let kg = acquaintances().await;
+        let all = TraversalOptions::default();
+        let friends = TraversalOptions {
+            types: vec!["friend".to_string()],
+            ..TraversalOptions::default()
+        };
+
+        let path = kg.shortest_path("a", "d", &all).await.unwrap();
+        assert_eq!(ids(&path), vec!["a", "c", "d"]);
+        assert_eq!(
+            path.relationships,
+            vec![
+                relationship("a", "c", "colleague"),
+                relationship("c", "d", "friend")
+            ]
+        );
+        assert_eq!(path.cost, 2.0);
+        let path = kg.shortest_path("a", "d", &friends).await.unwrap();
+        assert_eq!(ids(&path), vec!["a", "b", "c", "d"]);
+        assert_eq!(
+            ids(&kg.shortest_path("a", "a", &all).await.unwrap()),
+            vec!["a"]
+        );
+        assert!(kg.shortest_path("a", "x", &all).await.is_none());
+        assert!(kg.shortest_path("a", "ghost", &all).await.is_none());
+
+        let weight = |r: &Relationship| if r.type_ == "colleague" { 5.0 } else { 1.0 };
+        let path = kg
+            .weighted_shortest_path("a", "d", &all, weight)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(ids(&path), vec!["a", "b", "c", "d"]);
+        assert_eq!(path.cost, 3.0);
+        let cheap = |r: &Relationship| if r.type_ == "colleague" { 0.5 } else { 1.0 };
+        let path = kg
+            .weighted_shortest_path("a", "d", &all, cheap)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((ids(&path), path.cost), (vec!["a", "c", "d"], 1.5));
+        assert_eq!(
+            kg.weighted_shortest_path("a", "x", &all, weight)
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(kg
+            .weighted_shortest_path("a", "d", &all, |_| -1.0)
+            .await
+            .is_err());
+
+        let paths = kg.all_paths("a", "d", 3, &all).await;
+        let paths: Vec<Vec<&str>> = paths.iter().map(ids).collect();
+        assert_eq!(paths, vec![vec!["a", "b", "c", "d"], vec!["a", "c", "d"]]);
+        assert_eq!(kg.all_paths("a", "d", 2, &all).await.len(), 1);
+        assert!(kg.all_paths("a", "x", 5, &all).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_neighborhood() {
+        let kg = acquaintances().await;
+        let subgraph = kg.neighborhood("a", 1, &TraversalOptions::default()).await;
+        let entities: Vec<&str> = subgraph.entities.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(entities, vec!["a", "b", "c"]);
+        assert_eq!(
+            subgraph.relationships,
+            vec![
+                relationship("a", "b", "friend"),
+                relationship("a", "c", "colleague"),
+                relationship("b", "c", "friend"),
+            ]
+        );
+        assert_eq!(
+            kg.neighborhood("ghost", 3, &TraversalOptions::default())
+                .await,
+            Subgraph::default()
+        );
+    }
+
+    /// People with names and ages on top of [`acquaintances`].
+    async fn people() -> KnowledgeGraph {
+        let kg = acquaintances().await;
+        for (id, name, age) in [
+            ("a", "Ann", 34),
+            ("b", "Alice", 29),
+            ("c", "Carl", 41),
+            ("d", "Alice", 8),
+        ] {
+            kg.add_entity(Entity {
+                id: id.to_string(),
+                properties: HashMap::from([
+                    ("type".to_string(), "person".into()),
+                    ("name".to_string(), name.into()),
+                    ("age".to_string(), PropertyValue::Int(age)),
+                ]),
+            })
+            .await
+            .unwrap();
+        }
+        kg
+    }
+
+    fn column(result: &QueryResult, index: usize) -> Vec<String> {
+        result
+            .rows
+            .iter()
+            .map(|row| match &row[index] {
+                QueryValue::Property(value) => value.to_string(),
+                QueryValue::Entity(entity) => entity.id.clone(),
+                QueryValue::Relationship(r) => r.type_.clone(),
+                QueryValue::Null => "null".to_string(),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_query_patterns() {
+        let kg = people().await;
+
+        let result = kg
+            .query(r#"MATCH (a {type:"person"})-[:friend]->(b) WHERE b.name = "Alice" RETURN a.name, b"#)
+            .await
+            .unwrap();
+        assert_eq!(result.columns, vec!["a.name", "b"]);
+        assert_eq!(column(&result, 0), vec!["Ann", "Carl"]);
+        assert!(matches!(&result.rows[0][1], QueryValue::Entity(e) if e.id == "b"));
+
+        let result = kg
+            .query("match (x)<-[r:friend|colleague]-(y) where x.id = 'c' return y.name as name, r")
+            .await
+            .unwrap();
+        assert_eq!(result.columns, vec!["name", "r"]);
+        assert_eq!(column(&result, 0), vec!["Alice", "Ann"]);
+        assert_eq!(column(&result, 1), vec!["friend", "colleague"]);
+
+        // Two hops, undirected, numeric comparison, and a join across patterns.
+        let result = kg
+            .query("MATCH (a)-[:friend]->(b)-[:friend]->(c), (c)-[]-(d) WHERE a.age > 30 AND NOT d.id = b.id RETURN a, c, d")
+            .await
+            .unwrap();
+        let rows: Vec<Vec<String>> = (0..result.rows.len())
+            .map(|i| (0..3).map(|j| column(&result, j)[i].clone()).collect())
+            .collect();
+        assert_eq!(rows, vec![vec!["a", "c", "d"], vec!["a", "c", "a"]]);
+
+        let result = kg
+            .query("MATCH (p) WHERE p.name STARTS WITH 'A' OR p.age <= 8 RETURN p.name, p.nickname LIMIT 2")
+            .await
+            .unwrap();
+        assert_eq!(column(&result, 0), vec!["Ann", "Alice"]);
+        assert_eq!(column(&result, 1), vec!["null", "null"]);
+        let result = kg
+            .query("MATCH (p) WHERE p.age IS NULL RETURN p")
+            .await
+            .unwrap();
+        assert_eq!(column(&result, 0), vec!["e", "x"]);
+        // A relationship is not matched twice in one row, and dangling ones never.
+        let result = kg
+            .query("MATCH (a)-[:friend]-(b)-[:friend]-(c) WHERE a.id = 'a' RETURN c")
+            .await
+            .unwrap();
+        assert_eq!(column(&result, 0), vec!["c"]);
+    }
+
+    #[test]
+    fn test_query_plans_use_indexes() {
+        assert_eq!(
+            KnowledgeGraph::explain(
+                r#"MATCH (a {type:"person"})-[:friend]->(b) WHERE b.name = "Alice" RETURN b"#
+            )
+            .unwrap(),
+            vec![
+                r#"IndexSeek (a) on type = "person""#,
+                "Expand (a)-[:friend]->(b)",
+                "Filter",
+                "Return b",
+            ]
+        );
+        assert_eq!(
+            KnowledgeGraph::explain(
+                "(a)-[r]->(b)<-[:colleague]-(c) WHERE c.id = 'x' AND a.age > 3 RETURN r LIMIT 1"
+            )
+            .unwrap(),
+            vec![
+                r#"Lookup (c) by id "x""#,
+                "Expand (c)-[:colleague]->(b)",
+                "Expand (b)<-[r]-(a)",
+                "Filter",
+                "Return r",
+                "Limit 1",
+            ]
+        );
+        // An equality under OR does not hold for every row, so it cannot be used.
+        assert_eq!(
+            KnowledgeGraph::explain("(a), (a)-[]-(b) WHERE a.x = '1' OR b.x = '2' RETURN b")
+                .unwrap()[..2],
+            ["Scan (a) over all entities", "Check (a)"]
+        );
+    }
+
+    #[test]
+    fn test_query_errors() {
+        let message = |text: &str| match KnowledgeGraph::explain(text) {
+            Err(MetaSyntraXLError::KnowledgeGraphError(message)) => message,
+            other => panic!("expected an error for {:?}, got {:?}", text, other),
+        };
+        assert_eq!(
+            message("MATCH (a)-[:friend]->(b) RETURN"),
+            "Query parse error at line 1, column 32: expected a variable, found end of query"
+        );
+        assert_eq!(
+            message("MATCH (a)\nWHERE a.name = RETURN a"),
+            "Query parse error at line 2, column 16: expected a property or literal, found `RETURN`"
+        );
+        assert_eq!(
+            message("MATCH (a)<-[:friend]->(b) RETURN a"),
+            "Query parse error at line 1, column 21: expected `-`, found `->`"
+        );
+        assert_eq!(
+            message("MATCH (a {name: 'Ann) RETURN a"),
+            "Query parse error at line 1, column 17: unterminated string"
+        );
+        assert_eq!(
+            message("MATCH (a) RETURN a LIMIT 1.5"),
+            "Query parse error at line 1, column 26: expected a whole number, found number 1.5"
+        );
+        assert_eq!(message("MATCH (a) RETURN b"), "Unknown variable `b`");
+        assert_eq!(
+            message("MATCH (a)-[a]->(b) RETURN b"),
+            "Variable `a` is bound more than once"
+        );
+    }
+
+    fn time(text: &str) -> PropertyValue {
+        PropertyValue::DateTime(text.parse().unwrap())
+    }
+
+    /// Sensors with readings of every type, linked by weighted relationships.
+    async fn sensors() -> KnowledgeGraph {
+        let kg = KnowledgeGraph::new();
+        for (id, reading, installed) in [
+            ("s1", PropertyValue::Int(3), "2019-05-01T00:00:00Z"),
+            ("s2", PropertyValue::Float(3.0), "2021-01-15T12:00:00Z"),
+            ("s3", PropertyValue::Float(-0.5), "2022-07-30T08:30:00Z"),
+            ("s4", PropertyValue::from("3"), "2023-02-01T00:00:00Z"),
+            ("s5", PropertyValue::Int(12), "2018-11-11T11:11:11Z"),
+        ] {
+            kg.add_entity(Entity {
+                id: id.to_string(),
+                properties: HashMap::from([
+                    ("reading".to_string(), reading),
+                    ("installed".to_string(), time(installed)),
+                    ("active".to_string(), (id != "s3").into()),
+                    (
+                        "tags".to_string(),
+                        vec!["outdoor".into(), PropertyValue::Int(id.len() as i64)].into(),
+                    ),
+                ]),
+            })
+            .await
+            .unwrap();
+        }
+        for (from, to, weight) in [("s1", "s2", 0.9), ("s2", "s3", 0.2), ("s1", "s3", 1.5)] {
+            kg.add_relationship(Relationship {
+                from: from.to_string(),
+                to: to.to_string(),
+                type_: "feeds".to_string(),
+                properties: HashMap::from([
+                    ("weight".to_string(), weight.into()),
+                    ("source".to_string(), "survey".into()),
+                ]),
+            })
+            .await
+            .unwrap();
+        }
+        kg
+    }
+
+    fn entity_ids(entities: Vec<Entity>) -> Vec<String> {
+        entities.into_iter().map(|e| e.id).collect()
+    }
+
+    #[tokio::test]
+    async fn test_typed_properties() {
+        let kg = sensors().await;
+
+        // Equality is typed: 3, 3.0 and "3" are three different values.
+        assert_eq!(
+            entity_ids(kg.query_entities_by_property("reading", 3).await),
+            vec!["s1"]
+        );
+        assert_eq!(
+            entity_ids(kg.query_entities_by_property("reading", 3.0).await),
+            vec!["s2"]
+        );
+        assert_eq!(
+            entity_ids(kg.query_entities_by_property("reading", "3").await),
+            vec!["s4"]
+        );
+        assert_eq!(
+            kg.query_entities_by_property("active", false).await[0].id,
+            "s3"
+        );
+        assert_eq!(
+            kg.query_entities_by_property("installed", time("2018-11-11T11:11:11Z"))
+                .await[0]
+                .id,
+            "s5"
+        );
+
+        // Ranges cover integers and floats together, by value and then id.
+        assert_eq!(
+            entity_ids(kg.query_entities_by_range("reading", ..).await),
+            vec!["s3", "s1", "s2", "s5"]
+        );
+        assert_eq!(
+            entity_ids(kg.query_entities_by_range("reading", 0.0..=3.0).await),
+            vec!["s1", "s2"]
+        );
+        assert_eq!(
+            entity_ids(kg.query_entities_by_range("reading", 3.0..12.0).await),
+            vec!["s1", "s2"]
+        );
+        assert!(kg
+            .query_entities_by_range("reading", 5.0..1.0)
+            .await
+            .is_empty());
+        assert!(kg
+            .query_entities_by_range("reading", (Bound::Excluded(3.0), Bound::Excluded(3.0)))
+            .await
+            .is_empty());
+        assert!(kg.query_entities_by_range("installed", ..).await.is_empty());
+        kg.update_entity("s5", |properties| {
+            properties.insert("reading".to_string(), PropertyValue::Float(-1.0));
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            entity_ids(kg.query_entities_by_range("reading", ..0.0).await),
+            vec!["s5", "s3"]
+        );
+
+        // Relationship properties, e.g. as weights.
+        let path = kg
+            .weighted_shortest_path("s1", "s3", &TraversalOptions::default(), |r| {
+                r.properties["weight"].as_f64().unwrap()
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(path.entities.len(), 3);
+        assert!((path.cost - 1.1).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn test_typed_query_comparisons() {
+        let kg = sensors().await;
+        let ids = |result: QueryResult| column(&result, 0);
+
+        assert_eq!(
+            ids(kg
+                .query("MATCH (s) WHERE s.reading >= 3 RETURN s")
+                .await
+                .unwrap()),
+            vec!["s1", "s2", "s5"]
+        );
+        assert_eq!(
+            ids(kg.query("MATCH (s {reading: 3}) RETURN s").await.unwrap()),
+            vec!["s1", "s2"]
+        );
+        assert_eq!(
+            ids(kg
+                .query("MATCH (s) WHERE s.reading = '3' RETURN s")
+                .await
+                .unwrap()),
+            vec!["s4"]
+        );
+        assert_eq!(
+            ids(kg
+                .query("MATCH (s) WHERE s.installed < '2020-01-01T00:00:00Z' RETURN s")
+                .await
+                .unwrap()),
+            vec!["s1", "s5"]
+        );
+        assert_eq!(
+            ids(kg
+                .query("MATCH (s {installed: '2021-01-15T12:00:00Z'}) RETURN s")
+                .await
+                .unwrap()),
+            vec!["s2"]
+        );
+        assert_eq!(
+            ids(kg
+                .query("MATCH (s {active: false}) RETURN s")
+                .await
+                .unwrap()),
+            vec!["s3"]
+        );
+        assert_eq!(
+            ids(kg
+                .query("MATCH (s) WHERE s.tags CONTAINS 'outdoor' AND s.tags CONTAINS 2 RETURN s")
+                .await
+                .unwrap()),
+            vec!["s1", "s2", "s3", "s4", "s5"]
+        );
+        let result = kg
+            .query("MATCH (a)-[r:feeds]->(b) WHERE r.weight > 0.5 RETURN r.weight, r.source, b.reading")
+            .await
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![
+                    QueryValue::Property(PropertyValue::Float(0.9)),
+                    QueryValue::Property("survey".into()),
+                    QueryValue::Property(PropertyValue::Float(3.0)),
+                ],
+                vec![
+                    QueryValue::Property(PropertyValue::Float(1.5)),
+                    QueryValue::Property("survey".into()),
+                    QueryValue::Property(PropertyValue::Float(-0.5)),
+                ],
+            ]
+        );
+        assert_eq!(
+            &KnowledgeGraph::explain("MATCH (s {reading: 3}) RETURN s").unwrap()[0],
+            "IndexSeek (s) on reading = 3"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_property_serialization() {
+        let entity = Entity {
+            id: "e".to_string(),
+            properties: HashMap::from([
+                ("name".to_string(), "Ada".into()),
+                ("born".to_string(), time("1815-12-10T00:00:00Z")),
+                ("papers".to_string(), PropertyValue::Int(1)),
+                ("height".to_string(), PropertyValue::Float(1.65)),
+                ("whole".to_string(), PropertyValue::Float(2.0)),
+                ("alive".to_string(), false.into()),
+                (
+                    "notes".to_string(),
+                    vec!["G".into(), PropertyValue::Int(7)].into(),
+                ),
+            ]),
+        };
+        let json = serde_json::to_value(&entity).unwrap();
+        assert_eq!(
+            json["properties"]["born"],
+            serde_json::json!({"datetime": "1815-12-10T00:00:00Z"})
+        );
+        assert_eq!(json["properties"]["notes"], serde_json::json!(["G", 7]));
+        assert_eq!(serde_json::from_value::<Entity>(json).unwrap(), entity);
+
+        // Graphs written before properties were typed hold only strings, and
+        // relationships without properties.
+        let old: Relationship =
+            serde_json::from_str(r#"{"from":"a","to":"b","type_":"friend"}"#).unwrap();
+        assert!(old.properties.is_empty());
+        assert_eq!(
+            serde_json::to_string(&old).unwrap(),
+            r#"{"from":"a","to":"b","type_":"friend"}"#
+        );
+        let old: Entity = serde_json::from_str(r#"{"id":"a","properties":{"age":"34"}}"#).unwrap();
+        assert_eq!(old.properties["age"], "34".into());
+
+        let directory = temp_directory("typed");
+        let kg = open(&directory, None);
+        kg.add_entity(entity.clone()).await.unwrap();
+        let weighted = relationship("e", "e", "self");
+        let weighted = Relationship {
+            properties: HashMap::from([("weight".to_string(), PropertyValue::Float(0.5))]),
+            ..weighted
+        };
+        kg.add_relationship(weighted.clone()).await.unwrap();
+        drop(kg);
+        let kg = open(&directory, None);
+        assert_eq!(
+            kg.query_entities_by_property("papers", 1).await,
+            vec![entity]
+        );
+        assert_eq!(kg.get_relationships_by_type("self").await, vec![weighted]);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_non_finite_floats_persist() {
+        let entity = Entity {
+            id: "e".to_string(),
+            properties: HashMap::from([
+                ("name".to_string(), "edge".into()),
+                ("nan".to_string(), PropertyValue::Float(f64::NAN)),
+                ("inf".to_string(), PropertyValue::Float(f64::INFINITY)),
+                ("-inf".to_string(), PropertyValue::Float(f64::NEG_INFINITY)),
+            ]),
+        };
+        let json = serde_json::to_value(&entity).unwrap();
+        assert_eq!(
+            json["properties"]["nan"],
+            serde_json::json!({"float": "NaN"})
+        );
+        assert_eq!(
+            json["properties"]["-inf"],
+            serde_json::json!({"float": "-inf"})
+        );
+
+        let directory = temp_directory("non-finite");
+        let kg = open(&directory, None);
+        kg.add_entity(entity).await.unwrap();
+        drop(kg);
+        let kg = open(&directory, None);
+        let reopened = kg.query_entities_by_property("name", "edge").await;
+        let properties = &reopened[0].properties;
+        assert!(matches!(properties["nan"], PropertyValue::Float(value) if value.is_nan()));
+        assert_eq!(properties["inf"], PropertyValue::Float(f64::INFINITY));
+        assert_eq!(properties["-inf"], PropertyValue::Float(f64::NEG_INFINITY));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    fn endpoints(relationships: Vec<Relationship>) -> Vec<String> {
+        relationships
+            .iter()
+            .map(|r| format!("{}-{}", r.from, r.to))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_updates_and_deletes() {
+        let kg = acquaintances().await;
+
+        let updated = kg
+            .update_entity("a", |properties| {
+                properties.insert("name".to_string(), "Ann".into());
+            })
+            .await
+            .unwrap();
+        assert_eq!(updated.properties["name"], "Ann".into());
+        assert_eq!(
+            kg.query_entities_by_property("name", "Ann").await,
+            vec![updated]
+        );
+        assert!(matches!(
+            kg.update_entity("nobody", |_| {}).await,
+            Err(MetaSyntraXLError::KnowledgeGraphError(_))
+        ));
+
+        let merged = kg
+            .merge_entity(Entity {
+                id: "a".to_string(),
+                properties: HashMap::from([("age".to_string(), 34.into())]),
+            })
+            .await
+            .unwrap();
+        assert_eq!(merged.properties.len(), 2);
+        let created = kg
+            .merge_entity(Entity {
+                id: "f".to_string(),
+                properties: HashMap::new(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            kg.neighborhood("f", 0, &TraversalOptions::default())
+                .await
+                .entities,
+            vec![created]
+        );
+
+        // Removing c takes b -> c, c -> d and a -> c with it.
+        assert_eq!(kg.remove_entity("c").await.unwrap().unwrap().id, "c");
+        assert_eq!(kg.remove_entity("c").await.unwrap(), None);
+        assert_eq!(
+            endpoints(kg.get_relationships_by_type("friend").await),
+            vec!["a-b", "d-e", "a-ghost"]
+        );
+        assert!(kg.get_relationships_by_type("colleague").await.is_empty());
+        assert!(kg.get_incoming_entities("d", "friend").await.is_empty());
+
+        assert!(!kg
+            .merge_relationship(relationship("a", "b", "friend"))
+            .await
+            .unwrap());
+        assert!(kg
+            .merge_relationship(relationship("b", "a", "friend"))
+            .await
+            .unwrap());
+        kg.add_relationship(relationship("a", "b", "friend"))
+            .await
+            .unwrap();
+        assert_eq!(
+            kg.remove_relationship(&relationship("a", "b", "friend"))
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            kg.remove_relationship(&relationship("a", "b", "friend"))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(kg.remove_dangling_relationships().await.unwrap(), 1);
+        assert_eq!(
+            endpoints(kg.get_relationships_by_type("friend").await),
+            vec!["d-e", "b-a"]
+        );
+
+        // Removing an entity that never existed still clears relationships to it.
+        kg.add_relationship(relationship("a", "ghost", "friend"))
+            .await
+            .unwrap();
+        assert_eq!(kg.remove_entity("ghost").await.unwrap(), None);
+        assert!(kg.get_relationships_to("ghost", "friend").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_removals_compact_and_persist() {
+        let directory = temp_directory("removals");
+        let kg = open(&directory, None);
+        for i in 0..4 {
+            kg.add_entity(node(i)).await.unwrap();
+        }
+        for i in 0..3000 {
+            kg.add_relationship(next(i % 4, (i + 1) % 4)).await.unwrap();
+        }
+        kg.add_relationship(next(0, 2)).await.unwrap();
+        // 2250 removals leave 751 relationships, so the table is compacted.
+        assert_eq!(kg.remove_relationship(&next(0, 1)).await.unwrap(), 750);
+        assert_eq!(kg.remove_entity("3").await.unwrap().unwrap().id, "3");
+        assert_eq!(
+            contents(&kg).await,
+            (vec![0, 1, 2], [vec![(1, 2); 750], vec![(0, 2)]].concat())
+        );
+        assert_eq!(
+            kg.get_relationships_from("0", "next").await,
+            vec![next(0, 2)]
+        );
+        assert_eq!(kg.get_relationships_to("2", "next").await.len(), 751);
+        drop(kg);
+
+        let kg = open(&directory, None);
+        assert_eq!(
+            contents(&kg).await,
+            (vec![0, 1, 2], [vec![(1, 2); 750], vec![(0, 2)]].concat())
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_constraints() {
+        let constraints = GraphConstraints {
+            referential_integrity: true,
+            unique_relationships: true,
+        };
+        let kg = KnowledgeGraph::new()
+            .with_constraints(constraints.clone())
+            .unwrap();
+        kg.add_entity(node(0)).await.unwrap();
+        kg.add_entity(node(1)).await.unwrap();
+
+        match kg.add_relationship(next(0, 2)).await {
+            Err(MetaSyntraXLError::KnowledgeGraphError(message)) => {
+                assert_eq!(
+                    message,
+                    "Relationship 0 -[next]-> 2 references missing entity 2"
+                )
+            }
+            other => panic!("expected a missing entity, got {:?}", other),
+        }
+        assert!(kg.merge_relationship(next(2, 0)).await.is_err());
+        kg.add_relationship(next(0, 1)).await.unwrap();
+        match kg.add_relationship(next(0, 1)).await {
+            Err(MetaSyntraXLError::KnowledgeGraphError(message)) => {
+                assert_eq!(message, "Relationship 0 -[next]-> 1 already exists")
+            }
+            other => panic!("expected a duplicate, got {:?}", other),
+        }
+        assert!(!kg.merge_relationship(next(0, 1)).await.unwrap());
+        assert_eq!(contents(&kg).await, (vec![0, 1], vec![(0, 1)]));
+
+        // Existing contents are checked too.
+        assert!(acquaintances()
+            .await
+            .with_constraints(constraints.clone())
+            .is_err());
+        let kg = acquaintances().await;
+        kg.remove_dangling_relationships().await.unwrap();
+        let kg = kg.with_constraints(constraints.clone()).unwrap();
+        kg.add_relationship(relationship("a", "b", "colleague"))
+            .await
+            .unwrap();
+        let kg = KnowledgeGraph::new();
+        kg.add_relationship(next(0, 1)).await.unwrap();
+        kg.add_relationship(next(0, 1)).await.unwrap();
+        let unique = GraphConstraints {
+            unique_relationships: true,
+            ..GraphConstraints::default()
+        };
+        assert!(kg.with_constraints(unique).is_err());
+    }
+
+    /// Every entity and relationship of `kg`, sorted.
+    async fn everything(kg: &KnowledgeGraph) -> (Vec<Entity>, Vec<Relationship>) {
+        let mut entities: Vec<Entity> = kg
+            .entities
+            .read()
+            .await
+            .entities()
+            .values()
+            .cloned()
+            .collect();
+        entities.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut relationships: Vec<Relationship> = kg
+            .relationships
+            .read()
+            .await
+            .relationships()
+            .cloned()
+            .collect();
+        relationships.sort_by(|a, b| (&a.from, &a.type_, &a.to).cmp(&(&b.from, &b.type_, &b.to)));
+        (entities, relationships)
+    }
+
+    /// A graph with awkward ids, names and values and no relationship properties.
+    async fn miscellany() -> KnowledgeGraph {
+        let kg = KnowledgeGraph::new();
+        let born = "1815-12-10T00:00:00Z"
+            .parse::<chrono::DateTime<chrono::Utc>>()
+            .unwrap();
+        let entities = [
+            (
+                "ada",
+                vec![
+                    ("name", "Ada \"the\" Countess\nof Lovelace".into()),
+                    ("first name", "Ada".into()),
+                    ("born", born.into()),
+                    ("height", 1.65.into()),
+                    ("works", 3.into()),
+                    ("alive", false.into()),
+                    ("tags", vec!["math".into(), "poetry".into()].into()),
+                ],
+            ),
+            (
+                "Charles Babbage",
+                vec![("ratio", 2.0.into()), ("type", "inventor".into())],
+            ),
+            ("http://example.org/engine", vec![("name", "Engine".into())]),
+            ("urn:metasyntraxl:entity:raw", vec![("count", (-7).into())]),
+            ("_:b0", vec![("name", "unnamed".into())]),
+            ("machine", vec![]),
+        ];
+        for (id, properties) in entities {
+            let properties = properties
+                .into_iter()
+                .map(|(key, value): (&str, PropertyValue)| (key.to_string(), value))
+                .collect();
+            kg.add_entity(Entity {
+                id: id.to_string(),
+                properties,
+            })
+            .await
+            .unwrap();
+        }
+        for (from, to, type_) in [
+            ("ada", "Charles Babbage", "friend"),
+            ("ada", "ada", "knows"),
+            ("ada", "http://example.org/engine", "wrote"),
+            ("Charles Babbage", "http://example.org/engine", "designed"),
+            ("http://example.org/engine", "machine", "type"),
+            ("_:b0", "ada", "related to"),
+            (
+                "urn:metasyntraxl:entity:raw",
+                "machine",
+                "http://purl.org/dc/terms/isPartOf",
+            ),
+        ] {
+            kg.add_relationship(relationship(from, to, type_))
+                .await
+                .unwrap();
+        }
+        kg
+    }
+
+    #[tokio::test]
+    async fn test_rdf_round_trips() {
+        let kg = miscellany().await;
+        let vocabulary = Vocabulary::default();
+        let expected = everything(&kg).await;
+
+        let mut ntriples = Vec::new();
+        kg.export_ntriples(&mut ntriples, &vocabulary)
+            .await
+            .unwrap();
+        let mut turtle = Vec::new();
+        kg.export_turtle(&mut turtle, &vocabulary).await.unwrap();
+        let mut jsonld = Vec::new();
+        kg.export_jsonld(&mut jsonld, &vocabulary).await.unwrap();
+        let text = String::from_utf8(ntriples.clone()).unwrap();
+        assert_eq!(text.lines().count(), 20);
+        assert!(text.contains(
+            "<urn:metasyntraxl:entity:Charles%20Babbage> \
+             <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> \"inventor\" ."
+        ));
+        let text = String::from_utf8(turtle.clone()).unwrap();
+        assert!(text.contains("e:ada v:alive false ;"));
+        assert!(text.contains("\n    v:works 3 ;"));
+        assert!(text.contains("<http://example.org/engine> v:name \"Engine\" ;\n    a e:machine ."));
+
+        for (format, document) in [
+            ("ntriples", ntriples),
+            ("turtle", turtle),
+            ("jsonld", jsonld),
+        ] {
+            let imported = KnowledgeGraph::new();
+            for _ in 0..2 {
+                let count = match format {
+                    "ntriples" => imported.import_ntriples(&document[..], &vocabulary).await,
+                    "turtle" => imported.import_turtle(&document[..], &vocabulary).await,
+                    _ => imported.import_jsonld(&document[..], &vocabulary).await,
+                };
+                assert_eq!(count.unwrap(), 20, "{}", format);
+                // Importing again changes nothing.
+                assert_eq!(everything(&imported).await, expected, "{}", format);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_blank_nodes_are_scoped_per_import() {
+        let vocabulary = Vocabulary::default();
+        let kg = KnowledgeGraph::new();
+        let turtle = "@prefix v: <urn:metasyntraxl:vocabulary:> .\n\
+                      _:genid1 v:name \"labelled\" .\n\
+                      [] v:name \"anonymous\" .\n";
+        let jsonld = r#"{
+            "@context": {"@vocab": "urn:metasyntraxl:vocabulary:"},
+            "name": "anonymous"
+        }"#;
+        for _ in 0..2 {
+            kg.import_turtle(turtle.as_bytes(), &vocabulary)
+                .await
+                .unwrap();
+            kg.import_jsonld(jsonld.as_bytes(), &vocabulary)
+                .await
+                .unwrap();
+        }
+
+        let labelled = kg.query_entities_by_property("name", "labelled").await;
+        assert_eq!(entity_ids(labelled), vec!["_:genid1"]);
+        let mut anonymous = entity_ids(kg.query_entities_by_property("name", "anonymous").await);
+        anonymous.sort();
+        anonymous.dedup();
+        assert_eq!(anonymous.len(), 4, "{:?}", anonymous);
+        assert!(anonymous.iter().all(|id| id.starts_with("_:")));
+    }
+
+    #[tokio::test]
+    async fn test_imported_non_finite_doubles_persist() {
+        let directory = temp_directory("rdf-non-finite");
+        let kg = open(&directory, None);
+        let turtle = "@prefix xsd: <http://www.w3.org/2001/XMLSchema#> .\n\
+                      <urn:metasyntraxl:entity:probe> \
+                      <urn:metasyntraxl:vocabulary:reading> \"-INF\"^^xsd:double .\n";
+        kg.import_turtle(turtle.as_bytes(), &Vocabulary::default())
+            .await
+            .unwrap();
+        drop(kg);
+
+        let kg = open(&directory, None);
+        let probes = kg
+            .query_entities_by_property("reading", f64::NEG_INFINITY)
+            .await;
+        assert_eq!(entity_ids(probes), vec!["probe"]);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_turtle_import_maps_predicates() {
+        let foaf = "http://xmlns.com/foaf/0.1/";
+        let dc = "http://purl.org/dc/terms/";
+        let mut vocabulary = Vocabulary {
+            entity_namespace: "http://example.org/".to_string(),
+            predicate_namespace: foaf.to_string(),
+            ..Vocabulary::default()
+        };
+        for (predicate, name) in [
+            (format!("{}knows", foaf), "friend"),
+            (format!("{}creator", dc), "author"),
+            (format!("{}title", dc), "title"),
+            (format!("{}date", dc), "date"),
+            (format!("{}subject", dc), "subject"),
+            (format!("{}extent", dc), "pages"),
+        ] {
+            vocabulary.predicates.insert(predicate, name.to_string());
+        }
+        let kg = KnowledgeGraph::new();
+        let fixture = include_str!("fixtures/library.ttl");
+        assert_eq!(
+            kg.import_turtle(fixture.as_bytes(), &vocabulary)
+                .await
+                .unwrap(),
+            15
+        );
+
+        let ada = kg.query_entities_by_property("age", 36).await;
+        assert_eq!(entity_ids(ada), vec!["ada"]);
+        let notes = kg
+            .query_entities_by_property("title", "Notes on the\nAnalytical Engine")
+            .await
+            .remove(0);
+        let date = "1843-09-01T00:00:00Z"
+            .parse::<chrono::DateTime<chrono::Utc>>()
+            .unwrap();
+        assert_eq!(notes.properties["date"], date.into());
+        assert_eq!(notes.properties["pages"], 65.5.into());
+        assert_eq!(
+            notes.properties["subject"],
+            vec!["mathematics".into(), "computing".into()].into()
+        );
+        let result = kg
+            .query("MATCH (a)-[:friend]->(b) WHERE a.name = 'Charles Babbage' RETURN b.name")
+            .await
+            .unwrap();
+        assert_eq!(column(&result, 0), vec!["Ada Lovelace", "Anonymous"]);
+        let result = kg
+            .query("MATCH (n)-[:author]->(a)-[:type]->(t) RETURN t")
+            .await
+            .unwrap();
+        assert_eq!(
+            result.rows[0][0],
+            QueryValue::Entity(Entity {
+                id: format!("{}Person", foaf),
+                properties: HashMap::new(),
+            })
+        );
+
+        // Exporting with the same vocabulary writes the original predicates.
+        let mut out = Vec::new();
+        kg.export_ntriples(&mut out, &vocabulary).await.unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(
+            "<http://example.org/notes> <http://purl.org/dc/terms/creator> \
+             <http://example.org/ada> ."
+        ));
+        assert!(out.contains(
+            "<http://example.org/ada> <http://xmlns.com/foaf/0.1/age> \
+             \"36\"^^<http://www.w3.org/2001/XMLSchema#integer> ."
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_jsonld_import() {
+        let document = r#"{
+            "@context": {
+                "@vocab": "http://schema.org/",
+                "@base": "http://example.org/",
+                "knows": {"@id": "http://schema.org/knows", "@type": "@id"},
+                "born": {"@id": "birthDate", "@type": "http://www.w3.org/2001/XMLSchema#dateTime"}
+            },
+            "@graph": [{
+                "@id": "ada",
+                "@type": "Person",
+                "name": "Ada",
+                "born": "1815-12-10T00:00:00Z",
+                "knows": "charles",
+                "spouse": {"@id": "william", "name": "William"},
+                "height": 1.65,
+                "children": 3,
+                "alive": false,
+                "languages": {"@list": ["en", "fr"]}
+            }]
+        }"#;
+        let vocabulary = Vocabulary {
+            entity_namespace: "http://example.org/".to_string(),
+            predicate_namespace: "http://schema.org/".to_string(),
+            ..Vocabulary::default()
+        };
+        let kg = KnowledgeGraph::new();
+        assert_eq!(
+            kg.import_jsonld(document.as_bytes(), &vocabulary)
+                .await
+                .unwrap(),
+            11
+        );
+        let (entities, relationships) = everything(&kg).await;
+        let born = "1815-12-10T00:00:00Z"
+            .parse::<chrono::DateTime<chrono::Utc>>()
+            .unwrap();
+        let ada = &entities[0];
+        assert_eq!(ada.id, "ada");
+        assert_eq!(ada.properties["birthDate"], born.into());
+        assert_eq!(ada.properties["height"], 1.65.into());
+        assert_eq!(ada.properties["children"], 3.into());
+        assert_eq!(ada.properties["alive"], false.into());
+        assert_eq!(
+            ada.properties["languages"],
+            vec!["en".into(), "fr".into()].into()
+        );
+        assert_eq!(
+            relationships,
+            vec![
+                relationship("ada", "charles", "knows"),
+                relationship("ada", "william", "spouse"),
+                relationship("ada", "http://schema.org/Person", "type"),
+            ]
+        );
+        assert_eq!(
+            kg.query_entities_by_property("name", "William").await[0].id,
+            "william"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csv_import_and_export() {
+        let options = CsvOptions {
+            types: HashMap::from([("route".to_string(), "flight".to_string())]),
+            ..CsvOptions::default()
+        };
+        let kg = KnowledgeGraph::new();
+        let fixture = include_str!("fixtures/flights.csv");
+        assert_eq!(
+            kg.import_csv(fixture.as_bytes(), &options).await.unwrap(),
+            3
+        );
+        assert_eq!(
+            kg.import_csv(fixture.as_bytes(), &options).await.unwrap(),
+            3
+        );
+
+        let flights = kg.get_relationships_by_type("flight").await;
+        assert_eq!(flights.len(), 2);
+        assert_eq!(flights[0].properties["distance"], 5850.into());
+        let departs = "2024-03-01T09:30:00Z"
+            .parse::<chrono::DateTime<chrono::Utc>>()
+            .unwrap();
+        assert_eq!(flights[0].properties["departs"], departs.into());
+        assert_eq!(flights[1].properties["distance"], 5540.5.into());
+        assert_eq!(
+            flights[1].properties["airline"],
+            "British Airways, Ltd.".into()
+        );
+        assert!(!flights[1].properties.contains_key("departs"));
+        let hop = &kg.get_relationships_by_type("connects").await[0];
+        assert_eq!(hop.properties["airline"], "Note: \"short\"\nhop".into());
+        let airports = kg.query("MATCH (a) RETURN a.id").await.unwrap();
+        assert_eq!(column(&airports, 0), vec!["AMS", "JFK", "LHR"]);
+
+        let mut out = Vec::new();
+        kg.export_csv(&mut out, &options).await.unwrap();
+        assert_eq!(
+            String::from_utf8(out.clone()).unwrap(),
+            "from,to,type,airline,departs,distance\n\
+             AMS,JFK,route,KLM,2024-03-01T09:30:00Z,5850\n\
+             JFK,LHR,route,\"British Airways, Ltd.\",,5540.5\n\
+             LHR,AMS,connects,\"Note: \"\"short\"\"\nhop\",,357\n"
+        );
+        let imported = KnowledgeGraph::new();
+        imported.import_csv(&out[..], &options).await.unwrap();
+        assert_eq!(everything(&imported).await, everything(&kg).await);
+
+        // Without inference every property is a string.
+        let options = CsvOptions {
+            delimiter: ';',
+            infer_types: false,
+            ..CsvOptions::default()
+        };
+        let kg = KnowledgeGraph::new();
+        let list = "from;to;type;weight;tags\na;b;link;2.0;\"[1,\"\"x\"\"]\"\n";
+        kg.import_csv(list.as_bytes(), &options).await.unwrap();
+        let link = &kg.get_relationships_by_type("link").await[0];
+        assert_eq!(link.properties["weight"], "2.0".into());
+        assert_eq!(link.properties["tags"], "[1,\"x\"]".into());
+        let kg = KnowledgeGraph::new();
+        kg.import_csv(
+            list.as_bytes(),
+            &CsvOptions {
+                delimiter: ';',
+                ..CsvOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+        let link = &kg.get_relationships_by_type("link").await[0];
+        assert_eq!(link.properties["weight"], 2.0.into());
+        assert_eq!(link.properties["tags"], vec![1.into(), "x".into()].into());
+    }
+
+    #[tokio::test]
+    async fn test_csv_round_trips_property_values() {
+        let time = "2024-03-01T09:30:00Z";
+        let properties: HashMap<String, PropertyValue> = [
+            ("int_text", "42".into()),
+            ("bool_text", "true".into()),
+            ("time_text", time.into()),
+            ("nan_text", "NaN".into()),
+            ("empty", "".into()),
+            ("padded", " x ".into()),
+            ("int", 42.into()),
+            (
+                "time",
+                time.parse::<chrono::DateTime<chrono::Utc>>()
+                    .unwrap()
+                    .into(),
+            ),
+            ("infinity", f64::INFINITY.into()),
+            ("negative_infinity", f64::NEG_INFINITY.into()),
+            ("nan", f64::NAN.into()),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+        let kg = KnowledgeGraph::new();
+        kg.add_relationship(Relationship {
+            properties: properties.clone(),
+            ..next(0, 1)
+        })
+        .await
+        .unwrap();
+
+        let options = CsvOptions::default();
+        let mut out = Vec::new();
+        kg.export_csv(&mut out, &options).await.unwrap();
+        let imported = KnowledgeGraph::new();
+        imported.import_csv(&out[..], &options).await.unwrap();
+        let mut restored = imported.get_relationships_by_type("next").await[0]
+            .properties
+            .clone();
+        match restored.remove("nan") {
+            Some(PropertyValue::Float(value)) => assert!(value.is_nan()),
+            other => panic!("unexpected {:?}", other),
+        }
+        let mut expected = properties;
+        expected.remove("nan");
+        assert_eq!(restored, expected);
+
+        // Quoted cells written by other tools are strings too, but JSON lists are
+        // still read as lists.
+        let quoted = "from,to,type,weight,tags,note\na,b,link,\"2\",\"[1,2]\",\"\"\n";
+        kg.import_csv(quoted.as_bytes(), &options).await.unwrap();
+        let link = &kg.get_relationships_by_type("link").await[0];
+        assert_eq!(link.properties["weight"], "2".into());
+        assert_eq!(link.properties["tags"], vec![1.into(), 2.into()].into());
+        assert_eq!(link.properties["note"], "".into());
+    }
+
+    #[tokio::test]
+    async fn test_import_errors() {
+        let vocabulary = Vocabulary::default();
+        let kg = KnowledgeGraph::new();
+        let error = |result: Result<usize, MetaSyntraXLError>| result.unwrap_err().to_string();
+
+        let ntriples = "<a:x> <a:p> <a:y> .\n<a:x> <a:p> .\n";
+        let message = error(kg.import_ntriples(ntriples.as_bytes(), &vocabulary).await);
+        assert!(
+            message.contains("Turtle parse error at line 2, column 13"),
+            "{}",
+            message
+        );
+        // The triples before the error stay imported.
+        assert_eq!(kg.get_relationships_by_type("a:p").await.len(), 1);
+
+        for (turtle, expected) in [
+            ("x:a x:b x:c .", "line 1, column 4: undefined prefix `x:`"),
+            ("<a:x> <a:p> (1 2) .", "collections are not supported"),
+            (
+                "<a:x> <a:p> \"open\n\" .",
+                "line 1, column 18: newline in a short string",
+            ),
+            (
+                "@prefix p: <a:> .\np:x p:y \"\"\"long",
+                "line 2, column 16: unterminated string",
+            ),
+            ("<a:x> <a:p> <a:y>", "expected `.`, found end of input"),
+        ] {
+            let message = error(kg.import_turtle(turtle.as_bytes(), &vocabulary).await);
+            assert!(message.contains(expected), "{}", message);
+        }
+
+        let message = error(
+            kg.import_jsonld(
+                r#"{"@context": "http://schema.org/"}"#.as_bytes(),
+                &vocabulary,
+            )
+            .await,
+        );
+        assert!(message.contains("remote contexts are not supported"));
+        let message = error(
+            kg.import_jsonld(r#"{"name": "x"}"#.as_bytes(), &vocabulary)
+                .await,
+        );
+        assert!(message.contains("cannot expand `name` without `@vocab`"));
+
+        let options = CsvOptions::default();
+        for (csv, expected) in [
+            ("source,to,type\n", "line 1: missing column `from`"),
+            (
+                "from,to,type\n\na,b,c\na,b\n",
+                "line 4: expected 3 fields, found 2",
+            ),
+            ("from,to,type\na,,c\n", "line 2: empty `to`"),
+            (
+                "from,to,type,note\na,b,c,\"open\n\n",
+                "line 2: unterminated quoted field",
+            ),
+        ] {
+            let message = error(kg.import_csv(csv.as_bytes(), &options).await);
+            assert!(message.contains(expected), "{}", message);
+        }
+    }
+
+    /// N-Triples generated a line at a time, ending with a malformed line.
+    struct GeneratedTriples {
+        lines: usize,
+        line: usize,
+        pending: Vec<u8>,
+    }
+
+    impl std::io::Read for GeneratedTriples {
+        fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+            if self.pending.is_empty() && self.line <= self.lines {
+                self.line += 1;
+                self.pending = if self.line <= self.lines {
+                    format!("<n:{}> <p:next> <n:{}> .\n", self.line - 1, self.line)
+                } else {
+                    "<n:0> <p:next> \"unterminated\n".to_string()
+                }
+                .into_bytes();
+            }
+            let count = buffer.len().min(self.pending.len());
+            buffer[..count].copy_from_slice(&self.pending[..count]);
+            self.pending.drain(..count);
+            Ok(count)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streaming_import() {
+        let kg = KnowledgeGraph::new();
+        let reader = std::io::BufReader::new(GeneratedTriples {
+            lines: 20_000,
+            line: 0,
+            pending: Vec::new(),
+        });
+        let message = kg
+            .import_ntriples(reader, &Vocabulary::default())
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("line 20001"), "{}", message);
+        assert_eq!(kg.get_relationships_by_type("p:next").await.len(), 20_000);
+        assert!(kg.get_relationships_from("n:19999", "p:next").await[0].to == "n:20000");
+    }
+
+    fn node(id: usize) -> Entity {
+        Entity {
+            id: id.to_string(),
+            properties: HashMap::from([("type".to_string(), "node".into())]),
+        }
+    }
+
+    fn next(from: usize, to: usize) -> Relationship {
+        Relationship {
+            from: from.to_string(),
+            to: to.to_string(),
+            type_: "next".to_string(),
+            properties: HashMap::new(),
+        }
+    }
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("knowledge-graph-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn open(directory: &Path, snapshot_every: Option<usize>) -> KnowledgeGraph {
+        let options = LogStorageOptions {
+            sync: false,
+            snapshot_every,
+        };
+        let storage = LogStorage::with_options(directory, options).unwrap();
+        KnowledgeGraph::with_storage(Box::new(storage)).unwrap()
+    }
+
+    /// The entities and relationships of a graph built from `node` and `next`,
+    /// sorted by id.
+    async fn contents(kg: &KnowledgeGraph) -> (Vec<usize>, Vec<(usize, usize)>) {
+        let mut ids: Vec<usize> = kg
+            .query_entities_by_property("type", "node")
+            .await
+            .iter()
+            .map(|e| e.id.parse().unwrap())
+            .collect();
+        ids.sort_unstable();
+        let edges = kg
+            .get_relationships_by_type("next")
+            .await
+            .iter()
+            .map(|r| (r.from.parse().unwrap(), r.to.parse().unwrap()))
+            .collect();
+        (ids, edges)
+    }
+
+    #[tokio::test]
+    async fn test_log_storage_survives_reopening() {
+        let directory = temp_directory("reopen");
+        let kg = open(&directory, None);
+        for i in 0..5 {
+            kg.add_entity(node(i)).await.unwrap();
+        }
+        kg.add_relationship(next(0, 1)).await.unwrap();
+        drop(kg);
+
+        let kg = open(&directory, None);
+        assert_eq!(contents(&kg).await, (vec![0, 1, 2, 3, 4], vec![(0, 1)]));
+        kg.snapshot().await.unwrap();
+        kg.add_relationship(next(1, 2)).await.unwrap();
+        kg.add_entity(Entity {
+            id: "4".to_string(),
+            properties: HashMap::from([("type".to_string(), "replaced".into())]),
+        })
+        .await
+        .unwrap();
+        drop(kg);
+
+        let kg = open(&directory, None);
+        assert_eq!(
+            contents(&kg).await,
+            (vec![0, 1, 2, 3], vec![(0, 1), (1, 2)])
+        );
+        assert_eq!(
+            kg.query_entities_by_property("type", "replaced")
+                .await
+                .len(),
+            1
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_log_storage_snapshots_periodically() {
+        let directory = temp_directory("periodic");
+        let kg = open(&directory, Some(4));
+        for i in 0..10 {
+            kg.add_entity(node(i)).await.unwrap();
+        }
+        drop(kg);
+        assert!(directory.join("snapshot.json").exists());
+        let log = fs::read_to_string(directory.join("log.jsonl")).unwrap();
+        assert_eq!(log.lines().count(), 2);
+
+        let kg = open(&directory, Some(4));
+        assert_eq!(contents(&kg).await.0, (0..10).collect::<Vec<_>>());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_log_storage_drops_torn_write() {
+        let directory = temp_directory("torn");
+        let kg = open(&directory, None);
+        kg.add_entity(node(0)).await.unwrap();
+        kg.add_entity(node(1)).await.unwrap();
+        drop(kg);
+
+        // A write cut short by a crash: the record is incomplete and unterminated.
+        let log = directory.join("log.jsonl");
+        let mut file = OpenOptions::new().append(true).open(&log).unwrap();
+        file.write_all(br#"{"sequence":3,"op":"add_entity","entity":{"id":"2","#)
+            .unwrap();
+        drop(file);
+
+        let kg = open(&directory, None);
+        assert_eq!(contents(&kg).await.0, vec![0, 1]);
+        kg.add_entity(node(2)).await.unwrap();
+        drop(kg);
+        let kg = open(&directory, None);
+        assert_eq!(contents(&kg).await.0, vec![0, 1, 2]);
+        drop(kg);
+
+        // A complete record that does not parse is corruption, not a torn write.
+        let mut file = OpenOptions::new().append(true).open(&log).unwrap();
+        file.write_all(b"{\"sequence\":\n").unwrap();
+        drop(file);
+        let storage = LogStorage::open(&directory).unwrap();
+        assert!(matches!(
+            KnowledgeGraph::with_storage(Box::new(storage)),
+            Err(MetaSyntraXLError::KnowledgeGraphError(_))
+        ));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_log_storage_skips_records_in_snapshot() {
+        let directory = temp_directory("replay");
+        let kg = open(&directory, None);
+        kg.add_entity(node(0)).await.unwrap();
+        kg.add_entity(node(1)).await.unwrap();
+        kg.add_relationship(next(0, 1)).await.unwrap();
+        let log = fs::read(directory.join("log.jsonl")).unwrap();
+        kg.snapshot().await.unwrap();
+        drop(kg);
+
+        // As if the process died after writing the snapshot but before truncating the log.
+        fs::write(directory.join("log.jsonl"), log).unwrap();
+        let kg = open(&directory, None);
+        assert_eq!(contents(&kg).await, (vec![0, 1], vec![(0, 1)]));
+        kg.add_relationship(next(1, 0)).await.unwrap();
+        drop(kg);
+        let kg = open(&directory, None);
+        assert_eq!(contents(&kg).await.1, vec![(0, 1), (1, 0)]);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    /// Records the threads it runs on, and fails every append after the first `limit`.
+    struct ThreadRecordingStorage {
+        threads: Arc<Mutex<Vec<std::thread::ThreadId>>>,
+        limit: usize,
+    }
+
+    impl GraphStorage for ThreadRecordingStorage {
+        fn load(&mut self) -> Result<GraphState, MetaSyntraXLError> {
+            Ok(GraphState::default())
+        }
+
+        fn append(&mut self, _operation: &GraphOperation) -> Result<(), MetaSyntraXLError> {
+            let mut threads = self.threads.lock().unwrap();
+            if threads.len() >= self.limit {
+                return Err(MetaSyntraXLError::KnowledgeGraphError("full".to_string()));
+            }
+            threads.push(std::thread::current().id());
+            Ok(())
+        }
+
+        fn snapshot(&mut self, _state: &GraphState) -> Result<(), MetaSyntraXLError> {
+            self.threads
+                .lock()
+                .unwrap()
+                .push(std::thread::current().id());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_storage_runs_off_the_executor() {
+        let threads = Arc::new(Mutex::new(Vec::new()));
+        let storage = ThreadRecordingStorage {
+            threads: Arc::clone(&threads),
+            limit: 2,
+        };
+        let kg = KnowledgeGraph::with_storage(Box::new(storage)).unwrap();
+        kg.add_entity(node(0)).await.unwrap();
+        kg.snapshot().await.unwrap();
+        assert!(kg.add_entity(node(1)).await.is_err());
+        assert_eq!(contents(&kg).await.0, vec![0]);
+
+        // The test's runtime has a single thread, which every call must avoid.
+        let threads = threads.lock().unwrap();
+        assert_eq!(threads.len(), 2);
+        assert!(!threads.contains(&std::thread::current().id()));
+    }
+
+    /// Not a test on its own: run by `test_log_storage_recovers_after_kill` in a
+    /// child process, which it extends with a chain of nodes until it is killed.
+    #[tokio::test]
+    #[ignore = "run by test_log_storage_recovers_after_kill"]
+    async fn crash_writer() {
+        let directory = std::env::var(CRASH_WRITER_DIRECTORY)
+            .map(PathBuf::from)
+            .expect("the directory to write to is set by the parent test");
+        let kg = open(&directory, Some(25));
+        for i in 0.. {
+            kg.add_entity(node(i)).await.unwrap();
+            if i > 0 {
+                kg.add_relationship(next(i - 1, i)).await.unwrap();
+            }
+        }
+    }
+
+    const CRASH_WRITER_DIRECTORY: &str = "KNOWLEDGE_GRAPH_CRASH_WRITER_DIRECTORY";
+
+    #[tokio::test]
+    async fn test_log_storage_recovers_after_kill() {
+        for round in 0..5 {
+            let directory = temp_directory(&format!("kill-{}", round));
+            let mut child = Command::new(std::env::current_exe().unwrap())
+                .args([
+                    "--ignored",
+                    "--exact",
+                    "knowledge_graph::tests::crash_writer",
+                ])
+                .env(CRASH_WRITER_DIRECTORY, &directory)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .unwrap();
+            let started = Instant::now();
+            while !directory.join("snapshot.json").exists() {
+                assert!(
+                    started.elapsed() < Duration::from_secs(30),
+                    "writer never snapshotted"
+                );
+                assert!(child.try_wait().unwrap().is_none(), "writer exited");
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            std::thread::sleep(Duration::from_millis(5 * round));
+            child.kill().unwrap();
+            child.wait().unwrap();
+
+            // Whatever was cut off, the recovered graph is a prefix of the chain.
+            let kg = open(&directory, None);
+            let (ids, edges) = contents(&kg).await;
+            // The first snapshot covers 25 operations, 13 of them entities.
+            assert!(ids.len() >= 13);
+            assert_eq!(ids, (0..ids.len()).collect::<Vec<_>>());
+            assert!(edges.len() + 2 >= ids.len() && edges.len() < ids.len());
+            assert_eq!(
+                edges,
+                (1..=edges.len()).map(|i| (i - 1, i)).collect::<Vec<_>>()
+            );
+
+            kg.add_entity(node(ids.len())).await.unwrap();
+            drop(kg);
+            let kg = open(&directory, None);
+            assert_eq!(contents(&kg).await.0.len(), ids.len() + 1);
+            fs::remove_dir_all(&directory).unwrap();
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,590 @@
+// src/knowledge_graph/plan.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[KNOWLEDGE-GRAPH]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::query::{
+    Comparison, Condition, Literal, NodePattern, Operand, Pattern, Query, RelationshipPattern,
+    ANONYMOUS,
+};
+use super::traversal::{Direction, Graph};
+use super::{Entity, PropertyValue, Relationship};
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// One value of a query result row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryValue {
+    Entity(Entity),
+    Relationship(Relationship),
+    Property(PropertyValue),
+    /// A property the entity or relationship does not have.
+    Null,
+}
+
+/// The rows a query returns, with one value per column.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<QueryValue>>,
+}
+
+/// How a query binds its variables, one pattern at a time: each pattern starts
+/// from its cheapest node and expands along adjacency lists from there.
+pub(crate) struct Plan<'q> {
+    query: &'q Query,
+    steps: Vec<Step<'q>>,
+}
+
+enum Step<'q> {
+    /// Binds `node` to the entity with id `id`.
+    Lookup { node: &'q NodePattern, id: &'q str },
+    /// Binds `node` to the entities whose `key` is `value`, from the property index.
+    Seek {
+        node: &'q NodePattern,
+        key: &'q str,
+        value: &'q Literal,
+    },
+    /// Binds `node` to every entity.
+    Scan { node: &'q NodePattern },
+    /// Checks `node`, bound by an earlier pattern, against this occurrence.
+    Check { node: &'q NodePattern },
+    /// Binds `relationship` and `to` by following the relationships of `from`,
+    /// or checks `to` if it is bound already.
+    Expand {
+        from: &'q str,
+        relationship: &'q RelationshipPattern,
+        direction: Direction,
+        to: &'q NodePattern,
+    },
+}
+
+#[derive(Clone, Copy)]
+enum Element<'g> {
+    Entity(&'g Entity),
+    Relationship(&'g Relationship),
+}
+
+type Bindings<'q, 'g> = Vec<(&'q str, Element<'g>)>;
+
+/// A property, or a field such as an entity's id.
+enum Field<'g> {
+    Text(&'g str),
+    Property(&'g PropertyValue),
+}
+
+#[derive(Clone, Copy)]
+enum Value<'a> {
+    Text(&'a str),
+    Number(f64),
+    Bool(bool),
+    Time(DateTime<Utc>),
+    List(&'a [PropertyValue]),
+    Null,
+}
+
+impl<'a> From<&'a PropertyValue> for Value<'a> {
+    fn from(value: &'a PropertyValue) -> Self {
+        match value {
+            PropertyValue::String(text) => Value::Text(text),
+            PropertyValue::Int(number) => Value::Number(*number as f64),
+            PropertyValue::Float(number) => Value::Number(*number),
+            PropertyValue::Bool(value) => Value::Bool(*value),
+            PropertyValue::DateTime(time) => Value::Time(*time),
+            PropertyValue::List(values) => Value::List(values),
+        }
+    }
+}
+
+impl<'a> From<&'a Literal> for Value<'a> {
+    fn from(literal: &'a Literal) -> Self {
+        match literal {
+            Literal::String(text) => Value::Text(text),
+            Literal::Number(number) => Value::Number(*number),
+            Literal::Bool(value) => Value::Bool(*value),
+            Literal::Null => Value::Null,
+        }
+    }
+}
+
+impl<'a> From<Option<Field<'a>>> for Value<'a> {
+    fn from(field: Option<Field<'a>>) -> Self {
+        match field {
+            Some(Field::Text(text)) => Value::Text(text),
+            Some(Field::Property(value)) => Value::from(value),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<'q> Plan<'q> {
+    pub(crate) fn new(query: &'q Query) -> Self {
+        let equalities = equalities(query.condition.as_ref());
+        let mut bound: HashSet<&str> = HashSet::new();
+        let mut steps = Vec::new();
+        for pattern in &query.patterns {
+            let (anchor, step) = start(pattern, &bound, &equalities);
+            steps.push(step);
+            for i in anchor..pattern.relationships.len() {
+                steps.push(Step::Expand {
+                    from: &pattern.nodes[i].variable,
+                    relationship: &pattern.relationships[i],
+                    direction: pattern.relationships[i].direction,
+                    to: &pattern.nodes[i + 1],
+                });
+            }
+            for i in (0..anchor).rev() {
+                let direction = match pattern.relationships[i].direction {
+                    Direction::Outgoing => Direction::Incoming,
+                    Direction::Incoming => Direction::Outgoing,
+                    Direction::Both => Direction::Both,
+                };
+                steps.push(Step::Expand {
+                    from: &pattern.nodes[i + 1].variable,
+                    relationship: &pattern.relationships[i],
+                    direction,
+                    to: &pattern.nodes[i],
+                });
+            }
+            bound.extend(pattern.nodes.iter().map(|n| n.variable.as_str()));
+        }
+        Self { query, steps }
+    }
+
+    /// One line per step, in the order they run.
+    pub(crate) fn describe(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .steps
+            .iter()
+            .map(|step| match step {
+                Step::Lookup { node, id } => {
+                    format!("Lookup ({}) by id {:?}", name(&node.variable), id)
+                }
+                Step::Seek { node, key, value } => {
+                    let value = match value {
+                        Literal::String(text) => format!("{:?}", text),
+                        Literal::Number(number) => number.to_string(),
+                        Literal::Bool(value) => value.to_string(),
+                        Literal::Null => "null".to_string(),
+                    };
+                    format!(
+                        "IndexSeek ({}) on {} = {}",
+                        name(&node.variable),
+                        key,
+                        value
+                    )
+                }
+                Step::Scan { node } => format!("Scan ({}) over all entities", name(&node.variable)),
+                Step::Check { node } => format!("Check ({})", name(&node.variable)),
+                Step::Expand {
+                    from,
+                    relationship,
+                    direction,
+                    to,
+                } => {
+                    let (left, right) = match direction {
+                        Direction::Outgoing => ("-", "->"),
+                        Direction::Incoming => ("<-", "-"),
+                        Direction::Both => ("-", "-"),
+                    };
+                    let types = if relationship.types.is_empty() {
+                        String::new()
+                    } else {
+                        format!(":{}", relationship.types.join("|"))
+                    };
+                    format!(
+                        "Expand ({}){}[{}{}]{}({})",
+                        name(from),
+                        left,
+                        name(&relationship.variable),
+                        types,
+                        right,
+                        name(&to.variable)
+                    )
+                }
+            })
+            .collect();
+        if self.query.condition.is_some() {
+            lines.push("Filter".to_string());
+        }
+        let columns: Vec<&str> = self
+            .query
+            .returns
+            .iter()
+            .map(|r| r.column.as_str())
+            .collect();
+        lines.push(format!("Return {}", columns.join(", ")));
+        if let Some(limit) = self.query.limit {
+            lines.push(format!("Limit {}", limit));
+        }
+        lines
+    }
+
+    pub(crate) fn execute(&self, graph: &Graph) -> QueryResult {
+        let mut result = QueryResult {
+            columns: self
+                .query
+                .returns
+                .iter()
+                .map(|r| r.column.clone())
+                .collect(),
+            rows: Vec::new(),
+        };
+        if self.query.limit != Some(0) {
+            self.run(graph, 0, &mut Vec::new(), &mut result);
+        }
+        result
+    }
+
+    /// Binds the variables of `steps[step..]` in every way that matches, adding a
+    /// row for each; returns `false` once the limit is reached.
+    fn run<'g>(
+        &self,
+        graph: &Graph<'g>,
+        step: usize,
+        bindings: &mut Bindings<'q, 'g>,
+        result: &mut QueryResult,
+    ) -> bool {
+        let current = match self.steps.get(step) {
+            Some(current) => current,
+            None => {
+                if let Some(condition) = &self.query.condition {
+                    if !evaluate(condition, bindings) {
+                        return true;
+                    }
+                }
+                result.rows.push(self.row(bindings));
+                return self.query.limit != Some(result.rows.len());
+            }
+        };
+
+        let mut candidates: Vec<(Option<&'g Relationship>, &'g Entity)> = Vec::new();
+        let node = match current {
+            Step::Lookup { node, id } => {
+                candidates.extend(graph.entities.get(id).map(|e| (None, e)));
+                node
+            }
+            Step::Seek { node, key, value } => {
+                let mut entities: Vec<&Entity> = candidates_of(value)
+                    .iter()
+                    .flat_map(|value| graph.entities.with_property(key, value))
+                    .collect();
+                entities.sort_by(|a, b| a.id.cmp(&b.id));
+                candidates.extend(entities.into_iter().map(|e| (None, e)));
+                node
+            }
+            Step::Scan { node } => {
+                let mut entities: Vec<&Entity> = graph.entities.entities().values().collect();
+                entities.sort_by(|a, b| a.id.cmp(&b.id));
+                candidates.extend(entities.into_iter().map(|e| (None, e)));
+                node
+            }
+            Step::Check { node } => {
+                if let Some(Element::Entity(entity)) = lookup(bindings, &node.variable) {
+                    candidates.push((None, entity));
+                }
+                node
+            }
+            Step::Expand {
+                from,
+                relationship,
+                direction,
+                to,
+            } => {
+                let from = match lookup(bindings, from) {
+                    Some(Element::Entity(entity)) => entity,
+                    _ => return true,
+                };
+                for (outgoing, followed) in [
+                    (true, *direction != Direction::Incoming),
+                    (false, *direction != Direction::Outgoing),
+                ] {
+                    if !followed {
+                        continue;
+                    }
+                    for r in graph
+                        .relationships
+                        .adjacent(&from.id, outgoing, &relationship.types)
+                    {
+                        // An undirected pattern already met a self-loop going out.
+                        if !outgoing && *direction == Direction::Both && r.from == r.to {
+                            continue;
+                        }
+                        let other = if outgoing { &r.to } else { &r.from };
+                        if let Some(entity) = graph.entities.get(other) {
+                            candidates.push((Some(r), entity));
+                        }
+                    }
+                }
+                to
+            }
+        };
+
+        let bound = match lookup(bindings, &node.variable) {
+            Some(Element::Entity(entity)) => Some(entity),
+            _ => None,
+        };
+        for (relationship, entity) in candidates {
+            if matches!(bound, Some(b) if b.id != entity.id) || !satisfies(node, entity) {
+                continue;
+            }
+            let depth = bindings.len();
+            if let (
+                Some(r),
+                Step::Expand {
+                    relationship: pattern,
+                    ..
+                },
+            ) = (relationship, current)
+            {
+                // A relationship is matched at most once per row.
+                let used = bindings
+                    .iter()
+                    .any(|(_, e)| matches!(e, Element::Relationship(b) if std::ptr::eq(*b, r)));
+                if used {
+                    continue;
+                }
+                bindings.push((&pattern.variable, Element::Relationship(r)));
+            }
+            if bound.is_none() {
+                bindings.push((&node.variable, Element::Entity(entity)));
+            }
+            let more = self.run(graph, step + 1, bindings, result);
+            bindings.truncate(depth);
+            if !more {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn row(&self, bindings: &Bindings) -> Vec<QueryValue> {
+        self.query
+            .returns
+            .iter()
+            .map(|item| {
+                let element =
+                    lookup(bindings, &item.variable).expect("returned variables are bound");
+                match (&item.key, element) {
+                    (None, Element::Entity(entity)) => QueryValue::Entity(entity.clone()),
+                    (None, Element::Relationship(r)) => QueryValue::Relationship(r.clone()),
+                    (Some(key), element) => match property(element, key) {
+                        Some(Field::Text(text)) => QueryValue::Property(text.into()),
+                        Some(Field::Property(value)) => QueryValue::Property(value.clone()),
+                        None => QueryValue::Null,
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+/// Where a pattern starts: a node bound by an earlier pattern, then one with a
+/// known id, then one with a known property value, else a scan of the first.
+fn start<'q>(
+    pattern: &'q Pattern,
+    bound: &HashSet<&str>,
+    equalities: &[(&'q str, &'q str, &'q Literal)],
+) -> (usize, Step<'q>) {
+    let known = |node: &'q NodePattern| {
+        node.properties
+            .iter()
+            .map(|(key, value)| (key.as_str(), value))
+            .chain(
+                equalities
+                    .iter()
+                    .filter(move |(variable, _, _)| *variable == node.variable)
+                    .map(|&(_, key, value)| (key, value)),
+            )
+    };
+    if let Some(i) = pattern
+        .nodes
+        .iter()
+        .position(|n| bound.contains(n.variable.as_str()))
+    {
+        return (
+            i,
+            Step::Check {
+                node: &pattern.nodes[i],
+            },
+        );
+    }
+    for (i, node) in pattern.nodes.iter().enumerate() {
+        if let Some((_, Literal::String(id))) = known(node).find(|(key, _)| *key == "id") {
+            return (i, Step::Lookup { node, id });
+        }
+    }
+    for (i, node) in pattern.nodes.iter().enumerate() {
+        // `id` is not a property, so it is not in the property index.
+        if let Some((key, value)) = known(node).find(|(key, _)| *key != "id") {
+            return (i, Step::Seek { node, key, value });
+        }
+    }
+    (
+        0,
+        Step::Scan {
+            node: &pattern.nodes[0],
+        },
+    )
+}
+
+/// The `variable.key = literal` comparisons that every row has to pass, i.e. the
+/// ones joined to the rest of the condition by `AND` alone.
+fn equalities(condition: Option<&Condition>) -> Vec<(&str, &str, &Literal)> {
+    let mut equalities = Vec::new();
+    let mut stack: Vec<&Condition> = condition.into_iter().collect();
+    while let Some(condition) = stack.pop() {
+        match condition {
+            Condition::And(a, b) => {
+                stack.push(b);
+                stack.push(a);
+            }
+            Condition::Compare(left, Comparison::Equal, right) => match (left, right) {
+                (Operand::Property(variable, key), Operand::Literal(value))
+                | (Operand::Literal(value), Operand::Property(variable, key))
+                    if *value != Literal::Null =>
+                {
+                    equalities.push((variable.as_str(), key.as_str(), value))
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    equalities
+}
+
+/// Anonymous variables are shown as nothing.
+fn name(variable: &str) -> &str {
+    if variable.starts_with(ANONYMOUS) {
+        ""
+    } else {
+        variable
+    }
+}
+
+fn lookup<'g>(bindings: &Bindings<'_, 'g>, variable: &str) -> Option<Element<'g>> {
+    bindings
+        .iter()
+        .rev()
+        .find(|(v, _)| *v == variable)
+        .map(|&(_, element)| element)
+}
+
+fn satisfies(node: &NodePattern, entity: &Entity) -> bool {
+    node.properties.iter().all(|(key, value)| {
+        compare(
+            Value::from(property(Element::Entity(entity), key)),
+            Comparison::Equal,
+            Value::from(value),
+        )
+    })
+}
+
+/// The property values equal to `literal`, which the index can look up; a string
+/// also equals the datetime it spells and a whole number the integer.
+fn candidates_of(literal: &Literal) -> Vec<PropertyValue> {
+    match literal {
+        Literal::String(text) => {
+            let mut candidates = vec![PropertyValue::String(text.clone())];
+            candidates.extend(time(text).map(PropertyValue::DateTime));
+            candidates
+        }
+        Literal::Number(number) => {
+            let mut candidates = vec![PropertyValue::Float(*number)];
+            if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+                candidates.push(PropertyValue::Int(*number as i64));
+            }
+            candidates
+        }
+        Literal::Bool(value) => vec![PropertyValue::Bool(*value)],
+        Literal::Null => Vec::new(),
+    }
+}
+
+fn time(text: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// `id` is an entity's id; relationships have `from`, `to` and `type` besides
+/// their properties.
+fn property<'g>(element: Element<'g>, key: &str) -> Option<Field<'g>> {
+    match element {
+        Element::Entity(entity) if key == "id" => Some(Field::Text(&entity.id)),
+        Element::Entity(entity) => entity.properties.get(key).map(Field::Property),
+        Element::Relationship(r) => match key {
+            "from" => Some(Field::Text(&r.from)),
+            "to" => Some(Field::Text(&r.to)),
+            "type" => Some(Field::Text(&r.type_)),
+            _ => r.properties.get(key).map(Field::Property),
+        },
+    }
+}
+
+fn evaluate(condition: &Condition, bindings: &Bindings) -> bool {
+    match condition {
+        Condition::And(a, b) => evaluate(a, bindings) && evaluate(b, bindings),
+        Condition::Or(a, b) => evaluate(a, bindings) || evaluate(b, bindings),
+        Condition::Not(a) => !evaluate(a, bindings),
+        Condition::IsNull(operand) => matches!(value(operand, bindings), Value::Null),
+        Condition::Compare(left, comparison, right) => {
+            compare(value(left, bindings), *comparison, value(right, bindings))
+        }
+    }
+}
+
+fn value<'a>(operand: &'a Operand, bindings: &Bindings<'_, 'a>) -> Value<'a> {
+    match operand {
+        Operand::Property(variable, key) => {
+            Value::from(lookup(bindings, variable).and_then(|element| property(element, key)))
+        }
+        Operand::Literal(literal) => Value::from(literal),
+    }
+}
+
+/// Values of different types are never equal, except that integers and floats
+/// compare as numbers and a string compares with a datetime as the datetime it
+/// spells. `CONTAINS` finds a substring of a string or an element of a list.
+/// Any comparison with null is false.
+fn compare(left: Value, comparison: Comparison, right: Value) -> bool {
+    if let (Value::List(items), Comparison::Contains) = (left, comparison) {
+        return items
+            .iter()
+            .any(|item| compare(Value::from(item), Comparison::Equal, right));
+    }
+    let ordering = match (left, right) {
+        (Value::Null, _) | (_, Value::Null) => return false,
+        (Value::Text(a), Value::Text(b)) => {
+            match comparison {
+                Comparison::Contains => return a.contains(b),
+                Comparison::StartsWith => return a.starts_with(b),
+                Comparison::EndsWith => return a.ends_with(b),
+                _ => {}
+            }
+            Some(a.cmp(b))
+        }
+        (Value::Number(a), Value::Number(b)) => a.partial_cmp(&b),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(&b)),
+        (Value::Time(a), Value::Time(b)) => Some(a.cmp(&b)),
+        (Value::Time(a), Value::Text(b)) => time(b).map(|b| a.cmp(&b)),
+        (Value::Text(a), Value::Time(b)) => time(a).map(|a| a.cmp(&b)),
+        (Value::List(a), Value::List(b)) => {
+            let equal = a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(a, b)| compare(Value::from(a), Comparison::Equal, Value::from(b)));
+            equal.then_some(Ordering::Equal)
+        }
+        _ => None,
+    };
+    match comparison {
+        Comparison::Equal => ordering == Some(Ordering::Equal),
+        Comparison::NotEqual => ordering != Some(Ordering::Equal),
+        Comparison::Less => ordering == Some(Ordering::Less),
+        Comparison::LessOrEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        Comparison::Greater => ordering == Some(Ordering::Greater),
+        Comparison::GreaterOrEqual => {
+            matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+        }
+        Comparison::Contains | Comparison::StartsWith | Comparison::EndsWith => false,
+    }
+}
\ No newline at end of file
@@ -0,0 +1,604 @@
+// src/knowledge_graph/query.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[KNOWLEDGE-GRAPH]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::traversal::Direction;
+use crate::errors::MetaSyntraXLError;
+
+/// A parsed query; see [`super::KnowledgeGraph::query`] for the syntax.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Query {
+    pub(crate) patterns: Vec<Pattern>,
+    pub(crate) condition: Option<Condition>,
+    pub(crate) returns: Vec<ReturnItem>,
+    pub(crate) limit: Option<usize>,
+}
+
+/// A chain of nodes; `relationships[i]` links `nodes[i]` and `nodes[i + 1]`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Pattern {
+    pub(crate) nodes: Vec<NodePattern>,
+    pub(crate) relationships: Vec<RelationshipPattern>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NodePattern {
+    /// The variable, or a generated name that cannot clash with one.
+    pub(crate) variable: String,
+    pub(crate) properties: Vec<(String, Literal)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RelationshipPattern {
+    pub(crate) variable: String,
+    /// Empty matches every type.
+    pub(crate) types: Vec<String>,
+    /// Relative to the pattern read left to right.
+    pub(crate) direction: Direction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Condition {
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+    Compare(Operand, Comparison, Operand),
+    IsNull(Operand),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Operand {
+    Property(String, String),
+    Literal(Literal),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Literal {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ReturnItem {
+    pub(crate) variable: String,
+    pub(crate) key: Option<String>,
+    /// The column name: the alias, or the item as written.
+    pub(crate) column: String,
+}
+
+/// Prefix of the names given to anonymous nodes and relationships; variables
+/// cannot start with a space.
+pub(crate) const ANONYMOUS: &str = " anonymous";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Symbol(&'static str),
+    Word(String),
+    String(String),
+    Number(f64),
+    End,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Symbol(symbol) => format!("`{}`", symbol),
+            Token::Word(word) => format!("`{}`", word),
+            Token::String(text) => format!("string {:?}", text),
+            Token::Number(number) => format!("number {}", number),
+            Token::End => "end of query".to_string(),
+        }
+    }
+}
+
+/// Longest first, so `<-` wins over `<`.
+const SYMBOLS: [&str; 20] = [
+    "<-", "->", "<>", "!=", "<=", ">=", "(", ")", "[", "]", "{", "}", ":", ",", ".", "|", "-", "=",
+    "<", ">",
+];
+
+/// Splits `text` into tokens, each with its byte offset.
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, MetaSyntraXLError> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    loop {
+        let trimmed = rest.trim_start();
+        let offset = text.len() - trimmed.len();
+        rest = trimmed;
+        let c = match rest.chars().next() {
+            Some(c) => c,
+            None => break,
+        };
+
+        if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+            tokens.push((Token::Symbol(symbol), offset));
+            rest = &rest[symbol.len()..];
+        } else if c == '"' || c == '\'' {
+            let mut value = String::new();
+            let mut chars = rest.char_indices().skip(1);
+            let end = loop {
+                match chars.next() {
+                    Some((i, q)) if q == c => break i,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, 'n')) => value.push('\n'),
+                        Some((_, 't')) => value.push('\t'),
+                        Some((_, escaped)) => value.push(escaped),
+                        None => return Err(parse_error(text, offset, "unterminated string")),
+                    },
+                    Some((_, other)) => value.push(other),
+                    None => return Err(parse_error(text, offset, "unterminated string")),
+                }
+            };
+            tokens.push((Token::String(value), offset));
+            rest = &rest[end + 1..];
+        } else if c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            let number = rest[..end].parse().map_err(|_| {
+                parse_error(text, offset, &format!("invalid number `{}`", &rest[..end]))
+            })?;
+            tokens.push((Token::Number(number), offset));
+            rest = &rest[end..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push((Token::Word(rest[..end].to_string()), offset));
+            rest = &rest[end..];
+        } else {
+            return Err(parse_error(
+                text,
+                offset,
+                &format!("unexpected character `{}`", c),
+            ));
+        }
+    }
+    tokens.push((Token::End, text.len()));
+    Ok(tokens)
+}
+
+/// A `KnowledgeGraphError` pointing at `offset` in `text`, by line and column.
+fn parse_error(text: &str, offset: usize, message: &str) -> MetaSyntraXLError {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    MetaSyntraXLError::KnowledgeGraphError(format!(
+        "Query parse error at line {}, column {}: {}",
+        line, column, message
+    ))
+}
+
+pub(crate) fn parse(text: &str) -> Result<Query, MetaSyntraXLError> {
+    let mut parser = Parser {
+        text,
+        tokens: tokenize(text)?,
+        position: 0,
+        anonymous: 0,
+    };
+    let query = parser.query()?;
+    check_variables(&query)?;
+    Ok(query)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    anonymous: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.position].0.clone();
+        if token != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error(&self, expected: &str) -> MetaSyntraXLError {
+        let (token, offset) = &self.tokens[self.position];
+        parse_error(
+            self.text,
+            *offset,
+            &format!("expected {}, found {}", expected, token.describe()),
+        )
+    }
+
+    fn at_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Token::Symbol(s) if *s == symbol)
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Word(w) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn symbol(&mut self, symbol: &str) -> Result<(), MetaSyntraXLError> {
+        if self.at_symbol(symbol) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.error(&format!("`{}`", symbol)))
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), MetaSyntraXLError> {
+        if self.at_keyword(keyword) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.error(keyword))
+        }
+    }
+
+    /// A name: an identifier, or a quoted string for names that are not one.
+    fn name(&mut self, what: &str) -> Result<String, MetaSyntraXLError> {
+        match self.peek().clone() {
+            Token::Word(word) | Token::String(word) => {
+                self.advance();
+                Ok(word)
+            }
+            _ => Err(self.error(what)),
+        }
+    }
+
+    fn variable(&mut self) -> Result<String, MetaSyntraXLError> {
+        match self.peek().clone() {
+            Token::Word(word) => {
+                self.advance();
+                Ok(word)
+            }
+            _ => Err(self.error("a variable")),
+        }
+    }
+
+    fn anonymous(&mut self) -> String {
+        self.anonymous += 1;
+        format!("{}{}", ANONYMOUS, self.anonymous)
+    }
+
+    fn query(&mut self) -> Result<Query, MetaSyntraXLError> {
+        if self.at_keyword("MATCH") {
+            self.advance();
+        }
+        let mut patterns = vec![self.pattern()?];
+        while self.at_symbol(",") {
+            self.advance();
+            patterns.push(self.pattern()?);
+        }
+
+        let condition = if self.at_keyword("WHERE") {
+            self.advance();
+            Some(self.or()?)
+        } else {
+            None
+        };
+
+        self.keyword("RETURN")?;
+        let mut returns = vec![self.return_item()?];
+        while self.at_symbol(",") {
+            self.advance();
+            returns.push(self.return_item()?);
+        }
+
+        let limit = if self.at_keyword("LIMIT") {
+            self.advance();
+            match self.peek().clone() {
+                Token::Number(n) if n.fract() == 0.0 && n >= 0.0 => {
+                    self.advance();
+                    Some(n as usize)
+                }
+                _ => return Err(self.error("a whole number")),
+            }
+        } else {
+            None
+        };
+
+        if *self.peek() != Token::End {
+            return Err(self.error("end of query"));
+        }
+        Ok(Query {
+            patterns,
+            condition,
+            returns,
+            limit,
+        })
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, MetaSyntraXLError> {
+        let mut nodes = vec![self.node()?];
+        let mut relationships = Vec::new();
+        while self.at_symbol("-") || self.at_symbol("<-") {
+            relationships.push(self.relationship()?);
+            nodes.push(self.node()?);
+        }
+        Ok(Pattern {
+            nodes,
+            relationships,
+        })
+    }
+
+    fn node(&mut self) -> Result<NodePattern, MetaSyntraXLError> {
+        self.symbol("(")?;
+        let variable = match self.peek() {
+            Token::Word(_) => self.variable()?,
+            _ => self.anonymous(),
+        };
+        let mut properties = Vec::new();
+        if self.at_symbol("{") {
+            self.advance();
+            loop {
+                let key = self.name("a property name")?;
+                self.symbol(":")?;
+                let value = match self.literal()? {
+                    Some(Literal::Null) | None => {
+                        return Err(self.error("a string, number or boolean"));
+                    }
+                    Some(literal) => literal,
+                };
+                properties.push((key, value));
+                if self.at_symbol(",") {
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+            self.symbol("}")?;
+        }
+        self.symbol(")")?;
+        Ok(NodePattern {
+            variable,
+            properties,
+        })
+    }
+
+    fn relationship(&mut self) -> Result<RelationshipPattern, MetaSyntraXLError> {
+        let incoming = self.at_symbol("<-");
+        self.advance();
+        self.symbol("[")?;
+        let variable = match self.peek() {
+            Token::Word(_) => self.variable()?,
+            _ => self.anonymous(),
+        };
+        let mut types = Vec::new();
+        if self.at_symbol(":") {
+            self.advance();
+            types.push(self.name("a relationship type")?);
+            while self.at_symbol("|") {
+                self.advance();
+                types.push(self.name("a relationship type")?);
+            }
+        }
+        self.symbol("]")?;
+        let direction = if self.at_symbol("->") {
+            if incoming {
+                return Err(self.error("`-`"));
+            }
+            Direction::Outgoing
+        } else if self.at_symbol("-") {
+            if incoming {
+                Direction::Incoming
+            } else {
+                Direction::Both
+            }
+        } else {
+            return Err(self.error(if incoming { "`-`" } else { "`->` or `-`" }));
+        };
+        self.advance();
+        Ok(RelationshipPattern {
+            variable,
+            types,
+            direction,
+        })
+    }
+
+    fn or(&mut self) -> Result<Condition, MetaSyntraXLError> {
+        let mut condition = self.and()?;
+        while self.at_keyword("OR") {
+            self.advance();
+            condition = Condition::Or(Box::new(condition), Box::new(self.and()?));
+        }
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<Condition, MetaSyntraXLError> {
+        let mut condition = self.not()?;
+        while self.at_keyword("AND") {
+            self.advance();
+            condition = Condition::And(Box::new(condition), Box::new(self.not()?));
+        }
+        Ok(condition)
+    }
+
+    fn not(&mut self) -> Result<Condition, MetaSyntraXLError> {
+        if self.at_keyword("NOT") {
+            self.advance();
+            return Ok(Condition::Not(Box::new(self.not()?)));
+        }
+        if self.at_symbol("(") {
+            self.advance();
+            let condition = self.or()?;
+            self.symbol(")")?;
+            return Ok(condition);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Condition, MetaSyntraXLError> {
+        let left = self.operand()?;
+        if self.at_keyword("IS") {
+            self.advance();
+            let negated = self.at_keyword("NOT");
+            if negated {
+                self.advance();
+            }
+            self.keyword("NULL")?;
+            let condition = Condition::IsNull(left);
+            return Ok(if negated {
+                Condition::Not(Box::new(condition))
+            } else {
+                condition
+            });
+        }
+
+        let comparison = match self.peek().clone() {
+            Token::Symbol("=") => Comparison::Equal,
+            Token::Symbol("<>") | Token::Symbol("!=") => Comparison::NotEqual,
+            Token::Symbol("<") => Comparison::Less,
+            Token::Symbol("<=") => Comparison::LessOrEqual,
+            Token::Symbol(">") => Comparison::Greater,
+            Token::Symbol(">=") => Comparison::GreaterOrEqual,
+            Token::Word(w) if w.eq_ignore_ascii_case("CONTAINS") => Comparison::Contains,
+            Token::Word(w) if w.eq_ignore_ascii_case("STARTS") => {
+                self.advance();
+                self.keyword("WITH")?;
+                return Ok(Condition::Compare(
+                    left,
+                    Comparison::StartsWith,
+                    self.operand()?,
+                ));
+            }
+            Token::Word(w) if w.eq_ignore_ascii_case("ENDS") => {
+                self.advance();
+                self.keyword("WITH")?;
+                return Ok(Condition::Compare(
+                    left,
+                    Comparison::EndsWith,
+                    self.operand()?,
+                ));
+            }
+            _ => return Err(self.error("a comparison")),
+        };
+        self.advance();
+        Ok(Condition::Compare(left, comparison, self.operand()?))
+    }
+
+    fn operand(&mut self) -> Result<Operand, MetaSyntraXLError> {
+        if let Some(literal) = self.literal()? {
+            return Ok(Operand::Literal(literal));
+        }
+        match self.peek() {
+            Token::Word(_) if self.tokens[self.position + 1].0 == Token::Symbol(".") => {
+                let variable = self.variable()?;
+                self.symbol(".")?;
+                let key = self.name("a property name")?;
+                Ok(Operand::Property(variable, key))
+            }
+            _ => Err(self.error("a property or literal")),
+        }
+    }
+
+    /// The literal at the current token, if it is one.
+    fn literal(&mut self) -> Result<Option<Literal>, MetaSyntraXLError> {
+        let literal = match self.peek().clone() {
+            Token::String(text) => Literal::String(text),
+            Token::Number(number) => Literal::Number(number),
+            Token::Symbol("-") => {
+                self.advance();
+                match self.peek().clone() {
+                    Token::Number(number) => Literal::Number(-number),
+                    _ => return Err(self.error("a number")),
+                }
+            }
+            Token::Word(word) if word.eq_ignore_ascii_case("true") => Literal::Bool(true),
+            Token::Word(word) if word.eq_ignore_ascii_case("false") => Literal::Bool(false),
+            Token::Word(word) if word.eq_ignore_ascii_case("null") => Literal::Null,
+            _ => return Ok(None),
+        };
+        self.advance();
+        Ok(Some(literal))
+    }
+
+    fn return_item(&mut self) -> Result<ReturnItem, MetaSyntraXLError> {
+        let variable = self.variable()?;
+        let key = if self.at_symbol(".") {
+            self.advance();
+            Some(self.name("a property name")?)
+        } else {
+            None
+        };
+        let column = if self.at_keyword("AS") {
+            self.advance();
+            self.name("an alias")?
+        } else {
+            match &key {
+                Some(key) => format!("{}.{}", variable, key),
+                None => variable.clone(),
+            }
+        };
+        Ok(ReturnItem {
+            variable,
+            key,
+            column,
+        })
+    }
+}
+
+/// Checks that every variable used in `WHERE` and `RETURN` is bound by a pattern,
+/// and that no variable names both a node and a relationship.
+fn check_variables(query: &Query) -> Result<(), MetaSyntraXLError> {
+    let nodes: Vec<&str> = query
+        .patterns
+        .iter()
+        .flat_map(|p| p.nodes.iter().map(|n| n.variable.as_str()))
+        .collect();
+    let mut relationships: Vec<&str> = Vec::new();
+    for relationship in query.patterns.iter().flat_map(|p| &p.relationships) {
+        let variable = relationship.variable.as_str();
+        if nodes.contains(&variable) || relationships.contains(&variable) {
+            return Err(MetaSyntraXLError::KnowledgeGraphError(format!(
+                "Variable `{}` is bound more than once",
+                variable
+            )));
+        }
+        relationships.push(variable);
+    }
+
+    let mut used: Vec<&str> = query.returns.iter().map(|r| r.variable.as_str()).collect();
+    let mut stack: Vec<&Condition> = query.condition.iter().collect();
+    while let Some(condition) = stack.pop() {
+        match condition {
+            Condition::And(a, b) | Condition::Or(a, b) => {
+                stack.push(a);
+                stack.push(b);
+            }
+            Condition::Not(a) => stack.push(a),
+            Condition::Compare(left, _, right) => {
+                for operand in [left, right] {
+                    if let Operand::Property(variable, _) = operand {
+                        used.push(variable);
+                    }
+                }
+            }
+            Condition::IsNull(Operand::Property(variable, _)) => used.push(variable),
+            Condition::IsNull(Operand::Literal(_)) => {}
+        }
+    }
+    match used
+        .into_iter()
+        .find(|v| !nodes.contains(v) && !relationships.contains(v))
+    {
+        Some(variable) => Err(MetaSyntraXLError::KnowledgeGraphError(format!(
+            "Unknown variable `{}`",
+            variable
+        ))),
+        None => Ok(()),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,1414 @@
+// src/knowledge_graph/rdf.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[KNOWLEDGE-GRAPH]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::index::{EntityTable, RelationshipTable};
+use super::{PropertyValue, Relationship};
+use crate::errors::MetaSyntraXLError;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{BufRead, Write};
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// How RDF terms map onto entity ids, relationship types and property keys, and
+/// back.
+///
+/// On import, a predicate listed in `predicates` becomes the name it maps to, one
+/// under `predicate_namespace` the rest of its IRI, and any other its full IRI;
+/// entity IRIs under `entity_namespace` lose the namespace, other IRIs are kept
+/// whole and blank nodes become `_:label`. Anonymous blank nodes get a label
+/// unique to the import they come from. Export reverses this, so ids and names
+/// that are not IRIs are written under the namespaces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vocabulary {
+    pub entity_namespace: String,
+    pub predicate_namespace: String,
+    /// Predicate IRIs and the relationship type or property key each stands for.
+    pub predicates: HashMap<String, String>,
+}
+
+impl Default for Vocabulary {
+    /// `rdf:type` maps to `type`.
+    fn default() -> Self {
+        Self {
+            entity_namespace: "urn:metasyntraxl:entity:".to_string(),
+            predicate_namespace: "urn:metasyntraxl:vocabulary:".to_string(),
+            predicates: HashMap::from([(RDF_TYPE.to_string(), "type".to_string())]),
+        }
+    }
+}
+
+impl Vocabulary {
+    fn entity_id(&self, term: &Term) -> String {
+        match term {
+            Term::Iri(iri) => match iri.strip_prefix(&self.entity_namespace) {
+                Some(local) => decode(local),
+                None => iri.clone(),
+            },
+            Term::Blank(label) => format!("_:{}", label),
+            Term::Literal(literal) => literal.lexical.clone(),
+        }
+    }
+
+    fn entity_term(&self, id: &str) -> Term {
+        if let Some(label) = id.strip_prefix("_:") {
+            if is_blank_label(label) {
+                return Term::Blank(label.to_string());
+            }
+        }
+        if is_absolute_iri(id) && !id.starts_with(&self.entity_namespace) {
+            Term::Iri(id.to_string())
+        } else {
+            Term::Iri(format!("{}{}", self.entity_namespace, encode(id)))
+        }
+    }
+
+    fn name(&self, predicate: &str) -> String {
+        if let Some(name) = self.predicates.get(predicate) {
+            return name.clone();
+        }
+        match predicate.strip_prefix(&self.predicate_namespace) {
+            Some(local) => decode(local),
+            None => predicate.to_string(),
+        }
+    }
+
+    fn predicate(&self, name: &str) -> String {
+        let mapped = self
+            .predicates
+            .iter()
+            .filter(|(_, n)| *n == name)
+            .map(|(iri, _)| iri)
+            .min();
+        if let Some(iri) = mapped {
+            return iri.clone();
+        }
+        if is_absolute_iri(name)
+            && !name.starts_with(&self.predicate_namespace)
+            && !self.predicates.contains_key(name)
+        {
+            name.to_string()
+        } else {
+            format!("{}{}", self.predicate_namespace, encode(name))
+        }
+    }
+
+    pub(crate) fn statement(&self, triple: Triple) -> Statement {
+        let subject = self.entity_id(&triple.subject);
+        let name = self.name(&triple.predicate);
+        match triple.object {
+            Term::Literal(literal) => Statement::Property {
+                subject,
+                key: name,
+                value: literal.value(),
+            },
+            object => Statement::Relationship(Relationship {
+                from: subject,
+                to: self.entity_id(&object),
+                type_: name,
+                properties: HashMap::new(),
+            }),
+        }
+    }
+}
+
+/// One imported fact.
+pub(crate) enum Statement {
+    /// Sets `key` on `subject`, or adds `value` to the values already there.
+    Property {
+        subject: String,
+        key: String,
+        value: PropertyValue,
+    },
+    Relationship(Relationship),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Term {
+    Iri(String),
+    Blank(String),
+    Literal(Literal),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Literal {
+    lexical: String,
+    datatype: Option<String>,
+    language: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Triple {
+    subject: Term,
+    predicate: String,
+    object: Term,
+}
+
+impl Literal {
+    fn plain(lexical: impl Into<String>) -> Self {
+        Self {
+            lexical: lexical.into(),
+            datatype: None,
+            language: None,
+        }
+    }
+
+    fn typed(lexical: impl Into<String>, datatype: &str) -> Self {
+        Self {
+            lexical: lexical.into(),
+            datatype: Some(format!("{}{}", XSD, datatype)),
+            language: None,
+        }
+    }
+
+    /// The value of the literal, typed by its XSD datatype; a lexical form that
+    /// does not fit the datatype, or any other datatype, gives a string.
+    fn value(self) -> PropertyValue {
+        let datatype = self.datatype.as_deref().and_then(|d| d.strip_prefix(XSD));
+        let text = self.lexical.trim();
+        let typed = match datatype {
+            Some(
+                "integer" | "int" | "long" | "short" | "byte" | "nonNegativeInteger"
+                | "positiveInteger" | "negativeInteger" | "nonPositiveInteger" | "unsignedInt"
+                | "unsignedLong" | "unsignedShort" | "unsignedByte",
+            ) => text.parse().ok().map(PropertyValue::Int),
+            Some("double" | "float" | "decimal") => parse_double(text).map(PropertyValue::Float),
+            Some("boolean") => match text {
+                "true" | "1" => Some(PropertyValue::Bool(true)),
+                "false" | "0" => Some(PropertyValue::Bool(false)),
+                _ => None,
+            },
+            Some("dateTime" | "dateTimeStamp") => DateTime::parse_from_rfc3339(text)
+                .ok()
+                .map(|time| PropertyValue::DateTime(time.with_timezone(&Utc))),
+            _ => None,
+        };
+        typed.unwrap_or(PropertyValue::String(self.lexical))
+    }
+}
+
+fn parse_double(text: &str) -> Option<f64> {
+    match text {
+        "INF" | "+INF" => Some(f64::INFINITY),
+        "-INF" => Some(f64::NEG_INFINITY),
+        "NaN" => Some(f64::NAN),
+        _ if text.contains(|c: char| c.is_ascii_digit()) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+fn format_double(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "INF" } else { "-INF" }.to_string()
+    } else {
+        // `Debug` keeps the decimal point of whole numbers and uses exponents for
+        // very large or small ones.
+        format!("{:?}", value)
+    }
+}
+
+/// The literals `value` is written as; a list gives one per element.
+fn literals(value: &PropertyValue, out: &mut Vec<Literal>) {
+    match value {
+        PropertyValue::String(text) => out.push(Literal::plain(text.as_str())),
+        PropertyValue::Int(number) => out.push(Literal::typed(number.to_string(), "integer")),
+        PropertyValue::Float(number) => out.push(Literal::typed(format_double(*number), "double")),
+        PropertyValue::Bool(value) => out.push(Literal::typed(value.to_string(), "boolean")),
+        PropertyValue::DateTime(time) => out.push(Literal::typed(
+            time.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            "dateTime",
+        )),
+        PropertyValue::List(values) => {
+            for value in values {
+                literals(value, out);
+            }
+        }
+    }
+}
+
+fn is_absolute_iri(text: &str) -> bool {
+    let scheme = match text.split_once(':') {
+        Some((scheme, _)) => scheme,
+        None => return false,
+    };
+    let mut chars = scheme.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        && !text
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "<>\"{}|^`\\".contains(c))
+}
+
+/// Labels the anonymous blank nodes of one document. The labels carry a random
+/// scope, so anonymous nodes of separate imports stay apart and do not take the
+/// label of a node written as `_:label`.
+struct BlankNodes {
+    prefix: String,
+    count: usize,
+}
+
+impl BlankNodes {
+    fn new(name: &str) -> Self {
+        Self {
+            prefix: format!("{}-{:016x}-", name, rand::random::<u64>()),
+            count: 0,
+        }
+    }
+
+    fn next(&mut self) -> Term {
+        self.count += 1;
+        Term::Blank(format!("{}{}", self.prefix, self.count))
+    }
+}
+
+fn is_blank_label(label: &str) -> bool {
+    !label.is_empty()
+        && !label.ends_with('.')
+        && !label.starts_with(['-', '.'])
+        && label
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_-.".contains(c))
+}
+
+/// Percent-encodes everything but letters, digits and `-._~`.
+fn encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() || "-._~".contains(c) {
+            encoded.push(c);
+        } else {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+    encoded
+}
+
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// An entity, or an id that relationships leave from without being an entity,
+/// with the relationships leaving it.
+pub(crate) struct Subject<'a> {
+    id: &'a str,
+    properties: Option<&'a HashMap<String, PropertyValue>>,
+    relationships: Vec<&'a Relationship>,
+}
+
+/// The graph by subject: entities in id order, then the missing sources of
+/// relationships in order of appearance.
+pub(crate) fn subjects<'a>(
+    relationships: &'a RelationshipTable,
+    entities: &'a EntityTable,
+) -> Vec<Subject<'a>> {
+    let mut ids: Vec<&String> = entities.entities().keys().collect();
+    ids.sort();
+    let mut subjects: Vec<Subject> = ids
+        .into_iter()
+        .map(|id| Subject {
+            id,
+            properties: entities.get(id).map(|e| &e.properties),
+            relationships: relationships.adjacent(id, true, &[]),
+        })
+        .collect();
+    let mut missing: Vec<&str> = Vec::new();
+    for relationship in relationships.relationships() {
+        if entities.get(&relationship.from).is_none() && !missing.contains(&&*relationship.from) {
+            missing.push(&relationship.from);
+        }
+    }
+    subjects.extend(missing.into_iter().map(|id| Subject {
+        id,
+        properties: None,
+        relationships: relationships.adjacent(id, true, &[]),
+    }));
+    subjects
+}
+
+/// The triples of a subject: its properties by key, then its relationships.
+/// Relationship properties have no place in RDF and are left out.
+fn triples(subject: &Subject, vocabulary: &Vocabulary) -> Vec<Triple> {
+    let term = vocabulary.entity_term(subject.id);
+    let mut triples = Vec::new();
+    let properties: BTreeMap<&String, &PropertyValue> =
+        subject.properties.into_iter().flatten().collect();
+    for (key, value) in properties {
+        let mut values = Vec::new();
+        literals(value, &mut values);
+        let predicate = vocabulary.predicate(key);
+        triples.extend(values.into_iter().map(|literal| Triple {
+            subject: term.clone(),
+            predicate: predicate.clone(),
+            object: Term::Literal(literal),
+        }));
+    }
+    for relationship in &subject.relationships {
+        triples.push(Triple {
+            subject: term.clone(),
+            predicate: vocabulary.predicate(&relationship.type_),
+            object: vocabulary.entity_term(&relationship.to),
+        });
+    }
+    triples
+}
+
+fn escape_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn ntriples_term(term: &Term) -> String {
+    match term {
+        Term::Iri(iri) => format!("<{}>", iri),
+        Term::Blank(label) => format!("_:{}", label),
+        Term::Literal(literal) => {
+            let mut text = escape_string(&literal.lexical);
+            if let Some(language) = &literal.language {
+                text.push('@');
+                text.push_str(language);
+            } else if let Some(datatype) = &literal.datatype {
+                text.push_str(&format!("^^<{}>", datatype));
+            }
+            text
+        }
+    }
+}
+
+/// Writes one triple per line, subjects in the order of [`subjects`].
+pub(crate) fn write_ntriples(
+    out: &mut impl Write,
+    subjects: &[Subject],
+    vocabulary: &Vocabulary,
+) -> Result<(), MetaSyntraXLError> {
+    for subject in subjects {
+        for triple in triples(subject, vocabulary) {
+            writeln!(
+                out,
+                "{} <{}> {} .",
+                ntriples_term(&triple.subject),
+                triple.predicate,
+                ntriples_term(&triple.object)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes one block per subject, with prefixes for the namespaces.
+pub(crate) fn write_turtle(
+    out: &mut impl Write,
+    subjects: &[Subject],
+    vocabulary: &Vocabulary,
+) -> Result<(), MetaSyntraXLError> {
+    let prefixes = [
+        ("e", vocabulary.entity_namespace.as_str()),
+        ("v", vocabulary.predicate_namespace.as_str()),
+        ("xsd", XSD),
+    ];
+    for (prefix, namespace) in prefixes {
+        writeln!(out, "@prefix {}: <{}> .", prefix, namespace)?;
+    }
+    let iri = |iri: &str| {
+        for (prefix, namespace) in prefixes {
+            if let Some(local) = iri.strip_prefix(namespace) {
+                let simple = local.starts_with(|c: char| c.is_alphanumeric() || c == '_')
+                    && local
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+                if simple {
+                    return format!("{}:{}", prefix, local);
+                }
+            }
+        }
+        format!("<{}>", iri)
+    };
+    let term = |term: &Term| match term {
+        Term::Iri(text) => iri(text),
+        Term::Literal(literal) if literal.language.is_none() => {
+            let datatype = literal
+                .datatype
+                .as_deref()
+                .and_then(|d| d.strip_prefix(XSD));
+            match datatype {
+                Some("integer" | "boolean") => literal.lexical.clone(),
+                Some(datatype) => format!("{}^^xsd:{}", escape_string(&literal.lexical), datatype),
+                None if literal.datatype.is_some() => ntriples_term(term),
+                None => escape_string(&literal.lexical),
+            }
+        }
+        _ => ntriples_term(term),
+    };
+
+    for subject in subjects {
+        let triples = triples(subject, vocabulary);
+        let first = match triples.first() {
+            Some(first) => first,
+            None => continue,
+        };
+        write!(out, "\n{}", term(&first.subject))?;
+        let mut previous: Option<&str> = None;
+        for triple in &triples {
+            if previous == Some(triple.predicate.as_str()) {
+                write!(out, ", {}", term(&triple.object))?;
+                continue;
+            }
+            let predicate = if triple.predicate == RDF_TYPE {
+                "a".to_string()
+            } else {
+                iri(&triple.predicate)
+            };
+            let separator = if previous.is_some() { " ;\n   " } else { "" };
+            write!(out, "{} {} {}", separator, predicate, term(&triple.object))?;
+            previous = Some(&triple.predicate);
+        }
+        writeln!(out, " .")?;
+    }
+    Ok(())
+}
+
+/// Writes a JSON-LD document with one node object per subject in `@graph`.
+/// Names and ids under the vocabulary's namespaces are written relative to
+/// `@vocab` and `@base`.
+pub(crate) fn write_jsonld(
+    out: &mut impl Write,
+    subjects: &[Subject],
+    vocabulary: &Vocabulary,
+) -> Result<(), MetaSyntraXLError> {
+    let mut context = Map::new();
+    context.insert(
+        "@vocab".to_string(),
+        vocabulary.predicate_namespace.clone().into(),
+    );
+    context.insert(
+        "@base".to_string(),
+        vocabulary.entity_namespace.clone().into(),
+    );
+    let id = |id: &str| match vocabulary.entity_term(id) {
+        Term::Iri(iri) => match iri.strip_prefix(&vocabulary.entity_namespace) {
+            Some(local) => local.to_string(),
+            None => iri,
+        },
+        Term::Blank(label) => format!("_:{}", label),
+        Term::Literal(_) => unreachable!("entities are never literals"),
+    };
+    let simple = |name: &str| !name.is_empty() && encode(name) == name && !name.starts_with('.');
+    let mut key = |name: &str| {
+        let iri = vocabulary.predicate(name);
+        if simple(name) && iri == format!("{}{}", vocabulary.predicate_namespace, name) {
+            name.to_string()
+        } else if simple(name) && vocabulary.predicates.contains_key(&iri) {
+            context.insert(name.to_string(), iri.into());
+            name.to_string()
+        } else {
+            iri
+        }
+    };
+
+    let mut graph = Vec::new();
+    for subject in subjects {
+        let mut node = Map::new();
+        node.insert("@id".to_string(), id(subject.id).into());
+        let properties: BTreeMap<&String, &PropertyValue> =
+            subject.properties.into_iter().flatten().collect();
+        let mut values: Vec<(String, Value)> = Vec::new();
+        for (name, value) in properties {
+            let mut items = Vec::new();
+            literals(value, &mut items);
+            let name = key(name);
+            values.extend(
+                items
+                    .into_iter()
+                    .map(|literal| (name.clone(), jsonld_literal(literal))),
+            );
+        }
+        for relationship in &subject.relationships {
+            let mut target = Map::new();
+            target.insert("@id".to_string(), id(&relationship.to).into());
+            values.push((key(&relationship.type_), Value::Object(target)));
+        }
+        for (name, value) in values {
+            match node.get_mut(&name) {
+                Some(Value::Array(items)) => items.push(value),
+                _ => {
+                    node.insert(name, Value::Array(vec![value]));
+                }
+            }
+        }
+        for value in node.values_mut() {
+            if matches!(value, Value::Array(items) if items.len() == 1) {
+                *value = value.as_array_mut().expect("an array").remove(0);
+            }
+        }
+        graph.push(Value::Object(node));
+    }
+
+    let mut document = Map::new();
+    document.insert("@context".to_string(), Value::Object(context));
+    document.insert("@graph".to_string(), Value::Array(graph));
+    serde_json::to_writer_pretty(&mut *out, &Value::Object(document)).map_err(|e| {
+        MetaSyntraXLError::KnowledgeGraphError(format!("Cannot write JSON-LD: {}", e))
+    })?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn jsonld_literal(literal: Literal) -> Value {
+    let datatype = literal
+        .datatype
+        .as_deref()
+        .and_then(|d| d.strip_prefix(XSD));
+    let native = match datatype {
+        None if literal.datatype.is_none() && literal.language.is_none() => {
+            Some(Value::String(literal.lexical.clone()))
+        }
+        Some("integer") => literal.lexical.parse::<i64>().ok().map(Value::from),
+        Some("double") => literal
+            .lexical
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number),
+        Some("boolean") => literal.lexical.parse::<bool>().ok().map(Value::Bool),
+        _ => None,
+    };
+    native.unwrap_or_else(|| {
+        let mut value = Map::new();
+        value.insert("@value".to_string(), literal.lexical.into());
+        if let Some(language) = literal.language {
+            value.insert("@language".to_string(), language.into());
+        } else if let Some(datatype) = literal.datatype {
+            value.insert("@type".to_string(), datatype.into());
+        }
+        Value::Object(value)
+    })
+}
+
+/// Reads a JSON-LD document into triples. The document is one JSON value, so it
+/// is read whole.
+///
+/// Supports an inline `@context` (object or array of objects) with `@vocab`,
+/// `@base`, terms and compact IRIs, type coercion to `@id` or a datatype, node
+/// objects with `@id` and `@type`, nested node objects, `@value` objects with
+/// `@type` or `@language`, and `@list` or `@set` values, which are read as
+/// repeated values. Other keywords are ignored.
+pub(crate) fn read_jsonld(document: Value) -> Result<Vec<Triple>, MetaSyntraXLError> {
+    let mut reader = JsonLdReader {
+        context: Context::default(),
+        triples: Vec::new(),
+        blanks: BlankNodes::new("jsonld"),
+    };
+    let nodes = match document {
+        Value::Object(mut object) => {
+            if let Some(context) = object.remove("@context") {
+                reader.context.extend(&context)?;
+            }
+            match object.remove("@graph") {
+                Some(Value::Array(nodes)) => nodes,
+                Some(node) => vec![node],
+                None => vec![Value::Object(object)],
+            }
+        }
+        Value::Array(nodes) => nodes,
+        _ => return Err(jsonld_error("expected an object or an array of objects")),
+    };
+    for node in nodes {
+        match node {
+            Value::Object(node) => {
+                reader.node(node)?;
+            }
+            _ => return Err(jsonld_error("expected a node object")),
+        }
+    }
+    Ok(reader.triples)
+}
+
+fn jsonld_error(message: &str) -> MetaSyntraXLError {
+    MetaSyntraXLError::KnowledgeGraphError(format!("JSON-LD error: {}", message))
+}
+
+#[derive(Default, Clone)]
+struct Context {
+    vocab: Option<String>,
+    base: Option<String>,
+    terms: HashMap<String, TermDefinition>,
+}
+
+#[derive(Clone)]
+struct TermDefinition {
+    iri: String,
+    /// `@id`, or a datatype IRI.
+    coercion: Option<String>,
+}
+
+impl Context {
+    fn extend(&mut self, context: &Value) -> Result<(), MetaSyntraXLError> {
+        match context {
+            Value::Array(contexts) => contexts.iter().try_for_each(|c| self.extend(c)),
+            Value::Null => {
+                *self = Context::default();
+                Ok(())
+            }
+            Value::Object(definitions) => {
+                for (term, definition) in definitions {
+                    match (term.as_str(), definition) {
+                        ("@vocab", Value::String(vocab)) => self.vocab = Some(vocab.clone()),
+                        ("@base", Value::String(base)) => self.base = Some(base.clone()),
+                        (term, _) if term.starts_with('@') => {}
+                        (term, Value::String(iri)) => {
+                            let iri = self.expand_key(iri)?;
+                            self.terms.insert(
+                                term.to_string(),
+                                TermDefinition {
+                                    iri,
+                                    coercion: None,
+                                },
+                            );
+                        }
+                        (term, Value::Object(definition)) => {
+                            let iri = match definition.get("@id") {
+                                Some(Value::String(iri)) => self.expand_key(iri)?,
+                                _ => self.expand_key(term)?,
+                            };
+                            let coercion = match definition.get("@type") {
+                                Some(Value::String(t)) if t == "@id" => Some(t.clone()),
+                                Some(Value::String(t)) => Some(self.expand_key(t)?),
+                                _ => None,
+                            };
+                            self.terms
+                                .insert(term.to_string(), TermDefinition { iri, coercion });
+                        }
+                        (term, _) => {
+                            return Err(jsonld_error(&format!(
+                                "unsupported definition of term `{}`",
+                                term
+                            )))
+                        }
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(jsonld_error("remote contexts are not supported")),
+        }
+    }
+
+    /// Expands a compact IRI whose prefix is a term ending in a delimiter.
+    fn expand_compact(&self, text: &str) -> Option<String> {
+        let (prefix, suffix) = text.split_once(':')?;
+        let definition = self.terms.get(prefix)?;
+        definition
+            .iri
+            .ends_with(['/', '#', ':', '?', '[', ']', '@'])
+            .then(|| format!("{}{}", definition.iri, suffix))
+    }
+
+    /// A key or type: a term, a compact or absolute IRI, or a name under `@vocab`.
+    fn expand_key(&self, key: &str) -> Result<String, MetaSyntraXLError> {
+        if let Some(definition) = self.terms.get(key) {
+            return Ok(definition.iri.clone());
+        }
+        if let Some(iri) = self.expand_compact(key) {
+            return Ok(iri);
+        }
+        if key.contains(':') {
+            return Ok(key.to_string());
+        }
+        match &self.vocab {
+            Some(vocab) => Ok(format!("{}{}", vocab, key)),
+            None => Err(jsonld_error(&format!(
+                "cannot expand `{}` without `@vocab`",
+                key
+            ))),
+        }
+    }
+
+    /// A node reference: a blank node, a compact or absolute IRI, or an IRI
+    /// relative to `@base`.
+    fn expand_id(&self, id: &str) -> Term {
+        if let Some(label) = id.strip_prefix("_:") {
+            return Term::Blank(label.to_string());
+        }
+        if let Some(iri) = self.expand_compact(id) {
+            return Term::Iri(iri);
+        }
+        if is_absolute_iri(id) {
+            return Term::Iri(id.to_string());
+        }
+        Term::Iri(format!("{}{}", self.base.as_deref().unwrap_or(""), id))
+    }
+}
+
+struct JsonLdReader {
+    context: Context,
+    triples: Vec<Triple>,
+    blanks: BlankNodes,
+}
+
+impl JsonLdReader {
+    fn node(&mut self, mut node: Map<String, Value>) -> Result<Term, MetaSyntraXLError> {
+        let saved = self.context.clone();
+        if let Some(context) = node.remove("@context") {
+            self.context.extend(&context)?;
+        }
+        let subject = match node.remove("@id") {
+            Some(Value::String(id)) => self.context.expand_id(&id),
+            Some(_) => return Err(jsonld_error("`@id` must be a string")),
+            None => self.blanks.next(),
+        };
+        if let Some(types) = node.remove("@type") {
+            for type_ in array(types) {
+                let iri = match type_ {
+                    Value::String(type_) => self.context.expand_key(&type_)?,
+                    _ => return Err(jsonld_error("`@type` must be a string")),
+                };
+                self.triples.push(Triple {
+                    subject: subject.clone(),
+                    predicate: RDF_TYPE.to_string(),
+                    object: Term::Iri(iri),
+                });
+            }
+        }
+        for (key, values) in node {
+            if key.starts_with('@') {
+                continue;
+            }
+            let predicate = self.context.expand_key(&key)?;
+            let coercion = self
+                .context
+                .terms
+                .get(&key)
+                .and_then(|d| d.coercion.clone());
+            for value in array(values) {
+                for object in self.objects(value, coercion.as_deref())? {
+                    self.triples.push(Triple {
+                        subject: subject.clone(),
+                        predicate: predicate.clone(),
+                        object,
+                    });
+                }
+            }
+        }
+        self.context = saved;
+        Ok(subject)
+    }
+
+    fn objects(
+        &mut self,
+        value: Value,
+        coercion: Option<&str>,
+    ) -> Result<Vec<Term>, MetaSyntraXLError> {
+        let object = match value {
+            Value::Null => return Ok(Vec::new()),
+            Value::String(text) if coercion == Some("@id") => self.context.expand_id(&text),
+            Value::String(text) => match coercion {
+                Some(datatype) => Term::Literal(Literal {
+                    lexical: text,
+                    datatype: Some(datatype.to_string()),
+                    language: None,
+                }),
+                None => Term::Literal(Literal::plain(text)),
+            },
+            Value::Number(number) => match number.as_i64() {
+                Some(integer) => Term::Literal(Literal::typed(integer.to_string(), "integer")),
+                None => Term::Literal(Literal::typed(
+                    format_double(number.as_f64().unwrap_or(f64::NAN)),
+                    "double",
+                )),
+            },
+            Value::Bool(value) => Term::Literal(Literal::typed(value.to_string(), "boolean")),
+            Value::Array(values) => {
+                let mut objects = Vec::new();
+                for value in values {
+                    objects.extend(self.objects(value, coercion)?);
+                }
+                return Ok(objects);
+            }
+            Value::Object(mut object) => {
+                if let Some(lexical) = object.remove("@value") {
+                    let lexical = match lexical {
+                        Value::String(text) => text,
+                        other => other.to_string(),
+                    };
+                    let datatype = match object.remove("@type") {
+                        Some(Value::String(datatype)) => Some(self.context.expand_key(&datatype)?),
+                        _ => None,
+                    };
+                    let language = match object.remove("@language") {
+                        Some(Value::String(language)) => Some(language),
+                        _ => None,
+                    };
+                    Term::Literal(Literal {
+                        lexical,
+                        datatype,
+                        language,
+                    })
+                } else if let Some(items) = object.remove("@list").or_else(|| object.remove("@set"))
+                {
+                    return self.objects(items, coercion);
+                } else if object.len() == 1 && object.contains_key("@id") {
+                    match &object["@id"] {
+                        Value::String(id) => self.context.expand_id(id),
+                        _ => return Err(jsonld_error("`@id` must be a string")),
+                    }
+                } else {
+                    self.node(object)?
+                }
+            }
+        };
+        Ok(vec![object])
+    }
+}
+
+fn array(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(values) => values,
+        value => vec![value],
+    }
+}
+
+/// Reads Turtle, and so N-Triples, a statement at a time from `reader`.
+///
+/// Supports `@prefix`, `@base` and their SPARQL forms, IRIs (relative ones are
+/// appended to the base), prefixed names, `a`, predicate and object lists, blank
+/// node labels and property lists, short and long strings with language tags or
+/// datatypes, numbers and booleans. Collections are not supported.
+pub(crate) struct TurtleReader<R> {
+    source: Source<R>,
+    base: String,
+    prefixes: HashMap<String, String>,
+    pending: VecDeque<Triple>,
+    blanks: BlankNodes,
+}
+
+impl<R: BufRead> TurtleReader<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            source: Source {
+                reader,
+                buffer: Vec::new(),
+                position: 0,
+                line: 1,
+                column: 1,
+                done: false,
+            },
+            base: String::new(),
+            prefixes: HashMap::new(),
+            pending: VecDeque::new(),
+            blanks: BlankNodes::new("genid"),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for TurtleReader<R> {
+    type Item = Result<Triple, MetaSyntraXLError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            match self.statement() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(error) => {
+                    // Give up on the rest of the input after an error.
+                    self.source.done = true;
+                    self.source.buffer.clear();
+                    self.source.position = 0;
+                    return Some(Err(error));
+                }
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+/// The characters of a reader, read a line at a time as they are needed.
+struct Source<R> {
+    reader: R,
+    buffer: Vec<char>,
+    position: usize,
+    line: usize,
+    column: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Source<R> {
+    fn peek_at(&mut self, ahead: usize) -> Result<Option<char>, MetaSyntraXLError> {
+        while self.position + ahead >= self.buffer.len() && !self.done {
+            if self.position == self.buffer.len() {
+                self.buffer.clear();
+                self.position = 0;
+            }
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                self.done = true;
+            }
+            self.buffer.extend(line.chars());
+        }
+        Ok(self.buffer.get(self.position + ahead).copied())
+    }
+
+    fn peek(&mut self) -> Result<Option<char>, MetaSyntraXLError> {
+        self.peek_at(0)
+    }
+
+    fn next(&mut self) -> Result<Option<char>, MetaSyntraXLError> {
+        let c = self.peek()?;
+        if let Some(c) = c {
+            self.position += 1;
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        Ok(c)
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || "_-:%\\".contains(c)
+}
+
+impl<R: BufRead> TurtleReader<R> {
+    fn error(&self, message: &str) -> MetaSyntraXLError {
+        MetaSyntraXLError::KnowledgeGraphError(format!(
+            "Turtle parse error at line {}, column {}: {}",
+            self.source.line, self.source.column, message
+        ))
+    }
+
+    fn unexpected(&mut self, expected: &str) -> MetaSyntraXLError {
+        match self.source.peek() {
+            Ok(Some(c)) => self.error(&format!("expected {}, found `{}`", expected, c)),
+            Ok(None) => self.error(&format!("expected {}, found end of input", expected)),
+            Err(error) => error,
+        }
+    }
+
+    fn skip_space(&mut self) -> Result<(), MetaSyntraXLError> {
+        while let Some(c) = self.source.peek()? {
+            if c == '#' {
+                while !matches!(self.source.next()?, Some('\n') | None) {}
+            } else if c.is_whitespace() {
+                self.source.next()?;
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), MetaSyntraXLError> {
+        if self.source.peek()? == Some(expected) {
+            self.source.next()?;
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", expected)))
+        }
+    }
+
+    /// Whether the input continues with `keyword`, in any case, and then a space.
+    fn at_keyword(&mut self, keyword: &str) -> Result<bool, MetaSyntraXLError> {
+        for (i, k) in keyword.chars().enumerate() {
+            if !matches!(self.source.peek_at(i)?, Some(c) if c.eq_ignore_ascii_case(&k)) {
+                return Ok(false);
+            }
+        }
+        Ok(matches!(self.source.peek_at(keyword.len())?, Some(c) if c.is_whitespace()))
+    }
+
+    /// Reads a statement into `pending`; `false` at the end of the input.
+    fn statement(&mut self) -> Result<bool, MetaSyntraXLError> {
+        self.skip_space()?;
+        match self.source.peek()? {
+            None => return Ok(false),
+            Some('@') => {
+                self.source.next()?;
+                if self.at_keyword("prefix")? {
+                    self.prefix()?;
+                } else if self.at_keyword("base")? {
+                    self.base_directive()?;
+                } else {
+                    return Err(self.unexpected("`@prefix` or `@base`"));
+                }
+                self.skip_space()?;
+                self.expect('.')?;
+            }
+            Some(_) if self.at_keyword("prefix")? => self.prefix()?,
+            Some(_) if self.at_keyword("base")? => self.base_directive()?,
+            Some('[') => {
+                let subject = self.blank_node_property_list()?;
+                self.skip_space()?;
+                if self.source.peek()? != Some('.') {
+                    self.predicate_object_list(&subject)?;
+                    self.skip_space()?;
+                }
+                self.expect('.')?;
+            }
+            Some(_) => {
+                let subject = self.resource()?;
+                self.skip_space()?;
+                self.predicate_object_list(&subject)?;
+                self.skip_space()?;
+                self.expect('.')?;
+            }
+        }
+        Ok(true)
+    }
+
+    fn prefix(&mut self) -> Result<(), MetaSyntraXLError> {
+        for _ in 0.."prefix".len() {
+            self.source.next()?;
+        }
+        self.skip_space()?;
+        let name = self.name()?;
+        let prefix = match name.strip_suffix(':') {
+            Some(prefix) if !prefix.contains(':') => prefix.to_string(),
+            _ => return Err(self.error(&format!("expected a prefix name, found `{}`", name))),
+        };
+        self.skip_space()?;
+        let iri = self.iriref()?;
+        self.prefixes.insert(prefix, iri);
+        Ok(())
+    }
+
+    fn base_directive(&mut self) -> Result<(), MetaSyntraXLError> {
+        for _ in 0.."base".len() {
+            self.source.next()?;
+        }
+        self.skip_space()?;
+        self.base = self.iriref()?;
+        Ok(())
+    }
+
+    fn predicate_object_list(&mut self, subject: &Term) -> Result<(), MetaSyntraXLError> {
+        loop {
+            let predicate = self.verb()?;
+            loop {
+                self.skip_space()?;
+                let object = self.object()?;
+                self.pending.push_back(Triple {
+                    subject: subject.clone(),
+                    predicate: predicate.clone(),
+                    object,
+                });
+                self.skip_space()?;
+                if self.source.peek()? != Some(',') {
+                    break;
+                }
+                self.source.next()?;
+            }
+            if self.source.peek()? != Some(';') {
+                return Ok(());
+            }
+            while self.source.peek()? == Some(';') {
+                self.source.next()?;
+                self.skip_space()?;
+            }
+            if matches!(self.source.peek()?, Some('.' | ']') | None) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn verb(&mut self) -> Result<String, MetaSyntraXLError> {
+        let keyword = self.source.peek()? == Some('a')
+            && !matches!(self.source.peek_at(1)?, Some(c) if is_name_char(c));
+        if keyword {
+            self.source.next()?;
+            return Ok(RDF_TYPE.to_string());
+        }
+        match self.resource()? {
+            Term::Iri(iri) => Ok(iri),
+            _ => Err(self.error("expected a predicate IRI")),
+        }
+    }
+
+    /// An IRI, a prefixed name or a blank node label.
+    fn resource(&mut self) -> Result<Term, MetaSyntraXLError> {
+        match self.source.peek()? {
+            Some('<') => Ok(Term::Iri(self.iriref()?)),
+            Some('_') if self.source.peek_at(1)? == Some(':') => {
+                self.source.next()?;
+                self.source.next()?;
+                let label = self.name()?;
+                if label.is_empty() {
+                    return Err(self.unexpected("a blank node label"));
+                }
+                Ok(Term::Blank(label))
+            }
+            Some(c) if is_name_char(c) => {
+                let name = self.name()?;
+                self.prefixed(&name).map(Term::Iri)
+            }
+            _ => Err(self.unexpected("an IRI, prefixed name or blank node")),
+        }
+    }
+
+    fn prefixed(&self, name: &str) -> Result<String, MetaSyntraXLError> {
+        let (prefix, local) = name
+            .split_once(':')
+            .ok_or_else(|| self.error(&format!("expected a prefixed name, found `{}`", name)))?;
+        let namespace = self
+            .prefixes
+            .get(prefix)
+            .ok_or_else(|| self.error(&format!("undefined prefix `{}:`", prefix)))?;
+        Ok(format!("{}{}", namespace, local))
+    }
+
+    fn object(&mut self) -> Result<Term, MetaSyntraXLError> {
+        match self.source.peek()? {
+            Some('[') => self.blank_node_property_list(),
+            Some('(') => Err(self.error("collections are not supported")),
+            Some('"' | '\'') => self.literal().map(Term::Literal),
+            Some(c) if c.is_ascii_digit() || c == '+' || c == '-' || c == '.' => {
+                self.number().map(Term::Literal)
+            }
+            Some('t' | 'f') => {
+                for value in ["true", "false"] {
+                    let matched = value
+                        .chars()
+                        .enumerate()
+                        .all(|(i, c)| matches!(self.source.peek_at(i), Ok(Some(p)) if p == c));
+                    let ended =
+                        !matches!(self.source.peek_at(value.len())?, Some(c) if is_name_char(c));
+                    if matched && ended {
+                        for _ in 0..value.len() {
+                            self.source.next()?;
+                        }
+                        return Ok(Term::Literal(Literal::typed(value, "boolean")));
+                    }
+                }
+                self.resource()
+            }
+            _ => self.resource(),
+        }
+    }
+
+    fn blank_node_property_list(&mut self) -> Result<Term, MetaSyntraXLError> {
+        self.expect('[')?;
+        let node = self.blanks.next();
+        self.skip_space()?;
+        if self.source.peek()? != Some(']') {
+            self.predicate_object_list(&node)?;
+            self.skip_space()?;
+        }
+        self.expect(']')?;
+        Ok(node)
+    }
+
+    /// A run of name characters, with `\` escapes resolved; a `.` is included
+    /// only when more name characters follow it.
+    fn name(&mut self) -> Result<String, MetaSyntraXLError> {
+        let mut name = String::new();
+        loop {
+            match self.source.peek()? {
+                Some('\\') => {
+                    self.source.next()?;
+                    match self.source.next()? {
+                        Some(c) => name.push(c),
+                        None => return Err(self.unexpected("an escaped character")),
+                    }
+                }
+                Some('.') if matches!(self.source.peek_at(1)?, Some(c) if is_name_char(c)) => {
+                    self.source.next()?;
+                    name.push('.');
+                }
+                Some(c) if is_name_char(c) => {
+                    self.source.next()?;
+                    name.push(c);
+                }
+                _ => return Ok(name),
+            }
+        }
+    }
+
+    fn iriref(&mut self) -> Result<String, MetaSyntraXLError> {
+        self.expect('<')?;
+        let mut iri = String::new();
+        loop {
+            match self.source.next()? {
+                Some('>') => break,
+                Some('\\') => iri.push(self.unicode_escape()?),
+                Some(c) if c == '\n' || c == '<' || c == '"' => {
+                    return Err(self.error(&format!("`{}` in an IRI", c.escape_default())))
+                }
+                Some(c) => iri.push(c),
+                None => return Err(self.error("unterminated IRI")),
+            }
+        }
+        if is_absolute_iri(&iri) {
+            Ok(iri)
+        } else {
+            Ok(format!("{}{}", self.base, iri))
+        }
+    }
+
+    /// The character of a `\u` or `\U` escape, after its backslash.
+    fn unicode_escape(&mut self) -> Result<char, MetaSyntraXLError> {
+        let digits = match self.source.next()? {
+            Some('u') => 4,
+            Some('U') => 8,
+            _ => return Err(self.error("expected `\\u` or `\\U`")),
+        };
+        let mut code = String::new();
+        for _ in 0..digits {
+            code.extend(self.source.next()?);
+        }
+        u32::from_str_radix(&code, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error(&format!("invalid escape `{}`", code)))
+    }
+
+    fn literal(&mut self) -> Result<Literal, MetaSyntraXLError> {
+        let quote = self.source.next()?.expect("a quote");
+        let long = self.source.peek()? == Some(quote) && self.source.peek_at(1)? == Some(quote);
+        if long {
+            self.source.next()?;
+            self.source.next()?;
+        } else if self.source.peek()? == Some(quote) {
+            // An empty short string.
+            self.source.next()?;
+            return self.annotations(String::new());
+        }
+        let mut lexical = String::new();
+        loop {
+            if !long && self.source.peek()? == Some('\n') {
+                return Err(self.error("newline in a short string"));
+            }
+            match self.source.next()? {
+                Some(c) if c == quote && !long => break,
+                Some(c)
+                    if c == quote
+                        && self.source.peek()? == Some(quote)
+                        && self.source.peek_at(1)? == Some(quote)
+                        && self.source.peek_at(2)? != Some(quote) =>
+                {
+                    self.source.next()?;
+                    self.source.next()?;
+                    break;
+                }
+                Some('\\') => match self.source.peek()? {
+                    Some('u' | 'U') => lexical.push(self.unicode_escape()?),
+                    Some(c) => {
+                        self.source.next()?;
+                        lexical.push(match c {
+                            't' => '\t',
+                            'b' => '\u{8}',
+                            'n' => '\n',
+                            'r' => '\r',
+                            'f' => '\u{c}',
+                            '"' | '\'' | '\\' => c,
+                            _ => return Err(self.error(&format!("invalid escape `\\{}`", c))),
+                        });
+                    }
+                    None => return Err(self.error("unterminated string")),
+                },
+                Some(c) => lexical.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+        self.annotations(lexical)
+    }
+
+    /// The language tag or datatype after a string.
+    fn annotations(&mut self, lexical: String) -> Result<Literal, MetaSyntraXLError> {
+        let mut literal = Literal::plain(lexical);
+        if self.source.peek()? == Some('@') {
+            self.source.next()?;
+            let mut language = String::new();
+            while let Some(c) = self.source.peek()? {
+                if !(c.is_ascii_alphanumeric() || c == '-') {
+                    break;
+                }
+                self.source.next()?;
+                language.push(c);
+            }
+            if language.is_empty() {
+                return Err(self.unexpected("a language tag"));
+            }
+            literal.language = Some(language);
+        } else if self.source.peek()? == Some('^') && self.source.peek_at(1)? == Some('^') {
+            self.source.next()?;
+            self.source.next()?;
+            match self.resource()? {
+                Term::Iri(datatype) => literal.datatype = Some(datatype),
+                _ => return Err(self.error("expected a datatype IRI")),
+            }
+        }
+        Ok(literal)
+    }
+
+    fn number(&mut self) -> Result<Literal, MetaSyntraXLError> {
+        let mut text = String::new();
+        if let Some(sign @ ('+' | '-')) = self.source.peek()? {
+            self.source.next()?;
+            text.push(sign);
+        }
+        let mut datatype = "integer";
+        loop {
+            match self.source.peek()? {
+                Some(c) if c.is_ascii_digit() => {}
+                Some('.')
+                    if datatype == "integer"
+                        && matches!(self.source.peek_at(1)?, Some(c) if c.is_ascii_digit()) =>
+                {
+                    datatype = "decimal";
+                }
+                Some('e' | 'E') if datatype != "double" => {
+                    datatype = "double";
+                    text.push('e');
+                    self.source.next()?;
+                    if let Some(sign @ ('+' | '-')) = self.source.peek()? {
+                        self.source.next()?;
+                        text.push(sign);
+                    }
+                    continue;
+                }
+                _ => break,
+            }
+            text.extend(self.source.next()?);
+        }
+        if !text.contains(|c: char| c.is_ascii_digit()) {
+            return Err(self.error(&format!("invalid number `{}`", text)));
+        }
+        Ok(Literal::typed(text, datatype))
+    }
+}
\ No newline at end of file
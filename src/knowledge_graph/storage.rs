@@ -0,0 +1,303 @@
+// src/knowledge_graph/storage.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[KNOWLEDGE-GRAPH]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::{Entity, Relationship};
+use crate::errors::MetaSyntraXLError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+const SNAPSHOT_FILE: &str = "snapshot.json";
+const LOG_FILE: &str = "log.jsonl";
+
+/// One change to a knowledge graph, as recorded by a [`GraphStorage`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum GraphOperation {
+    /// Adds `entity`, replacing any entity with the same id.
+    AddEntity {
+        entity: Entity,
+    },
+    AddRelationship {
+        relationship: Relationship,
+    },
+    /// Removes the entity with id `id` and every relationship leaving or arriving
+    /// at it.
+    RemoveEntity {
+        id: String,
+    },
+    /// Removes every relationship equal to `relationship`.
+    RemoveRelationship {
+        relationship: Relationship,
+    },
+}
+
+/// Everything a knowledge graph holds.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GraphState {
+    pub entities: HashMap<String, Entity>,
+    pub relationships: Vec<Relationship>,
+}
+
+impl GraphState {
+    pub fn apply(&mut self, operation: GraphOperation) {
+        match operation {
+            GraphOperation::AddEntity { entity } => {
+                self.entities.insert(entity.id.clone(), entity);
+            }
+            GraphOperation::AddRelationship { relationship } => {
+                self.relationships.push(relationship);
+            }
+            GraphOperation::RemoveEntity { id } => {
+                self.entities.remove(&id);
+                self.relationships.retain(|r| r.from != id && r.to != id);
+            }
+            GraphOperation::RemoveRelationship { relationship } => {
+                self.relationships.retain(|r| *r != relationship);
+            }
+        }
+    }
+}
+
+/// Where a [`super::KnowledgeGraph`] keeps its contents.
+///
+/// The graph appends every operation before applying it, so a storage that has
+/// accepted an operation must return it from the next [`GraphStorage::load`].
+///
+/// Implementations may block on I/O: the graph calls `load` from
+/// [`super::KnowledgeGraph::with_storage`] on the caller's thread, and every other
+/// method on tokio's blocking thread pool, so a tokio runtime is required.
+pub trait GraphStorage: Send {
+    /// The state recorded so far.
+    fn load(&mut self) -> Result<GraphState, MetaSyntraXLError>;
+
+    /// Records `operation`.
+    fn append(&mut self, operation: &GraphOperation) -> Result<(), MetaSyntraXLError>;
+
+    /// Replaces everything recorded so far by `state`.
+    fn snapshot(&mut self, state: &GraphState) -> Result<(), MetaSyntraXLError>;
+
+    /// Whether the graph should call [`GraphStorage::snapshot`] after the last append.
+    fn needs_snapshot(&self) -> bool {
+        false
+    }
+}
+
+/// Keeps nothing; the graph lives only in memory.
+#[derive(Debug, Default)]
+pub struct MemoryStorage;
+
+impl GraphStorage for MemoryStorage {
+    fn load(&mut self) -> Result<GraphState, MetaSyntraXLError> {
+        Ok(GraphState::default())
+    }
+
+    fn append(&mut self, _operation: &GraphOperation) -> Result<(), MetaSyntraXLError> {
+        Ok(())
+    }
+
+    fn snapshot(&mut self, _state: &GraphState) -> Result<(), MetaSyntraXLError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogStorageOptions {
+    /// Flush every append to disk before it is acknowledged; without it, a crash
+    /// of the machine (not just the process) can lose recent operations. The flush
+    /// runs on the blocking thread pool, so it slows writers but not the executor.
+    pub sync: bool,
+    /// Take a snapshot and truncate the log after this many appends; `None` only
+    /// snapshots on [`super::KnowledgeGraph::snapshot`].
+    pub snapshot_every: Option<usize>,
+}
+
+impl Default for LogStorageOptions {
+    fn default() -> Self {
+        Self {
+            sync: true,
+            snapshot_every: Some(10_000),
+        }
+    }
+}
+
+/// An append-only log of operations in a directory, compacted into a snapshot.
+///
+/// The directory holds `snapshot.json`, replaced atomically by renaming, and
+/// `log.jsonl` with one JSON operation per line. Every record carries a sequence
+/// number, so records already covered by the snapshot are skipped on replay. A
+/// last line without its newline is the trace of a write cut short by a crash;
+/// it is dropped when the log is opened.
+#[derive(Debug)]
+pub struct LogStorage {
+    directory: PathBuf,
+    options: LogStorageOptions,
+    log: Option<File>,
+    /// The sequence number of the last record appended or replayed.
+    sequence: u64,
+    appended_since_snapshot: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LogRecord {
+    sequence: u64,
+    #[serde(flatten)]
+    operation: GraphOperation,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    sequence: u64,
+    #[serde(flatten)]
+    state: GraphState,
+}
+
+impl LogStorage {
+    /// Uses `directory`, creating it if needed, with the default options.
+    pub fn open(directory: impl AsRef<Path>) -> Result<Self, MetaSyntraXLError> {
+        Self::with_options(directory, LogStorageOptions::default())
+    }
+
+    pub fn with_options(
+        directory: impl AsRef<Path>,
+        options: LogStorageOptions,
+    ) -> Result<Self, MetaSyntraXLError> {
+        let directory = directory.as_ref().to_path_buf();
+        fs::create_dir_all(&directory)?;
+        Ok(Self {
+            directory,
+            options,
+            log: None,
+            sequence: 0,
+            appended_since_snapshot: 0,
+        })
+    }
+
+    fn log_file(&mut self) -> Result<&mut File, MetaSyntraXLError> {
+        if self.log.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.directory.join(LOG_FILE))?;
+            self.log = Some(file);
+        }
+        Ok(self.log.as_mut().expect("the log was just opened"))
+    }
+
+    fn read_snapshot(&self) -> Result<Snapshot, MetaSyntraXLError> {
+        let path = self.directory.join(SNAPSHOT_FILE);
+        if !path.exists() {
+            return Ok(Snapshot {
+                sequence: 0,
+                state: GraphState::default(),
+            });
+        }
+        let text = fs::read_to_string(&path)?;
+        serde_json::from_str(&text).map_err(|e| {
+            MetaSyntraXLError::KnowledgeGraphError(format!(
+                "Corrupt snapshot {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+}
+
+impl GraphStorage for LogStorage {
+    /// Replays the log onto the snapshot, dropping a torn last record.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KnowledgeGraphError` if the snapshot or a complete log record is
+    /// not valid JSON, and `IoError` if the files cannot be read.
+    fn load(&mut self) -> Result<GraphState, MetaSyntraXLError> {
+        let _ = fs::remove_file(self.directory.join(format!("{}.tmp", SNAPSHOT_FILE)));
+        let Snapshot {
+            sequence,
+            mut state,
+        } = self.read_snapshot()?;
+        self.sequence = sequence;
+
+        let path = self.directory.join(LOG_FILE);
+        let mut bytes = Vec::new();
+        if path.exists() {
+            File::open(&path)?.read_to_end(&mut bytes)?;
+        }
+        let complete = bytes.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        for (number, line) in bytes[..complete].split(|&b| b == b'\n').enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let record: LogRecord = serde_json::from_slice(line).map_err(|e| {
+                MetaSyntraXLError::KnowledgeGraphError(format!(
+                    "Corrupt record on line {} of {}: {}",
+                    number + 1,
+                    path.display(),
+                    e
+                ))
+            })?;
+            if record.sequence > self.sequence {
+                self.sequence = record.sequence;
+                state.apply(record.operation);
+            }
+        }
+        if complete < bytes.len() {
+            OpenOptions::new()
+                .write(true)
+                .open(&path)?
+                .set_len(complete as u64)?;
+        }
+
+        self.log = None;
+        self.appended_since_snapshot = 0;
+        Ok(state)
+    }
+
+    fn append(&mut self, operation: &GraphOperation) -> Result<(), MetaSyntraXLError> {
+        let record = LogRecord {
+            sequence: self.sequence + 1,
+            operation: operation.clone(),
+        };
+        let mut line = serde_json::to_vec(&record).expect("operations serialize to JSON");
+        line.push(b'\n');
+        let sync = self.options.sync;
+        let file = self.log_file()?;
+        // One write per record, so a crash leaves at most one torn line at the end.
+        file.write_all(&line)?;
+        if sync {
+            file.sync_data()?;
+        }
+        self.sequence = record.sequence;
+        self.appended_since_snapshot += 1;
+        Ok(())
+    }
+
+    fn snapshot(&mut self, state: &GraphState) -> Result<(), MetaSyntraXLError> {
+        let snapshot = Snapshot {
+            sequence: self.sequence,
+            state: state.clone(),
+        };
+        let path = self.directory.join(SNAPSHOT_FILE);
+        let temporary = self.directory.join(format!("{}.tmp", SNAPSHOT_FILE));
+        let mut file = File::create(&temporary)?;
+        serde_json::to_writer(&mut file, &snapshot).map_err(|e| {
+            MetaSyntraXLError::KnowledgeGraphError(format!("Cannot write snapshot: {}", e))
+        })?;
+        file.sync_all()?;
+        fs::rename(&temporary, &path)?;
+        if let Ok(directory) = File::open(&self.directory) {
+            // Makes the rename durable where directories can be synced.
+            let _ = directory.sync_all();
+        }
+
+        // Records up to `sequence` are skipped on replay, so a crash before this
+        // point only leaves a longer log.
+        self.log_file()?.set_len(0)?;
+        self.appended_since_snapshot = 0;
+        Ok(())
+    }
+
+    fn needs_snapshot(&self) -> bool {
+        matches!(self.options.snapshot_every, Some(every) if self.appended_since_snapshot >= every)
+    }
+}
\ No newline at end of file
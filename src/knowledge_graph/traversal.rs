@@ -0,0 +1,378 @@
+// src/knowledge_graph/traversal.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[KNOWLEDGE-GRAPH]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use super::index::{EntityTable, RelationshipTable};
+use super::{Entity, Relationship};
+use crate::errors::MetaSyntraXLError;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+/// Which relationships a traversal follows from an entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    /// From `from` to `to`.
+    #[default]
+    Outgoing,
+    /// From `to` back to `from`.
+    Incoming,
+    /// Either way.
+    Both,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraversalOptions {
+    pub direction: Direction,
+    /// Relationship types to follow; empty follows every type.
+    pub types: Vec<String>,
+}
+
+/// An entity reached by a traversal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visit {
+    pub entity: Entity,
+    /// Hops from the start entity.
+    pub depth: usize,
+    /// The relationship the entity was reached through; `None` for the start.
+    pub via: Option<Relationship>,
+}
+
+/// A walk through the graph; `relationships[i]` links `entities[i]` and
+/// `entities[i + 1]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphPath {
+    pub entities: Vec<Entity>,
+    pub relationships: Vec<Relationship>,
+    /// The sum of the relationship weights; the number of relationships for
+    /// unweighted queries.
+    pub cost: f64,
+}
+
+/// Some entities and the relationships among them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Subgraph {
+    pub entities: Vec<Entity>,
+    pub relationships: Vec<Relationship>,
+}
+
+/// Read access to both tables for the duration of a query.
+pub(crate) struct Graph<'a> {
+    pub(crate) relationships: &'a RelationshipTable,
+    pub(crate) entities: &'a EntityTable,
+}
+
+impl<'a> Graph<'a> {
+    /// The relationships `options` follows from `id`, each with the existing entity
+    /// at its other end; outgoing relationships come first.
+    fn steps(&self, id: &str, options: &TraversalOptions) -> Vec<(&'a Relationship, &'a Entity)> {
+        let mut steps = Vec::new();
+        for (outgoing, followed) in [
+            (true, options.direction != Direction::Incoming),
+            (false, options.direction != Direction::Outgoing),
+        ] {
+            if !followed {
+                continue;
+            }
+            for relationship in self.relationships.adjacent(id, outgoing, &options.types) {
+                let other = if outgoing {
+                    &relationship.to
+                } else {
+                    &relationship.from
+                };
+                if let Some(entity) = self.entities.get(other) {
+                    steps.push((relationship, entity));
+                }
+            }
+        }
+        steps
+    }
+
+    pub(crate) fn breadth_first(
+        &self,
+        start: &str,
+        max_depth: Option<usize>,
+        options: &TraversalOptions,
+    ) -> Vec<Visit> {
+        let start = match self.entities.get(start) {
+            Some(start) => start,
+            None => return Vec::new(),
+        };
+        let mut visited: HashSet<&str> = HashSet::from([start.id.as_str()]);
+        let mut visits = vec![Visit {
+            entity: start.clone(),
+            depth: 0,
+            via: None,
+        }];
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((entity, depth)) = queue.pop_front() {
+            if matches!(max_depth, Some(max) if depth >= max) {
+                continue;
+            }
+            for (relationship, next) in self.steps(&entity.id, options) {
+                if visited.insert(next.id.as_str()) {
+                    visits.push(Visit {
+                        entity: next.clone(),
+                        depth: depth + 1,
+                        via: Some(relationship.clone()),
+                    });
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+        visits
+    }
+
+    pub(crate) fn depth_first(
+        &self,
+        start: &str,
+        max_depth: Option<usize>,
+        options: &TraversalOptions,
+    ) -> Vec<Visit> {
+        let start = match self.entities.get(start) {
+            Some(start) => start,
+            None => return Vec::new(),
+        };
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut visits = Vec::new();
+        let mut stack: Vec<(&Entity, usize, Option<&Relationship>)> = vec![(start, 0, None)];
+        while let Some((entity, depth, via)) = stack.pop() {
+            if !visited.insert(entity.id.as_str()) {
+                continue;
+            }
+            visits.push(Visit {
+                entity: entity.clone(),
+                depth,
+                via: via.cloned(),
+            });
+            if matches!(max_depth, Some(max) if depth >= max) {
+                continue;
+            }
+            // Reversed, so the first step is explored first.
+            for (relationship, next) in self.steps(&entity.id, options).into_iter().rev() {
+                if !visited.contains(next.id.as_str()) {
+                    stack.push((next, depth + 1, Some(relationship)));
+                }
+            }
+        }
+        visits
+    }
+
+    /// A path with the fewest relationships, found by breadth-first search.
+    pub(crate) fn shortest_path(
+        &self,
+        from: &str,
+        to: &str,
+        options: &TraversalOptions,
+    ) -> Option<GraphPath> {
+        let start = self.entities.get(from)?;
+        let mut previous: HashMap<&str, (&Relationship, &Entity)> = HashMap::new();
+        let mut visited: HashSet<&str> = HashSet::from([start.id.as_str()]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(entity) = queue.pop_front() {
+            if entity.id == to {
+                let path = self.trace(start, entity, &previous);
+                let cost = path.relationships.len() as f64;
+                return Some(GraphPath { cost, ..path });
+            }
+            for (relationship, next) in self.steps(&entity.id, options) {
+                if visited.insert(next.id.as_str()) {
+                    previous.insert(next.id.as_str(), (relationship, entity));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// A path with the least total weight, found by Dijkstra's algorithm.
+    pub(crate) fn weighted_shortest_path(
+        &self,
+        from: &str,
+        to: &str,
+        options: &TraversalOptions,
+        weight: impl Fn(&Relationship) -> f64,
+    ) -> Result<Option<GraphPath>, MetaSyntraXLError> {
+        let start = match self.entities.get(from) {
+            Some(start) => start,
+            None => return Ok(None),
+        };
+        let mut distance: HashMap<&str, f64> = HashMap::from([(start.id.as_str(), 0.0)]);
+        let mut previous: HashMap<&str, (&Relationship, &Entity)> = HashMap::new();
+        let mut settled: HashSet<&str> = HashSet::new();
+        let mut heap = BinaryHeap::from([Candidate {
+            cost: 0.0,
+            entity: start,
+        }]);
+        while let Some(Candidate { cost, entity }) = heap.pop() {
+            if !settled.insert(entity.id.as_str()) {
+                continue;
+            }
+            if entity.id == to {
+                let path = self.trace(start, entity, &previous);
+                return Ok(Some(GraphPath { cost, ..path }));
+            }
+            for (relationship, next) in self.steps(&entity.id, options) {
+                let step = weight(relationship);
+                if !(step >= 0.0 && step.is_finite()) {
+                    return Err(MetaSyntraXLError::KnowledgeGraphError(format!(
+                        "Relationship {} -[{}]-> {} has weight {}, expected a finite non-negative number",
+                        relationship.from, relationship.type_, relationship.to, step
+                    )));
+                }
+                let candidate = cost + step;
+                if !matches!(distance.get(next.id.as_str()), Some(&known) if known <= candidate) {
+                    distance.insert(next.id.as_str(), candidate);
+                    previous.insert(next.id.as_str(), (relationship, entity));
+                    heap.push(Candidate {
+                        cost: candidate,
+                        entity: next,
+                    });
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Every path from `from` to `to` with at most `max_length` relationships that
+    /// does not visit an entity twice, in depth-first order.
+    pub(crate) fn all_paths(
+        &self,
+        from: &str,
+        to: &str,
+        max_length: usize,
+        options: &TraversalOptions,
+    ) -> Vec<GraphPath> {
+        let start = match self.entities.get(from) {
+            Some(start) => start,
+            None => return Vec::new(),
+        };
+        let mut paths = Vec::new();
+        let mut entities = vec![start];
+        let mut relationships = Vec::new();
+        self.extend_paths(
+            to,
+            max_length,
+            options,
+            &mut entities,
+            &mut relationships,
+            &mut paths,
+        );
+        paths
+    }
+
+    fn extend_paths(
+        &self,
+        to: &str,
+        max_length: usize,
+        options: &TraversalOptions,
+        entities: &mut Vec<&'a Entity>,
+        relationships: &mut Vec<&'a Relationship>,
+        paths: &mut Vec<GraphPath>,
+    ) {
+        let last = entities[entities.len() - 1];
+        if last.id == to {
+            paths.push(GraphPath {
+                entities: entities.iter().map(|&e| e.clone()).collect(),
+                relationships: relationships.iter().map(|&r| r.clone()).collect(),
+                cost: relationships.len() as f64,
+            });
+            return;
+        }
+        if relationships.len() == max_length {
+            return;
+        }
+        for (relationship, next) in self.steps(&last.id, options) {
+            if entities.iter().any(|e| e.id == next.id) {
+                continue;
+            }
+            entities.push(next);
+            relationships.push(relationship);
+            self.extend_paths(to, max_length, options, entities, relationships, paths);
+            entities.pop();
+            relationships.pop();
+        }
+    }
+
+    /// The entities within `radius` hops of `center` and every relationship that
+    /// `options` would follow between two of them.
+    pub(crate) fn neighborhood(
+        &self,
+        center: &str,
+        radius: usize,
+        options: &TraversalOptions,
+    ) -> Subgraph {
+        let entities: Vec<Entity> = self
+            .breadth_first(center, Some(radius), options)
+            .into_iter()
+            .map(|visit| visit.entity)
+            .collect();
+        let members: HashSet<&str> = entities.iter().map(|e| e.id.as_str()).collect();
+        let mut relationships = Vec::new();
+        for entity in &entities {
+            for relationship in self
+                .relationships
+                .adjacent(&entity.id, true, &options.types)
+            {
+                if members.contains(relationship.to.as_str()) {
+                    relationships.push(relationship.clone());
+                }
+            }
+        }
+        Subgraph {
+            entities,
+            relationships,
+        }
+    }
+
+    /// The path from `start` to `end` recorded in `previous`, with a zero cost.
+    fn trace(
+        &self,
+        start: &Entity,
+        end: &'a Entity,
+        previous: &HashMap<&str, (&'a Relationship, &'a Entity)>,
+    ) -> GraphPath {
+        let mut entities = vec![end.clone()];
+        let mut relationships = Vec::new();
+        let mut current = end;
+        while current.id != start.id {
+            let (relationship, entity) = previous[current.id.as_str()];
+            relationships.push(relationship.clone());
+            entities.push(entity.clone());
+            current = entity;
+        }
+        entities.reverse();
+        relationships.reverse();
+        GraphPath {
+            entities,
+            relationships,
+            cost: 0.0,
+        }
+    }
+}
+
+/// An entry of Dijkstra's queue, ordered so the cheapest pops first.
+struct Candidate<'a> {
+    cost: f64,
+    entity: &'a Entity,
+}
+
+impl PartialEq for Candidate<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate<'_> {}
+
+impl PartialOrd for Candidate<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Costs are finite, so `total_cmp` agrees with `<`; ties go to the smaller id.
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| other.entity.id.cmp(&self.entity.id))
+    }
+}
\ No newline at end of file
@@ -0,0 +1,225 @@
+// src/knowledge_graph/value.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[KNOWLEDGE-GRAPH]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The value of an entity or relationship property.
+///
+/// In JSON, strings, numbers, booleans and lists are written as themselves, a
+/// datetime as `{"datetime": "<RFC 3339>"}` and a float JSON has no number for as
+/// `{"float": "NaN"}`, `"inf"` or `"-inf"`, so graphs stored before properties
+/// were typed load with every value a string.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Repr", into = "Repr")]
+pub enum PropertyValue {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    DateTime(DateTime<Utc>),
+    List(Vec<PropertyValue>),
+}
+
+impl PropertyValue {
+    /// The value of an `Int` or `Float`, as a float.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            PropertyValue::Int(value) => Some(*value as f64),
+            PropertyValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PropertyValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyValue::String(value) => write!(f, "{}", value),
+            PropertyValue::Int(value) => write!(f, "{}", value),
+            PropertyValue::Float(value) => write!(f, "{}", value),
+            PropertyValue::Bool(value) => write!(f, "{}", value),
+            PropertyValue::DateTime(value) => write!(f, "{}", value.to_rfc3339()),
+            PropertyValue::List(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl From<&str> for PropertyValue {
+    fn from(value: &str) -> Self {
+        PropertyValue::String(value.to_string())
+    }
+}
+
+impl From<String> for PropertyValue {
+    fn from(value: String) -> Self {
+        PropertyValue::String(value)
+    }
+}
+
+impl From<&String> for PropertyValue {
+    fn from(value: &String) -> Self {
+        PropertyValue::String(value.clone())
+    }
+}
+
+impl From<i64> for PropertyValue {
+    fn from(value: i64) -> Self {
+        PropertyValue::Int(value)
+    }
+}
+
+impl From<f64> for PropertyValue {
+    fn from(value: f64) -> Self {
+        PropertyValue::Float(value)
+    }
+}
+
+impl From<bool> for PropertyValue {
+    fn from(value: bool) -> Self {
+        PropertyValue::Bool(value)
+    }
+}
+
+impl From<DateTime<Utc>> for PropertyValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        PropertyValue::DateTime(value)
+    }
+}
+
+impl From<Vec<PropertyValue>> for PropertyValue {
+    fn from(values: Vec<PropertyValue>) -> Self {
+        PropertyValue::List(values)
+    }
+}
+
+/// The JSON form of a [`PropertyValue`]; the order of the variants decides how
+/// a number is read, integers first.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Repr {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    List(Vec<PropertyValue>),
+    DateTime { datetime: DateTime<Utc> },
+    NonFinite { float: NonFinite },
+}
+
+#[derive(Serialize, Deserialize)]
+enum NonFinite {
+    #[serde(rename = "NaN")]
+    NaN,
+    #[serde(rename = "inf")]
+    Infinity,
+    #[serde(rename = "-inf")]
+    NegInfinity,
+}
+
+impl From<Repr> for PropertyValue {
+    fn from(repr: Repr) -> Self {
+        match repr {
+            Repr::Bool(value) => PropertyValue::Bool(value),
+            Repr::Int(value) => PropertyValue::Int(value),
+            Repr::Float(value) => PropertyValue::Float(value),
+            Repr::String(value) => PropertyValue::String(value),
+            Repr::List(values) => PropertyValue::List(values),
+            Repr::DateTime { datetime } => PropertyValue::DateTime(datetime),
+            Repr::NonFinite { float } => PropertyValue::Float(match float {
+                NonFinite::NaN => f64::NAN,
+                NonFinite::Infinity => f64::INFINITY,
+                NonFinite::NegInfinity => f64::NEG_INFINITY,
+            }),
+        }
+    }
+}
+
+impl From<PropertyValue> for Repr {
+    fn from(value: PropertyValue) -> Self {
+        match value {
+            PropertyValue::Bool(value) => Repr::Bool(value),
+            PropertyValue::Int(value) => Repr::Int(value),
+            PropertyValue::Float(value) if value.is_nan() => Repr::NonFinite {
+                float: NonFinite::NaN,
+            },
+            PropertyValue::Float(value) if value.is_infinite() => Repr::NonFinite {
+                float: if value > 0.0 {
+                    NonFinite::Infinity
+                } else {
+                    NonFinite::NegInfinity
+                },
+            },
+            PropertyValue::Float(value) => Repr::Float(value),
+            PropertyValue::String(value) => Repr::String(value),
+            PropertyValue::List(values) => Repr::List(values),
+            PropertyValue::DateTime(datetime) => Repr::DateTime { datetime },
+        }
+    }
+}
+
+/// A hashable stand-in for a [`PropertyValue`], equal exactly when the values are.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum IndexKey {
+    String(String),
+    Int(i64),
+    /// The bits of the float, with `-0.0` read as `0.0`.
+    Float(u64),
+    Bool(bool),
+    DateTime(DateTime<Utc>),
+    List(Vec<IndexKey>),
+}
+
+impl From<&PropertyValue> for IndexKey {
+    fn from(value: &PropertyValue) -> Self {
+        match value {
+            PropertyValue::String(value) => IndexKey::String(value.clone()),
+            PropertyValue::Int(value) => IndexKey::Int(*value),
+            PropertyValue::Float(value) => IndexKey::Float((value + 0.0).to_bits()),
+            PropertyValue::Bool(value) => IndexKey::Bool(*value),
+            PropertyValue::DateTime(value) => IndexKey::DateTime(*value),
+            PropertyValue::List(values) => IndexKey::List(values.iter().map(Self::from).collect()),
+        }
+    }
+}
+
+/// A number in the range index: `Int` and `Float` values, ordered as floats.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Number(pub(crate) f64);
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Number {}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // NaN is never indexed, and `+ 0.0` makes `-0.0` equal to `0.0`.
+        (self.0 + 0.0).total_cmp(&(other.0 + 0.0))
+    }
+}
\ No newline at end of file
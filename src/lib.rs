@@ -6,6 +6,7 @@ pub mod errors;
 pub mod ensemble;
 pub mod gradient_cache;
 pub mod knowledge_graph;
+pub mod logging;
 pub mod ppo;
 pub mod retrieval_system;
 pub mod thought_chain;
@@ -0,0 +1,28 @@
+// src/logging.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[LOGGING]Xyn>=====S===t===u====d===i===o===s====[R|$>
+use crate::config::{LogFormat, LoggingConfig};
+use crate::errors::MetaSyntraXLError;
+use tracing_subscriber::EnvFilter;
+
+/// Installs the global tracing subscriber described by the `[logging]` section.
+///
+/// `RUST_LOG`, when set, takes precedence over the configured level so that a
+/// single deployment can be turned up for debugging without editing the config.
+/// In JSON mode every event carries its enclosing spans (request id, model index,
+/// retrieval query, step number) so a request can be followed end to end.
+pub fn init(config: &LoggingConfig) -> Result<(), MetaSyntraXLError> {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&config.level))
+        .map_err(|e| MetaSyntraXLError::ConfigError(format!("Invalid log level: {}", e)))?;
+
+    let result = match config.format {
+        LogFormat::Json => tracing_subscriber::fmt()
+            .json()
+            .with_env_filter(filter)
+            .with_current_span(true)
+            .with_span_list(true)
+            .try_init(),
+        LogFormat::Text => tracing_subscriber::fmt().with_env_filter(filter).try_init(),
+    };
+
+    result.map_err(|e| MetaSyntraXLError::ConfigError(format!("Failed to install logger: {}", e)))
+}
@@ -1,13 +1,12 @@
 // src/main.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[MAIN]Xyn>=====S===t===u====d===i===o===s====[R|$>
-use env_logger;
-use log::{info, error};
+use tracing::{error, info, warn};
 use tch::{Tensor, nn};
 use crate::{
     controller::Controller,
     errors::MetaSyntraXLError,
 };
 
-use crate::config::Config;
+use crate::config::{Config, EnsembleConfig, LoggingConfig, ValidationConfig};
 
 mod config;
 mod transformer_rag;
@@ -24,24 +23,50 @@ mod tokenizer;
 mod gradient_cache;
 mod errors;
 mod controller;
+mod logging;
 
 #[tokio::main]
 async fn main() -> Result<(), MetaSyntraXLError> {
-    env_logger::init();
+    let mut config = Config::default();
+
+    let config_path = std::env::var("CONFIG_PATH").unwrap_or_else(|_| "config/config.toml".to_string());
+    let file_logging = LoggingConfig::from_file(&config_path);
+    if let Ok(logging) = &file_logging {
+        config.logging = logging.clone();
+    }
+
+    logging::init(&config.logging)?;
+    if let Err(e) = file_logging {
+        warn!(config_path = %config_path, "Using default logging configuration: {}", e);
+    }
+    match EnsembleConfig::from_file(&config_path) {
+        Ok(ensemble) => config.ensemble = ensemble,
+        Err(e) => warn!(config_path = %config_path, "Using default ensemble configuration: {}", e),
+    }
+    match ValidationConfig::from_file(&config_path) {
+        Ok(validation) => config.validation = validation,
+        Err(e) => warn!(config_path = %config_path, "Using default validation configuration: {}", e),
+    }
     info!("Starting MetaSyntraXL...");
 
     let vs = nn::VarStore::new(tch::Device::Cpu);
 
-    let config = Config::default();
-
     let controller = Controller::new(&vs.root(), &config)?;
 
     let input = Tensor::of_slice(&[1, 2, 3, 4])
         .to_kind(tch::Kind::Int64)
         .unsqueeze(0);
 
-    match controller.process(&input).await {
-        Ok(output) => info!("Processing successful: {:?}", output),
+    match controller.process_validated(&input).await {
+        Ok(result) => match result.validation {
+            Some(validation) => info!(
+                posterior = ?validation.posterior,
+                valid = validation.valid,
+                "Processing successful: {:?}",
+                result.output
+            ),
+            None => info!("Processing successful: {:?}", result.output),
+        },
         Err(e) => error!("Processing failed: {:?}", e),
     }
 
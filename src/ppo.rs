@@ -1,6 +1,7 @@
 // src/ppo.rs ~=#######D]====A===r===c====M===o===o===n====<Lord[PPO]Xyn>=====S===t===u====d===i===o===s====[R|$>
 use crate::errors::MetaSyntraXLError;
 use tch::{nn, nn::Module, nn::OptimizerConfig, Kind, Tensor};
+use tracing::{debug, error, info_span};
 
 pub struct PPO<'a> {
     vs: &'a nn::VarStore,
@@ -9,6 +10,7 @@ pub struct PPO<'a> {
     optimizer: nn::Optimizer,
     clip_param: f64,
     max_grad_norm: f64,
+    update_step: u64,
 }
 
 impl<'a> PPO<'a> {
@@ -53,6 +55,7 @@ impl<'a> PPO<'a> {
             optimizer,
             clip_param: 0.2,
             max_grad_norm: 0.5,
+            update_step: 0,
         }
     }
 
@@ -81,7 +84,7 @@ impl<'a> PPO<'a> {
         advantages: &Tensor
     ) -> Result<(), MetaSyntraXLError> {
         let (_action_probs, _state_values) = self.evaluate(states).map_err(|e| {
-            eprintln!("Error during evaluate: {:?}", e);
+            error!(error = ?e, "Error during evaluate");
             MetaSyntraXLError::EvaluationError // Corrected error variant
         })?;
 
@@ -98,6 +101,10 @@ impl<'a> PPO<'a> {
         returns: &Tensor,
         advantages: &Tensor,
     ) -> Result<(), MetaSyntraXLError> {
+        let span = info_span!("ppo.update", step = self.update_step);
+        let _enter = span.enter();
+        self.update_step += 1;
+
         let (action_probs, state_values) = self.evaluate(states)?;
 
         let new_log_probs = action_probs
@@ -118,6 +125,7 @@ impl<'a> PPO<'a> {
             .mean(Kind::Float);
 
         let loss: Tensor = actor_loss + 0.5 * critic_loss;
+        debug!(loss = loss.double_value(&[]), "Computed PPO loss");
 
         self.optimizer.zero_grad();
         loss.backward();
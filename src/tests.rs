@@ -7,7 +7,9 @@ use crate::errors::MetaSyntraXLError;
 use crate::transformer_rag::TransformerRAG;
 use crate::ensemble::Ensemble;
 use crate::gradient_cache::GradientCache;
-use crate::config::{Config, ElasticsearchConfig, PrometheusConfig};
+use crate::config::{
+    Config, ElasticsearchConfig, EnsembleConfig, LoggingConfig, PrometheusConfig, ValidationConfig,
+};
 use std::collections::HashMap;  
 
 #[tokio::test]
@@ -33,6 +35,9 @@ async fn test_transformer_rag_forward() -> Result<(), MetaSyntraXLError> {
         prometheus: PrometheusConfig {
             port: 9090,
         },
+        logging: LoggingConfig::default(),
+        ensemble: EnsembleConfig::default(),
+        validation: ValidationConfig::default(),
     };
     let transformer_rag = TransformerRAG::new(&vs.root(), &config)?;
     let input = Tensor::of_slice(&[1, 2, 3, 4]).unsqueeze(0);
@@ -64,6 +69,9 @@ async fn test_ensemble_bagging() -> Result<(), MetaSyntraXLError> {
         prometheus: PrometheusConfig {
             port: 9090,
         },
+        logging: LoggingConfig::default(),
+        ensemble: EnsembleConfig::default(),
+        validation: ValidationConfig::default(),
     };
     let ensemble = Ensemble::new(
         &vs.root(),
@@ -74,6 +82,7 @@ async fn test_ensemble_bagging() -> Result<(), MetaSyntraXLError> {
     )?;
     let input = Tensor::of_slice(&[1.0; 512]).unsqueeze(0);
     let prediction = ensemble.bagging_predict(&input).await?;
-    assert_eq!(prediction.size(), &[512]);
+    assert_eq!(prediction.output.size(), &[512]);
+    assert!(prediction.dropped.is_empty());
     Ok(())
 }
\ No newline at end of file
@@ -5,6 +5,7 @@ use std::sync::Arc;
 use tch::nn;
 use tokio::sync::Mutex;
 use rand::seq::SliceRandom;
+use tracing::{debug, info_span, instrument, Instrument};
 
 pub struct ThoughtChain<'a> {
     entities: Vec<Arc<Mutex<CognitiveThoughtEntity<'a>>>>,
@@ -31,15 +32,20 @@ impl<'a> ThoughtChain<'a> {
         }
     }
 
+    #[instrument(name = "thought_chain.process", skip_all, fields(num_entities = self.entities.len()))]
     pub async fn process(&self, input: &tch::Tensor) -> Result<tch::Tensor, MetaSyntraXLError> {
         let mut current_input = input.shallow_clone();
-        for entity in &self.entities {
+        for (step, entity) in self.entities.iter().enumerate() {
             let mut cte = entity.lock().await;
-            current_input = cte.process(&current_input).await;
+            current_input = cte
+                .process(&current_input)
+                .instrument(info_span!("thought_chain.step", step))
+                .await;
         }
         Ok(current_input)
     }
 
+    #[instrument(name = "thought_chain.evolve", skip_all, fields(population = self.entities.len()))]
     pub async fn evolve(&mut self, _vs: &'a nn::VarStore) -> Result<(), MetaSyntraXLError> {
         self.entities.sort_by(|a, b| {
             let a_fitness = futures::executor::block_on(a.lock()).fitness;
@@ -51,6 +57,7 @@ impl<'a> ThoughtChain<'a> {
 
         let elite_count = (self.entities.len() as f64 * self.elite_fraction).ceil() as usize;
         let elites = self.entities[..elite_count].to_vec();
+        debug!(elite_count, "Selected elites for reproduction");
 
         let mut new_entities = elites.clone();
 
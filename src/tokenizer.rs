@@ -8,9 +8,10 @@ pub struct Tokenizer {
 
 impl Tokenizer {
     pub fn new() -> Self {
+        // `encode` falls back to `<UNK>`, so it must always be in the vocabulary.
         Self {
-            vocab: HashMap::new(),
-            reverse_vocab: HashMap::new(),
+            vocab: HashMap::from([("<UNK>".to_string(), 0)]),
+            reverse_vocab: HashMap::from([(0, "<UNK>".to_string())]),
         }
     }
 
@@ -79,6 +79,66 @@ impl TransformerModel {
 
         logits
     }
+
+    /// Returns shallow handles to every trainable tensor of the model.
+    pub fn parameters(&self) -> Vec<Tensor> {
+        let mut parameters = vec![
+            self.embedding.ws.shallow_clone(),
+            self.positional_embedding.ws.shallow_clone(),
+        ];
+        for layer in &self.encoder_layers {
+            parameters.extend(layer.parameters());
+        }
+        parameters.extend(layer_norm_parameters(&self.layer_norm));
+        parameters.extend(linear_parameters(&self.output_layer));
+        parameters
+    }
+}
+
+/// Applies one plain SGD update to `parameters` from their accumulated gradients,
+/// then clears the gradients.
+pub(crate) fn sgd_step(parameters: &[Tensor], learning_rate: f64) {
+    tch::no_grad(|| {
+        for parameter in parameters {
+            let mut grad = parameter.grad();
+            if grad.defined() {
+                let mut parameter = parameter.shallow_clone();
+                let _ = parameter.sub_(&(&grad * learning_rate));
+                let _ = grad.zero_();
+            }
+        }
+    });
+}
+
+/// Copies the current values of `parameters` so they can be restored later.
+pub(crate) fn snapshot(parameters: &[Tensor]) -> Vec<Tensor> {
+    parameters.iter().map(|p| p.detach().copy()).collect()
+}
+
+/// Overwrites `parameters` in place with values taken by [`snapshot`].
+pub(crate) fn restore(parameters: &[Tensor], saved: &[Tensor]) {
+    tch::no_grad(|| {
+        for (parameter, value) in parameters.iter().zip(saved) {
+            let _ = parameter.shallow_clone().copy_(value);
+        }
+    });
+}
+
+pub(crate) fn linear_parameters(linear: &nn::Linear) -> Vec<Tensor> {
+    let mut parameters = vec![linear.ws.shallow_clone()];
+    if let Some(bs) = &linear.bs {
+        parameters.push(bs.shallow_clone());
+    }
+    parameters
+}
+
+fn layer_norm_parameters(layer_norm: &nn::LayerNorm) -> Vec<Tensor> {
+    layer_norm
+        .ws
+        .iter()
+        .chain(layer_norm.bs.iter())
+        .map(|t| t.shallow_clone())
+        .collect()
 }
 
 pub struct EncoderLayer {
@@ -124,4 +184,11 @@ impl EncoderLayer {
 
         x
     }
+
+    pub fn parameters(&self) -> Vec<Tensor> {
+        let mut parameters = linear_parameters(&self.linear1);
+        parameters.extend(linear_parameters(&self.linear2));
+        parameters.extend(layer_norm_parameters(&self.norm1));
+        parameters
+    }
 }
\ No newline at end of file
@@ -3,9 +3,10 @@ use crate::config::Config;
 use crate::errors::MetaSyntraXLError;
 use crate::retrieval_system::RetrievalSystem;
 use crate::tokenizer::Tokenizer;
-use crate::transformer_model::TransformerModel;
+use crate::transformer_model::{self, TransformerModel};
 use tch::nn::Path;
 use tch::{Device, Kind, Tensor};
+use tracing::{debug, instrument, Span};
 
 pub struct TransformerRAG {
     transformer: TransformerModel,
@@ -16,8 +17,17 @@ pub struct TransformerRAG {
 
 impl TransformerRAG {
     pub fn new(vs: &Path, config: &Config) -> Result<Self, MetaSyntraXLError> {
+        Self::with_retrieval_system(vs, config, RetrievalSystem::new(config)?)
+    }
+
+    /// Like [`TransformerRAG::new`], augmenting inputs with documents from
+    /// `retrieval_system`.
+    pub fn with_retrieval_system(
+        vs: &Path,
+        config: &Config,
+        retrieval_system: RetrievalSystem,
+    ) -> Result<Self, MetaSyntraXLError> {
         let tokenizer = Tokenizer::new();
-        let retrieval_system = RetrievalSystem::new(config)?;
         let device = if config.use_cuda && Device::cuda_if_available().is_cuda() {
             Device::Cuda(0)
         } else {
@@ -35,6 +45,38 @@ impl TransformerRAG {
     }
 
     pub async fn forward(&self, input: &Tensor) -> Result<Tensor, MetaSyntraXLError> {
+        self.forward_with_documents(input)
+            .await
+            .map(|(output, _)| output)
+    }
+
+    /// Like [`TransformerRAG::forward`], also returning the documents retrieved to
+    /// augment `input`.
+    #[instrument(name = "transformer_rag.forward", skip_all)]
+    pub async fn forward_with_documents(
+        &self,
+        input: &Tensor,
+    ) -> Result<(Tensor, Vec<String>), MetaSyntraXLError> {
+        let (augmented_tensor, documents) = self.augment_with_documents(input).await?;
+        Ok((self.transform(&augmented_tensor), documents))
+    }
+
+    /// Retrieves documents for `input` and returns the augmented token tensor.
+    ///
+    /// This is the asynchronous half of [`TransformerRAG::forward`]; the decoded
+    /// query is recorded on the `transformer_rag.augment` span.
+    pub async fn augment(&self, input: &Tensor) -> Result<Tensor, MetaSyntraXLError> {
+        self.augment_with_documents(input)
+            .await
+            .map(|(augmented_tensor, _)| augmented_tensor)
+    }
+
+    /// Like [`TransformerRAG::augment`], also returning the retrieved documents.
+    #[instrument(name = "transformer_rag.augment", skip_all, fields(query = tracing::field::Empty))]
+    pub async fn augment_with_documents(
+        &self,
+        input: &Tensor,
+    ) -> Result<(Tensor, Vec<String>), MetaSyntraXLError> {
         let input = input.to_device(self.device);
 
         let input_tokens: Vec<i64> = input
@@ -45,8 +87,10 @@ impl TransformerRAG {
             .collect();
 
         let input_text = self.tokenizer.decode(&input_tokens);
+        Span::current().record("query", input_text.as_str());
 
         let retrieved_docs = self.retrieval_system.retrieve(&input_text).await?;
+        debug!(documents = retrieved_docs.len(), "Retrieved documents");
 
         let augmented_input = format!(
             "{} {}",
@@ -61,8 +105,127 @@ impl TransformerRAG {
             .unsqueeze(0)
             .to_device(self.device);
 
-        let output = self.transformer.forward(&augmented_tensor);
+        Ok((augmented_tensor, retrieved_docs))
+    }
+
+    /// Runs the transformer on tokens produced by [`TransformerRAG::augment`].
+    ///
+    /// This is the synchronous, compute-heavy half of [`TransformerRAG::forward`].
+    pub fn transform(&self, augmented_tensor: &Tensor) -> Tensor {
+        self.transformer.forward(augmented_tensor)
+    }
+
+    /// Returns shallow handles to the trainable tensors of the underlying transformer.
+    pub fn parameters(&self) -> Vec<Tensor> {
+        self.transformer.parameters()
+    }
+
+    /// Sequence-pooled logits of the underlying transformer, skipping retrieval.
+    ///
+    /// This is the prediction that [`TransformerRAG::train_step`] fits.
+    pub fn pooled_logits(&self, input: &Tensor) -> Tensor {
+        let input = input.to_device(self.device).to_kind(Kind::Int64);
+        pool_sequence(&self.transformer.forward(&input))
+    }
+
+    /// Runs one SGD step of sequence classification on the underlying transformer.
+    ///
+    /// Retrieval is skipped: the transformer is trained on `input` tokens directly.
+    /// Logits are mean-pooled over the sequence and `targets` holds one class index
+    /// per batch row. When `sample_weights` is given, each row's loss is scaled by
+    /// its weight and the sum is divided by the number of rows, so a weight of one
+    /// trains like no weight and a row's weight also scales its update across calls.
+    ///
+    /// # Returns
+    ///
+    /// * `f64` - The cross-entropy loss before the update.
+    pub fn train_step(
+        &self,
+        input: &Tensor,
+        targets: &Tensor,
+        sample_weights: Option<&Tensor>,
+        learning_rate: f64,
+    ) -> Result<f64, MetaSyntraXLError> {
+        let input = input.to_device(self.device).to_kind(Kind::Int64);
+        let targets = targets.to_device(self.device).to_kind(Kind::Int64);
+
+        if targets.size() != [input.size()[0]] {
+            return Err(MetaSyntraXLError::TransformerError(format!(
+                "Expected one target per batch row, got targets of shape {:?} for input of shape {:?}",
+                targets.size(),
+                input.size()
+            )));
+        }
+
+        let logits = pool_sequence(&self.transformer.forward(&input));
+        let loss = match sample_weights {
+            Some(weights) => {
+                let weights = weights.to_device(self.device).to_kind(Kind::Float);
+                let row_losses = -logits
+                    .log_softmax(-1, Kind::Float)
+                    .gather(1, &targets.unsqueeze(1), false)
+                    .squeeze_dim(1);
+                (row_losses * &weights).sum(Kind::Float) / input.size()[0] as f64
+            }
+            None => logits.cross_entropy_for_logits(&targets),
+        };
+
+        loss.backward();
+        transformer_model::sgd_step(&self.parameters(), learning_rate);
+
+        Ok(loss.double_value(&[]))
+    }
+}
+
+/// Mean-pools `[batch, seq, vocab]` logits over the sequence into `[batch, vocab]`.
+pub(crate) fn pool_sequence(logits: &Tensor) -> Tensor {
+    logits.mean_dim(Some(&[1i64][..]), false, Kind::Float)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_train_step_sample_weights_scale_updates() -> Result<(), MetaSyntraXLError> {
+        let config = Config {
+            vocab_size: 8,
+            embed_dim: 8,
+            num_heads: 2,
+            hidden_dim: 16,
+            num_layers: 1,
+            max_len: 8,
+            output_size: 2,
+            dropout: 0.0,
+            ..Config::default()
+        };
+        let vs = tch::nn::VarStore::new(Device::Cpu);
+        let model = TransformerRAG::new(&vs.root(), &config)?;
+        let initial = transformer_model::snapshot(&model.parameters());
+        let input = Tensor::of_slice(&[1i64, 2, 3]).unsqueeze(0);
+        let target = Tensor::of_slice(&[1i64]);
+
+        // Every fit starts from the same parameters and takes one step on the same
+        // single-row sample, as `fit_boosting` trains its members.
+        let fit = |weight: Option<f32>| -> Result<Vec<Tensor>, MetaSyntraXLError> {
+            transformer_model::restore(&model.parameters(), &initial);
+            let weight = weight.map(|w| Tensor::of_slice(&[w]));
+            model.train_step(&input, &target, weight.as_ref(), 0.1)?;
+            Ok(transformer_model::snapshot(&model.parameters()))
+        };
+        let distance = |a: &[Tensor], b: &[Tensor]| -> f64 {
+            a.iter()
+                .zip(b)
+                .map(|(a, b)| (a - b).abs().sum(Kind::Float).double_value(&[]))
+                .sum()
+        };
 
-        Ok(output)
+        let unweighted = fit(None)?;
+        assert!(distance(&unweighted, &fit(Some(1.0))?) < 1e-6);
+        let heavy = fit(Some(5.0))?;
+        let light = fit(Some(0.2))?;
+        assert!(distance(&heavy, &light) > 1e-3);
+        assert!(distance(&heavy, &unweighted) > distance(&light, &unweighted));
+        Ok(())
     }
 }
\ No newline at end of file